@@ -0,0 +1,765 @@
+use crate::compare_pictures::SimilarityInformation;
+use crate::datastore::{open_datastore, DatastoreLock};
+use crate::features::MAX_COLOR_CHANNELS;
+use crate::file_handler::{is_file, write_file_atomically};
+use crate::histogram::BIN_COUNT;
+use crate::search::{search_with_options, SearchOptions};
+use crate::search_index::SearchIndex;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::ErrorKind;
+
+// The similarity of two pictures is `(1 - |b1 - b2| + cos) / 2`, where `b` is the average
+// brightness and `cos` the mean cosine similarity of the histograms of their color channels (see
+// `calculate_similarities`). Every entry is embedded as its brightness and a unit vector `u`: the
+// concatenation of its normalized channel histograms, each divided by the square root of the
+// channel count. Then `u1 · u2` is exactly the mean cosine similarity, and
+// `|b1 - b2| + ||u1 - u2||` is a metric that grows with the dissimilarity, so a VP-tree can find
+// the candidates that are then ranked by their exact similarity.
+
+/// The magic number at the start of every persisted ANN index.
+const ANN_MAGIC: [u8; 4] = *b"IMSA";
+const ANN_FORMAT_VERSION: u16 = 1;
+const NO_NODE: u32 = u32::MAX;
+/// Entries inserted after the tree was built are searched linearly until there are this many.
+const MIN_PENDING_BEFORE_REBUILD: usize = 256;
+
+/// Returns the path of the ANN index that belongs to a datastore.
+pub fn ann_index_path(datastore_path: &str) -> String {
+    format!("{datastore_path}.ann")
+}
+
+/// Controls the trade-off between recall and speed of `AnnIndex::search`.
+///
+/// # Fields
+///
+/// * `epsilon`: Subtrees are skipped unless they can contain a candidate that is closer than the
+///   current candidates by a factor of `1 + epsilon`. `0.0` finds the exact nearest candidates
+///   (with respect to the metric of the index), larger values visit fewer entries and may miss
+///   some of the most similar pictures.
+/// * `oversampling`: The search collects `k * oversampling` candidates before they are ranked by
+///   their exact similarity. Larger values improve the recall.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnSearchOptions {
+    pub epsilon: f64,
+    pub oversampling: usize,
+}
+
+impl Default for AnnSearchOptions {
+    fn default() -> Self {
+        AnnSearchOptions {
+            epsilon: 0.0,
+            oversampling: 4,
+        }
+    }
+}
+
+/// A search result of `AnnIndex::search`.
+///
+/// # Fields
+///
+/// * `filepath`: The filepath of the found picture.
+/// * `similarity`: The similarity as computed by `calculate_similarities`.
+/// * `cosine_similarity`: The mean cosine similarity of the histograms.
+/// * `brightness_similarity`: `1 - ` the difference of the average brightness.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnMatch {
+    pub filepath: String,
+    pub similarity: f64,
+    pub cosine_similarity: f64,
+    pub brightness_similarity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AnnPoint {
+    filepath: String,
+    average_brightness: f32,
+    channel_count: u8,
+    removed: bool,
+    vector: Vec<f32>,
+}
+
+impl AnnPoint {
+    fn new(entry: &SearchIndex, dimensions: usize) -> Self {
+        let channel_count = entry.histogram.len();
+        let scale = 1.0 / (channel_count.max(1) as f64).sqrt();
+        let mut vector = Vec::with_capacity(dimensions);
+        for histogram in &entry.histogram {
+            let normalized = histogram.normalize();
+            let magnitude = normalized.iter().map(|bin| bin * bin).sum::<f64>().sqrt();
+            for bin in normalized {
+                let value = if magnitude > 0.0 {
+                    bin / magnitude * scale
+                } else {
+                    0.0
+                };
+                vector.push(value as f32);
+            }
+        }
+        vector.resize(dimensions, 0.0);
+
+        AnnPoint {
+            filepath: entry.filepath.clone(),
+            average_brightness: entry.average_brightness,
+            channel_count: channel_count as u8,
+            removed: false,
+            vector,
+        }
+    }
+
+    /// The metric of the index.
+    fn distance(&self, other: &AnnPoint) -> f64 {
+        let brightness_distance =
+            (f64::from(self.average_brightness) - f64::from(other.average_brightness)).abs();
+        let vector_distance = self
+            .vector
+            .iter()
+            .zip(&other.vector)
+            .map(|(a, b)| f64::from(a - b) * f64::from(a - b))
+            .sum::<f64>()
+            .sqrt();
+        brightness_distance + vector_distance
+    }
+
+    fn to_match(&self, query: &AnnPoint) -> AnnMatch {
+        let brightness_similarity =
+            1.0 - (f64::from(query.average_brightness) - f64::from(self.average_brightness)).abs();
+        // pictures with a different number of color channels can not be compared
+        let cosine_similarity = if query.channel_count == self.channel_count {
+            self.vector
+                .iter()
+                .zip(&query.vector)
+                .map(|(a, b)| f64::from(*a) * f64::from(*b))
+                .sum()
+        } else {
+            0.0
+        };
+        AnnMatch {
+            filepath: self.filepath.clone(),
+            similarity: (brightness_similarity + cosine_similarity) / 2.0,
+            cosine_similarity,
+            brightness_similarity,
+        }
+    }
+}
+
+/// A node of the VP-tree. Points whose distance to the vantage point is at most `radius` are in the
+/// `inside` subtree, all others in the `outside` subtree.
+#[derive(Debug, Clone, PartialEq)]
+struct VpNode {
+    point: u32,
+    radius: f32,
+    inside: u32,
+    outside: u32,
+}
+
+/// A candidate of a search, ordered by its distance.
+#[derive(Debug, PartialEq)]
+struct Candidate {
+    distance: f64,
+    point: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance)
+    }
+}
+
+/// An approximate nearest neighbour index over the entries of a datastore.
+///
+/// The index is a VP-tree over the descriptors of the entries, which finds similar pictures
+/// without comparing the query with every entry. It can be changed incrementally: inserted entries
+/// are searched linearly and removed entries are only marked as removed, until there are enough of
+/// them that `insert` and `remove` rebuild the tree.
+///
+/// The index is persisted next to the datastore (see `ann_index_path` and `load_ann_index`).
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::ann_index::{AnnIndex, AnnSearchOptions};
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let flower =
+///     generate_suchindex("src/tests/files/pictures_for_testing/flower_purple_1.png".to_string());
+///
+/// let index = AnnIndex::build(&[bird.clone(), flower]);
+/// let matches = index.search(&bird, 1, &AnnSearchOptions::default());
+///
+/// assert_eq!(matches[0].filepath, bird.filepath);
+/// assert!((matches[0].similarity - 1.0).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnIndex {
+    dimensions: usize,
+    points: Vec<AnnPoint>,
+    positions: HashMap<String, usize>,
+    nodes: Vec<VpNode>,
+    root: u32,
+    /// Points that were inserted after the tree was built.
+    pending: Vec<usize>,
+    removed_count: usize,
+    /// Identifies the state of the datastore the index was last synchronized with.
+    datastore_fingerprint: u64,
+}
+
+impl Default for AnnIndex {
+    fn default() -> Self {
+        AnnIndex::new()
+    }
+}
+
+impl AnnIndex {
+    /// Creates an empty index.
+    pub fn new() -> Self {
+        AnnIndex {
+            dimensions: usize::from(MAX_COLOR_CHANNELS) * usize::from(BIN_COUNT),
+            points: Vec::new(),
+            positions: HashMap::new(),
+            nodes: Vec::new(),
+            root: NO_NODE,
+            pending: Vec::new(),
+            removed_count: 0,
+            datastore_fingerprint: 0,
+        }
+    }
+
+    /// Creates an index of the given entries.
+    pub fn build(entries: &[SearchIndex]) -> Self {
+        let mut index = AnnIndex::new();
+        for entry in entries {
+            index.add_point(entry);
+        }
+        index.rebuild();
+        index
+    }
+
+    /// Returns the number of entries in the index.
+    pub fn len(&self) -> usize {
+        self.points.len() - self.removed_count
+    }
+
+    /// Returns `true` if the index has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds an entry to the index. An entry with the same filepath is replaced.
+    pub fn insert(&mut self, entry: &SearchIndex) {
+        self.remove(&entry.filepath);
+        let position = self.add_point(entry);
+        self.pending.push(position);
+
+        if self.pending.len() > MIN_PENDING_BEFORE_REBUILD.max(self.points.len() / 8) {
+            self.rebuild();
+        }
+    }
+
+    /// Removes the entry of the given filepath from the index.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the index contained an entry of the filepath.
+    pub fn remove(&mut self, filepath: &str) -> bool {
+        let Some(position) = self.positions.remove(filepath) else {
+            return false;
+        };
+        self.points[position].removed = true;
+        self.removed_count += 1;
+
+        if self.removed_count > self.points.len() / 2 {
+            self.rebuild();
+        }
+        true
+    }
+
+    /// Builds the tree again from all entries, including the pending ones, and drops the removed
+    /// entries.
+    pub fn rebuild(&mut self) {
+        let points = std::mem::take(&mut self.points);
+        self.points = points.into_iter().filter(|point| !point.removed).collect();
+        self.positions = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(position, point)| (point.filepath.clone(), position))
+            .collect();
+        self.removed_count = 0;
+        self.pending.clear();
+        self.nodes.clear();
+
+        let mut positions: Vec<usize> = (0..self.points.len()).collect();
+        self.root = self.build_node(&mut positions);
+    }
+
+    /// Finds the `k` entries most similar to the query.
+    ///
+    /// The candidates are found with the tree and ranked by their exact similarity. How many
+    /// entries are visited is controlled by the `options`.
+    ///
+    /// # Returns
+    ///
+    /// At most `k` matches, the most similar first.
+    pub fn search(
+        &self,
+        query: &SearchIndex,
+        k: usize,
+        options: &AnnSearchOptions,
+    ) -> Vec<AnnMatch> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let query = AnnPoint::new(query, self.dimensions);
+        let candidate_count = k.saturating_mul(options.oversampling.max(1));
+        let mut candidates: BinaryHeap<Candidate> = BinaryHeap::new();
+
+        self.search_node(self.root, &query, candidate_count, options, &mut candidates);
+        for &position in &self.pending {
+            self.consider(position, &query, candidate_count, &mut candidates);
+        }
+
+        let mut matches: Vec<AnnMatch> = candidates
+            .into_iter()
+            .map(|candidate| self.points[candidate.point].to_match(&query))
+            .collect();
+        matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        matches.truncate(k);
+        matches
+    }
+
+    /// Brings the index up to date with the entries of a datastore.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the index changed.
+    pub fn sync_with(&mut self, entries: &[SearchIndex]) -> bool {
+        let mut changed = false;
+        let mut stored_filepaths: HashMap<&str, ()> = HashMap::new();
+        for entry in entries {
+            stored_filepaths.insert(&entry.filepath, ());
+            let point = AnnPoint::new(entry, self.dimensions);
+            let is_current = self
+                .positions
+                .get(&entry.filepath)
+                .is_some_and(|&position| self.points[position] == point);
+            if !is_current {
+                self.insert(entry);
+                changed = true;
+            }
+        }
+
+        let outdated_filepaths: Vec<String> = self
+            .positions
+            .keys()
+            .filter(|filepath| !stored_filepaths.contains_key(filepath.as_str()))
+            .cloned()
+            .collect();
+        for filepath in outdated_filepaths {
+            self.remove(&filepath);
+            changed = true;
+        }
+        changed
+    }
+
+    fn add_point(&mut self, entry: &SearchIndex) -> usize {
+        let position = self.points.len();
+        self.points.push(AnnPoint::new(entry, self.dimensions));
+        self.positions.insert(entry.filepath.clone(), position);
+        position
+    }
+
+    fn build_node(&mut self, positions: &mut [usize]) -> u32 {
+        let Some((&mut vantage_point, others)) = positions.split_first_mut() else {
+            return NO_NODE;
+        };
+        let node = self.nodes.len();
+        self.nodes.push(VpNode {
+            point: vantage_point as u32,
+            radius: 0.0,
+            inside: NO_NODE,
+            outside: NO_NODE,
+        });
+        if others.is_empty() {
+            return node as u32;
+        }
+
+        let points = &self.points;
+        let distance = |position: &usize| points[vantage_point].distance(&points[*position]);
+        let median = others.len() / 2;
+        others.select_nth_unstable_by(median, |a, b| distance(a).total_cmp(&distance(b)));
+        let radius = distance(&others[median]) as f32;
+
+        let (inside, outside) = others.split_at_mut(median + 1);
+        let inside = self.build_node(inside);
+        let outside = self.build_node(outside);
+        self.nodes[node] = VpNode {
+            point: vantage_point as u32,
+            radius,
+            inside,
+            outside,
+        };
+        node as u32
+    }
+
+    fn search_node(
+        &self,
+        node: u32,
+        query: &AnnPoint,
+        candidate_count: usize,
+        options: &AnnSearchOptions,
+        candidates: &mut BinaryHeap<Candidate>,
+    ) {
+        let Some(node) = self.nodes.get(node as usize) else {
+            return;
+        };
+        let distance = self.consider(node.point as usize, query, candidate_count, candidates);
+        let radius = f64::from(node.radius);
+
+        let reach = |candidates: &BinaryHeap<Candidate>| match candidates.peek() {
+            Some(worst) if candidates.len() >= candidate_count => {
+                worst.distance / (1.0 + options.epsilon)
+            }
+            _ => f64::INFINITY,
+        };
+
+        // the subtree the query falls into is searched first, it most likely holds the nearest
+        if distance <= radius {
+            self.search_node(node.inside, query, candidate_count, options, candidates);
+            if distance + reach(candidates) >= radius {
+                self.search_node(node.outside, query, candidate_count, options, candidates);
+            }
+        } else {
+            self.search_node(node.outside, query, candidate_count, options, candidates);
+            if distance - reach(candidates) <= radius {
+                self.search_node(node.inside, query, candidate_count, options, candidates);
+            }
+        }
+    }
+
+    /// Adds the point to the candidates if it is closer than the current ones and returns its
+    /// distance to the query.
+    fn consider(
+        &self,
+        position: usize,
+        query: &AnnPoint,
+        candidate_count: usize,
+        candidates: &mut BinaryHeap<Candidate>,
+    ) -> f64 {
+        let point = &self.points[position];
+        let distance = point.distance(query);
+        if point.removed {
+            return distance;
+        }
+        if candidates.len() < candidate_count {
+            candidates.push(Candidate {
+                distance,
+                point: position,
+            });
+        } else if candidates
+            .peek()
+            .is_some_and(|worst| distance < worst.distance)
+        {
+            candidates.pop();
+            candidates.push(Candidate {
+                distance,
+                point: position,
+            });
+        }
+        distance
+    }
+
+    /// Writes the index to a file, replacing it atomically.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can not be written.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&ANN_MAGIC);
+        bytes.extend_from_slice(&ANN_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[0; 2]);
+        bytes.extend_from_slice(&(self.dimensions as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.datastore_fingerprint.to_le_bytes());
+        bytes.extend_from_slice(&self.root.to_le_bytes());
+        bytes.extend_from_slice(&(self.points.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.nodes.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.pending.len() as u64).to_le_bytes());
+
+        for point in &self.points {
+            bytes.extend_from_slice(&(point.filepath.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(point.filepath.as_bytes());
+            bytes.extend_from_slice(&point.average_brightness.to_le_bytes());
+            bytes.push(point.channel_count);
+            bytes.push(u8::from(point.removed));
+            for value in &point.vector {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        for node in &self.nodes {
+            bytes.extend_from_slice(&node.point.to_le_bytes());
+            bytes.extend_from_slice(&node.radius.to_le_bytes());
+            bytes.extend_from_slice(&node.inside.to_le_bytes());
+            bytes.extend_from_slice(&node.outside.to_le_bytes());
+        }
+        for &position in &self.pending {
+            bytes.extend_from_slice(&(position as u32).to_le_bytes());
+        }
+
+        write_file_atomically(path, &bytes)?;
+        Ok(())
+    }
+
+    /// Reads an index that was written by `save`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can not be read or is not a valid index of the current
+    /// descriptors.
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let bytes = fs::read(path)?;
+        let mut reader = ByteReader {
+            bytes: &bytes,
+            offset: 0,
+        };
+
+        if reader.take(4)? != ANN_MAGIC {
+            return Err(format!("{path} is not an ANN index").into());
+        }
+        let format_version = u16::from_le_bytes(reader.array()?);
+        if format_version != ANN_FORMAT_VERSION {
+            return Err(
+                format!("{path} has the unsupported format version {format_version}").into(),
+            );
+        }
+        reader.take(2)?;
+
+        let mut index = AnnIndex::new();
+        let dimensions = u32::from_le_bytes(reader.array()?) as usize;
+        if dimensions != index.dimensions {
+            return Err(format!("{path} was built with other descriptors").into());
+        }
+        index.datastore_fingerprint = u64::from_le_bytes(reader.array()?);
+        index.root = u32::from_le_bytes(reader.array()?);
+        let point_count = u64::from_le_bytes(reader.array()?) as usize;
+        let node_count = u64::from_le_bytes(reader.array()?) as usize;
+        let pending_count = u64::from_le_bytes(reader.array()?) as usize;
+
+        for position in 0..point_count {
+            let filepath_length = u32::from_le_bytes(reader.array()?) as usize;
+            let filepath = String::from_utf8(reader.take(filepath_length)?.to_vec())?;
+            let average_brightness = f32::from_le_bytes(reader.array()?);
+            let [channel_count, removed] = reader.array()?;
+            let mut vector = Vec::with_capacity(dimensions);
+            for _ in 0..dimensions {
+                vector.push(f32::from_le_bytes(reader.array()?));
+            }
+            if removed == 0 {
+                index.positions.insert(filepath.clone(), position);
+            } else {
+                index.removed_count += 1;
+            }
+            index.points.push(AnnPoint {
+                filepath,
+                average_brightness,
+                channel_count,
+                removed: removed != 0,
+                vector,
+            });
+        }
+        for _ in 0..node_count {
+            let node = VpNode {
+                point: u32::from_le_bytes(reader.array()?),
+                radius: f32::from_le_bytes(reader.array()?),
+                inside: u32::from_le_bytes(reader.array()?),
+                outside: u32::from_le_bytes(reader.array()?),
+            };
+            if node.point as usize >= point_count {
+                return Err(format!("{path} is corrupted").into());
+            }
+            index.nodes.push(node);
+        }
+        for _ in 0..pending_count {
+            let position = u32::from_le_bytes(reader.array()?) as usize;
+            if position >= point_count {
+                return Err(format!("{path} is corrupted").into());
+            }
+            index.pending.push(position);
+        }
+        if reader.offset != bytes.len() {
+            return Err(format!("{path} is corrupted").into());
+        }
+        Ok(index)
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let end = self
+            .offset
+            .checked_add(length)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or("the ANN index is truncated")?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Box<dyn Error>> {
+        Ok(self.take(N)?.try_into()?)
+    }
+}
+
+/// The saved ANN index of a datastore can not be read, e.g. because it was truncated or written by
+/// another version of this library. `rebuild_ann_index` replaces it.
+#[derive(Debug, PartialEq)]
+pub struct UnreadableAnnIndex {
+    pub index_path: String,
+    pub reason: String,
+}
+
+impl Display for UnreadableAnnIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The ANN index {} can not be read ({}).",
+            self.index_path, self.reason
+        )
+    }
+}
+
+impl Error for UnreadableAnnIndex {}
+
+/// Identifies the current contents of a datastore file by its size and a hash of its bytes, so a
+/// change is noticed even if it keeps the size and the modification time. SQLite datastores write
+/// their changes to a separate log file first, so it is included.
+///
+/// # Errors
+///
+/// Returns an error if a file exists but can not be read.
+fn datastore_fingerprint(datastore_path: &str) -> Result<u64, Box<dyn Error>> {
+    let mut fingerprint: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut add = |bytes: &[u8]| {
+        for &byte in bytes {
+            fingerprint ^= u64::from(byte);
+            fingerprint = fingerprint.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    let mut found = false;
+    for path in [datastore_path.to_string(), format!("{datastore_path}-wal")] {
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(Box::new(err)),
+        };
+        found = true;
+        add(&(contents.len() as u64).to_le_bytes());
+        add(&contents);
+    }
+    // 0 is never the fingerprint of a saved index
+    Ok(if found { fingerprint.max(1) } else { 0 })
+}
+
+/// Loads the ANN index of the datastore at the given path and brings it up to date.
+///
+/// If the datastore changed since the index was saved, or there is no index yet, the index is
+/// synchronized with the entries of the datastore and saved again (see `ann_index_path`). The
+/// datastore is locked with `DatastoreLock::acquire_shared` meanwhile, so it can not change between
+/// computing its fingerprint and reading its entries.
+///
+/// # Errors
+///
+/// Returns `UnreadableAnnIndex` if the saved index can not be read, see `rebuild_ann_index`. Returns
+/// another error if the datastore can not be read or the index can not be saved.
+pub fn load_ann_index(datastore_path: &str) -> Result<AnnIndex, Box<dyn Error>> {
+    update_ann_index(datastore_path, true)
+}
+
+/// Same as `load_ann_index`, but the saved index is not read: the index is built from the entries
+/// of the datastore and replaces it.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or the index can not be saved.
+pub fn rebuild_ann_index(datastore_path: &str) -> Result<AnnIndex, Box<dyn Error>> {
+    update_ann_index(datastore_path, false)
+}
+
+fn update_ann_index(datastore_path: &str, read_saved: bool) -> Result<AnnIndex, Box<dyn Error>> {
+    let index_path = ann_index_path(datastore_path);
+    let _datastore_lock = DatastoreLock::acquire_shared(datastore_path)?;
+    // SQLite datastores are changed without the lock, so the fingerprint is computed before the
+    // entries are read: a change in between makes the next load synchronize the index again
+    let fingerprint = datastore_fingerprint(datastore_path)?;
+    let mut index = if read_saved && is_file(&index_path) {
+        AnnIndex::load(&index_path).map_err(|err| UnreadableAnnIndex {
+            index_path: index_path.clone(),
+            reason: err.to_string(),
+        })?
+    } else {
+        AnnIndex::new()
+    };
+    if index.datastore_fingerprint == fingerprint && fingerprint != 0 {
+        return Ok(index);
+    }
+
+    let entries = open_datastore(datastore_path)?.entries()?;
+    index.sync_with(&entries);
+    index.datastore_fingerprint = fingerprint;
+
+    // other processes holding the shared lock may save the index at the same time
+    let _index_lock = DatastoreLock::acquire(&index_path)?;
+    index.save(&index_path)?;
+    Ok(index)
+}
+
+/// Finds the `k` pictures of the configured datastore most similar to the picture at the given
+/// path, using its ANN index (see `load_ann_index` and `SearchOptions::approximate`).
+///
+/// In contrast to `get_all_similar_pictures`, the picture is not compared with every entry. The
+/// `options` trade recall for speed.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::ann_index::{search_similar_pictures, AnnSearchOptions};
+///
+/// let similar_pictures = search_similar_pictures(
+///     "src/tests/files/pictures_for_testing/bird.png",
+///     3,
+///     &AnnSearchOptions::default(),
+/// )
+/// .unwrap();
+/// assert!(similar_pictures.len() <= 3);
+/// ```
+pub fn search_similar_pictures(
+    path: &str,
+    k: usize,
+    options: &AnnSearchOptions,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    search_with_options(
+        path,
+        &SearchOptions::top(k).with_approximate(options.clone()),
+    )
+}
@@ -0,0 +1,272 @@
+use crate::compare_pictures::SimilarityInformation;
+use crate::datastore::{open_datastore, Datastore};
+use crate::features::FeatureConfiguration;
+use crate::file_handler::{collect_picture_files, format_filepath, is_directory};
+use crate::get_datastore_path;
+use crate::search::{rank_stored_entries, SearchOptions};
+use crate::search_index::{try_generate_suchindex, SearchIndex};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// The results of a batch search.
+///
+/// # Fields
+///
+/// * `results`: The ranked results of every query that could be searched, by query filepath.
+/// * `failed`: The error message of every query that could not be read, by query filepath.
+#[derive(Debug, Default)]
+pub struct BatchSearchResults {
+    pub results: BTreeMap<String, Vec<SimilarityInformation>>,
+    pub failed: BTreeMap<String, String>,
+}
+
+/// One line of the JSON lines output of `stream_batch_search`.
+#[derive(Serialize)]
+struct BatchSearchLine<'a> {
+    query: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<&'a [SimilarityInformation]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+type QueryResult = Result<Vec<SimilarityInformation>, String>;
+
+/// Searches the configured datastore for the pictures most similar to each of the queries.
+///
+/// The datastore is read only once and the queries are searched in parallel, see
+/// `batch_search_datastore`.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read. Queries that can not be read are reported in
+/// `BatchSearchResults::failed` instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::batch_search::batch_search;
+/// use imsearch::search::SearchOptions;
+///
+/// let queries = vec!["src/tests/files/pictures_for_testing/bird.png".to_string()];
+/// let batch = batch_search(&queries, &SearchOptions::top(3)).unwrap();
+/// assert!(batch.results.contains_key(&queries[0]));
+/// ```
+pub fn batch_search(
+    queries: &[String],
+    options: &SearchOptions,
+) -> Result<BatchSearchResults, Box<dyn Error>> {
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    batch_search_datastore(datastore.as_ref(), queries, options)
+}
+
+/// Searches the given datastore for the pictures most similar to each of the queries.
+///
+/// The entries are read once and shared by one thread per available CPU, which take the next
+/// query until all are searched.
+///
+/// # Arguments
+///
+/// * `datastore` - The datastore that is searched.
+/// * `queries` - The filepaths of the query pictures.
+/// * `options` - Selects the results of every query, like for `search_with_options`.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::batch_search::batch_search_datastore;
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::search::SearchOptions;
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = "src/tests/files/pictures_for_testing/bird.png".to_string();
+/// let datastore = MemoryDatastore::with_entries(vec![generate_suchindex(bird.clone())]);
+/// let queries = vec![bird.clone(), "pictures/missing.png".to_string()];
+///
+/// let batch = batch_search_datastore(&datastore, &queries, &SearchOptions::top(1)).unwrap();
+/// assert_eq!(batch.results[&bird].len(), 1);
+/// assert!(batch.failed.contains_key("pictures/missing.png"));
+/// ```
+pub fn batch_search_datastore(
+    datastore: &dyn Datastore,
+    queries: &[String],
+    options: &SearchOptions,
+) -> Result<BatchSearchResults, Box<dyn Error>> {
+    let entries = datastore.entries()?;
+    let features = datastore.features();
+    let mut batch = BatchSearchResults::default();
+    search_in_parallel(&entries, &features, queries, options, |query, result| {
+        match result {
+            Ok(results) => {
+                batch.results.insert(query.to_string(), results);
+            }
+            Err(message) => {
+                batch.failed.insert(query.to_string(), message);
+            }
+        }
+        Ok(())
+    })?;
+    Ok(batch)
+}
+
+/// Same as `batch_search_datastore`, but every result is written to `writer` as soon as its query
+/// is searched instead of being collected, so the results of large batches don't have to fit into
+/// memory.
+///
+/// Every query is written as one line of JSON (JSON lines) in the order the queries finish:
+/// `{"query": ..., "results": [...]}`, or `{"query": ..., "error": ...}` if the query can not be
+/// read.
+///
+/// # Returns
+///
+/// The number of queries that failed.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or the output can not be written.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::batch_search::stream_batch_search;
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::search::SearchOptions;
+///
+/// let queries = vec!["src/tests/files/pictures_for_testing/bird.png".to_string()];
+/// let mut output = Vec::new();
+///
+/// let failed =
+///     stream_batch_search(&MemoryDatastore::new(), &queries, &SearchOptions::top(3), &mut output)
+///         .unwrap();
+/// assert_eq!(failed, 0);
+/// assert_eq!(String::from_utf8(output).unwrap().lines().count(), 1);
+/// ```
+pub fn stream_batch_search(
+    datastore: &dyn Datastore,
+    queries: &[String],
+    options: &SearchOptions,
+    writer: &mut dyn Write,
+) -> Result<usize, Box<dyn Error>> {
+    let entries = datastore.entries()?;
+    let features = datastore.features();
+    let mut failed_count = 0;
+    search_in_parallel(&entries, &features, queries, options, |query, result| {
+        let line = match &result {
+            Ok(results) => BatchSearchLine {
+                query,
+                results: Some(results),
+                error: None,
+            },
+            Err(message) => {
+                failed_count += 1;
+                BatchSearchLine {
+                    query,
+                    results: None,
+                    error: Some(message),
+                }
+            }
+        };
+        serde_json::to_writer(&mut *writer, &line)?;
+        writeln!(writer)?;
+        Ok(())
+    })?;
+    writer.flush()?;
+    Ok(failed_count)
+}
+
+/// Searches the configured datastore for each of the queries and writes the results to the file
+/// at `output_path`, see `stream_batch_search`.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or the file can not be written.
+pub fn batch_search_to_file(
+    queries: &[String],
+    options: &SearchOptions,
+    output_path: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    stream_batch_search(datastore.as_ref(), queries, options, &mut writer)
+}
+
+/// Expands the given paths into the query pictures: folders are replaced by the pictures inside
+/// them (including their subfolders), other paths are kept as they are.
+///
+/// # Errors
+///
+/// Returns an error if a folder can not be walked.
+pub fn collect_queries(paths: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut queries = Vec::new();
+    for path in paths {
+        if is_directory(path) {
+            queries.extend(collect_picture_files(path)?);
+        } else {
+            queries.push(format_filepath(path));
+        }
+    }
+    Ok(queries)
+}
+
+/// Searches the entries for every query with a pool of scoped threads and hands each result to
+/// `on_result` on the calling thread, in the order the queries finish. The queries get the
+/// descriptors of the `features` of the entries and are ranked with `rank_stored_entries`.
+fn search_in_parallel<F>(
+    entries: &[SearchIndex],
+    features: &FeatureConfiguration,
+    queries: &[String],
+    options: &SearchOptions,
+    mut on_result: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&str, QueryResult) -> Result<(), Box<dyn Error>>,
+{
+    let thread_count = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(queries.len());
+    let next_query = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel::<(usize, QueryResult)>();
+        for _ in 0..thread_count {
+            let sender = sender.clone();
+            let next_query = &next_query;
+            scope.spawn(move || loop {
+                let index = next_query.fetch_add(1, Ordering::Relaxed);
+                let Some(query) = queries.get(index) else {
+                    break;
+                };
+                let result = try_generate_suchindex(query.clone(), features)
+                    .map(|search_index| {
+                        rank_stored_entries(&search_index, entries, features, options)
+                    })
+                    .map_err(|err| err.to_string());
+                if sender.send((index, result)).is_err() {
+                    // the receiver stopped because the output failed
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (index, result) in receiver {
+            if let Err(err) = on_result(&queries[index], result) {
+                // let the workers stop at their next query
+                next_query.store(queries.len(), Ordering::Relaxed);
+                return Err(err);
+            }
+        }
+        Ok(())
+    })
+}
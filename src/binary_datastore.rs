@@ -0,0 +1,1431 @@
+use crate::color_moments::ColorMoments;
+use crate::color_type::ColorType;
+use crate::correlogram::{
+    AutoCorrelogram, CORRELOGRAM_DISTANCES, CORRELOGRAM_LEVELS, MAX_CORRELOGRAM_LEVELS,
+};
+use crate::datastore::{
+    needs_migration, read_stored_datastore_file, write_datastore_file, ChangeBuffer, Datastore,
+    DatastoreError, DatastoreLock, Modification,
+};
+use crate::features::{DescriptorSet, FeatureConfiguration};
+use crate::file_handler::{extract_filename, is_file, write_file_atomically};
+use crate::migration::{migrate_entries, MigrationSummary, SCHEMA_VERSION};
+use crate::region::{GridCell, Rect, GRID_SIZE};
+use crate::search_index::SearchIndex;
+use crate::shape::{ShapeDescriptor, EDGE_DIRECTION_BINS};
+use crate::statistics::{ChannelStatistics, ImageStatistics, PERCENTILES};
+use crate::Histogram;
+use memmap2::Mmap;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+
+// Layout of a binary datastore (all numbers little endian):
+//
+// header (HEADER_SIZE bytes)
+//   0..4     magic "IMSB"
+//   4..6     format version (u16)
+//   6..8     header size (u16)
+//   8        histogram bin count (u8)
+//   9        histogram color channels (u8)
+//   10..12   schema version of the entries (u16, 0 in files written before it was stored: 2)
+//   12..16   record size (u32)
+//   16..24   record count (u64)
+//   24..32   heap offset (u64)
+//   32..40   heap size (u64)
+//   40..42   descriptor version (u16, 0 in files written before it was stored)
+//   42       descriptor set (u8, 0: full, 1: compact without histograms, see `DescriptorSet`)
+//   43       correlogram levels (u8)
+//   44       correlogram distance count (u8, at most MAX_CORRELOGRAM_DISTANCES)
+//   48..64   correlogram distances (u32)
+//   64..124  descriptor sections, see `DescriptorSections`: offset from the start of the file
+//            (u64) and values per entry (u32) of the grid cells, statistics, colour moments,
+//            correlogram and shape
+// records (record count * record size bytes)
+//   0..8     filepath offset in the heap (u64)
+//   8..12    filepath length (u32)
+//   12..20   extras offset in the heap (u64)
+//   20..24   extras length (u32), 0 if there are no extras
+//   24..32   content hash (u64)
+//   32..36   average brightness (f32)
+//   36       color channel count (u8)
+//   37       flags (u8), which of the optional fields the entry has
+//   38       color type (u8, see `encode_color_type`)
+//   39       statistics channel count (u8)
+//   40..44   lines (u32)
+//   44..48   columns (u32)
+//   48..56   file size (u64)
+//   56..60   lightness (f32)
+//   64..     histogram bins (u32), color channels * bin count, unused channels are 0
+// descriptor sections (record count * values per entry values each, 0 if no entry has the
+// descriptor), the slots of entries without the descriptor are 0
+//   grid cells      every cell: x, y, width and height (u32), average brightness (f32) and
+//                   histogram bins (u32) like the record
+//   statistics      RMS contrast, Michelson contrast, colourfulness and entropy, then for every
+//                   color channel mean, standard deviation, skewness, min, max and the
+//                   percentiles (f32)
+//   colour moments  means, standard deviations and skewnesses (f32)
+//   correlogram     the values of every distance (u8)
+//   shape           edge directions, edge density and Hu moments (f32)
+// heap (heap size bytes)
+//   UTF-8 filepaths and the extras: the filename if it can not be derived from the filepath,
+//   then the number of tags (u32) and the tags, strings as length (u32) and UTF-8 bytes
+//
+// Every descriptor has a slot of the same size for each entry, so decoding an entry only reads
+// numbers at known offsets, and one descriptor of all entries can be scanned without touching
+// the others. A new field of `SearchIndex` needs a new format version.
+//
+// Format version 1 had a 64 byte header without the correlogram parameters and the sections,
+// and 40 byte records without the fields from byte 38 on. All other fields of an entry were
+// stored as JSON in the extras. It is still read, and written as the current version the next
+// time the datastore changes.
+
+/// The magic number at the start of every binary datastore.
+pub const BINARY_MAGIC: [u8; 4] = *b"IMSB";
+/// The newest version of the binary format this library can read and the one it writes.
+pub const BINARY_FORMAT_VERSION: u16 = 2;
+
+const HEADER_SIZE: usize = 128;
+const RECORD_CORE_SIZE: usize = 64;
+const HEADER_SIZE_V1: usize = 64;
+const RECORD_CORE_SIZE_V1: usize = 40;
+/// The number of correlogram distances the header has room for.
+const MAX_CORRELOGRAM_DISTANCES: usize = 4;
+const _: () = assert!(CORRELOGRAM_DISTANCES.len() <= MAX_CORRELOGRAM_DISTANCES);
+/// The values of a grid cell besides its histograms: the region and the average brightness.
+const GRID_CELL_CORE_VALUES: usize = 5;
+/// The values of a channel of the statistics besides the percentiles.
+const CHANNEL_STATISTICS_CORE_VALUES: usize = 5;
+/// The values of the statistics that do not belong to a channel.
+const GLOBAL_STATISTICS_VALUES: usize = 4;
+const COLOR_MOMENTS_VALUES: usize = 9;
+/// The values of the shape descriptor besides the edge directions.
+const SHAPE_CORE_VALUES: usize = 8;
+
+const FLAG_HAS_CONTENT_HASH: u8 = 0b0000_0001;
+const FLAG_HAS_FILENAME: u8 = 0b0000_0010;
+const FLAG_HAS_LIGHTNESS: u8 = 0b0000_0100;
+const FLAG_HAS_GRID_CELLS: u8 = 0b0000_1000;
+const FLAG_HAS_STATISTICS: u8 = 0b0001_0000;
+const FLAG_HAS_COLOR_MOMENTS: u8 = 0b0010_0000;
+const FLAG_HAS_CORRELOGRAM: u8 = 0b0100_0000;
+const FLAG_HAS_SHAPE: u8 = 0b1000_0000;
+
+/// Where one descriptor of all entries is stored, see `DescriptorSections`.
+///
+/// # Fields
+///
+/// * `offset`: The offset of the section from the start of the file.
+/// * `count`: The number of values every entry has in the section, 0 if no entry has the
+///   descriptor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DescriptorSection {
+    pub offset: u64,
+    pub count: u32,
+}
+
+/// The sections of the descriptors that do not fit into the records. Each one holds a slot of the
+/// same size for every entry. Datastores of format version 1 have no sections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DescriptorSections {
+    pub grid_cells: DescriptorSection,
+    pub statistics: DescriptorSection,
+    pub color_moments: DescriptorSection,
+    pub correlogram: DescriptorSection,
+    pub shape: DescriptorSection,
+}
+
+impl DescriptorSections {
+    /// Returns the sections in the order of the header and the file, with the size of a value.
+    fn with_value_sizes(&self) -> [(DescriptorSection, usize); 5] {
+        [
+            (self.grid_cells, 4),
+            (self.statistics, 4),
+            (self.color_moments, 4),
+            (self.correlogram, 1),
+            (self.shape, 4),
+        ]
+    }
+}
+
+/// The header of a binary datastore.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryHeader {
+    pub format_version: u16,
+    pub schema_version: u32,
+    pub features: FeatureConfiguration,
+    pub record_size: u32,
+    pub record_count: u64,
+    pub heap_offset: u64,
+    pub heap_size: u64,
+    pub correlogram_levels: u8,
+    pub correlogram_distances: Vec<u32>,
+    pub sections: DescriptorSections,
+}
+
+impl BinaryHeader {
+    /// Returns `true` if the datastore has to be migrated, see `migrate_binary_datastore`.
+    pub fn needs_migration(&self) -> bool {
+        self.schema_version != SCHEMA_VERSION || !self.features.is_current()
+    }
+}
+
+fn header_size(format_version: u16) -> usize {
+    if format_version < 2 {
+        HEADER_SIZE_V1
+    } else {
+        HEADER_SIZE
+    }
+}
+
+fn record_size(format_version: u16, features: &FeatureConfiguration) -> usize {
+    let core_size = if format_version < 2 {
+        RECORD_CORE_SIZE_V1
+    } else {
+        RECORD_CORE_SIZE
+    };
+    core_size + histogram_values(features) * 4
+}
+
+fn histogram_values(features: &FeatureConfiguration) -> usize {
+    usize::from(features.color_channels) * usize::from(features.bin_count)
+}
+
+/// Encodes the entries in the binary datastore format.
+///
+/// # Errors
+///
+/// Returns an error if an entry has more color channels than `features.color_channels` or a
+/// histogram whose bin count differs from `features.bin_count`, or if a descriptor has another
+/// size than this version of the library computes, e.g. a correlogram with other distances.
+/// Descriptors the configuration does not keep are left out.
+pub fn encode_binary_datastore(
+    entries: &[SearchIndex],
+    features: &FeatureConfiguration,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let entries = features.stored_entries(entries);
+    let record_size = record_size(BINARY_FORMAT_VERSION, features);
+    let colour_count = usize::from(CORRELOGRAM_LEVELS).pow(3);
+    let section_counts = [
+        entries
+            .iter()
+            .any(|entry| !entry.grid_cells.is_empty())
+            .then_some(
+                (GRID_SIZE * GRID_SIZE) as usize
+                    * (GRID_CELL_CORE_VALUES + histogram_values(features)),
+            ),
+        entries
+            .iter()
+            .any(|entry| entry.statistics.is_some())
+            .then_some(
+                GLOBAL_STATISTICS_VALUES
+                    + usize::from(features.color_channels)
+                        * (CHANNEL_STATISTICS_CORE_VALUES + PERCENTILES.len()),
+            ),
+        entries
+            .iter()
+            .any(|entry| entry.color_moments.is_some())
+            .then_some(COLOR_MOMENTS_VALUES),
+        entries
+            .iter()
+            .any(|entry| entry.correlogram.is_some())
+            .then_some(CORRELOGRAM_DISTANCES.len() * colour_count),
+        entries
+            .iter()
+            .any(|entry| entry.shape.is_some())
+            .then_some(EDGE_DIRECTION_BINS + SHAPE_CORE_VALUES),
+    ]
+    .map(|count| count.unwrap_or(0));
+    let slot_sizes: Vec<usize> = section_counts
+        .iter()
+        .zip(DescriptorSections::default().with_value_sizes())
+        .map(|(count, (_, value_size))| count * value_size)
+        .collect();
+
+    let mut records: Vec<u8> = Vec::with_capacity(entries.len() * record_size);
+    let mut sections: [Vec<u8>; 5] = Default::default();
+    let mut heap: Vec<u8> = Vec::new();
+
+    for entry in entries.iter() {
+        let mut flags = 0;
+        let content_hash = match u64::from_str_radix(&entry.content_hash, 16) {
+            Ok(hash) if entry.content_hash.len() == 16 => {
+                flags |= FLAG_HAS_CONTENT_HASH;
+                hash
+            }
+            _ => 0,
+        };
+        let color_type = encode_color_type(&entry.filepath, entry.color_type)?;
+
+        // --- variable-sized data ---
+        let filepath_offset = heap.len() as u64;
+        heap.extend_from_slice(entry.filepath.as_bytes());
+
+        let extras_offset = heap.len() as u64;
+        if entry.filename != extract_filename(entry.filepath.clone()) {
+            flags |= FLAG_HAS_FILENAME;
+            push_string(&mut heap, &entry.filename);
+        }
+        if !entry.tags.is_empty() {
+            push_u32(&mut heap, entry.tags.len() as u32);
+            for tag in &entry.tags {
+                push_string(&mut heap, tag);
+            }
+        }
+        let extras_length = heap.len() as u64 - extras_offset;
+
+        // --- descriptor sections ---
+        let [grid_cells, statistics, color_moments, correlogram, shape] = &mut sections;
+        if entry.grid_cells.is_empty() {
+            grid_cells.resize(grid_cells.len() + slot_sizes[0], 0);
+        } else {
+            flags |= FLAG_HAS_GRID_CELLS;
+            push_grid_cells(grid_cells, entry, features)?;
+        }
+        match &entry.statistics {
+            Some(entry_statistics) => {
+                flags |= FLAG_HAS_STATISTICS;
+                push_statistics(statistics, &entry.filepath, entry_statistics, features)?;
+            }
+            None => statistics.resize(statistics.len() + slot_sizes[1], 0),
+        }
+        match &entry.color_moments {
+            Some(moments) => {
+                flags |= FLAG_HAS_COLOR_MOMENTS;
+                for value in moments
+                    .mean
+                    .iter()
+                    .chain(&moments.standard_deviation)
+                    .chain(&moments.skewness)
+                {
+                    push_f32(color_moments, *value);
+                }
+            }
+            None => color_moments.resize(color_moments.len() + slot_sizes[2], 0),
+        }
+        match &entry.correlogram {
+            Some(entry_correlogram) => {
+                flags |= FLAG_HAS_CORRELOGRAM;
+                push_correlogram(correlogram, &entry.filepath, entry_correlogram)?;
+            }
+            None => correlogram.resize(correlogram.len() + slot_sizes[3], 0),
+        }
+        match &entry.shape {
+            Some(entry_shape) => {
+                flags |= FLAG_HAS_SHAPE;
+                push_shape(shape, &entry.filepath, entry_shape)?;
+            }
+            None => shape.resize(shape.len() + slot_sizes[4], 0),
+        }
+        if entry.lightness.is_some() {
+            flags |= FLAG_HAS_LIGHTNESS;
+        }
+
+        // --- fixed-size record ---
+        records.extend_from_slice(&filepath_offset.to_le_bytes());
+        push_u32(&mut records, entry.filepath.len() as u32);
+        records.extend_from_slice(&extras_offset.to_le_bytes());
+        push_u32(&mut records, extras_length as u32);
+        records.extend_from_slice(&content_hash.to_le_bytes());
+        push_f32(&mut records, entry.average_brightness);
+        records.push(entry.histogram.len() as u8);
+        records.push(flags);
+        records.push(color_type);
+        records.push(
+            entry
+                .statistics
+                .as_ref()
+                .map_or(0, |statistics| statistics.channels.len() as u8),
+        );
+        push_u32(&mut records, entry.lines);
+        push_u32(&mut records, entry.columns);
+        records.extend_from_slice(&entry.file_size.to_le_bytes());
+        push_f32(&mut records, entry.lightness.unwrap_or(0.0));
+        records.extend_from_slice(&[0; 4]);
+        push_histograms(&mut records, &entry.filepath, &entry.histogram, features)?;
+    }
+
+    let records_end = (HEADER_SIZE + records.len()) as u64;
+    let heap_offset = records_end
+        + sections
+            .iter()
+            .map(|section| section.len() as u64)
+            .sum::<u64>();
+    let mut bytes: Vec<u8> = Vec::with_capacity(heap_offset as usize + heap.len());
+    bytes.extend_from_slice(&BINARY_MAGIC);
+    bytes.extend_from_slice(&BINARY_FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(HEADER_SIZE as u16).to_le_bytes());
+    bytes.push(features.bin_count);
+    bytes.push(features.color_channels);
+    bytes.extend_from_slice(&(SCHEMA_VERSION as u16).to_le_bytes());
+    push_u32(&mut bytes, record_size as u32);
+    bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&heap_offset.to_le_bytes());
+    bytes.extend_from_slice(&(heap.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&features.descriptor_version.to_le_bytes());
+    bytes.push(match features.descriptors {
+        DescriptorSet::Full => 0,
+        DescriptorSet::Compact => 1,
+    });
+    bytes.push(CORRELOGRAM_LEVELS);
+    bytes.push(CORRELOGRAM_DISTANCES.len() as u8);
+    bytes.resize(48, 0);
+    for index in 0..MAX_CORRELOGRAM_DISTANCES {
+        push_u32(
+            &mut bytes,
+            CORRELOGRAM_DISTANCES.get(index).copied().unwrap_or(0),
+        );
+    }
+    let mut section_offset = records_end;
+    for (count, section) in section_counts.iter().zip(&sections) {
+        bytes.extend_from_slice(&section_offset.to_le_bytes());
+        push_u32(&mut bytes, *count as u32);
+        section_offset += section.len() as u64;
+    }
+    bytes.resize(HEADER_SIZE, 0);
+    bytes.extend_from_slice(&records);
+    for section in &sections {
+        bytes.extend_from_slice(section);
+    }
+    bytes.extend_from_slice(&heap);
+
+    Ok(bytes)
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_f32(bytes: &mut Vec<u8>, value: f32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_string(bytes: &mut Vec<u8>, value: &str) {
+    push_u32(bytes, value.len() as u32);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+/// Appends `features.color_channels` * `features.bin_count` histogram bins, unused channels are 0.
+fn push_histograms(
+    bytes: &mut Vec<u8>,
+    filepath: &str,
+    histograms: &[Histogram],
+    features: &FeatureConfiguration,
+) -> Result<(), Box<dyn Error>> {
+    if histograms.len() > usize::from(features.color_channels) {
+        return Err(format!(
+            "{filepath} has {} color channels, the binary datastore stores at most {}",
+            histograms.len(),
+            features.color_channels
+        )
+        .into());
+    }
+    for channel in 0..usize::from(features.color_channels) {
+        let bins = histograms.get(channel).map(|histogram| &histogram.bins);
+        if let Some(bins) = bins {
+            if bins.len() != usize::from(features.bin_count) {
+                return Err(format!(
+                    "{filepath} has histograms with {} bins, the binary datastore stores {}",
+                    bins.len(),
+                    features.bin_count
+                )
+                .into());
+            }
+        }
+        for bin in 0..usize::from(features.bin_count) {
+            push_u32(bytes, bins.map(|bins| bins[bin]).unwrap_or(0));
+        }
+    }
+    Ok(())
+}
+
+fn push_grid_cells(
+    bytes: &mut Vec<u8>,
+    entry: &SearchIndex,
+    features: &FeatureConfiguration,
+) -> Result<(), Box<dyn Error>> {
+    let cell_count = (GRID_SIZE * GRID_SIZE) as usize;
+    if entry.grid_cells.len() != cell_count {
+        return Err(format!(
+            "{} has {} grid cells, the binary datastore stores {cell_count}",
+            entry.filepath,
+            entry.grid_cells.len()
+        )
+        .into());
+    }
+    for cell in &entry.grid_cells {
+        // the record stores the color channel count of the picture for the cells too
+        if cell.histogram.len() != entry.histogram.len() {
+            return Err(format!(
+                "{} has grid cells with {} color channels, but {} color channels",
+                entry.filepath,
+                cell.histogram.len(),
+                entry.histogram.len()
+            )
+            .into());
+        }
+        for value in [
+            cell.region.x,
+            cell.region.y,
+            cell.region.width,
+            cell.region.height,
+        ] {
+            push_u32(bytes, value);
+        }
+        push_f32(bytes, cell.average_brightness);
+        push_histograms(bytes, &entry.filepath, &cell.histogram, features)?;
+    }
+    Ok(())
+}
+
+fn push_statistics(
+    bytes: &mut Vec<u8>,
+    filepath: &str,
+    statistics: &ImageStatistics,
+    features: &FeatureConfiguration,
+) -> Result<(), Box<dyn Error>> {
+    if statistics.channels.len() > usize::from(features.color_channels) {
+        return Err(format!(
+            "{filepath} has statistics of {} color channels, the binary datastore stores at most {}",
+            statistics.channels.len(),
+            features.color_channels
+        )
+        .into());
+    }
+    for value in [
+        statistics.rms_contrast,
+        statistics.michelson_contrast,
+        statistics.colourfulness,
+        statistics.entropy,
+    ] {
+        push_f32(bytes, value);
+    }
+    let channel_values = CHANNEL_STATISTICS_CORE_VALUES + PERCENTILES.len();
+    for channel in 0..usize::from(features.color_channels) {
+        let Some(channel) = statistics.channels.get(channel) else {
+            bytes.resize(bytes.len() + channel_values * 4, 0);
+            continue;
+        };
+        if channel.percentiles.len() != PERCENTILES.len() {
+            return Err(format!(
+                "{filepath} has statistics with {} percentiles, the binary datastore stores {}",
+                channel.percentiles.len(),
+                PERCENTILES.len()
+            )
+            .into());
+        }
+        for value in [
+            channel.mean,
+            channel.standard_deviation,
+            channel.skewness,
+            channel.min,
+            channel.max,
+        ]
+        .iter()
+        .chain(&channel.percentiles)
+        {
+            push_f32(bytes, *value);
+        }
+    }
+    Ok(())
+}
+
+fn push_correlogram(
+    bytes: &mut Vec<u8>,
+    filepath: &str,
+    correlogram: &AutoCorrelogram,
+) -> Result<(), Box<dyn Error>> {
+    let colour_count = usize::from(CORRELOGRAM_LEVELS).pow(3);
+    if correlogram.levels != CORRELOGRAM_LEVELS
+        || correlogram.distances != CORRELOGRAM_DISTANCES
+        || correlogram.values.len() != CORRELOGRAM_DISTANCES.len()
+        || correlogram
+            .values
+            .iter()
+            .any(|values| values.len() != colour_count)
+    {
+        return Err(format!(
+            "{filepath} has a correlogram with other levels or distances than the binary datastore stores"
+        )
+        .into());
+    }
+    for values in &correlogram.values {
+        bytes.extend_from_slice(values);
+    }
+    Ok(())
+}
+
+fn push_shape(
+    bytes: &mut Vec<u8>,
+    filepath: &str,
+    shape: &ShapeDescriptor,
+) -> Result<(), Box<dyn Error>> {
+    if shape.edge_directions.len() != EDGE_DIRECTION_BINS {
+        return Err(format!(
+            "{filepath} has {} edge directions, the binary datastore stores {EDGE_DIRECTION_BINS}",
+            shape.edge_directions.len()
+        )
+        .into());
+    }
+    for value in shape
+        .edge_directions
+        .iter()
+        .chain([&shape.edge_density])
+        .chain(&shape.hu_moments)
+    {
+        push_f32(bytes, *value);
+    }
+    Ok(())
+}
+
+/// Encodes a color type in one byte: the known color models are numbered, `Channels` sets the
+/// highest bit and stores the channel count in the others.
+fn encode_color_type(filepath: &str, color_type: ColorType) -> Result<u8, Box<dyn Error>> {
+    Ok(match color_type {
+        ColorType::Gray => 0,
+        ColorType::GrayAlpha => 1,
+        ColorType::Rgb => 2,
+        ColorType::Rgba => 3,
+        ColorType::Hsv => 4,
+        ColorType::YCbCr => 5,
+        ColorType::Channels(count) if count < 0x80 => 0x80 | count as u8,
+        ColorType::Channels(count) => {
+            return Err(format!(
+                "{filepath} has {count} color channels, the binary datastore stores at most 127"
+            )
+            .into())
+        }
+    })
+}
+
+fn decode_color_type(byte: u8) -> Option<ColorType> {
+    match byte {
+        0 => Some(ColorType::Gray),
+        1 => Some(ColorType::GrayAlpha),
+        2 => Some(ColorType::Rgb),
+        3 => Some(ColorType::Rgba),
+        4 => Some(ColorType::Hsv),
+        5 => Some(ColorType::YCbCr),
+        byte if byte & 0x80 != 0 => Some(ColorType::Channels(usize::from(byte & 0x7f))),
+        _ => None,
+    }
+}
+
+/// Writes the entries to a binary datastore file, replacing its content atomically.
+///
+/// # Errors
+///
+/// Returns an error if the entries can not be encoded (see `encode_binary_datastore`) or the file
+/// can not be written.
+pub fn write_binary_datastore(
+    path: &str,
+    entries: &[SearchIndex],
+    features: &FeatureConfiguration,
+) -> Result<(), Box<dyn Error>> {
+    let bytes = encode_binary_datastore(entries, features)?;
+    write_file_atomically(path, &bytes)?;
+    Ok(())
+}
+
+/// Returns `true` if the file at the given path starts with the magic number of a binary datastore.
+pub fn is_binary_datastore(path: &str) -> bool {
+    let mut magic = [0; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| magic == BINARY_MAGIC)
+        .unwrap_or(false)
+}
+
+/// A read-only binary datastore that is memory mapped.
+///
+/// Opening the datastore only reads and checks the header. The fixed-size records are read on
+/// access, so e.g. the average brightness of all entries can be scanned without decoding any
+/// filepaths or histograms. The other descriptors are stored in slots of the same size for every
+/// entry (see `DescriptorSections`), so `get` only reads numbers at known offsets.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::binary_datastore::{write_binary_datastore, BinaryDatastore};
+/// use imsearch::features::FeatureConfiguration;
+/// use imsearch::search_index::SearchIndex;
+///
+/// let path = std::env::temp_dir().join("imsearch_doc_binary_datastore.imsb");
+/// let path = path.to_str().unwrap();
+/// let entries = vec![SearchIndex::new("pictures/bird.png".to_string(), 0.4, vec![])];
+/// write_binary_datastore(path, &entries, &FeatureConfiguration::default()).unwrap();
+///
+/// let datastore = BinaryDatastore::open(path).unwrap();
+/// assert_eq!(datastore.len(), 1);
+/// assert_eq!(datastore.filepath(0).unwrap(), "pictures/bird.png");
+/// assert_eq!(datastore.get(0).unwrap(), entries[0]);
+/// ```
+#[derive(Debug)]
+pub struct BinaryDatastore {
+    path: String,
+    header: BinaryHeader,
+    mmap: Mmap,
+}
+
+impl BinaryDatastore {
+    /// Memory maps the binary datastore at the given path and checks its header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::UnsupportedVersion` if the file was written by a newer version of
+    /// this library, `DatastoreError::Corrupted` if it is not a valid binary datastore, and an IO
+    /// error if it can not be opened.
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() < HEADER_SIZE_V1 as u64 {
+            return Err(corrupted(path, "the file is shorter than the header"));
+        }
+        // SAFETY: the mapping is only read. Datastores are replaced by renaming a new file over
+        // them (see `write_file_atomically`), which does not change the mapped file.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let header = parse_header(path, &mmap)?;
+
+        Ok(BinaryDatastore {
+            path: path.to_string(),
+            header,
+            mmap,
+        })
+    }
+
+    pub fn header(&self) -> &BinaryHeader {
+        &self.header
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.header.record_count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn record(&self, index: usize) -> &[u8] {
+        assert!(index < self.len(), "record index {index} out of bounds");
+        let record_size = self.header.record_size as usize;
+        let start = header_size(self.header.format_version) + index * record_size;
+        &self.mmap[start..start + record_size]
+    }
+
+    fn heap_slice(&self, offset: u64, length: u32) -> Result<&[u8], Box<dyn Error>> {
+        let outside_of_heap = || corrupted(&self.path, "a record points outside of the heap");
+        let end = offset
+            .checked_add(u64::from(length))
+            .ok_or_else(outside_of_heap)?;
+        if end > self.header.heap_size {
+            return Err(outside_of_heap());
+        }
+        // `parse_header` checked that the heap ends at the end of the file
+        let start = self
+            .header
+            .heap_offset
+            .checked_add(offset)
+            .ok_or_else(outside_of_heap)? as usize;
+        Ok(&self.mmap[start..start + length as usize])
+    }
+
+    /// Returns the slot of the entry at the given index in a descriptor section, `None` if the
+    /// entry does not have the descriptor.
+    fn slot(
+        &self,
+        section: DescriptorSection,
+        value_size: usize,
+        index: usize,
+        present: bool,
+    ) -> Result<Option<&[u8]>, Box<dyn Error>> {
+        if !present {
+            return Ok(None);
+        }
+        if section.count == 0 {
+            return Err(corrupted(
+                &self.path,
+                "a record has a descriptor without a section",
+            ));
+        }
+        let slot_size = section.count as usize * value_size;
+        // `parse_header` checked that the sections lie inside the file
+        let start = section.offset as usize + index * slot_size;
+        Ok(Some(&self.mmap[start..start + slot_size]))
+    }
+
+    /// Returns the average brightness of the entry at the given index without decoding the entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.len()`.
+    pub fn average_brightness(&self, index: usize) -> f32 {
+        read_f32(self.record(index), 32)
+    }
+
+    /// Returns the filepath of the entry at the given index without copying it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::Corrupted` if the filepath is not stored correctly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.len()`.
+    pub fn filepath(&self, index: usize) -> Result<&str, Box<dyn Error>> {
+        let record = self.record(index);
+        let bytes = self.heap_slice(read_u64(record, 0), read_u32(record, 8))?;
+        std::str::from_utf8(bytes).map_err(|_| corrupted(&self.path, "a filepath is not UTF-8"))
+    }
+
+    /// Decodes the entry at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::Corrupted` if the entry is not stored correctly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.len()`.
+    pub fn get(&self, index: usize) -> Result<SearchIndex, Box<dyn Error>> {
+        let record = self.record(index);
+        let filepath = self.filepath(index)?.to_string();
+        let extras = self.heap_slice(read_u64(record, 12), read_u32(record, 20))?;
+        let content_hash = read_u64(record, 24);
+        let average_brightness = self.average_brightness(index);
+        let channel_count = usize::from(record[36]);
+        let flags = record[37];
+
+        if channel_count > usize::from(self.header.features.color_channels) {
+            return Err(corrupted(
+                &self.path,
+                "a record has too many color channels",
+            ));
+        }
+        let histograms_start = record.len() - histogram_values(&self.header.features) * 4;
+        let histograms = read_histograms(
+            &record[histograms_start..],
+            channel_count,
+            usize::from(self.header.features.bin_count),
+        );
+
+        let mut entry = if self.header.format_version < 2 {
+            self.decode_json_extras(filepath, extras, average_brightness, histograms)?
+        } else {
+            let mut entry = SearchIndex::new(filepath, average_brightness, histograms);
+            self.decode_fields(index, record, extras, &mut entry)?;
+            entry
+        };
+        if flags & FLAG_HAS_CONTENT_HASH != 0 {
+            entry.content_hash = format!("{content_hash:016x}");
+        }
+
+        Ok(entry)
+    }
+
+    /// Decodes the fields of an entry of format version 2 that are not part of
+    /// `SearchIndex::new`.
+    fn decode_fields(
+        &self,
+        index: usize,
+        record: &[u8],
+        extras: &[u8],
+        entry: &mut SearchIndex,
+    ) -> Result<(), Box<dyn Error>> {
+        let features = &self.header.features;
+        let sections = self.header.sections;
+        let flags = record[37];
+        let statistics_channels = usize::from(record[39]);
+        if statistics_channels > usize::from(features.color_channels) {
+            return Err(corrupted(
+                &self.path,
+                "a record has statistics of too many color channels",
+            ));
+        }
+
+        let (filename, tags) = self.decode_extras(extras, flags & FLAG_HAS_FILENAME != 0)?;
+        if let Some(filename) = filename {
+            entry.filename = filename;
+        }
+        entry.tags = tags;
+        entry.color_type = decode_color_type(record[38])
+            .ok_or_else(|| corrupted(&self.path, "a record has an unknown color type"))?;
+        entry.lines = read_u32(record, 40);
+        entry.columns = read_u32(record, 44);
+        entry.file_size = read_u64(record, 48);
+        entry.lightness = (flags & FLAG_HAS_LIGHTNESS != 0).then(|| read_f32(record, 56));
+
+        let grid_cells = self.slot(
+            sections.grid_cells,
+            4,
+            index,
+            flags & FLAG_HAS_GRID_CELLS != 0,
+        )?;
+        if let Some(slot) = grid_cells {
+            let cell_size = (GRID_CELL_CORE_VALUES + histogram_values(features)) * 4;
+            entry.grid_cells = slot
+                .chunks_exact(cell_size)
+                .map(|cell| GridCell {
+                    region: Rect::new(
+                        read_u32(cell, 0),
+                        read_u32(cell, 4),
+                        read_u32(cell, 8),
+                        read_u32(cell, 12),
+                    ),
+                    average_brightness: read_f32(cell, 16),
+                    histogram: read_histograms(
+                        &cell[GRID_CELL_CORE_VALUES * 4..],
+                        entry.histogram.len(),
+                        usize::from(features.bin_count),
+                    ),
+                })
+                .collect();
+        }
+
+        let statistics = self.slot(
+            sections.statistics,
+            4,
+            index,
+            flags & FLAG_HAS_STATISTICS != 0,
+        )?;
+        entry.statistics = statistics.map(|slot| {
+            let values = read_f32s(slot);
+            let (global, channels) = values.split_at(GLOBAL_STATISTICS_VALUES);
+            let channel_values = channels.len() / usize::from(features.color_channels);
+            ImageStatistics {
+                channels: channels
+                    .chunks_exact(channel_values)
+                    .take(statistics_channels)
+                    .map(|channel| ChannelStatistics {
+                        mean: channel[0],
+                        standard_deviation: channel[1],
+                        skewness: channel[2],
+                        min: channel[3],
+                        max: channel[4],
+                        percentiles: channel[CHANNEL_STATISTICS_CORE_VALUES..].to_vec(),
+                    })
+                    .collect(),
+                rms_contrast: global[0],
+                michelson_contrast: global[1],
+                colourfulness: global[2],
+                entropy: global[3],
+            }
+        });
+
+        let color_moments = self.slot(
+            sections.color_moments,
+            4,
+            index,
+            flags & FLAG_HAS_COLOR_MOMENTS != 0,
+        )?;
+        entry.color_moments = color_moments.map(|slot| {
+            let values = read_f32s(slot);
+            ColorMoments {
+                mean: [values[0], values[1], values[2]],
+                standard_deviation: [values[3], values[4], values[5]],
+                skewness: [values[6], values[7], values[8]],
+            }
+        });
+
+        let correlogram = self.slot(
+            sections.correlogram,
+            1,
+            index,
+            flags & FLAG_HAS_CORRELOGRAM != 0,
+        )?;
+        entry.correlogram = correlogram.map(|slot| {
+            let distances = self.header.correlogram_distances.clone();
+            AutoCorrelogram {
+                levels: self.header.correlogram_levels,
+                values: slot
+                    .chunks_exact(slot.len() / distances.len())
+                    .map(<[u8]>::to_vec)
+                    .collect(),
+                distances,
+            }
+        });
+
+        let shape = self.slot(sections.shape, 4, index, flags & FLAG_HAS_SHAPE != 0)?;
+        entry.shape = shape.map(|slot| {
+            let values = read_f32s(slot);
+            let (edge_directions, rest) = values.split_at(values.len() - SHAPE_CORE_VALUES);
+            ShapeDescriptor {
+                edge_directions: edge_directions.to_vec(),
+                edge_density: rest[0],
+                hu_moments: rest[1..].try_into().unwrap(),
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Decodes the extras of a record of format version 2: the filename, if it is stored, and the
+    /// tags.
+    fn decode_extras(
+        &self,
+        mut extras: &[u8],
+        has_filename: bool,
+    ) -> Result<(Option<String>, Vec<String>), Box<dyn Error>> {
+        let filename = if has_filename {
+            Some(self.split_string(&mut extras)?)
+        } else {
+            None
+        };
+        let mut tags = Vec::new();
+        if !extras.is_empty() {
+            for _ in 0..self.split_u32(&mut extras)? {
+                tags.push(self.split_string(&mut extras)?);
+            }
+        }
+        Ok((filename, tags))
+    }
+
+    fn split_u32(&self, bytes: &mut &[u8]) -> Result<u32, Box<dyn Error>> {
+        let Some((value, rest)) = bytes.split_first_chunk::<4>() else {
+            return Err(corrupted(&self.path, "the extras of a record are cut off"));
+        };
+        *bytes = rest;
+        Ok(u32::from_le_bytes(*value))
+    }
+
+    fn split_string(&self, bytes: &mut &[u8]) -> Result<String, Box<dyn Error>> {
+        let length = self.split_u32(bytes)? as usize;
+        if length > bytes.len() {
+            return Err(corrupted(&self.path, "the extras of a record are cut off"));
+        }
+        let (value, rest) = bytes.split_at(length);
+        *bytes = rest;
+        String::from_utf8(value.to_vec())
+            .map_err(|_| corrupted(&self.path, "the extras of a record are not UTF-8"))
+    }
+
+    /// Decodes an entry of format version 1, whose other fields are stored as JSON in the extras.
+    fn decode_json_extras(
+        &self,
+        filepath: String,
+        extras: &[u8],
+        average_brightness: f32,
+        histograms: Vec<Histogram>,
+    ) -> Result<SearchIndex, Box<dyn Error>> {
+        if extras.is_empty() {
+            return Ok(SearchIndex::new(filepath, average_brightness, histograms));
+        }
+        let mut fields = match serde_json::to_value(SearchIndex::default())? {
+            Value::Object(fields) => fields,
+            _ => Map::new(),
+        };
+        let extras: Map<String, Value> = serde_json::from_slice(extras)
+            .map_err(|err| corrupted(&self.path, &err.to_string()))?;
+        fields.insert(
+            "filename".to_string(),
+            Value::String(extract_filename(filepath.clone())),
+        );
+        fields.extend(extras);
+        fields.insert("filepath".to_string(), Value::String(filepath));
+        fields.insert(
+            "average_brightness".to_string(),
+            serde_json::to_value(average_brightness)?,
+        );
+        fields.insert("histogram".to_string(), serde_json::to_value(histograms)?);
+        serde_json::from_value(Value::Object(fields))
+            .map_err(|err| corrupted(&self.path, &err.to_string()))
+    }
+
+    /// Decodes all entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::Corrupted` if an entry is not stored correctly.
+    pub fn entries(&self) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        (0..self.len()).map(|index| self.get(index)).collect()
+    }
+}
+
+fn parse_header(path: &str, bytes: &[u8]) -> Result<BinaryHeader, Box<dyn Error>> {
+    if bytes[0..4] != BINARY_MAGIC {
+        return Err(corrupted(path, "the file is not a binary datastore"));
+    }
+    let format_version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if format_version > BINARY_FORMAT_VERSION {
+        return Err(Box::new(DatastoreError::UnsupportedVersion {
+            path: path.to_string(),
+            found: u32::from(format_version),
+            supported: u32::from(BINARY_FORMAT_VERSION),
+        }));
+    }
+    let header_size = header_size(format_version);
+    if usize::from(u16::from_le_bytes([bytes[6], bytes[7]])) != header_size {
+        return Err(corrupted(path, "unexpected header size"));
+    }
+    if bytes.len() < header_size {
+        return Err(corrupted(path, "the file is shorter than the header"));
+    }
+    let schema_version = match u16::from_le_bytes([bytes[10], bytes[11]]) {
+        // the first binary datastores did not store it yet
+        0 => 2,
+        schema_version => u32::from(schema_version),
+    };
+    if schema_version > SCHEMA_VERSION {
+        return Err(Box::new(DatastoreError::UnsupportedVersion {
+            path: path.to_string(),
+            found: schema_version,
+            supported: SCHEMA_VERSION,
+        }));
+    }
+
+    let descriptors = match bytes[42] {
+        0 => DescriptorSet::Full,
+        1 => DescriptorSet::Compact,
+        _ => return Err(corrupted(path, "unknown descriptor set")),
+    };
+    let (correlogram_levels, correlogram_distances, sections) = if format_version < 2 {
+        (0, Vec::new(), DescriptorSections::default())
+    } else {
+        let distance_count = usize::from(bytes[44]);
+        if distance_count > MAX_CORRELOGRAM_DISTANCES {
+            return Err(corrupted(path, "too many correlogram distances"));
+        }
+        let section = |number: usize| DescriptorSection {
+            offset: read_u64(bytes, 64 + number * 12),
+            count: read_u32(bytes, 72 + number * 12),
+        };
+        (
+            bytes[43],
+            (0..distance_count)
+                .map(|distance| read_u32(bytes, 48 + distance * 4))
+                .collect(),
+            DescriptorSections {
+                grid_cells: section(0),
+                statistics: section(1),
+                color_moments: section(2),
+                correlogram: section(3),
+                shape: section(4),
+            },
+        )
+    };
+    let header = BinaryHeader {
+        format_version,
+        schema_version,
+        features: FeatureConfiguration {
+            bin_count: bytes[8],
+            color_channels: bytes[9],
+            descriptor_version: u16::from_le_bytes([bytes[40], bytes[41]]),
+            descriptors,
+        },
+        record_size: read_u32(bytes, 12),
+        record_count: read_u64(bytes, 16),
+        heap_offset: read_u64(bytes, 24),
+        heap_size: read_u64(bytes, 32),
+        correlogram_levels,
+        correlogram_distances,
+        sections,
+    };
+
+    if header.record_size as usize != record_size(format_version, &header.features) {
+        return Err(corrupted(path, "unexpected record size"));
+    }
+    let mut sections_end = header
+        .record_count
+        .checked_mul(u64::from(header.record_size))
+        .and_then(|records_size| records_size.checked_add(header_size as u64));
+    if format_version >= 2 {
+        check_section_counts(path, &header)?;
+        for (section, value_size) in header.sections.with_value_sizes() {
+            if sections_end != Some(section.offset) {
+                return Err(corrupted(path, "the file is truncated"));
+            }
+            sections_end = header
+                .record_count
+                .checked_mul(u64::from(section.count) * value_size as u64)
+                .and_then(|section_size| section_size.checked_add(section.offset));
+        }
+    }
+    if sections_end != Some(header.heap_offset)
+        || header.heap_offset.checked_add(header.heap_size) != Some(bytes.len() as u64)
+    {
+        return Err(corrupted(path, "the file is truncated"));
+    }
+
+    Ok(header)
+}
+
+/// Checks that the values per entry of the descriptor sections fit the descriptors they hold, so
+/// decoding a slot can not fail.
+fn check_section_counts(path: &str, header: &BinaryHeader) -> Result<(), Box<dyn Error>> {
+    let sections = &header.sections;
+    let color_channels = usize::from(header.features.color_channels);
+    let grid_cells = sections.grid_cells.count as usize;
+    let statistics = sections.statistics.count as usize;
+    let correlogram = sections.correlogram.count as usize;
+    let shape = sections.shape.count as usize;
+
+    let fits = grid_cells
+        .is_multiple_of(GRID_CELL_CORE_VALUES + histogram_values(&header.features))
+        && (statistics == 0
+            || (color_channels > 0
+                && statistics
+                    >= GLOBAL_STATISTICS_VALUES + color_channels * CHANNEL_STATISTICS_CORE_VALUES
+                && (statistics - GLOBAL_STATISTICS_VALUES).is_multiple_of(color_channels)))
+        && matches!(
+            sections.color_moments.count as usize,
+            0 | COLOR_MOMENTS_VALUES
+        )
+        && (correlogram == 0
+            || ((1..=MAX_CORRELOGRAM_LEVELS).contains(&header.correlogram_levels)
+                && correlogram
+                    == header.correlogram_distances.len()
+                        * usize::from(header.correlogram_levels).pow(3)))
+        && (shape == 0 || shape >= SHAPE_CORE_VALUES);
+    if !fits {
+        return Err(corrupted(path, "unexpected descriptor section size"));
+    }
+    Ok(())
+}
+
+fn read_histograms(bytes: &[u8], channel_count: usize, bin_count: usize) -> Vec<Histogram> {
+    (0..channel_count)
+        .map(|channel| Histogram {
+            bins: (0..bin_count)
+                .map(|bin| read_u32(bytes, (channel * bin_count + bin) * 4))
+                .collect(),
+        })
+        .collect()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_f32s(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+        .collect()
+}
+
+fn corrupted(path: &str, reason: &str) -> Box<dyn Error> {
+    Box::new(DatastoreError::Corrupted {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+/// A binary datastore that can be changed, see `Datastore`.
+///
+/// The entries are read once when the datastore is opened. Changes are buffered in memory until
+/// `flush` or `modify` is called, which apply them to the current content of the file while
+/// holding the `DatastoreLock` and write the whole file again.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::binary_datastore::BinaryFileDatastore;
+/// use imsearch::datastore::Datastore;
+/// use imsearch::search_index::SearchIndex;
+///
+/// let path = std::env::temp_dir().join("imsearch_doc_binary_file_datastore.imsb");
+/// let path = path.to_str().unwrap();
+/// # std::fs::remove_file(path).ok();
+///
+/// let mut datastore = BinaryFileDatastore::open(path).unwrap();
+/// datastore
+///     .insert(SearchIndex::new("pictures/bird.png".to_string(), 0.4, vec![]))
+///     .unwrap();
+/// datastore.flush().unwrap();
+///
+/// assert!(BinaryDatastore::open(path).is_ok());
+/// # use imsearch::binary_datastore::BinaryDatastore;
+/// ```
+#[derive(Debug)]
+pub struct BinaryFileDatastore {
+    path: String,
+    features: FeatureConfiguration,
+    buffer: ChangeBuffer,
+}
+
+impl BinaryFileDatastore {
+    /// Opens the binary datastore at the given path. A file that does not exist yet is created by
+    /// the first `flush`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::NeedsMigration` if the datastore was written by an older version
+    /// of this library (see `migrate_binary_datastore`), and an error if the file exists but can
+    /// not be read.
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let (features, entries) = read_binary_entries(path)?;
+        Ok(BinaryFileDatastore {
+            path: path.to_string(),
+            features,
+            buffer: ChangeBuffer::new(entries),
+        })
+    }
+}
+
+impl Datastore for BinaryFileDatastore {
+    fn insert(&mut self, entry: SearchIndex) -> Result<(), Box<dyn Error>> {
+        self.buffer.insert(entry);
+        Ok(())
+    }
+
+    fn get(&self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        Ok(self.buffer.get(filepath))
+    }
+
+    fn remove(&mut self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        Ok(self.buffer.remove(filepath))
+    }
+
+    fn entries(&self) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        Ok(self.buffer.entries().to_vec())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.buffer.has_changes() {
+            return Ok(());
+        }
+        self.modify(&mut |_| Ok(()))
+    }
+
+    /// Applies the modification to the current content of the file while holding the
+    /// `DatastoreLock` and writes the whole file again.
+    fn modify(&mut self, modification: &mut Modification) -> Result<(), Box<dyn Error>> {
+        let _lock = DatastoreLock::acquire(&self.path)?;
+        let (features, mut entries) = read_binary_entries(&self.path)?;
+        self.buffer.apply_to(&mut entries);
+        modification(&mut entries)?;
+        for entry in &mut entries {
+            features.keep_stored_descriptors(entry);
+        }
+        write_binary_datastore(&self.path, &entries, &features)?;
+        self.features = features;
+        self.buffer = ChangeBuffer::new(entries);
+        Ok(())
+    }
+
+    fn len(&self) -> Result<usize, Box<dyn Error>> {
+        Ok(self.buffer.entries().len())
+    }
+
+    fn features(&self) -> FeatureConfiguration {
+        self.features.clone()
+    }
+}
+
+/// Reads the `FeatureConfiguration` and the entries of the binary datastore at the given path. A
+/// file that does not exist counts as an empty datastore with the default configuration. An
+/// outdated datastore is not migrated, but returns `DatastoreError::NeedsMigration`.
+fn read_binary_entries(
+    path: &str,
+) -> Result<(FeatureConfiguration, Vec<SearchIndex>), Box<dyn Error>> {
+    if !is_file(path) {
+        return Ok((FeatureConfiguration::default(), Vec::new()));
+    }
+    let datastore = BinaryDatastore::open(path)?;
+    if datastore.header().needs_migration() {
+        return Err(needs_migration(path));
+    }
+    Ok((datastore.header().features.clone(), datastore.entries()?))
+}
+
+/// Migrates the binary datastore at the given path to the current `SCHEMA_VERSION` and
+/// `FeatureConfiguration`, see `migrate_entries`.
+///
+/// # Returns
+///
+/// What was migrated, or `None` if the datastore was already up to date.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read, migrated or written.
+pub fn migrate_binary_datastore(path: &str) -> Result<Option<MigrationSummary>, Box<dyn Error>> {
+    let _lock = DatastoreLock::acquire(path)?;
+    let datastore = BinaryDatastore::open(path)?;
+    let header = datastore.header().clone();
+    if !header.needs_migration() {
+        return Ok(None);
+    }
+
+    let entries = datastore
+        .entries()?
+        .into_iter()
+        .map(serde_json::to_value)
+        .collect::<Result<_, _>>()?;
+    let (entries, summary) = migrate_entries(entries, header.schema_version, &header.features)?;
+    let entries: Vec<SearchIndex> = serde_json::from_value(Value::Array(entries))?;
+    drop(datastore);
+    write_binary_datastore(path, &entries, &header.features.current())?;
+    Ok(Some(summary))
+}
+
+/// Makes the binary datastore at the given path compact, see `compact_datastore_at`.
+///
+/// # Returns
+///
+/// The number of entries.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written, and
+/// `DatastoreError::NeedsMigration` if it has to be migrated first.
+pub fn compact_binary_datastore(path: &str) -> Result<usize, Box<dyn Error>> {
+    let _lock = DatastoreLock::acquire(path)?;
+    let (_, entries) = read_binary_entries(path)?;
+    write_binary_datastore(path, &entries, &FeatureConfiguration::compact())?;
+    Ok(entries.len())
+}
+
+/// Converts a JSON datastore to a binary datastore.
+///
+/// # Returns
+///
+/// The number of converted entries.
+///
+/// # Errors
+///
+/// Returns an error if the JSON datastore can not be read or the binary datastore can not be
+/// written.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::binary_datastore::{convert_binary_to_json, convert_json_to_binary};
+///
+/// let binary_path = std::env::temp_dir().join("imsearch_doc_convert.imsb");
+/// let json_path = std::env::temp_dir().join("imsearch_doc_convert.json");
+/// let (binary_path, json_path) = (binary_path.to_str().unwrap(), json_path.to_str().unwrap());
+///
+/// convert_json_to_binary("src/tests/files/DataStoreJSON/data.json", binary_path).unwrap();
+/// convert_binary_to_json(binary_path, json_path).unwrap();
+/// ```
+pub fn convert_json_to_binary(json_path: &str, binary_path: &str) -> Result<usize, Box<dyn Error>> {
+    let (features, entries): (_, Vec<SearchIndex>) = read_stored_datastore_file(json_path)?;
+    let _lock = DatastoreLock::acquire(binary_path)?;
+    write_binary_datastore(binary_path, &entries, &features)?;
+    Ok(entries.len())
+}
+
+/// Converts a binary datastore to a JSON datastore.
+///
+/// # Returns
+///
+/// The number of converted entries.
+///
+/// # Errors
+///
+/// Returns an error if the binary datastore can not be read or the JSON datastore can not be
+/// written.
+pub fn convert_binary_to_json(binary_path: &str, json_path: &str) -> Result<usize, Box<dyn Error>> {
+    let (features, entries) = read_binary_entries(binary_path)?;
+    let _lock = DatastoreLock::acquire(json_path)?;
+    write_datastore_file(json_path, &entries, &features)?;
+    Ok(entries.len())
+}
@@ -0,0 +1,532 @@
+use crate::ann_index::{rebuild_ann_index, AnnSearchOptions, UnreadableAnnIndex};
+use crate::batch_search::{batch_search, batch_search_to_file, collect_queries};
+use crate::binary_datastore::{
+    convert_binary_to_json, convert_json_to_binary, is_binary_datastore,
+};
+use crate::datastore::{
+    migrate_datastore_at, open_datastore, prune_missing_files, recover_datastore, remove_by_path,
+    remove_where, set_tags, update_entry,
+};
+use crate::file_handler::format_filepath;
+use crate::get_datastore_path;
+use crate::migration::SCHEMA_VERSION;
+use crate::multi_query::{FusionStrategy, MultiQuery, DEFAULT_NEGATIVE_WEIGHT};
+use crate::orientation::search_invariant;
+use crate::print_calculated_similar_pictures;
+use crate::query_descriptor::SearchProfile;
+use crate::region::{search_region, search_region_anywhere, Rect};
+use crate::search::{search_with_profile, SearchOptions};
+use crate::search_index::{analyse_pictures, try_generate_suchindex};
+#[cfg(feature = "sqlite")]
+use crate::sqlite_datastore::{
+    convert_json_to_sqlite, convert_sqlite_to_json, has_sqlite_extension, is_sqlite_datastore,
+};
+use crate::sync::{sync_datastore, sync_library};
+use std::error::Error;
+
+/// A command given on the command line.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Add a picture or all pictures of a folder to the datastore.
+    Index(String),
+    /// Print the pictures most similar to a picture.
+    Search {
+        picture: String,
+        options: SearchOptions,
+        profile: SearchProfile,
+    },
+    /// Print the pictures most similar to a picture in any orientation, with the orientation of
+    /// the picture that matched best.
+    InvariantSearch {
+        picture: String,
+        options: SearchOptions,
+    },
+    /// Print the pictures most similar to a region of a picture, comparing it with whole pictures
+    /// or, with `anywhere`, with the grid cells of the pictures.
+    RegionSearch {
+        picture: String,
+        region: Rect,
+        anywhere: bool,
+        options: SearchOptions,
+    },
+    /// Print the pictures most similar to several pictures and unlike others.
+    MultiSearch {
+        positives: Vec<String>,
+        negatives: Vec<String>,
+        strategy: FusionStrategy,
+        negative_weight: f64,
+        options: SearchOptions,
+    },
+    /// Search the pictures most similar to each of many pictures, optionally writing the results
+    /// to a file as JSON lines.
+    BatchSearch {
+        pictures: Vec<String>,
+        options: SearchOptions,
+        output: Option<String>,
+    },
+    /// Synchronize the datastore with the given folders, or the configured ones if none are given.
+    Sync(Vec<String>),
+    /// Remove the entry of a picture.
+    Remove(String),
+    /// Remove the entries of all pictures inside a folder.
+    RemoveFolder(String),
+    /// Index a picture again and replace its entry.
+    Update(String),
+    /// Replace the tags of a picture. Without tags, the tags of the picture are removed.
+    Tag(String, Vec<String>),
+    /// Remove the entries of all pictures that do not exist anymore.
+    Prune,
+    /// Restore a corrupted datastore.
+    Recover,
+    /// Convert a JSON datastore to a binary or SQLite datastore or vice versa.
+    Convert(String, String),
+    /// Migrate the given datastore, or the configured one if none is given, to the current schema.
+    Migrate(Option<String>),
+    /// Print the usage.
+    Help,
+}
+
+pub const USAGE: &str = "Usage: imsearch [COMMAND]
+
+Without a command, imsearch asks for the pictures interactively.
+
+Commands:
+  index <path>            Add a picture or all pictures of a folder to the datastore
+  search <picture> [--limit <n>] [--offset <n>] [--min-similarity <s>]
+         [--profile colour|compact|shape|structure] [--approximate]
+                          Print the pictures most similar to a picture (default: the five best);
+                          the profile selects what makes pictures similar (default: colour),
+                          --approximate only compares the candidates of the ANN index
+  search <picture> --invariant [--limit <n>] ...
+                          Print the pictures most similar to the picture turned or mirrored in
+                          any way, with the orientation that matched best
+  search <pictures...> [--not <picture>]... [--strategy centroid|max|average]
+         [--negative-weight <w>] [--limit <n>] [--offset <n>] [--min-similarity <s>]
+                          Print the pictures most similar to all pictures but unlike the --not ones
+  search <picture> --region <x,y,width,height> [--anywhere] [--limit <n>] ...
+                          Print the pictures most similar to a region of the picture; with
+                          --anywhere the region may match any part of the pictures
+  batch-search <pictures or folders...> [--limit <n>] [--offset <n>] [--min-similarity <s>]
+               [--output <file>]
+                          Search many pictures at once, writing JSON lines to the output file if given
+  sync [folders...]       Synchronize the datastore with the folders (default: IMSEARCH_LIBRARY_ROOTS)
+  remove <picture>        Remove the entry of a picture
+  remove-folder <folder>  Remove the entries of all pictures inside a folder
+  update <picture>        Index a picture again and replace its entry
+  tag <picture> [tags...] Replace the tags of a picture (without tags: remove them)
+  prune                   Remove the entries of all pictures that do not exist anymore
+  recover                 Restore a corrupted datastore from its readable entries and backup
+  convert <from> <to>     Convert a JSON datastore to a binary or SQLite (.sqlite, .db) datastore
+                          or vice versa; SQLite needs the sqlite feature
+  migrate [datastore]     Migrate a datastore (default: the configured one) to the current schema
+  help                    Print this message";
+
+/// Parses the command line arguments (without the program name) into a `Command`.
+///
+/// # Errors
+///
+/// Returns a message describing the problem if the arguments are not a valid command.
+///
+/// # Examples
+///
+/// ```
+/// use imsearch::cli::{parse_command, Command};
+///
+/// let args = vec!["remove".to_string(), "pictures/bird.png".to_string()];
+/// assert_eq!(
+///     parse_command(&args),
+///     Ok(Command::Remove("pictures/bird.png".to_string()))
+/// );
+/// ```
+pub fn parse_command(args: &[String]) -> Result<Command, String> {
+    let Some((name, arguments)) = args.split_first() else {
+        return Err("No command given.".to_string());
+    };
+
+    let single_argument = |argument_name: &str| match arguments {
+        [argument] => Ok(argument.clone()),
+        _ => Err(format!(
+            "'{name}' expects exactly one argument: <{argument_name}>"
+        )),
+    };
+    let no_argument = |command: Command| match arguments {
+        [] => Ok(command),
+        _ => Err(format!("'{name}' expects no arguments")),
+    };
+
+    match name.as_str() {
+        "index" => single_argument("path").map(Command::Index),
+        "search" => parse_search(arguments),
+        "batch-search" => parse_batch_search(arguments),
+        "sync" => Ok(Command::Sync(arguments.to_vec())),
+        "remove" => single_argument("picture").map(Command::Remove),
+        "remove-folder" => single_argument("folder").map(Command::RemoveFolder),
+        "update" => single_argument("picture").map(Command::Update),
+        "tag" => match arguments {
+            [picture, tags @ ..] => Ok(Command::Tag(picture.clone(), tags.to_vec())),
+            [] => Err(format!(
+                "'{name}' expects a picture and its tags: <picture> [tags...]"
+            )),
+        },
+        "prune" => no_argument(Command::Prune),
+        "recover" => no_argument(Command::Recover),
+        "convert" => match arguments {
+            [source, target] => Ok(Command::Convert(source.clone(), target.clone())),
+            _ => Err(format!("'{name}' expects two arguments: <from> <to>")),
+        },
+        "migrate" => match arguments {
+            [] => Ok(Command::Migrate(None)),
+            [datastore] => Ok(Command::Migrate(Some(datastore.clone()))),
+            _ => Err(format!(
+                "'{name}' expects at most one argument: [datastore]"
+            )),
+        },
+        "help" | "--help" | "-h" => no_argument(Command::Help),
+        _ => Err(format!("Unknown command '{name}'.")),
+    }
+}
+
+/// The arguments of the `search` and `batch-search` commands.
+struct SearchArguments {
+    pictures: Vec<String>,
+    negatives: Vec<String>,
+    strategy: Option<FusionStrategy>,
+    negative_weight: Option<f64>,
+    options: SearchOptions,
+    output: Option<String>,
+    region: Option<Rect>,
+    anywhere: bool,
+    invariant: bool,
+    profile: Option<SearchProfile>,
+}
+
+/// Parses the arguments of the `search` command.
+fn parse_search(arguments: &[String]) -> Result<Command, String> {
+    let arguments = parse_search_arguments("search", arguments)?;
+    if arguments.output.is_some() {
+        return Err("Unknown option '--output' for 'search'".to_string());
+    }
+    if arguments.anywhere && arguments.region.is_none() {
+        return Err("'--anywhere' needs a '--region'".to_string());
+    }
+    let is_multi_search = arguments.negatives.len() + arguments.pictures.len() > 1
+        || arguments.strategy.is_some()
+        || arguments.negative_weight.is_some();
+    match arguments.pictures.as_slice() {
+        [] => Err("'search' expects at least one picture".to_string()),
+        _ if is_multi_search && arguments.region.is_some() => {
+            Err("'--region' can only be used with a single picture".to_string())
+        }
+        _ if arguments.invariant && (is_multi_search || arguments.region.is_some()) => {
+            Err("'--invariant' can only be used with a single picture and no region".to_string())
+        }
+        _ if arguments.profile.is_some()
+            && (is_multi_search || arguments.region.is_some() || arguments.invariant) =>
+        {
+            Err(
+                "'--profile' can only be used with a single picture and no region or '--invariant'"
+                    .to_string(),
+            )
+        }
+        _ if arguments.options.approximate.is_some()
+            && (is_multi_search
+                || arguments.region.is_some()
+                || arguments.invariant
+                || arguments.profile.unwrap_or_default() != SearchProfile::Colour) =>
+        {
+            Err(
+                "'--approximate' can only be used with a single picture and the colour profile"
+                    .to_string(),
+            )
+        }
+        [picture] if arguments.invariant => Ok(Command::InvariantSearch {
+            picture: picture.clone(),
+            options: arguments.options,
+        }),
+        [picture] if arguments.region.is_some() => Ok(Command::RegionSearch {
+            picture: picture.clone(),
+            region: arguments.region.unwrap_or_default(),
+            anywhere: arguments.anywhere,
+            options: arguments.options,
+        }),
+        [picture] if !is_multi_search => Ok(Command::Search {
+            picture: picture.clone(),
+            options: arguments.options,
+            profile: arguments.profile.unwrap_or_default(),
+        }),
+        _ => Ok(Command::MultiSearch {
+            positives: arguments.pictures,
+            negatives: arguments.negatives,
+            strategy: arguments.strategy.unwrap_or(FusionStrategy::Centroid),
+            negative_weight: arguments.negative_weight.unwrap_or(DEFAULT_NEGATIVE_WEIGHT),
+            options: arguments.options,
+        }),
+    }
+}
+
+/// Parses the arguments of the `batch-search` command.
+fn parse_batch_search(arguments: &[String]) -> Result<Command, String> {
+    let arguments = parse_search_arguments("batch-search", arguments)?;
+    if !arguments.negatives.is_empty()
+        || arguments.strategy.is_some()
+        || arguments.negative_weight.is_some()
+    {
+        return Err("'batch-search' does not support negative pictures".to_string());
+    }
+    if arguments.region.is_some() || arguments.anywhere {
+        return Err("'batch-search' does not support regions".to_string());
+    }
+    if arguments.invariant {
+        return Err("'batch-search' does not support '--invariant'".to_string());
+    }
+    if arguments.profile.is_some() {
+        return Err("'batch-search' does not support '--profile'".to_string());
+    }
+    if arguments.options.approximate.is_some() {
+        return Err("'batch-search' does not support '--approximate'".to_string());
+    }
+    if arguments.pictures.is_empty() {
+        return Err("'batch-search' expects at least one picture or folder".to_string());
+    }
+    Ok(Command::BatchSearch {
+        pictures: arguments.pictures,
+        options: arguments.options,
+        output: arguments.output,
+    })
+}
+
+/// Splits the arguments of a search command into the pictures and the values of the options.
+fn parse_search_arguments(name: &str, mut arguments: &[String]) -> Result<SearchArguments, String> {
+    let mut parsed = SearchArguments {
+        pictures: Vec::new(),
+        negatives: Vec::new(),
+        strategy: None,
+        negative_weight: None,
+        options: SearchOptions::default(),
+        output: None,
+        region: None,
+        anywhere: false,
+        invariant: false,
+        profile: None,
+    };
+    while let [argument, rest @ ..] = arguments {
+        if argument == "--anywhere" {
+            parsed.anywhere = true;
+            arguments = rest;
+            continue;
+        }
+        if argument == "--invariant" {
+            parsed.invariant = true;
+            arguments = rest;
+            continue;
+        }
+        if argument == "--approximate" {
+            parsed.options.approximate = Some(AnnSearchOptions::default());
+            arguments = rest;
+            continue;
+        }
+        if !argument.starts_with("--") {
+            parsed.pictures.push(argument.clone());
+            arguments = rest;
+            continue;
+        }
+        let [value, rest @ ..] = rest else {
+            return Err(format!("'{argument}' expects a value"));
+        };
+        let invalid_value = format!("Invalid value '{value}' for {argument}");
+        let options = &mut parsed.options;
+        match argument.as_str() {
+            "--limit" => options.limit = value.parse().map_err(|_| invalid_value)?,
+            "--offset" => options.offset = value.parse().map_err(|_| invalid_value)?,
+            "--min-similarity" => {
+                options.min_similarity = Some(value.parse().map_err(|_| invalid_value)?)
+            }
+            "--not" => parsed.negatives.push(value.clone()),
+            "--strategy" => parsed.strategy = Some(value.parse()?),
+            "--negative-weight" => {
+                parsed.negative_weight = Some(value.parse().map_err(|_| invalid_value)?)
+            }
+            "--output" => parsed.output = Some(value.clone()),
+            "--region" => parsed.region = Some(value.parse()?),
+            "--profile" => parsed.profile = Some(value.parse()?),
+            _ => return Err(format!("Unknown option '{argument}' for '{name}'")),
+        }
+        arguments = rest;
+    }
+    Ok(parsed)
+}
+
+/// Executes a `Command` on the configured datastore and prints the result.
+///
+/// # Errors
+///
+/// Returns an error if the underlying datastore operation fails.
+pub fn run_command(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Index(path) => analyse_pictures(&path)?,
+        Command::Search {
+            picture,
+            options,
+            profile,
+        } => {
+            let similar_pictures = match search_with_profile(&picture, &options, profile) {
+                Err(err) if err.downcast_ref::<UnreadableAnnIndex>().is_some() => {
+                    eprintln!("{err} It is built again.");
+                    rebuild_ann_index(&get_datastore_path()?)?;
+                    search_with_profile(&picture, &options, profile)?
+                }
+                result => result?,
+            };
+            print_calculated_similar_pictures(similar_pictures)
+        }
+        Command::InvariantSearch { picture, options } => {
+            print_calculated_similar_pictures(search_invariant(&picture, &options)?)
+        }
+        Command::RegionSearch {
+            picture,
+            region,
+            anywhere,
+            options,
+        } => {
+            if anywhere {
+                for region_match in search_region_anywhere(&picture, &region, &options)? {
+                    region_match.print();
+                }
+            } else {
+                print_calculated_similar_pictures(search_region(&picture, &region, &options)?)
+            }
+        }
+        Command::MultiSearch {
+            positives,
+            negatives,
+            strategy,
+            negative_weight,
+            options,
+        } => {
+            let query = MultiQuery::from_pictures(&positives, &negatives)?
+                .with_strategy(strategy)
+                .with_negative_weight(negative_weight);
+            print_calculated_similar_pictures(query.search(&options)?)
+        }
+        Command::BatchSearch {
+            pictures,
+            options,
+            output,
+        } => {
+            let queries = collect_queries(&pictures)?;
+            match output {
+                Some(output) => {
+                    let failed_count = batch_search_to_file(&queries, &options, &output)?;
+                    println!(
+                        "Searched {} pictures, {failed_count} could not be read. Results: {output}",
+                        queries.len()
+                    );
+                }
+                None => {
+                    let batch = batch_search(&queries, &options)?;
+                    for (query, results) in batch.results {
+                        println!("Results for {query}:");
+                        print_calculated_similar_pictures(results);
+                    }
+                    for (query, message) in batch.failed {
+                        eprintln!("{query} could not be searched: {message}");
+                    }
+                }
+            }
+        }
+        Command::Sync(folders) => {
+            let summary = if folders.is_empty() {
+                sync_library()?
+            } else {
+                let folders: Vec<&str> = folders.iter().map(String::as_str).collect();
+                sync_datastore(&folders)?
+            };
+            summary.print();
+        }
+        Command::Remove(picture) => {
+            let removed = remove_by_path(&picture)?;
+            println!("Removed {} entries.", removed.len());
+        }
+        Command::RemoveFolder(folder) => {
+            let mut folder = format_filepath(&folder);
+            if !folder.ends_with('/') {
+                folder.push('/');
+            }
+            let removed = remove_where(|entry| entry.filepath.starts_with(&folder))?;
+            println!("Removed {} entries.", removed.len());
+        }
+        Command::Update(picture) => {
+            let features = open_datastore(&get_datastore_path()?)?.features();
+            let entry = try_generate_suchindex(format_filepath(&picture), &features)?;
+            if update_entry(entry)? {
+                println!("Updated the entry of {picture}.");
+            } else {
+                return Err(format!("{picture} is not indexed. Use 'index' to add it.").into());
+            }
+        }
+        Command::Tag(picture, tags) => {
+            let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+            if set_tags(&picture, &tags)? {
+                println!("Tagged {picture} with {}.", tags.join(", "));
+            } else {
+                return Err(format!("{picture} is not indexed. Use 'index' to add it.").into());
+            }
+        }
+        Command::Prune => {
+            let removed = prune_missing_files()?;
+            for entry in &removed {
+                println!("  - {}", entry.filepath);
+            }
+            println!("Removed {} entries.", removed.len());
+        }
+        Command::Recover => {
+            let entry_count = recover_datastore()?;
+            println!("The datastore contains {entry_count} entries.");
+        }
+        Command::Convert(source, target) => {
+            let entry_count = convert_datastore(&source, &target)?;
+            println!("Converted {entry_count} entries.");
+        }
+        Command::Migrate(datastore) => {
+            let datastore = match datastore {
+                Some(datastore) => datastore,
+                None => get_datastore_path()?,
+            };
+            match migrate_datastore_at(&datastore)? {
+                Some(summary) => {
+                    if summary.previous_version == SCHEMA_VERSION {
+                        println!("Indexed {datastore} again with the current descriptors.");
+                    } else {
+                        println!(
+                            "Migrated {datastore} from schema {} to {SCHEMA_VERSION}.",
+                            summary.previous_version
+                        );
+                    }
+                    summary.print();
+                }
+                None => println!("{datastore} is already up to date."),
+            }
+        }
+        Command::Help => println!("{USAGE}"),
+    }
+    Ok(())
+}
+
+/// Converts between a JSON datastore and a binary or SQLite datastore, depending on the format of
+/// `source` and the file extension of `target`.
+fn convert_datastore(source: &str, target: &str) -> Result<usize, Box<dyn Error>> {
+    #[cfg(feature = "sqlite")]
+    {
+        if is_sqlite_datastore(source) {
+            return convert_sqlite_to_json(source, target);
+        }
+        if has_sqlite_extension(target) {
+            return convert_json_to_sqlite(source, target);
+        }
+    }
+    if is_binary_datastore(source) {
+        convert_binary_to_json(source, target)
+    } else {
+        convert_json_to_binary(source, target)
+    }
+}
@@ -0,0 +1,186 @@
+use crate::color_type::{rgb_to_hsv, ColorType};
+use crate::picture::Sample;
+use crate::picture_view::AsPictureView;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::f64::consts::TAU;
+
+/// The colour moments of a picture in HSV: the mean, the standard deviation and the skewness of
+/// the hue, the saturation and the value, 9 floats in total.
+///
+/// They are a compact alternative to the histograms (Stricker and Orengo, "Similarity of Color
+/// Images"): much smaller to store and faster to compare, but less accurate.
+///
+/// # Fields
+///
+/// * `mean`: The mean of hue, saturation and value, all in `[0.0, 1.0]`.
+/// * `standard_deviation`: The standard deviation of hue, saturation and value.
+/// * `skewness`: The cube root of the third central moment of hue, saturation and value, so it has
+///   the same unit as the other moments. It is negative if most values lie above the mean.
+///
+/// The hue is an angle, so its moments are circular: the mean is the mean direction of the hues,
+/// and the standard deviation and the skewness are computed from the differences to it, wrapped to
+/// `[-0.5, 0.5)`. Reds on both ends of the hue range are close to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ColorMoments {
+    pub mean: [f32; 3],
+    pub standard_deviation: [f32; 3],
+    pub skewness: [f32; 3],
+}
+
+impl ColorMoments {
+    /// Returns the moments as values in `[0.0, 1.0]` that can be compared with each other: the
+    /// standard deviation is doubled and the skewness shifted and doubled.
+    pub(crate) fn feature_vector(&self) -> Vec<f64> {
+        let mut features = Vec::with_capacity(9);
+        features.extend(self.mean.iter().map(|mean| f64::from(*mean)));
+        features.extend(
+            self.standard_deviation
+                .iter()
+                .map(|deviation| f64::from(2.0 * deviation).min(1.0)),
+        );
+        features.extend(
+            self.skewness
+                .iter()
+                .map(|skewness| f64::from(0.5 + skewness).clamp(0.0, 1.0)),
+        );
+        features
+    }
+}
+
+/// Returns the distance of two pictures' colour moments: the sum of the differences of their
+/// normalized moments (see `ColorMoments`), from 0 (same moments) to 9. The difference of the
+/// hue means is measured around the hue circle and doubled, so opposite hues are 1 apart.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_moments::{color_moments_distance, ColorMoments};
+///
+/// let black = ColorMoments::default();
+/// let mut gray = black;
+/// gray.mean[2] = 0.5;
+///
+/// assert_eq!(color_moments_distance(&black, &black), 0.0);
+/// assert_eq!(color_moments_distance(&black, &gray), 0.5);
+/// ```
+pub fn color_moments_distance(a: &ColorMoments, b: &ColorMoments) -> f64 {
+    feature_distance(&a.feature_vector(), &b.feature_vector())
+}
+
+/// Returns the similarity of two pictures' colour moments, `1 - ` their distance divided by 9, so
+/// it can be weighted like the other features.
+pub fn compare_color_moments(a: &ColorMoments, b: &ColorMoments) -> f64 {
+    compare_color_moment_features(&a.feature_vector(), &b.feature_vector()).unwrap_or(0.0)
+}
+
+/// Compares the feature vectors of two pictures' colour moments like `compare_color_moments`,
+/// `None` if their lengths differ or they are empty.
+pub(crate) fn compare_color_moment_features(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    Some(1.0 - feature_distance(a, b) / a.len() as f64)
+}
+
+fn feature_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .enumerate()
+        .map(|(index, (a, b))| {
+            let difference = (a - b).abs();
+            // the first feature is the mean hue
+            if index == 0 {
+                2.0 * difference.min(1.0 - difference)
+            } else {
+                difference
+            }
+        })
+        .sum()
+}
+
+/// Computes the colour moments of a picture or view in two passes over its pixels, the second one
+/// for the moments of the hue around its mean.
+///
+/// # Errors
+///
+/// Returns an error if the picture has no pixels or its colour model is not known
+/// (`ColorType::Channels`), because it can not be converted to HSV.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_moments::compute_color_moments;
+/// use imsearch::color_type::ColorType;
+/// use imsearch::picture::PictureU8;
+///
+/// let pic = PictureU8::new(2, 1, ColorType::Rgb, vec![255, 0, 0, 255, 255, 255]).unwrap();
+/// let moments = compute_color_moments(&pic).unwrap();
+///
+/// // pure red is fully saturated, white not at all
+/// assert_eq!(moments.mean, [0.0, 0.5, 1.0]);
+/// assert_eq!(moments.standard_deviation[1], 0.5);
+/// ```
+pub fn compute_color_moments<P: AsPictureView>(pic: &P) -> Result<ColorMoments, Box<dyn Error>> {
+    let view = pic.as_view();
+    let color_type = view.color_type();
+    if matches!(color_type, ColorType::Channels(_)) {
+        return Err(
+            format!("Can not compute the colour moments of a picture with {color_type}").into(),
+        );
+    }
+    let hsv_pixels = || {
+        let mut normalized = Vec::with_capacity(view.color_channel_count());
+        view.pixel_samples().map(move |pixel| {
+            normalized.clear();
+            normalized.extend(pixel.iter().map(|sample| sample.to_normalized()));
+            let [red, green, blue, _] = color_type
+                .to_rgba(&normalized)
+                .expect("the colour model is known");
+            rgb_to_hsv(red, green, blue).map(f64::from)
+        })
+    };
+
+    // the hue is summed as a direction, saturation and value as their powers
+    let (mut hue_sine, mut hue_cosine) = (0.0_f64, 0.0_f64);
+    let mut sums = [[0.0_f64; 3]; 2];
+    let mut count: u64 = 0;
+    for [hue, saturation, value] in hsv_pixels() {
+        hue_sine += (TAU * hue).sin();
+        hue_cosine += (TAU * hue).cos();
+        for (sums, value) in sums.iter_mut().zip([saturation, value]) {
+            sums[0] += value;
+            sums[1] += value * value;
+            sums[2] += value * value * value;
+        }
+        count += 1;
+    }
+    if count == 0 {
+        return Err("Can not compute the colour moments of a picture without pixels".into());
+    }
+
+    let mut moments = ColorMoments::default();
+    let mean_hue = (hue_sine.atan2(hue_cosine) / TAU).rem_euclid(1.0);
+    let mut hue_sums = [0.0_f64; 2];
+    for [hue, _, _] in hsv_pixels() {
+        let difference = (hue - mean_hue + 0.5).rem_euclid(1.0) - 0.5;
+        hue_sums[0] += difference * difference;
+        hue_sums[1] += difference * difference * difference;
+    }
+    // the mean is rounded to `f32` like the stored value, so it can not become 1
+    moments.mean[0] = (mean_hue as f32).rem_euclid(1.0);
+    moments.standard_deviation[0] = (hue_sums[0] / count as f64).sqrt() as f32;
+    moments.skewness[0] = (hue_sums[1] / count as f64).cbrt() as f32;
+
+    for (channel, [sum, sum_of_squares, sum_of_cubes]) in sums.into_iter().enumerate() {
+        let mean = sum / count as f64;
+        let mean_of_squares = sum_of_squares / count as f64;
+        let variance = (mean_of_squares - mean * mean).max(0.0);
+        let third_moment =
+            sum_of_cubes / count as f64 - 3.0 * mean * mean_of_squares + 2.0 * mean * mean * mean;
+        moments.mean[channel + 1] = mean as f32;
+        moments.standard_deviation[channel + 1] = variance.sqrt() as f32;
+        moments.skewness[channel + 1] = third_moment.cbrt() as f32;
+    }
+    Ok(moments)
+}
@@ -0,0 +1,283 @@
+use crate::grayscale::LumaFormula;
+use crate::picture::{Picture, Sample};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The colour model of the samples of a picture.
+///
+/// All values are stored in the range of the sample type, e.g. a hue of 180 degrees is `0.5` in a
+/// `PictureF32` and `127` in a `PictureU8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ColorType {
+    Gray,
+    /// Gray with an alpha channel.
+    GrayAlpha,
+    Rgb,
+    /// Red, green and blue with an alpha channel.
+    Rgba,
+    /// Hue (a full turn is the whole range), saturation and value.
+    Hsv,
+    /// Luma and the blue and red difference of ITU-R BT.601 with the full range, like JPEG.
+    /// The differences are centered in the middle of the range.
+    YCbCr,
+    /// Channels without a known colour model, e.g. the result of `reorder_channels`.
+    Channels(usize),
+}
+
+impl Default for ColorType {
+    /// Pictures read by this crate usually have an alpha channel.
+    fn default() -> Self {
+        ColorType::Rgba
+    }
+}
+
+impl ColorType {
+    /// Guesses the colour model of pictures that only know their number of channels: 1 is gray,
+    /// 2 gray with alpha, 3 RGB and 4 RGBA.
+    pub fn from_channel_count(color_channel_count: usize) -> Self {
+        match color_channel_count {
+            1 => ColorType::Gray,
+            2 => ColorType::GrayAlpha,
+            3 => ColorType::Rgb,
+            4 => ColorType::Rgba,
+            count => ColorType::Channels(count),
+        }
+    }
+
+    pub fn channel_count(&self) -> usize {
+        match self {
+            ColorType::Gray => 1,
+            ColorType::GrayAlpha => 2,
+            ColorType::Rgb | ColorType::Hsv | ColorType::YCbCr => 3,
+            ColorType::Rgba => 4,
+            ColorType::Channels(count) => *count,
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        matches!(self, ColorType::GrayAlpha | ColorType::Rgba)
+    }
+
+    /// Returns the short names of the channels, e.g. `["R", "G", "B"]`.
+    pub fn channel_names(&self) -> Vec<String> {
+        let names: &[&str] = match self {
+            ColorType::Gray => &["Y"],
+            ColorType::GrayAlpha => &["Y", "A"],
+            ColorType::Rgb => &["R", "G", "B"],
+            ColorType::Rgba => &["R", "G", "B", "A"],
+            ColorType::Hsv => &["H", "S", "V"],
+            ColorType::YCbCr => &["Y", "Cb", "Cr"],
+            ColorType::Channels(count) => {
+                return (0..*count).map(|channel| channel.to_string()).collect()
+            }
+        };
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// Returns the normalized red, green and blue values and the alpha value of a pixel, `None`
+    /// for `ColorType::Channels`.
+    pub(crate) fn to_rgba(self, pixel: &[f32]) -> Option<[f32; 4]> {
+        Some(match self {
+            ColorType::Gray => [pixel[0], pixel[0], pixel[0], 1.0],
+            ColorType::GrayAlpha => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            ColorType::Rgb => [pixel[0], pixel[1], pixel[2], 1.0],
+            ColorType::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            ColorType::Hsv => {
+                let [red, green, blue] = hsv_to_rgb(pixel[0], pixel[1], pixel[2]);
+                [red, green, blue, 1.0]
+            }
+            ColorType::YCbCr => {
+                let (luma, blue_difference, red_difference) =
+                    (pixel[0], pixel[1] - 0.5, pixel[2] - 0.5);
+                [
+                    (luma + 1.402 * red_difference).clamp(0.0, 1.0),
+                    (luma - 0.344_136 * blue_difference - 0.714_136 * red_difference)
+                        .clamp(0.0, 1.0),
+                    (luma + 1.772 * blue_difference).clamp(0.0, 1.0),
+                    1.0,
+                ]
+            }
+            ColorType::Channels(_) => return None,
+        })
+    }
+
+    /// Returns the normalized gray value of a pixel with the given formula, `None` for
+    /// `ColorType::Channels`.
+    pub(crate) fn gray_of(self, pixel: &[f32], formula: LumaFormula) -> Option<f32> {
+        match (self, formula) {
+            (
+                ColorType::Gray | ColorType::GrayAlpha,
+                LumaFormula::Legacy | LumaFormula::Rec601 | LumaFormula::Rec709,
+            ) => Some(pixel[0]),
+            _ => self
+                .to_rgba(pixel)
+                .map(|[red, green, blue, _]| formula.luma(red, green, blue)),
+        }
+    }
+
+    /// Appends the normalized channels of a pixel with the given red, green, blue and alpha
+    /// values. Nothing is appended for `ColorType::Channels`.
+    pub(crate) fn push_from_rgba(self, [red, green, blue, alpha]: [f32; 4], pixel: &mut Vec<f32>) {
+        match self {
+            ColorType::Gray => pixel.push(LumaFormula::Legacy.luma(red, green, blue)),
+            ColorType::GrayAlpha => {
+                pixel.extend([LumaFormula::Legacy.luma(red, green, blue), alpha])
+            }
+            ColorType::Rgb => pixel.extend([red, green, blue]),
+            ColorType::Rgba => pixel.extend([red, green, blue, alpha]),
+            ColorType::Hsv => pixel.extend(rgb_to_hsv(red, green, blue)),
+            ColorType::YCbCr => pixel.extend([
+                0.299 * red + 0.587 * green + 0.114 * blue,
+                (0.5 - 0.168_736 * red - 0.331_264 * green + 0.5 * blue).clamp(0.0, 1.0),
+                (0.5 + 0.5 * red - 0.418_688 * green - 0.081_312 * blue).clamp(0.0, 1.0),
+            ]),
+            ColorType::Channels(_) => {}
+        }
+    }
+
+    /// Returns the colour type of decoded PNG data.
+    pub(crate) fn from_png(color_type: png::ColorType) -> Self {
+        match color_type {
+            png::ColorType::Grayscale => ColorType::Gray,
+            png::ColorType::GrayscaleAlpha => ColorType::GrayAlpha,
+            png::ColorType::Rgb => ColorType::Rgb,
+            png::ColorType::Rgba => ColorType::Rgba,
+            // palettes are expanded when a picture is read, see `try_read_picture`
+            png::ColorType::Indexed => ColorType::Channels(1),
+        }
+    }
+}
+
+impl Display for ColorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorType::Gray => write!(f, "gray"),
+            ColorType::GrayAlpha => write!(f, "gray-alpha"),
+            ColorType::Rgb => write!(f, "rgb"),
+            ColorType::Rgba => write!(f, "rgba"),
+            ColorType::Hsv => write!(f, "hsv"),
+            ColorType::YCbCr => write!(f, "ycbcr"),
+            ColorType::Channels(count) => write!(f, "{count}-channels"),
+        }
+    }
+}
+
+impl FromStr for ColorType {
+    type Err = String;
+
+    /// Parses the names written by `Display`, e.g. `rgba` or `5-channels`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "gray" => Ok(ColorType::Gray),
+            "gray-alpha" => Ok(ColorType::GrayAlpha),
+            "rgb" => Ok(ColorType::Rgb),
+            "rgba" => Ok(ColorType::Rgba),
+            "hsv" => Ok(ColorType::Hsv),
+            "ycbcr" => Ok(ColorType::YCbCr),
+            _ => name
+                .strip_suffix("-channels")
+                .and_then(|count| count.parse().ok())
+                .map(ColorType::Channels)
+                .ok_or_else(|| format!("Unknown colour type '{name}'")),
+        }
+    }
+}
+
+impl<T: Sample> Picture<T> {
+    /// Converts the picture to another colour model.
+    ///
+    /// Alpha channels are dropped if the new colour type has none, and are opaque if the picture
+    /// had none. Gray is computed with `LumaFormula::Legacy`, like the gray intensity of
+    /// `AverageBrightness`; `to_grayscale` supports other formulas.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the picture or the new colour type is `ColorType::Channels`, because
+    /// their colour model is not known.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use imsearch::color_type::ColorType;
+    /// use imsearch::picture::PictureU8;
+    ///
+    /// let red = PictureU8::new(1, 1, ColorType::Rgb, vec![255, 0, 0]).unwrap();
+    ///
+    /// let hsv = red.to_color_type(ColorType::Hsv).unwrap();
+    /// assert_eq!(hsv.data, vec![0, 255, 255]);
+    /// let rgba = hsv.to_color_type(ColorType::Rgba).unwrap();
+    /// assert_eq!(rgba.data, vec![255, 0, 0, 255]);
+    /// ```
+    pub fn to_color_type(&self, color_type: ColorType) -> Result<Picture<T>, Box<dyn Error>> {
+        if color_type == self.color_type {
+            return Ok(self.clone());
+        }
+        if matches!(color_type, ColorType::Channels(_))
+            || matches!(self.color_type, ColorType::Channels(_))
+        {
+            return Err(format!(
+                "Can not convert a picture from {} to {color_type}: the colour model of the channels is not known",
+                self.color_type
+            )
+            .into());
+        }
+        let mut normalized = Vec::with_capacity(self.color_channel_count);
+        let mut converted = Vec::new();
+        for pixel in self.pixels() {
+            normalized.clear();
+            normalized.extend(pixel.iter().map(|sample| sample.to_normalized()));
+            if let Some(rgba) = self.color_type.to_rgba(&normalized) {
+                normalized.clear();
+                color_type.push_from_rgba(rgba, &mut normalized);
+                converted.extend(
+                    normalized
+                        .iter()
+                        .map(|value| T::from_f32(value * T::MAX_VALUE.to_f32())),
+                );
+            }
+        }
+        Ok(Picture {
+            lines: self.lines,
+            columns: self.columns,
+            color_channel_count: color_type.channel_count(),
+            color_type,
+            data: converted,
+        })
+    }
+}
+
+/// Converts normalized RGB to normalized HSV; the hue of gray is 0.
+pub(crate) fn rgb_to_hsv(red: f32, green: f32, blue: f32) -> [f32; 3] {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == red {
+        ((green - blue) / delta).rem_euclid(6.0)
+    } else if max == green {
+        (blue - red) / delta + 2.0
+    } else {
+        (red - green) / delta + 4.0
+    } / 6.0;
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    [hue, saturation, max]
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [f32; 3] {
+    let sector = (hue * 6.0).rem_euclid(6.0);
+    let chroma = value * saturation;
+    let second = chroma * (1.0 - ((sector % 2.0) - 1.0).abs());
+    let (red, green, blue) = match sector as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let lightest = value - chroma;
+    [red + lightest, green + lightest, blue + lightest]
+}
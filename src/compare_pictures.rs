@@ -1,14 +1,25 @@
 use crate::cosinus_similarity::determine_similarity_of_search_index_histograms;
-use crate::search_index::SearchIndex;
-use crate::search_index::{generate_suchindex, read_data_from_datastore};
+use crate::datastore::{open_datastore, Datastore};
+use crate::get_datastore_path;
+use crate::orientation::Orientation;
+use crate::search::{rank_stored_entries, SearchOptions};
+use crate::search_index::{try_generate_suchindex, SearchIndex};
+use serde::Serialize;
 use std::error::Error;
 
-#[derive(Debug, Clone)]
+/// The similarity of a stored picture to a query picture.
+///
+/// `similarity` is the average of `cosine_similarity`, the mean cosine similarity of the
+/// histograms, and `average_brightness`, which is `1 - ` the difference of the average brightness.
+/// Searches that try several orientations of the query report the best one in `orientation`.
+#[derive(Debug, Clone, Serialize)]
 pub struct SimilarityInformation {
     similarity: f64,
     search_index: SearchIndex,
     cosine_similarity: f64,
     average_brightness: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    orientation: Option<Orientation>,
 }
 impl SimilarityInformation {
     pub fn new(
@@ -22,54 +33,180 @@ impl SimilarityInformation {
             search_index,
             cosine_similarity,
             average_brightness,
+            orientation: None,
         }
     }
+
+    /// Records the orientation of the query that matched the entry best.
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = Some(orientation);
+        self
+    }
+
+    pub fn similarity(&self) -> f64 {
+        self.similarity
+    }
+
+    pub fn search_index(&self) -> &SearchIndex {
+        &self.search_index
+    }
+
+    pub fn cosine_similarity(&self) -> f64 {
+        self.cosine_similarity
+    }
+
+    /// Returns the similarity of the average brightness, `1 - ` their difference.
+    pub fn average_brightness(&self) -> f32 {
+        self.average_brightness
+    }
+
+    /// Returns the orientation of the query that matched best, `None` if only the query as it is
+    /// was compared.
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.orientation
+    }
+
     pub fn print(&self) {
         println!("______________________________");
         println!("Similarity:           {:3.2}%", self.similarity * 100.0);
         println!("Picture filepath:     {}", self.search_index.filepath);
-        println!("Cosine-Similarity:    {:3.2}%", self.cosine_similarity * 100.0
+        println!(
+            "Cosine-Similarity:    {:3.2}%",
+            self.cosine_similarity * 100.0
         );
         println!(
             "Average brightness:   {:3.2}%",
             self.average_brightness * 100.0
         );
+        if let Some(orientation) = self.orientation {
+            println!("Query orientation:    {orientation}");
+        }
         println!("______________________________");
     }
 }
 
-pub fn calculate_similarities(path: &str) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
-    let search_index = generate_suchindex(path.to_string());
-
-    let search_indexes_database: Vec<SearchIndex> =
-        read_data_from_datastore().expect("Fehler beim Lesen der Daten aus dem Datastore");
-    let mut similarities = Vec::<SimilarityInformation>::new();
-
-    for database_element in &search_indexes_database {
-        let difference_of_brightness = (search_index.average_brightness - database_element.average_brightness).abs();
-        let avg_brightness = 1.0 - difference_of_brightness;
-        let cosine_similarity = determine_similarity_of_search_index_histograms(
-            search_index.clone(),
-            database_element.clone(),
-        );
-        let avg_similarity = compute_average(avg_brightness, cosine_similarity);
-        let similarity_measure = SimilarityInformation::new(
-            avg_similarity,
-            database_element.clone(),
+/// The values of a `SimilarityInformation` without the entry they belong to.
+///
+/// Searches compute them for every entry and only copy the entries of the results they keep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Similarity {
+    pub(crate) similarity: f64,
+    pub(crate) cosine_similarity: f64,
+    pub(crate) average_brightness: f32,
+    pub(crate) orientation: Option<Orientation>,
+}
+
+impl Similarity {
+    pub(crate) fn new(similarity: f64, cosine_similarity: f64, average_brightness: f32) -> Self {
+        Similarity {
+            similarity,
             cosine_similarity,
-            avg_brightness,
-        );
+            average_brightness,
+            orientation: None,
+        }
+    }
+
+    pub(crate) fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = Some(orientation);
+        self
+    }
 
-        similarities.push(similarity_measure);
+    pub(crate) fn with_entry(self, search_index: SearchIndex) -> SimilarityInformation {
+        SimilarityInformation {
+            similarity: self.similarity,
+            search_index,
+            cosine_similarity: self.cosine_similarity,
+            average_brightness: self.average_brightness,
+            orientation: self.orientation,
+        }
     }
-    sort_similarity_measures_by_similarity(&mut similarities);
-    Ok(similarities)
 }
+
+pub fn calculate_similarities(path: &str) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    calculate_similarities_in(datastore.as_ref(), path)
+}
+
+/// Compares the picture at the given path with all entries of the given datastore, see
+/// `rank_stored_entries`.
+///
+/// # Returns
+///
+/// The `SimilarityInformation` of all entries, the most similar first.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::compare_pictures::calculate_similarities_in;
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = "src/tests/files/pictures_for_testing/bird.png";
+/// let datastore = MemoryDatastore::with_entries(vec![generate_suchindex(bird.to_string())]);
+///
+/// let similarities = calculate_similarities_in(&datastore, bird).unwrap();
+/// assert_eq!(similarities.len(), 1);
+/// ```
+pub fn calculate_similarities_in(
+    datastore: &dyn Datastore,
+    path: &str,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    let features = datastore.features();
+    let search_index = try_generate_suchindex(path.to_string(), &features)?;
+
+    let search_indexes_database: Vec<SearchIndex> = datastore.entries()?;
+    let options = SearchOptions::top(search_indexes_database.len());
+    Ok(rank_stored_entries(
+        &search_index,
+        &search_indexes_database,
+        &features,
+        &options,
+    ))
+}
+
+/// Computes the similarity of a stored entry to the query.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::compare_pictures::compare_search_indexes;
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let similarity = compare_search_indexes(&bird, &bird);
+/// assert!((similarity.similarity() - 1.0).abs() < 1e-6);
+/// ```
+pub fn compare_search_indexes(query: &SearchIndex, entry: &SearchIndex) -> SimilarityInformation {
+    compute_similarity(query, entry).with_entry(entry.clone())
+}
+
+/// Same as `compare_search_indexes`, but without a copy of the entry.
+pub(crate) fn compute_similarity(query: &SearchIndex, entry: &SearchIndex) -> Similarity {
+    let difference_of_brightness = (query.average_brightness - entry.average_brightness).abs();
+    let avg_brightness = 1.0 - difference_of_brightness;
+    let cosine_similarity = determine_similarity_of_search_index_histograms(query, entry);
+    let avg_similarity = compute_average(avg_brightness, cosine_similarity);
+    Similarity::new(avg_similarity, cosine_similarity, avg_brightness)
+}
+
+/// Returns an upper bound of the similarity of an entry to the query that only depends on their
+/// average brightness, so it is much cheaper to compute than the similarity itself.
+pub(crate) fn similarity_upper_bound(query: &SearchIndex, entry: &SearchIndex) -> f64 {
+    brightness_upper_bound(query.average_brightness, entry.average_brightness)
+}
+
+/// Same as `similarity_upper_bound`, for the average brightness of a query and an entry or a part
+/// of it.
+pub(crate) fn brightness_upper_bound(query_brightness: f32, entry_brightness: f32) -> f64 {
+    let difference_of_brightness = (query_brightness - entry_brightness).abs();
+    compute_average(1.0 - difference_of_brightness, 1.0)
+}
+
 fn compute_average(value1: f32, value2: f64) -> f64 {
     let value1_f64: f64 = f64::from(value1);
     (value1_f64 + value2) / 2.0
 }
-
-fn sort_similarity_measures_by_similarity(similarity_measures: &mut [SimilarityInformation]) {
-    similarity_measures.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap());
-}
@@ -0,0 +1,279 @@
+use crate::color_type::ColorType;
+use crate::picture::{PictureU8, Sample};
+use crate::picture_view::AsPictureView;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// The distances in pixels at which the correlogram of a `SearchIndex` is computed.
+pub const CORRELOGRAM_DISTANCES: [u32; 4] = [1, 3, 5, 7];
+
+/// The number of levels red, green and blue are quantized to for the correlogram of a
+/// `SearchIndex`, which gives `4 * 4 * 4 = 64` colours.
+pub const CORRELOGRAM_LEVELS: u8 = 4;
+
+/// The largest number of levels per channel, so the colour indices fit into a `u8`.
+pub const MAX_CORRELOGRAM_LEVELS: u8 = 6;
+
+/// Pixels whose neighbourhoods are counted at most; larger pictures are sampled on a regular grid.
+const MAX_SAMPLED_PIXELS: usize = 1 << 16;
+
+/// The colour auto-correlogram of a picture: for every quantized colour and distance `d`, the
+/// probability that a pixel at distance `d` of a pixel of that colour has the same colour.
+///
+/// Unlike a histogram it captures how the colours are laid out: a picture with one large red area
+/// and one with scattered red pixels have the same red histogram, but the large area has a much
+/// higher red auto-correlation.
+///
+/// # Fields
+///
+/// * `levels`: The number of levels red, green and blue are quantized to (see `quantize_colors`).
+/// * `distances`: The distances in pixels, measured as the larger of the horizontal and the
+///   vertical distance.
+/// * `values`: `values[i][colour]` is the probability for `distances[i]`, stored as a fraction of
+///   255 to keep the datastores small. Colours that do not occur in the picture have 0.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AutoCorrelogram {
+    pub levels: u8,
+    pub distances: Vec<u32>,
+    pub values: Vec<Vec<u8>>,
+}
+
+impl AutoCorrelogram {
+    /// Returns the probability of the colour at the distance, `None` if it was not computed.
+    pub fn get(&self, colour: u8, distance: u32) -> Option<f32> {
+        let index = self.distances.iter().position(|known| *known == distance)?;
+        let value = self.values.get(index)?.get(usize::from(colour))?;
+        Some(f32::from(*value) / 255.0)
+    }
+
+    /// Returns the values of all distances one after the other.
+    pub(crate) fn feature_vector(&self) -> Vec<f64> {
+        self.values
+            .iter()
+            .flatten()
+            .map(|value| f64::from(*value) / 255.0)
+            .collect()
+    }
+}
+
+/// Returns the index of the quantized colour of normalized red, green and blue values.
+fn colour_index(levels: u8, red: f32, green: f32, blue: f32) -> u8 {
+    let quantize = |value: f32| ((value.clamp(0.0, 1.0) * f32::from(levels)) as u8).min(levels - 1);
+    (quantize(red) * levels + quantize(green)) * levels + quantize(blue)
+}
+
+/// Quantizes the colours of a picture or view to `levels` levels of red, green and blue and
+/// returns a single channel picture of the colour indices `(red * levels + green) * levels + blue`.
+///
+/// # Errors
+///
+/// Returns an error if `levels` is not within `1..=MAX_CORRELOGRAM_LEVELS` or the colour model of
+/// the picture is not known (`ColorType::Channels`).
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::correlogram::quantize_colors;
+/// use imsearch::picture::PictureU8;
+///
+/// let pic = PictureU8::new(2, 1, ColorType::Rgb, vec![255, 0, 0, 0, 0, 255]).unwrap();
+/// let colours = quantize_colors(&pic, 2).unwrap();
+///
+/// assert_eq!(colours.data, vec![4, 1]);
+/// ```
+pub fn quantize_colors<P: AsPictureView>(pic: &P, levels: u8) -> Result<PictureU8, Box<dyn Error>> {
+    if !(1..=MAX_CORRELOGRAM_LEVELS).contains(&levels) {
+        return Err(format!(
+            "The colours can only be quantized to 1 to {MAX_CORRELOGRAM_LEVELS} levels, not {levels}"
+        )
+        .into());
+    }
+    let view = pic.as_view();
+    let color_type = view.color_type();
+    if matches!(color_type, ColorType::Channels(_)) {
+        return Err(format!("Can not quantize the colours of a picture with {color_type}").into());
+    }
+    let mut normalized = Vec::with_capacity(view.color_channel_count());
+    let data = view
+        .pixel_samples()
+        .map(|pixel| {
+            normalized.clear();
+            normalized.extend(pixel.iter().map(|sample| sample.to_normalized()));
+            let [red, green, blue, _] = color_type
+                .to_rgba(&normalized)
+                .expect("the colour model is known");
+            colour_index(levels, red, green, blue)
+        })
+        .collect();
+    PictureU8::new(view.columns(), view.lines(), ColorType::Channels(1), data)
+}
+
+/// Computes the colour auto-correlogram of a picture or view, see `AutoCorrelogram`.
+///
+/// The colours are quantized with `quantize_colors` first. Of pictures with more than 65536
+/// pixels, only the neighbourhoods of pixels on a regular grid are counted.
+///
+/// # Errors
+///
+/// Returns an error if the colours can not be quantized (see `quantize_colors`), a distance is 0
+/// or the picture has no pixels.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::correlogram::compute_auto_correlogram;
+/// use imsearch::picture::PictureU8;
+///
+/// // a black picture with a white line in the middle
+/// let mut data = vec![0; 3 * 3];
+/// data[3..6].fill(255);
+/// let pic = PictureU8::new(3, 3, ColorType::Gray, data).unwrap();
+///
+/// let correlogram = compute_auto_correlogram(&pic, 2, &[1]).unwrap();
+/// // the white pixels have 8 or 5 neighbours, 2 or 1 of which are white
+/// let white = correlogram.get(7, 1).unwrap();
+/// assert!((white - 4.0 / 18.0).abs() < 1.0 / 255.0);
+/// // the white line splits the black pixels
+/// assert!(correlogram.get(0, 1).unwrap() < 0.5);
+/// ```
+pub fn compute_auto_correlogram<P: AsPictureView>(
+    pic: &P,
+    levels: u8,
+    distances: &[u32],
+) -> Result<AutoCorrelogram, Box<dyn Error>> {
+    if distances.contains(&0) {
+        return Err("The distances of a correlogram must be at least 1".into());
+    }
+    let colours = quantize_colors(pic, levels)?;
+    let (columns, lines) = (colours.columns as usize, colours.lines as usize);
+    if columns * lines == 0 {
+        return Err("Can not compute the correlogram of a picture without pixels".into());
+    }
+    let colour_count = usize::from(levels).pow(3);
+    let step = ((columns * lines) as f64 / MAX_SAMPLED_PIXELS as f64)
+        .sqrt()
+        .ceil()
+        .max(1.0) as usize;
+
+    let mut same = vec![vec![0_u64; colour_count]; distances.len()];
+    let mut neighbours = same.clone();
+    for y in (0..lines).step_by(step) {
+        for x in (0..columns).step_by(step) {
+            let colour = colours.data[y * columns + x];
+            for (index, distance) in distances.iter().enumerate() {
+                let (same_count, count) = count_ring(&colours, x, y, *distance as usize, colour);
+                same[index][usize::from(colour)] += same_count;
+                neighbours[index][usize::from(colour)] += count;
+            }
+        }
+    }
+
+    let values = same
+        .iter()
+        .zip(&neighbours)
+        .map(|(same, neighbours)| {
+            same.iter()
+                .zip(neighbours)
+                .map(|(same, count)| {
+                    if *count == 0 {
+                        0
+                    } else {
+                        (*same as f64 / *count as f64 * 255.0).round() as u8
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Ok(AutoCorrelogram {
+        levels,
+        distances: distances.to_vec(),
+        values,
+    })
+}
+
+/// Counts the pixels inside the picture at exactly `distance` of `(x, y)` and those of them that
+/// have the given colour.
+fn count_ring(colours: &PictureU8, x: usize, y: usize, distance: usize, colour: u8) -> (u64, u64) {
+    let (columns, lines) = (colours.columns as usize, colours.lines as usize);
+    let mut same = 0;
+    let mut count = 0;
+    let mut visit = |x: usize, y: usize| {
+        count += 1;
+        if colours.data[y * columns + x] == colour {
+            same += 1;
+        }
+    };
+    let left = x.saturating_sub(distance);
+    let right = (x + distance).min(columns - 1);
+    // the top and the bottom row of the ring
+    for ring_y in [
+        y.checked_sub(distance),
+        Some(y + distance).filter(|y| *y < lines),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        for ring_x in left..=right {
+            visit(ring_x, ring_y);
+        }
+    }
+    // the left and the right column without the corners
+    let top = y.saturating_sub(distance - 1);
+    let bottom = (y + distance - 1).min(lines - 1);
+    for ring_x in [
+        x.checked_sub(distance),
+        Some(x + distance).filter(|x| *x < columns),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        for ring_y in top..=bottom {
+            visit(ring_x, ring_y);
+        }
+    }
+    (same, count)
+}
+
+/// Returns the similarity of two correlograms, `None` if they were computed with different levels
+/// or distances.
+///
+/// Every value that is not 0 in both correlograms contributes its relative difference
+/// `|a - b| / (1 + a + b)`, and the similarity is `1 - ` the mean of these differences.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::correlogram::{compare_auto_correlograms, compute_auto_correlogram};
+/// use imsearch::read_picture;
+///
+/// let bird = read_picture("src/tests/files/pictures_for_testing/bird.png");
+/// let correlogram = compute_auto_correlogram(&bird, 4, &[1, 3]).unwrap();
+/// assert_eq!(compare_auto_correlograms(&correlogram, &correlogram), Some(1.0));
+/// ```
+pub fn compare_auto_correlograms(a: &AutoCorrelogram, b: &AutoCorrelogram) -> Option<f64> {
+    if a.levels != b.levels || a.distances != b.distances {
+        return None;
+    }
+    compare_correlogram_values(&a.feature_vector(), &b.feature_vector())
+}
+
+/// Compares the values of two correlograms like `compare_auto_correlograms`, `None` if their
+/// lengths differ or they are empty.
+pub(crate) fn compare_correlogram_values(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let (difference, count) = a
+        .iter()
+        .zip(b)
+        .filter(|(a, b)| **a != 0.0 || **b != 0.0)
+        .fold((0.0, 0_usize), |(difference, count), (a, b)| {
+            (difference + (a - b).abs() / (1.0 + a + b), count + 1)
+        });
+    if count == 0 {
+        return Some(1.0);
+    }
+    Some(1.0 - difference / count as f64)
+}
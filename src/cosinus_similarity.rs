@@ -2,8 +2,8 @@ use crate::search_index::SearchIndex;
 
 // Determines the similarity of two histograms. The histograms are extracted from a SearchIndex.
 pub fn determine_similarity_of_search_index_histograms(
-    search_index1: SearchIndex,
-    search_index2: SearchIndex,
+    search_index1: &SearchIndex,
+    search_index2: &SearchIndex,
 ) -> f64 {
     let normalized_histograms1 = get_normalized_histogram_of_search_index(search_index1);
     let normalized_histograms2 = get_normalized_histogram_of_search_index(search_index2);
@@ -12,13 +12,19 @@ pub fn determine_similarity_of_search_index_histograms(
 }
 
 // Normalized histograms are compared. Via
-pub fn compare_vec_of_histograms(normalized_histogram1: Vec<Vec<f64>>, normalized_histogram2: Vec<Vec<f64>>) -> f64 {
+pub fn compare_vec_of_histograms(
+    normalized_histogram1: Vec<Vec<f64>>,
+    normalized_histogram2: Vec<Vec<f64>>,
+) -> f64 {
     if normalized_histogram1.len() != normalized_histogram2.len() {
         println!("Input vectors have different lengths");
         return 0.0;
     }
     let mut similarities: Vec<f64> = Vec::new();
-    for (hist1, hist2) in normalized_histogram1.iter().zip(normalized_histogram2.iter()) {
+    for (hist1, hist2) in normalized_histogram1
+        .iter()
+        .zip(normalized_histogram2.iter())
+    {
         let similarity = compute_cosine_similarity(hist1, hist2);
         similarities.push(similarity);
     }
@@ -61,13 +67,10 @@ fn compute_average_of_vec(values: Vec<f64>) -> f64 {
     sum / values.len() as f64
 }
 
-pub fn get_normalized_histogram_of_search_index(search_index: SearchIndex) -> Vec<Vec<f64>> {
-    let mut results: Vec<Vec<f64>> = Vec::new();
-    for entry in search_index {
-        for bin in entry.histogram {
-            let normalized_bins = bin.normalize();
-            results.push(normalized_bins);
-        }
-    }
-    results
+pub fn get_normalized_histogram_of_search_index(search_index: &SearchIndex) -> Vec<Vec<f64>> {
+    search_index
+        .histogram
+        .iter()
+        .map(|histogram| histogram.normalize())
+        .collect()
 }
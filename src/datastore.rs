@@ -0,0 +1,1085 @@
+use crate::binary_datastore::{
+    compact_binary_datastore, is_binary_datastore, migrate_binary_datastore, BinaryFileDatastore,
+};
+use crate::features::FeatureConfiguration;
+use crate::file_handler::{format_filepath, is_file, write_file_atomically};
+use crate::get_datastore_path;
+use crate::json_datastore::JsonDatastore;
+use crate::migration::{migrate_entries, MigrationSummary, LEGACY_FEATURES, SCHEMA_VERSION};
+use crate::search_index::SearchIndex;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_datastore::{
+    has_sqlite_extension, is_sqlite_datastore, migrate_sqlite_datastore, SqliteDatastore,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long `DatastoreLock::acquire` waits for another process to release the lock.
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Errors that are specific to the datastore.
+///
+/// They are returned boxed as `Box<dyn Error>` like all other errors of this crate and can be
+/// told apart with `downcast_ref::<DatastoreError>()`.
+#[derive(Debug, PartialEq)]
+pub enum DatastoreError {
+    /// Another process holds the lock of the datastore for longer than `LOCK_TIMEOUT`.
+    Locked { lock_path: String },
+    /// The datastore file can not be parsed, e.g. because it was truncated.
+    /// `recover_datastore` can restore the readable entries.
+    Corrupted { path: String, reason: String },
+    /// The datastore was written by an older version of this library or with an older
+    /// `FeatureConfiguration`. `migrate_datastore` upgrades it.
+    NeedsMigration { path: String },
+    /// The datastore was written by a newer version of this library.
+    UnsupportedVersion {
+        path: String,
+        found: u32,
+        supported: u32,
+    },
+}
+
+impl Display for DatastoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatastoreError::Locked { lock_path } => write!(
+                f,
+                "The datastore is locked by another process (see {lock_path}). Try again when it is done."
+            ),
+            DatastoreError::Corrupted { path, reason } => write!(
+                f,
+                "The datastore {path} is corrupted ({reason}). Run 'imsearch recover' to restore it."
+            ),
+            DatastoreError::NeedsMigration { path } => write!(
+                f,
+                "The datastore {path} was written by an older version of imsearch. Run 'imsearch migrate' to upgrade it; this indexes its pictures again."
+            ),
+            DatastoreError::UnsupportedVersion {
+                path,
+                found,
+                supported,
+            } => write!(
+                f,
+                "The datastore {path} has version {found}, but this version of imsearch only supports up to version {supported}. Please update imsearch."
+            ),
+        }
+    }
+}
+
+impl Error for DatastoreError {}
+
+/// Returns the path of the lock file that belongs to a datastore.
+pub fn lock_path(datastore_path: &str) -> String {
+    format!("{datastore_path}.lock")
+}
+
+/// Returns the path of the backup that is kept of the previous version of a datastore.
+pub fn backup_path(datastore_path: &str) -> String {
+    format!("{datastore_path}.bak")
+}
+
+/// An advisory lock on a datastore file.
+///
+/// The lock is an OS file lock (`File::try_lock`) on a file next to the datastore (see
+/// `lock_path`), so only one process at a time can hold it. It only protects against other users of
+/// this crate, which take the lock for every change of the datastore. The lock is released when the
+/// value is dropped, and by the OS if the process ends without dropping it, so a crashed process
+/// never leaves a lock behind. The lock file itself is kept and contains the id of the process that
+/// held the lock last.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::datastore::DatastoreLock;
+/// let datastore_path = std::env::temp_dir().join("imsearch_doc_lock.json");
+/// let datastore_path = datastore_path.to_str().unwrap();
+///
+/// let lock = DatastoreLock::acquire(datastore_path).unwrap();
+/// // ... read and write the datastore ...
+/// drop(lock);
+/// ```
+#[derive(Debug)]
+pub struct DatastoreLock {
+    // closing the file releases the lock
+    lock_file: File,
+}
+
+impl DatastoreLock {
+    /// Acquires the lock of the datastore at the given path, waiting at most `LOCK_TIMEOUT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::Locked` if the lock is not released in time, or an IO error if the
+    /// lock file can not be opened or locked.
+    pub fn acquire(datastore_path: &str) -> Result<Self, Box<dyn Error>> {
+        Self::acquire_with_timeout(datastore_path, LOCK_TIMEOUT)
+    }
+
+    /// Acquires the lock of the datastore at the given path, waiting at most `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::Locked` if the lock is not released in time, or an IO error if the
+    /// lock file can not be opened or locked.
+    pub fn acquire_with_timeout(
+        datastore_path: &str,
+        timeout: Duration,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::acquire_with(datastore_path, timeout, false)
+    }
+
+    /// Acquires a shared lock of the datastore at the given path, waiting at most `LOCK_TIMEOUT`.
+    ///
+    /// Any number of processes can hold a shared lock at the same time, but not while another one
+    /// holds the lock taken by `acquire`. This keeps the datastore unchanged while it is read
+    /// together with files derived from it, like its ANN index.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::Locked` if the lock is not released in time, or an IO error if the
+    /// lock file can not be opened or locked.
+    pub fn acquire_shared(datastore_path: &str) -> Result<Self, Box<dyn Error>> {
+        Self::acquire_with(datastore_path, LOCK_TIMEOUT, true)
+    }
+
+    fn acquire_with(
+        datastore_path: &str,
+        timeout: Duration,
+        shared: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let lock_path = lock_path(datastore_path);
+        // the lock file is never removed: a process waiting for the lock of a removed file could
+        // hold it at the same time as one that locked a new file at the same path
+        let mut lock_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)?;
+        let start = Instant::now();
+
+        loop {
+            let locked = if shared {
+                lock_file.try_lock_shared()
+            } else {
+                lock_file.try_lock()
+            };
+            match locked {
+                Ok(()) => {
+                    if !shared {
+                        // the owner can be found if the lock is held for a long time
+                        lock_file.set_len(0)?;
+                        write!(lock_file, "{}", std::process::id())?;
+                    }
+                    return Ok(DatastoreLock { lock_file });
+                }
+                Err(TryLockError::WouldBlock) => {
+                    if start.elapsed() >= timeout {
+                        return Err(Box::new(DatastoreError::Locked { lock_path }));
+                    }
+                    thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(TryLockError::Error(err)) => return Err(Box::new(err)),
+            }
+        }
+    }
+}
+
+impl Drop for DatastoreLock {
+    fn drop(&mut self) {
+        let _ = self.lock_file.unlock();
+    }
+}
+
+/// The layout of a JSON datastore file.
+#[derive(Serialize, Deserialize)]
+struct DatastoreFile<E> {
+    schema_version: u32,
+    features: FeatureConfiguration,
+    entries: E,
+}
+
+/// The content of a datastore file before it is migrated.
+struct StoredEntries {
+    schema_version: u32,
+    features: FeatureConfiguration,
+    entries: Vec<Value>,
+}
+
+impl StoredEntries {
+    fn needs_migration(&self) -> bool {
+        self.schema_version < SCHEMA_VERSION || !self.features.is_current()
+    }
+
+    fn migrate(self) -> Result<(Vec<Value>, MigrationSummary), Box<dyn Error>> {
+        migrate_entries(self.entries, self.schema_version, &self.features)
+    }
+}
+
+pub(crate) fn needs_migration(datastore_path: &str) -> Box<dyn Error> {
+    Box::new(DatastoreError::NeedsMigration {
+        path: datastore_path.to_string(),
+    })
+}
+
+fn corrupted(datastore_path: &str, reason: String) -> Box<dyn Error> {
+    Box::new(DatastoreError::Corrupted {
+        path: datastore_path.to_string(),
+        reason,
+    })
+}
+
+/// Reads a datastore file without migrating it. Returns `None` if the file does not exist or is
+/// empty.
+fn load_datastore_file(datastore_path: &str) -> Result<Option<StoredEntries>, Box<dyn Error>> {
+    let data_str = match fs::read_to_string(datastore_path) {
+        Ok(data_str) => data_str,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(Box::new(err)),
+    };
+    if data_str.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let data: Value = serde_json::from_str(&data_str)
+        .map_err(|err| corrupted(datastore_path, err.to_string()))?;
+    let stored = match data {
+        // schema version 1 was a plain array of entries
+        Value::Array(entries) => StoredEntries {
+            schema_version: 1,
+            features: LEGACY_FEATURES,
+            entries,
+        },
+        data => {
+            let datastore_file: DatastoreFile<Vec<Value>> = serde_json::from_value(data)
+                .map_err(|err| corrupted(datastore_path, err.to_string()))?;
+            StoredEntries {
+                schema_version: datastore_file.schema_version,
+                features: datastore_file.features,
+                entries: datastore_file.entries,
+            }
+        }
+    };
+
+    if stored.schema_version > SCHEMA_VERSION {
+        return Err(Box::new(DatastoreError::UnsupportedVersion {
+            path: datastore_path.to_string(),
+            found: stored.schema_version,
+            supported: SCHEMA_VERSION,
+        }));
+    }
+    Ok(Some(stored))
+}
+
+fn entries_from_values<T>(
+    datastore_path: &str,
+    entries: Vec<Value>,
+) -> Result<Vec<T>, Box<dyn Error>>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    serde_json::from_value(Value::Array(entries))
+        .map_err(|err| corrupted(datastore_path, err.to_string()))
+}
+
+/// Serializes entries in the JSON datastore format of the current `SCHEMA_VERSION`, with the
+/// given `FeatureConfiguration`. Descriptors the configuration does not keep are left out.
+///
+/// # Errors
+///
+/// Returns an error if the entries can not be serialized.
+pub fn encode_datastore_file(
+    entries: &[SearchIndex],
+    features: &FeatureConfiguration,
+) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string_pretty(&DatastoreFile {
+        schema_version: SCHEMA_VERSION,
+        features: features.clone(),
+        entries: features.stored_entries(entries),
+    })?)
+}
+
+/// Same as `write_data_to_filepath`, but with the given `FeatureConfiguration`.
+pub(crate) fn write_datastore_file(
+    datastore_path: &str,
+    entries: &[SearchIndex],
+    features: &FeatureConfiguration,
+) -> Result<(), Box<dyn Error>> {
+    let data_str = encode_datastore_file(entries, features)?;
+    if is_file(datastore_path) {
+        fs::copy(datastore_path, backup_path(datastore_path))?;
+    }
+    write_file_atomically(datastore_path, data_str.as_bytes())?;
+    Ok(())
+}
+
+/// Reads all entries of the datastore file at the given path.
+///
+/// A datastore file that does not exist yet or is empty counts as an empty datastore.
+///
+/// Reading never changes the file. Datastores of an older schema version or feature configuration
+/// have to be upgraded with `migrate_datastore_at` first.
+///
+/// # Errors
+///
+/// Returns `DatastoreError::Corrupted` if the file can not be parsed,
+/// `DatastoreError::NeedsMigration` if it was written by an older version of this library,
+/// `DatastoreError::UnsupportedVersion` if it was written by a newer version, and an IO error if
+/// it can not be read.
+pub fn read_datastore_file<T>(datastore_path: &str) -> Result<Vec<T>, Box<dyn Error>>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    Ok(read_stored_datastore_file(datastore_path)?.1)
+}
+
+/// Same as `read_datastore_file`, but also returns the `FeatureConfiguration` of the entries, the
+/// default one if the file does not exist yet.
+pub(crate) fn read_stored_datastore_file<T>(
+    datastore_path: &str,
+) -> Result<(FeatureConfiguration, Vec<T>), Box<dyn Error>>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    match load_datastore_file(datastore_path)? {
+        None => Ok((FeatureConfiguration::default(), Vec::new())),
+        Some(stored) if stored.needs_migration() => Err(needs_migration(datastore_path)),
+        Some(stored) => Ok((
+            stored.features,
+            entries_from_values(datastore_path, stored.entries)?,
+        )),
+    }
+}
+
+/// Migrates the configured datastore to the current schema version, see `migrate_datastore_at`.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read, migrated or written.
+pub fn migrate_datastore() -> Result<Option<MigrationSummary>, Box<dyn Error>> {
+    migrate_datastore_at(&get_datastore_path()?)
+}
+
+/// Migrates the datastore at the given path to the current `SCHEMA_VERSION` and
+/// `FeatureConfiguration`, whatever its format.
+///
+/// This is the only way a datastore is migrated: reading an outdated datastore returns
+/// `DatastoreError::NeedsMigration`, because migrating may index all pictures again (see
+/// `migrate_entries`).
+///
+/// # Returns
+///
+/// What was migrated, or `None` if the datastore was already up to date.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read, migrated or written, and
+/// `DatastoreError::UnsupportedVersion` if it was written by a newer version of this library.
+pub fn migrate_datastore_at(
+    datastore_path: &str,
+) -> Result<Option<MigrationSummary>, Box<dyn Error>> {
+    #[cfg(feature = "sqlite")]
+    if is_sqlite_datastore(datastore_path) {
+        return migrate_sqlite_datastore(datastore_path);
+    }
+    if is_binary_datastore(datastore_path) {
+        return migrate_binary_datastore(datastore_path);
+    }
+
+    let _lock = DatastoreLock::acquire(datastore_path)?;
+    let Some(stored) = load_datastore_file(datastore_path)? else {
+        return Ok(None);
+    };
+    if !stored.needs_migration() {
+        return Ok(None);
+    }
+
+    let features = stored.features.current();
+    let (entries, summary) = stored.migrate()?;
+    let entries: Vec<SearchIndex> = entries_from_values(datastore_path, entries)?;
+    write_datastore_file(datastore_path, &entries, &features)?;
+    Ok(Some(summary))
+}
+
+/// Makes the configured datastore compact, see `compact_datastore_at`.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written, or can not be compact.
+pub fn compact_datastore() -> Result<usize, Box<dyn Error>> {
+    compact_datastore_at(&get_datastore_path()?)
+}
+
+/// Makes the datastore at the given path compact: its entries, and all entries stored later, only
+/// keep the average brightness, the lightness and the colour moments (see
+/// `DescriptorSet::Compact`). A datastore that does not exist yet is created empty.
+///
+/// The removed descriptors can only be computed again by indexing the pictures into a new
+/// datastore.
+///
+/// # Returns
+///
+/// The number of entries of the datastore.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written, or if it is an SQLite
+/// datastore, which always keeps all descriptors.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::datastore::{compact_datastore_at, open_datastore};
+/// use imsearch::features::DescriptorSet;
+///
+/// let datastore_path = std::env::temp_dir().join("imsearch_doc_compact_datastore.json");
+/// let datastore_path = datastore_path.to_str().unwrap();
+/// std::fs::copy("src/tests/files/DataStoreJSON/data.json", datastore_path).unwrap();
+///
+/// compact_datastore_at(datastore_path).unwrap();
+/// let datastore = open_datastore(datastore_path).unwrap();
+/// assert_eq!(datastore.features().descriptors, DescriptorSet::Compact);
+/// assert!(datastore.entries().unwrap()[0].histogram.is_empty());
+/// ```
+pub fn compact_datastore_at(datastore_path: &str) -> Result<usize, Box<dyn Error>> {
+    if is_binary_datastore(datastore_path) {
+        return compact_binary_datastore(datastore_path);
+    }
+    if !is_json_datastore(datastore_path) {
+        return Err("SQLite datastores always keep all descriptors.".into());
+    }
+    let _lock = DatastoreLock::acquire(datastore_path)?;
+    let (_, entries): (_, Vec<SearchIndex>) = read_stored_datastore_file(datastore_path)?;
+    write_datastore_file(datastore_path, &entries, &FeatureConfiguration::compact())?;
+    Ok(entries.len())
+}
+
+/// Reads the datastore, applies a modification to all entries and writes the result back.
+///
+/// All functions that change the datastore go through this function. It opens the datastore with
+/// `open_datastore` and applies the modification with `Datastore::modify`, so it works with every
+/// backend. Concurrent processes can not overwrite each other's changes, and the datastore is only
+/// written if the modification succeeds.
+///
+/// # Arguments
+///
+/// * `modification` - A closure that changes the entries and returns a result for the caller.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read, locked or written, or if the modification
+/// fails.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::datastore::modify_datastore;
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_modify_datastore.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
+/// let entry_count = modify_datastore(|entries| Ok(entries.len())).unwrap();
+/// println!("The datastore contains {entry_count} entries.");
+/// ```
+pub fn modify_datastore<F, R>(modification: F) -> Result<R, Box<dyn Error>>
+where
+    F: FnOnce(&mut Vec<SearchIndex>) -> Result<R, Box<dyn Error>>,
+{
+    modify_datastore_at(&get_datastore_path()?, modification)
+}
+
+/// Same as `modify_datastore`, but for the datastore at the given path instead of the configured
+/// one.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written, or if the modification fails.
+pub fn modify_datastore_at<F, R>(datastore_path: &str, modification: F) -> Result<R, Box<dyn Error>>
+where
+    F: FnOnce(&mut Vec<SearchIndex>) -> Result<R, Box<dyn Error>>,
+{
+    let mut datastore = open_datastore(datastore_path)?;
+    let mut modification = Some(modification);
+    let mut result = None;
+    datastore.modify(&mut |entries| {
+        let modification = modification
+            .take()
+            .ok_or("the modification was applied twice")?;
+        result = Some(modification(entries)?);
+        Ok(())
+    })?;
+    result.ok_or_else(|| "the modification was not applied".into())
+}
+
+/// Removes all entries from the datastore for which the predicate returns `true`.
+///
+/// # Arguments
+///
+/// * `predicate` - Decides for each entry whether it is removed.
+///
+/// # Returns
+///
+/// The removed entries.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::datastore::remove_where;
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_remove_where.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
+/// // remove all entries of very dark pictures
+/// let removed = remove_where(|entry| entry.average_brightness < 0.01).unwrap();
+/// println!("Removed {} entries.", removed.len());
+/// ```
+pub fn remove_where<P>(predicate: P) -> Result<Vec<SearchIndex>, Box<dyn Error>>
+where
+    P: FnMut(&SearchIndex) -> bool,
+{
+    remove_where_at(&get_datastore_path()?, predicate)
+}
+
+/// Same as `remove_where`, but for the datastore at the given path instead of the configured one.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written.
+pub fn remove_where_at<P>(
+    datastore_path: &str,
+    predicate: P,
+) -> Result<Vec<SearchIndex>, Box<dyn Error>>
+where
+    P: FnMut(&SearchIndex) -> bool,
+{
+    modify_datastore_at(datastore_path, |entries| {
+        Ok(remove_entries_where(entries, predicate))
+    })
+}
+
+/// Removes all entries for which the predicate returns `true` from the given `Vec` and returns them.
+pub(crate) fn remove_entries_where<P>(
+    entries: &mut Vec<SearchIndex>,
+    mut predicate: P,
+) -> Vec<SearchIndex>
+where
+    P: FnMut(&SearchIndex) -> bool,
+{
+    let (removed, kept): (Vec<SearchIndex>, Vec<SearchIndex>) =
+        entries.drain(..).partition(|entry| predicate(entry));
+    *entries = kept;
+    removed
+}
+
+/// Removes all entries of the given filepath from the datastore.
+///
+/// # Arguments
+///
+/// * `path` - The filepath of the picture whose entries are removed.
+///
+/// # Returns
+///
+/// The removed entries. The `Vec` is empty if the filepath was not indexed.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written.
+pub fn remove_by_path(path: &str) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+    let path = format_filepath(path);
+    remove_where(|entry| entry.filepath == path)
+}
+
+/// Replaces the stored entry that has the same filepath as the given entry.
+///
+/// If the datastore contains several entries of the filepath, the first one is replaced and the
+/// others are removed.
+///
+/// # Arguments
+///
+/// * `updated_entry` - The new entry.
+///
+/// # Returns
+///
+/// `true` if an entry was replaced, `false` if the filepath was not indexed. In the latter case the
+/// datastore is left unchanged.
+///
+/// The `tags` of the stored entry are kept, use `set_tags` to change them.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::datastore::update_entry;
+/// # use imsearch::search_index::generate_suchindex;
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_update_entry.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
+/// // index the picture again, e.g. after it was edited
+/// let entry = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// update_entry(entry).unwrap();
+/// ```
+pub fn update_entry(mut updated_entry: SearchIndex) -> Result<bool, Box<dyn Error>> {
+    modify_datastore(|entries| {
+        if let Some(stored_entry) = entries
+            .iter()
+            .find(|entry| entry.filepath == updated_entry.filepath)
+        {
+            updated_entry.tags = stored_entry.tags.clone();
+        }
+        Ok(replace_entry(entries, updated_entry))
+    })
+}
+
+/// Replaces the tags of the entry of the given filepath.
+///
+/// # Returns
+///
+/// `true` if the tags were set, `false` if the filepath is not indexed.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::datastore::set_tags;
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_set_tags.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
+/// set_tags("src/tests/files/pictures_for_testing/bird.png", &["animal", "outdoor"]).unwrap();
+/// ```
+pub fn set_tags(path: &str, tags: &[&str]) -> Result<bool, Box<dyn Error>> {
+    let path = format_filepath(path);
+    modify_datastore(|entries| {
+        let mut found = false;
+        for entry in entries.iter_mut().filter(|entry| entry.filepath == path) {
+            entry.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            found = true;
+        }
+        Ok(found)
+    })
+}
+
+/// Replaces the first entry with the filepath of `updated_entry` and removes further entries of
+/// that filepath. Returns `false` without changing anything if the filepath is not contained.
+pub(crate) fn replace_entry(entries: &mut Vec<SearchIndex>, updated_entry: SearchIndex) -> bool {
+    let Some(position) = entries
+        .iter()
+        .position(|entry| entry.filepath == updated_entry.filepath)
+    else {
+        return false;
+    };
+    let filepath = updated_entry.filepath.clone();
+    entries[position] = updated_entry;
+
+    let mut index = 0;
+    entries.retain(|entry| {
+        let keep = index <= position || entry.filepath != filepath;
+        index += 1;
+        keep
+    });
+    true
+}
+
+/// Removes all entries whose files do not exist anymore.
+///
+/// # Returns
+///
+/// The removed entries.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written.
+pub fn prune_missing_files() -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+    prune_missing_files_at(&get_datastore_path()?)
+}
+
+/// Same as `prune_missing_files`, but for the datastore at the given path instead of the
+/// configured one.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written.
+pub fn prune_missing_files_at(datastore_path: &str) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+    remove_where_at(datastore_path, |entry| !is_file(&entry.filepath))
+}
+
+/// Restores the configured datastore after it was corrupted, see `recover_datastore_at`.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be locked or written.
+pub fn recover_datastore() -> Result<usize, Box<dyn Error>> {
+    recover_datastore_at(&get_datastore_path()?)
+}
+
+/// Restores a datastore file that can not be parsed anymore, e.g. because it was truncated.
+///
+/// The recovered datastore consists of all complete entries at the beginning of the corrupted
+/// file, followed by the entries of the backup of the previous version (see `backup_path`) whose
+/// filepaths are not contained yet. The corrupted file is kept as `<datastore>.corrupt`.
+///
+/// If the datastore can be parsed, nothing is changed. Only JSON datastores can be restored this
+/// way: SQLite and binary datastores are only checked by opening them.
+///
+/// # Returns
+///
+/// The number of entries in the datastore after the recovery.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be locked or written.
+pub fn recover_datastore_at(datastore_path: &str) -> Result<usize, Box<dyn Error>> {
+    if !is_json_datastore(datastore_path) {
+        return open_datastore(datastore_path)?.len();
+    }
+    let _lock = DatastoreLock::acquire(datastore_path)?;
+
+    match load_datastore_file(datastore_path) {
+        Ok(stored) => return Ok(stored.map_or(0, |stored| stored.entries.len())),
+        Err(err) => match err.downcast_ref::<DatastoreError>() {
+            Some(DatastoreError::Corrupted { .. }) => {}
+            _ => return Err(err),
+        },
+    }
+
+    let corrupted_data = fs::read_to_string(datastore_path)?;
+    let mut recovered_entries = salvage_entries(&corrupted_data);
+
+    // the backup also tells which descriptors the datastore keeps
+    let (features, backup_entries) =
+        load_backup_entries(&backup_path(datastore_path)).unwrap_or_default();
+    for backup_entry in backup_entries {
+        if !recovered_entries
+            .iter()
+            .any(|entry| entry.filepath == backup_entry.filepath)
+        {
+            recovered_entries.push(backup_entry);
+        }
+    }
+
+    fs::write(format!("{datastore_path}.corrupt"), corrupted_data)?;
+    // not write_data_to_filepath: the backup must not be replaced by the corrupted file
+    let data_str = encode_datastore_file(&recovered_entries, &features)?;
+    write_file_atomically(datastore_path, data_str.as_bytes())?;
+    Ok(recovered_entries.len())
+}
+
+/// Reads the backup of a datastore for `recover_datastore_at`. A backup of an older version is
+/// migrated in memory, the summary of that migration is not needed.
+fn load_backup_entries(
+    backup_path: &str,
+) -> Result<(FeatureConfiguration, Vec<SearchIndex>), Box<dyn Error>> {
+    match load_datastore_file(backup_path)? {
+        None => Ok((FeatureConfiguration::default(), Vec::new())),
+        Some(stored) if stored.needs_migration() => {
+            let features = stored.features.current();
+            let (entries, _) = stored.migrate()?;
+            Ok((features, entries_from_values(backup_path, entries)?))
+        }
+        Some(stored) => Ok((
+            stored.features,
+            entries_from_values(backup_path, stored.entries)?,
+        )),
+    }
+}
+
+/// Parses the complete entries at the beginning of the entries array of a datastore that was cut
+/// off.
+fn salvage_entries(corrupted_data: &str) -> Vec<SearchIndex> {
+    let mut entries = Vec::new();
+    // schema version 1 datastores are a plain array
+    let entries_start = corrupted_data.find("\"entries\"").unwrap_or(0);
+    let Some(array_start) = corrupted_data[entries_start..]
+        .find('[')
+        .map(|array_start| entries_start + array_start)
+    else {
+        return entries;
+    };
+
+    let mut rest = &corrupted_data[array_start + 1..];
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<SearchIndex>();
+        match stream.next() {
+            Some(Ok(entry)) => {
+                entries.push(entry);
+                rest = &rest[stream.byte_offset()..];
+            }
+            _ => return entries,
+        }
+    }
+}
+
+/// A modification of all entries of a datastore, see `Datastore::modify`.
+pub type Modification<'a> = dyn FnMut(&mut Vec<SearchIndex>) -> Result<(), Box<dyn Error>> + 'a;
+
+/// A storage backend for `SearchIndex` entries.
+///
+/// Entries are identified by their filepath, so a datastore holds at most one entry per picture.
+/// Implementations may buffer changes until `flush` is called; dropping a datastore without
+/// flushing it can lose them.
+///
+/// Implementations: `JsonDatastore`, `BinaryFileDatastore`, `MemoryDatastore` and, with the
+/// `sqlite` feature, `SqliteDatastore`. `open_datastore` picks the one that matches a file.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::datastore::Datastore;
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::search_index::SearchIndex;
+///
+/// fn count_dark_pictures(datastore: &dyn Datastore) -> usize {
+///     let entries = datastore.entries().unwrap();
+///     entries.iter().filter(|entry| entry.average_brightness < 0.2).count()
+/// }
+///
+/// let mut datastore = MemoryDatastore::new();
+/// datastore
+///     .insert(SearchIndex::new("pictures/night.png".to_string(), 0.1, vec![]))
+///     .unwrap();
+///
+/// assert!(datastore.get("pictures/night.png").unwrap().is_some());
+/// assert_eq!(count_dark_pictures(&datastore), 1);
+/// ```
+pub trait Datastore {
+    /// Stores an entry. An existing entry with the same filepath is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry can not be stored.
+    fn insert(&mut self, entry: SearchIndex) -> Result<(), Box<dyn Error>>;
+
+    /// Returns the entry of the picture at the given filepath, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the datastore can not be read.
+    fn get(&self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>>;
+
+    /// Removes the entry of the picture at the given filepath and returns it, if there was one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry can not be removed.
+    fn remove(&mut self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>>;
+
+    /// Returns all entries of the datastore.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the datastore can not be read.
+    fn entries(&self) -> Result<Vec<SearchIndex>, Box<dyn Error>>;
+
+    /// Writes all buffered changes to the underlying storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the changes can not be written.
+    fn flush(&mut self) -> Result<(), Box<dyn Error>>;
+
+    /// Applies a modification to all entries as a single change and stores the result.
+    ///
+    /// The buffered changes are applied before the modification and stored with it. Other users of
+    /// this crate can not change the datastore between reading the entries and storing the result,
+    /// file datastores e.g. hold the `DatastoreLock` meanwhile. Nothing is stored if the
+    /// modification fails. `modify_datastore` uses this for all changes of existing entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the datastore can not be read or written, or if the modification fails.
+    fn modify(&mut self, modification: &mut Modification) -> Result<(), Box<dyn Error>>;
+
+    /// Returns the `FeatureConfiguration` of the stored entries, which tells e.g. whether the
+    /// datastore is compact (see `DescriptorSet`).
+    fn features(&self) -> FeatureConfiguration {
+        FeatureConfiguration::default()
+    }
+
+    /// Stores all given entries, see `insert`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry can not be stored.
+    fn insert_all(&mut self, entries: Vec<SearchIndex>) -> Result<(), Box<dyn Error>> {
+        for entry in entries {
+            self.insert(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Returns all entries whose average brightness lies within `min..=max`.
+    ///
+    /// The default implementation filters `entries`; backends with an index on the brightness
+    /// override it to avoid reading every entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the datastore can not be read.
+    fn entries_in_brightness_range(
+        &self,
+        min: f32,
+        max: f32,
+    ) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|entry| (min..=max).contains(&entry.average_brightness))
+            .collect())
+    }
+
+    /// Returns the number of entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the datastore can not be read.
+    fn len(&self) -> Result<usize, Box<dyn Error>> {
+        Ok(self.entries()?.len())
+    }
+
+    /// Returns `true` if the datastore has no entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the datastore can not be read.
+    fn is_empty(&self) -> Result<bool, Box<dyn Error>> {
+        Ok(self.len()? == 0)
+    }
+}
+
+/// Opens the datastore at the given path with the backend that matches its format.
+///
+/// SQLite databases (or new files with one of the `SQLITE_EXTENSIONS`) are opened as
+/// `SqliteDatastore`, binary datastores as `BinaryFileDatastore` and all other files as
+/// `JsonDatastore`.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be opened.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::datastore::open_datastore;
+///
+/// let datastore = open_datastore("src/tests/files/DataStoreJSON/data.json").unwrap();
+/// println!("The datastore contains {} entries.", datastore.len().unwrap());
+/// ```
+pub fn open_datastore(datastore_path: &str) -> Result<Box<dyn Datastore>, Box<dyn Error>> {
+    #[cfg(feature = "sqlite")]
+    if is_sqlite_datastore(datastore_path)
+        || (!is_file(datastore_path) && has_sqlite_extension(datastore_path))
+    {
+        return Ok(Box::new(SqliteDatastore::open(datastore_path)?));
+    }
+    if is_binary_datastore(datastore_path) {
+        return Ok(Box::new(BinaryFileDatastore::open(datastore_path)?));
+    }
+    Ok(Box::new(JsonDatastore::open(datastore_path)?))
+}
+
+/// Returns `true` if the datastore at the given path is opened as `JsonDatastore`, see
+/// `open_datastore`.
+fn is_json_datastore(datastore_path: &str) -> bool {
+    #[cfg(feature = "sqlite")]
+    if is_sqlite_datastore(datastore_path)
+        || (!is_file(datastore_path) && has_sqlite_extension(datastore_path))
+    {
+        return false;
+    }
+    !is_binary_datastore(datastore_path)
+}
+
+/// A change of a datastore that has not been written to its file yet.
+#[derive(Debug, Clone)]
+pub(crate) enum Change {
+    Insert(Box<SearchIndex>),
+    Remove(String),
+}
+
+impl Change {
+    pub(crate) fn apply(&self, entries: &mut Vec<SearchIndex>) {
+        match self {
+            Change::Insert(entry) => {
+                if !replace_entry(entries, (**entry).clone()) {
+                    entries.push((**entry).clone());
+                }
+            }
+            Change::Remove(filepath) => {
+                remove_entries_where(entries, |entry| entry.filepath == *filepath);
+            }
+        }
+    }
+}
+
+/// The entries of a file datastore together with the changes that were not written to the file
+/// yet.
+///
+/// The changes are kept instead of only the resulting entries, so that `flush` can apply them to
+/// the current content of the file and does not overwrite changes made by other processes.
+#[derive(Debug, Default)]
+pub(crate) struct ChangeBuffer {
+    entries: Vec<SearchIndex>,
+    changes: Vec<Change>,
+}
+
+impl ChangeBuffer {
+    pub(crate) fn new(entries: Vec<SearchIndex>) -> Self {
+        ChangeBuffer {
+            entries,
+            changes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, entry: SearchIndex) {
+        self.record(Change::Insert(Box::new(entry)));
+    }
+
+    pub(crate) fn remove(&mut self, filepath: &str) -> Option<SearchIndex> {
+        let removed_entry = self.get(filepath);
+        self.record(Change::Remove(filepath.to_string()));
+        removed_entry
+    }
+
+    pub(crate) fn get(&self, filepath: &str) -> Option<SearchIndex> {
+        self.entries
+            .iter()
+            .find(|entry| entry.filepath == filepath)
+            .cloned()
+    }
+
+    pub(crate) fn entries(&self) -> &[SearchIndex] {
+        &self.entries
+    }
+
+    pub(crate) fn has_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Applies the buffered changes to the entries that are currently stored. The changes are kept
+    /// until the buffer is replaced by one of the stored result.
+    pub(crate) fn apply_to(&self, stored_entries: &mut Vec<SearchIndex>) {
+        for change in &self.changes {
+            change.apply(stored_entries);
+        }
+    }
+
+    fn record(&mut self, change: Change) {
+        change.apply(&mut self.entries);
+        self.changes.push(change);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::color_type::ColorType;
+use crate::grayscale::LumaFormula;
+use crate::picture::{Picture, PictureF32, PictureU8, Sample};
+use crate::picture_view::{AsPictureView, PictureView};
+use std::error::Error;
+use std::f32::consts::{PI, SQRT_2};
+
+/// The thresholds `canny_edges` uses for the shape descriptors of a `SearchIndex`: gradients above
+/// the high one start an edge, gradients above the low one continue it.
+pub const CANNY_THRESHOLDS: (f32, f32) = (0.1, 0.2);
+
+/// The largest gradient magnitude of the Sobel operator on normalized values.
+const MAX_SOBEL_MAGNITUDE: f32 = 4.0 * SQRT_2;
+
+/// Gradient magnitudes closer than this are equal, so rounding errors do not decide which pixels
+/// of an edge are kept.
+const MAGNITUDE_TOLERANCE: f32 = 1e-4;
+
+/// The brightness gradients of a picture.
+///
+/// # Fields
+///
+/// * `magnitude`: The strength of the gradient of every pixel, normalized to `[0.0, 1.0]`.
+/// * `direction`: The direction the brightness increases in, as an angle in radians from
+///   `-PI` to `PI`. 0 points to the right and `PI / 2` down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradients {
+    pub columns: u32,
+    pub lines: u32,
+    pub magnitude: Vec<f32>,
+    pub direction: Vec<f32>,
+}
+
+/// Computes the brightness gradients of a picture or view with the Sobel operator. The gray values
+/// are computed with `LumaFormula::Legacy`; the pixels outside the picture repeat its border.
+///
+/// # Errors
+///
+/// Returns an error if the colour model of the picture is not known (`ColorType::Channels`).
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::edges::compute_gradients;
+/// use imsearch::picture::PictureU8;
+///
+/// // black on the left, white on the right
+/// let pic = PictureU8::new(4, 1, ColorType::Gray, vec![0, 0, 255, 255]).unwrap();
+/// let gradients = compute_gradients(&pic).unwrap();
+///
+/// assert_eq!(gradients.magnitude[0], 0.0);
+/// assert!(gradients.magnitude[1] > 0.5);
+/// assert_eq!(gradients.direction[1], 0.0);
+/// ```
+pub fn compute_gradients<P: AsPictureView>(pic: &P) -> Result<Gradients, Box<dyn Error>> {
+    let gray = pic.as_view().to_grayscale(LumaFormula::Legacy)?;
+    Ok(sobel(&gray))
+}
+
+/// Returns the gray value at `(x, y)`, repeating the border for coordinates outside the picture.
+fn clamped(gray: &PictureF32, x: i64, y: i64) -> f32 {
+    let x = x.clamp(0, i64::from(gray.columns) - 1) as usize;
+    let y = y.clamp(0, i64::from(gray.lines) - 1) as usize;
+    gray.data[y * gray.columns as usize + x]
+}
+
+fn sobel(gray: &PictureF32) -> Gradients {
+    let mut magnitude = Vec::with_capacity(gray.data.len());
+    let mut direction = Vec::with_capacity(gray.data.len());
+    for y in 0..i64::from(gray.lines) {
+        for x in 0..i64::from(gray.columns) {
+            let at = |dx: i64, dy: i64| clamped(gray, x + dx, y + dy);
+            let horizontal = (at(1, -1) + 2.0 * at(1, 0) + at(1, 1))
+                - (at(-1, -1) + 2.0 * at(-1, 0) + at(-1, 1));
+            let vertical = (at(-1, 1) + 2.0 * at(0, 1) + at(1, 1))
+                - (at(-1, -1) + 2.0 * at(0, -1) + at(1, -1));
+            magnitude.push(horizontal.hypot(vertical) / MAX_SOBEL_MAGNITUDE);
+            direction.push(vertical.atan2(horizontal));
+        }
+    }
+    Gradients {
+        columns: gray.columns,
+        lines: gray.lines,
+        magnitude,
+        direction,
+    }
+}
+
+/// Smooths a gray picture with a 3x3 binomial filter, so noise does not produce edges.
+fn smooth(gray: &PictureF32) -> PictureF32 {
+    let mut data = Vec::with_capacity(gray.data.len());
+    for y in 0..i64::from(gray.lines) {
+        for x in 0..i64::from(gray.columns) {
+            let mut sum = 0.0;
+            for (dy, row_weight) in [(-1, 1.0), (0, 2.0), (1, 1.0)] {
+                for (dx, column_weight) in [(-1, 1.0), (0, 2.0), (1, 1.0)] {
+                    sum += row_weight * column_weight * clamped(gray, x + dx, y + dy);
+                }
+            }
+            data.push(sum / 16.0);
+        }
+    }
+    Picture {
+        data,
+        ..gray.clone()
+    }
+}
+
+/// Keeps the pixels whose gradient is stronger than that of both neighbours across the edge.
+fn suppress_non_maxima(gradients: &Gradients) -> Vec<f32> {
+    let (columns, lines) = (gradients.columns as i64, gradients.lines as i64);
+    let magnitude_at = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= columns || y >= lines {
+            0.0
+        } else {
+            gradients.magnitude[(y * columns + x) as usize]
+        }
+    };
+    let mut thin = vec![0.0; gradients.magnitude.len()];
+    for y in 0..lines {
+        for x in 0..columns {
+            let index = (y * columns + x) as usize;
+            let magnitude = gradients.magnitude[index];
+            // the direction rounded to one of 4 neighbour axes
+            let sector =
+                ((gradients.direction[index].rem_euclid(PI) / (PI / 4.0)).round() as i64) % 4;
+            let (dx, dy) = [(1, 0), (1, 1), (0, 1), (-1, 1)][sector as usize];
+            // of two equal neighbours on a step, the one on the darker side is kept
+            if magnitude >= magnitude_at(x + dx, y + dy) - MAGNITUDE_TOLERANCE
+                && magnitude > magnitude_at(x - dx, y - dy) + MAGNITUDE_TOLERANCE
+            {
+                thin[index] = magnitude;
+            }
+        }
+    }
+    thin
+}
+
+impl<T: Sample> PictureView<'_, T> {
+    /// Computes the strength of the brightness gradient of every pixel with the Sobel operator,
+    /// see `compute_gradients`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the colour model of the view is not known (`ColorType::Channels`).
+    pub fn sobel_edges(&self) -> Result<PictureF32, Box<dyn Error>> {
+        let gradients = compute_gradients(self)?;
+        Ok(Picture {
+            lines: gradients.lines,
+            columns: gradients.columns,
+            color_channel_count: 1,
+            color_type: ColorType::Gray,
+            data: gradients.magnitude,
+        })
+    }
+
+    /// Finds the edges of the view with the Canny edge detector: the gray values are smoothed,
+    /// the Sobel gradients thinned to lines of one pixel, and pixels whose gradient is above `high`
+    /// or connected to one through pixels above `low` become edges.
+    ///
+    /// Returns a gray picture in which edges are 255 and everything else is 0. The thresholds are
+    /// gradient magnitudes of `[0.0, 1.0]` (see `Gradients`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the colour model of the view is not known (`ColorType::Channels`) or
+    /// `low` is greater than `high`.
+    pub fn canny_edges(&self, low: f32, high: f32) -> Result<PictureU8, Box<dyn Error>> {
+        Ok(self.canny(low, high)?.0)
+    }
+
+    /// Finds the edges like `canny_edges` and also returns the gradients of the smoothed gray
+    /// values they were found with.
+    pub(crate) fn canny(
+        &self,
+        low: f32,
+        high: f32,
+    ) -> Result<(PictureU8, Gradients), Box<dyn Error>> {
+        if low > high {
+            return Err(format!(
+                "The low threshold {low} of the Canny edge detector is above the high one {high}"
+            )
+            .into());
+        }
+        let gray = smooth(&self.to_grayscale(LumaFormula::Legacy)?);
+        let gradients = sobel(&gray);
+        let thin = suppress_non_maxima(&gradients);
+
+        let (columns, lines) = (gray.columns as i64, gray.lines as i64);
+        let mut edges = vec![0_u8; thin.len()];
+        let mut pending: Vec<(i64, i64)> = Vec::new();
+        for (index, magnitude) in thin.iter().enumerate() {
+            if *magnitude > high {
+                edges[index] = u8::MAX;
+                pending.push((index as i64 % columns, index as i64 / columns));
+            }
+        }
+        while let Some((x, y)) = pending.pop() {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (x, y) = (x + dx, y + dy);
+                    if x < 0 || y < 0 || x >= columns || y >= lines {
+                        continue;
+                    }
+                    let index = (y * columns + x) as usize;
+                    if edges[index] == 0 && thin[index] > low {
+                        edges[index] = u8::MAX;
+                        pending.push((x, y));
+                    }
+                }
+            }
+        }
+        let edges = PictureU8::new(gray.columns, gray.lines, ColorType::Gray, edges)?;
+        Ok((edges, gradients))
+    }
+}
+
+impl<T: Sample> Picture<T> {
+    /// Computes the strength of the brightness gradient of every pixel, see
+    /// `PictureView::sobel_edges`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the colour model of the picture is not known (`ColorType::Channels`).
+    pub fn sobel_edges(&self) -> Result<PictureF32, Box<dyn Error>> {
+        self.view().sobel_edges()
+    }
+
+    /// Finds the edges of the picture with the Canny edge detector, see
+    /// `PictureView::canny_edges`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the colour model of the picture is not known (`ColorType::Channels`) or
+    /// `low` is greater than `high`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use imsearch::color_type::ColorType;
+    /// use imsearch::edges::CANNY_THRESHOLDS;
+    /// use imsearch::picture::PictureU8;
+    ///
+    /// // a white square on black
+    /// let mut data = vec![0; 8 * 8];
+    /// for y in 2..6 {
+    ///     data[y * 8 + 2..y * 8 + 6].fill(255);
+    /// }
+    /// let pic = PictureU8::new(8, 8, ColorType::Gray, data).unwrap();
+    ///
+    /// let (low, high) = CANNY_THRESHOLDS;
+    /// let edges = pic.canny_edges(low, high).unwrap();
+    /// assert_eq!(edges.color_type, ColorType::Gray);
+    /// // the corners of the picture are far from the square
+    /// assert_eq!(edges.get_pixel(0, 0), Some(&[0][..]));
+    /// assert!(edges.data.contains(&255));
+    /// ```
+    pub fn canny_edges(&self, low: f32, high: f32) -> Result<PictureU8, Box<dyn Error>> {
+        self.view().canny_edges(low, high)
+    }
+}
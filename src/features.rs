@@ -0,0 +1,152 @@
+use crate::histogram::BIN_COUNT;
+use crate::search_index::SearchIndex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The maximum number of color channels of a picture (RGBA).
+pub const MAX_COLOR_CHANNELS: u8 = 4;
+
+/// The version of the descriptors this version of the library computes besides the histograms.
+///
+/// Adding or changing a descriptor only increments this version: datastores of an older version are
+/// indexed again when they are migrated (see `migrate_entries`), so the schema version stays the
+/// same.
+///
+/// History of the descriptor versions:
+///
+/// 0. The descriptors of schema version 6.
+/// 1. Entries have the global `statistics` of their picture.
+/// 2. Entries have the `color_moments` of their picture.
+/// 3. Entries have the colour auto-`correlogram` of their picture.
+/// 4. Entries have the `shape` descriptor of their picture.
+/// 5. The hue moments of the `color_moments` are circular.
+pub const DESCRIPTOR_VERSION: u16 = 5;
+
+/// Which descriptors the entries of a datastore keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DescriptorSet {
+    /// All descriptors.
+    #[default]
+    Full,
+    /// Only the average brightness, the lightness and the colour moments, 11 floats per entry.
+    /// Searches compare them with `FeatureWeights::compact`. This trades accuracy for size in very
+    /// large datastores.
+    Compact,
+}
+
+/// Describes which descriptors a datastore contains and how they were computed.
+///
+/// Entries that were computed with a different configuration can not be compared with each other,
+/// so the configuration is stored together with the entries.
+///
+/// # Fields
+///
+/// * `bin_count`: The number of bins of each histogram (see `BIN_COUNT`).
+/// * `color_channels`: The maximum number of color channels whose histograms are stored.
+/// * `descriptor_version`: The `DESCRIPTOR_VERSION` the other descriptors were computed with, 0
+///   in datastores written before it was stored.
+/// * `descriptors`: Which descriptors the entries keep. Compact datastores store no histograms,
+///   so their `color_channels` are 0.
+///
+/// # Examples
+///
+/// ```
+/// use imsearch::features::FeatureConfiguration;
+/// use imsearch::histogram::BIN_COUNT;
+///
+/// let configuration = FeatureConfiguration::default();
+/// assert_eq!(configuration.bin_count, BIN_COUNT);
+///
+/// // entries of an older descriptor version are indexed again, but their histograms can still be
+/// // compared
+/// let outdated = FeatureConfiguration {
+///     descriptor_version: 0,
+///     ..FeatureConfiguration::default()
+/// };
+/// assert_ne!(outdated, configuration);
+/// assert!(outdated.has_same_histograms(&configuration));
+///
+/// // a compact datastore only keeps the brightness and the colour moments
+/// assert_eq!(FeatureConfiguration::compact().color_channels, 0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureConfiguration {
+    pub bin_count: u8,
+    pub color_channels: u8,
+    #[serde(default)]
+    pub descriptor_version: u16,
+    #[serde(default)]
+    pub descriptors: DescriptorSet,
+}
+
+impl FeatureConfiguration {
+    /// Returns the configuration of compact datastores, see `DescriptorSet::Compact`.
+    pub fn compact() -> Self {
+        FeatureConfiguration {
+            color_channels: 0,
+            descriptors: DescriptorSet::Compact,
+            ..FeatureConfiguration::default()
+        }
+    }
+
+    /// Returns the configuration this version of the library computes descriptors with, for the
+    /// same `descriptors`.
+    pub fn current(&self) -> Self {
+        match self.descriptors {
+            DescriptorSet::Full => FeatureConfiguration::default(),
+            DescriptorSet::Compact => FeatureConfiguration::compact(),
+        }
+    }
+
+    /// Returns `true` if the entries need no migration, see `current`.
+    pub fn is_current(&self) -> bool {
+        *self == self.current()
+    }
+
+    /// Removes the descriptors that datastores with this configuration do not keep from the entry.
+    pub fn keep_stored_descriptors(&self, entry: &mut SearchIndex) {
+        if self.descriptors == DescriptorSet::Compact {
+            entry.histogram.clear();
+            entry.grid_cells.clear();
+            entry.statistics = None;
+            entry.correlogram = None;
+            entry.shape = None;
+        }
+    }
+
+    /// Returns the entries as datastores with this configuration store them, see
+    /// `keep_stored_descriptors`.
+    pub(crate) fn stored_entries<'a>(&self, entries: &'a [SearchIndex]) -> Cow<'a, [SearchIndex]> {
+        match self.descriptors {
+            DescriptorSet::Full => Cow::Borrowed(entries),
+            DescriptorSet::Compact => Cow::Owned(
+                entries
+                    .iter()
+                    .map(|entry| {
+                        let mut entry = entry.clone();
+                        self.keep_stored_descriptors(&mut entry);
+                        entry
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns `true` if the histograms of both configurations can be compared with each other.
+    pub fn has_same_histograms(&self, other: &FeatureConfiguration) -> bool {
+        self.bin_count == other.bin_count && self.color_channels == other.color_channels
+    }
+}
+
+impl Default for FeatureConfiguration {
+    /// Returns the configuration this version of the library computes descriptors with.
+    fn default() -> Self {
+        FeatureConfiguration {
+            bin_count: BIN_COUNT,
+            color_channels: MAX_COLOR_CHANNELS,
+            descriptor_version: DESCRIPTOR_VERSION,
+            descriptors: DescriptorSet::Full,
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::compare_pictures::SimilarityInformation;
+use crate::datastore::Datastore;
+use crate::query_descriptor::{FeatureWeights, QueryDescriptor};
+use crate::search::{rank_by, SearchOptions};
+use crate::search_index::SearchIndex;
+use std::error::Error;
+
+/// Keeps a feature from getting an infinite weight if all relevant pictures agree on it.
+const MIN_DEVIATION: f64 = 0.05;
+
+/// Whether a result is what the user is looking for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feedback {
+    Relevant,
+    Irrelevant,
+}
+
+/// The weights of the Rocchio update of the query.
+///
+/// The refined query is `alpha` times the original query plus `beta` times the average of the
+/// relevant results minus `gamma` times the average of the irrelevant results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RocchioParameters {
+    pub alpha: f64,
+    pub beta: f64,
+    pub gamma: f64,
+}
+
+impl Default for RocchioParameters {
+    /// The weights commonly used for Rocchio: 1.0, 0.75 and 0.15.
+    fn default() -> Self {
+        RocchioParameters {
+            alpha: 1.0,
+            beta: 0.75,
+            gamma: 0.15,
+        }
+    }
+}
+
+/// An interactive search that is refined by marking results as relevant or irrelevant.
+///
+/// Every feedback updates the query descriptor with the Rocchio formula (see
+/// `RocchioParameters`) and the `FeatureWeights`: features in which the relevant results agree get
+/// a larger weight. Results marked as irrelevant are not returned again.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::feedback::{Feedback, FeedbackSession};
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::search::SearchOptions;
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let picture = |name: &str| {
+///     generate_suchindex(format!("src/tests/files/pictures_for_testing/{name}.png"))
+/// };
+/// let datastore = MemoryDatastore::with_entries(vec![
+///     picture("bird"),
+///     picture("flower_purple_1"),
+///     picture("flower_purple_2"),
+/// ]);
+///
+/// let mut session = FeedbackSession::new(&picture("flower_purple_1_modified"));
+/// let results = session.search(&datastore, &SearchOptions::top(3)).unwrap().to_vec();
+///
+/// let bird = results
+///     .iter()
+///     .find(|result| result.search_index().filename == "bird")
+///     .unwrap();
+/// session.add_feedback(bird, Feedback::Irrelevant);
+///
+/// let refined = session.search(&datastore, &SearchOptions::top(3)).unwrap();
+/// assert_eq!(refined.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FeedbackSession {
+    original_query: QueryDescriptor,
+    query: QueryDescriptor,
+    weights: FeatureWeights,
+    parameters: RocchioParameters,
+    relevant: Vec<SearchIndex>,
+    irrelevant: Vec<SearchIndex>,
+    results: Vec<SimilarityInformation>,
+}
+
+impl FeedbackSession {
+    /// Starts a session for pictures similar to the query, with uniform feature weights and the
+    /// default `RocchioParameters`.
+    pub fn new(query: &SearchIndex) -> Self {
+        let descriptor = QueryDescriptor::from_search_index(query);
+        FeedbackSession {
+            weights: FeatureWeights::uniform(descriptor.histograms.len()),
+            original_query: descriptor.clone(),
+            query: descriptor,
+            parameters: RocchioParameters::default(),
+            relevant: Vec::new(),
+            irrelevant: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn with_parameters(mut self, parameters: RocchioParameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Returns the current, refined query descriptor.
+    pub fn query(&self) -> &QueryDescriptor {
+        &self.query
+    }
+
+    /// Returns the current feature weights.
+    pub fn weights(&self) -> &FeatureWeights {
+        &self.weights
+    }
+
+    /// Returns the results of the last `search`.
+    pub fn results(&self) -> &[SimilarityInformation] {
+        &self.results
+    }
+
+    /// Ranks the entries of the datastore by their similarity to the current query and returns
+    /// the results selected by the `options`. Entries marked as irrelevant are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the datastore can not be read.
+    pub fn search(
+        &mut self,
+        datastore: &dyn Datastore,
+        options: &SearchOptions,
+    ) -> Result<&[SimilarityInformation], Box<dyn Error>> {
+        let entries: Vec<SearchIndex> = datastore
+            .entries()?
+            .into_iter()
+            .filter(|entry| !contains_filepath(&self.irrelevant, &entry.filepath))
+            .collect();
+        self.results = rank_by(
+            &entries,
+            options,
+            |entry| self.query.similarity_upper_bound(entry, &self.weights),
+            |entry| self.query.score(entry, &self.weights),
+        );
+        Ok(&self.results)
+    }
+
+    /// Marks a result as relevant or irrelevant and refines the query and the feature weights.
+    /// Marking a result again replaces its earlier feedback.
+    pub fn add_feedback(&mut self, result: &SimilarityInformation, feedback: Feedback) {
+        let entry = result.search_index();
+        self.relevant
+            .retain(|relevant| relevant.filepath != entry.filepath);
+        self.irrelevant
+            .retain(|irrelevant| irrelevant.filepath != entry.filepath);
+        match feedback {
+            Feedback::Relevant => self.relevant.push(entry.clone()),
+            Feedback::Irrelevant => self.irrelevant.push(entry.clone()),
+        }
+        self.refine_query();
+        self.refine_weights();
+    }
+
+    /// Applies the Rocchio formula to the original query.
+    ///
+    /// The brightness is the weighted average of the query and the relevant results, because
+    /// subtracting the brightness of the irrelevant results would not give a brightness.
+    fn refine_query(&mut self) {
+        let RocchioParameters { alpha, beta, gamma } = self.parameters;
+        let mut query = self.original_query.clone();
+        query.scale(alpha);
+        let mut brightness_weight = alpha;
+
+        if let Some(relevant_centroid) = QueryDescriptor::centroid(&self.relevant) {
+            query.add_weighted(&relevant_centroid, beta);
+            brightness_weight += beta;
+        }
+        if brightness_weight > 0.0 {
+            query.average_brightness =
+                (f64::from(query.average_brightness) / brightness_weight) as f32;
+        }
+        if let Some(mut irrelevant_centroid) = QueryDescriptor::centroid(&self.irrelevant) {
+            irrelevant_centroid.average_brightness = 0.0;
+            query.add_weighted(&irrelevant_centroid, -gamma);
+        }
+        self.query = query;
+    }
+
+    /// Scales the uniform weight of every feature with the inverse of its standard deviation over
+    /// the relevant results, so the features the relevant results agree on count more. The
+    /// weights keep the total of the uniform weights. With less than two relevant results, the
+    /// weights are uniform.
+    fn refine_weights(&mut self) {
+        let uniform = FeatureWeights::uniform(self.query.histograms.len());
+        if self.relevant.len() < 2 {
+            self.weights = uniform;
+            return;
+        }
+        let similarities: Vec<(f32, Vec<f64>)> = self
+            .relevant
+            .iter()
+            .map(|entry| self.query.feature_similarities(entry))
+            .collect();
+
+        let inverse_deviation =
+            |values: Vec<f64>| 1.0 / (standard_deviation(&values) + MIN_DEVIATION);
+        let brightness = uniform.brightness
+            * inverse_deviation(
+                similarities
+                    .iter()
+                    .map(|(brightness, _)| f64::from(*brightness))
+                    .collect(),
+            );
+        let histogram_channels: Vec<f64> = uniform
+            .histogram_channels
+            .iter()
+            .enumerate()
+            .map(|(channel, weight)| {
+                weight
+                    * inverse_deviation(
+                        similarities
+                            .iter()
+                            .map(|(_, channels)| channels[channel])
+                            .collect(),
+                    )
+            })
+            .collect();
+
+        let factor = uniform.total() / (brightness + histogram_channels.iter().sum::<f64>());
+        self.weights = FeatureWeights {
+            statistics: uniform.statistics,
+            color_moments: uniform.color_moments,
+            correlogram: uniform.correlogram,
+            shape: uniform.shape,
+            brightness: brightness * factor,
+            histogram_channels: histogram_channels
+                .into_iter()
+                .map(|weight| weight * factor)
+                .collect(),
+        };
+    }
+}
+
+fn contains_filepath(entries: &[SearchIndex], filepath: &str) -> bool {
+    entries.iter().any(|entry| entry.filepath == filepath)
+}
+
+fn standard_deviation(values: &[f64]) -> f64 {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+    variance.sqrt()
+}
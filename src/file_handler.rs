@@ -1,4 +1,5 @@
 use std::fs;
+use std::io::Write;
 use std::path::Path;
 
 /// Determines if the given filepath points to a directory.
@@ -187,3 +188,144 @@ pub fn extract_filename(filepath: String) -> String {
         filename.to_string()
     }
 }
+
+/// Determines if the given filepath points to a picture file this library can read (PNG).
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice representing the filepath to check.
+///
+/// # Returns
+///
+/// Returns `true` if the filepath points to a file with a `.png` extension, `false` otherwise.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::file_handler::is_picture_file;
+/// assert!(is_picture_file("src/tests/files/pictures_for_testing/bird.png"));
+/// assert!(!is_picture_file("src/tests/files/DataStoreJSON/data.json"));
+/// ```
+pub fn is_picture_file(filepath: &str) -> bool {
+    let path = Path::new(filepath);
+    path.is_file()
+        && path
+            .extension()
+            .map(|extension| extension.eq_ignore_ascii_case("png"))
+            .unwrap_or(false)
+}
+
+/// Collects all picture files in a folder and its subfolders.
+///
+/// The returned filepaths are formatted with `format_filepath` and sorted, so that walking the same
+/// folder twice yields the same order.
+///
+/// # Arguments
+///
+/// * `folder_path` - The path to the folder which should be walked.
+///
+/// # Errors
+///
+/// Returns an error if the folder or one of its subfolders can not be read.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::file_handler::collect_picture_files;
+/// let pictures = collect_picture_files("src/tests/files/pictures_for_testing").unwrap();
+/// assert!(pictures.contains(&"src/tests/files/pictures_for_testing/bird.png".to_string()));
+/// ```
+pub fn collect_picture_files(folder_path: &str) -> Result<Vec<String>, std::io::Error> {
+    let mut picture_files = Vec::new();
+    let mut folders_to_visit = vec![Path::new(folder_path).to_path_buf()];
+
+    while let Some(folder) = folders_to_visit.pop() {
+        for entry in fs::read_dir(&folder)?.filter_map(|entry| entry.ok()) {
+            let entry_path = entry.path();
+            if entry_path.is_dir() {
+                folders_to_visit.push(entry_path);
+            } else if let Some(file_path) = entry_path.to_str() {
+                if is_picture_file(file_path) {
+                    picture_files.push(format_filepath(file_path));
+                }
+            }
+        }
+    }
+
+    picture_files.sort();
+    Ok(picture_files)
+}
+
+/// Computes a content hash of a file.
+///
+/// The hash is the 64 bit FNV-1a hash of the file's bytes, formatted as 16 hexadecimal digits.
+/// It is used to recognise files that were moved or changed since they were indexed.
+/// It is not meant to be cryptographically secure.
+///
+/// # Arguments
+///
+/// * `filepath` - The path to the file which should be hashed.
+///
+/// # Errors
+///
+/// Returns an error if the file can not be read.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::file_handler::compute_file_hash;
+/// let hash = compute_file_hash("src/tests/files/pictures_for_testing/bird.png").unwrap();
+/// assert_eq!(hash.len(), 16);
+/// ```
+pub fn compute_file_hash(filepath: &str) -> Result<String, std::io::Error> {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let bytes = fs::read(filepath)?;
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    Ok(format!("{hash:016x}"))
+}
+
+/// Writes the contents to a file without ever leaving a partially written file behind.
+///
+/// The contents are first written to a temporary file next to the target, flushed to the disk and
+/// then renamed to the target filepath. Renaming replaces the target in one step, so other readers
+/// either see the old or the new contents, even if the process crashes while writing.
+///
+/// # Arguments
+///
+/// * `filepath` - The path to the file which is written.
+/// * `contents` - The new contents of the file.
+///
+/// # Errors
+///
+/// Returns an error if the temporary file can not be written or renamed.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::file_handler::write_file_atomically;
+/// let filepath = std::env::temp_dir().join("imsearch_doc_write_file_atomically.txt");
+/// write_file_atomically(filepath.to_str().unwrap(), b"Hello, world!").unwrap();
+/// assert_eq!(std::fs::read(&filepath).unwrap(), b"Hello, world!");
+/// ```
+pub fn write_file_atomically(filepath: &str, contents: &[u8]) -> Result<(), std::io::Error> {
+    let temp_filepath = format!("{filepath}.{}.tmp", std::process::id());
+
+    let result = (|| {
+        let mut temp_file = fs::File::create(&temp_filepath)?;
+        temp_file.write_all(contents)?;
+        temp_file.sync_all()?;
+        fs::rename(&temp_filepath, filepath)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_filepath);
+    }
+    result
+}
@@ -0,0 +1,198 @@
+use crate::color_type::ColorType;
+use crate::picture::{Picture, PictureF32, Sample};
+use crate::picture_view::{AsPictureView, PictureView};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A formula that turns red, green and blue into a single gray value.
+///
+/// All formulas take normalized sRGB values (`[0.0, 1.0]`, gamma encoded like the samples of a PNG
+/// file) and return a gray value in `[0.0, 1.0]`. Gray pixels keep their value with the luma
+/// formulas (`Legacy`, `Rec601` and `Rec709`); `RelativeLuminance` is linear in the emitted light
+/// and `Lightness` in the perceived lightness.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::grayscale::LumaFormula;
+///
+/// // pure green looks much brighter than pure blue
+/// let green = LumaFormula::Lightness.luma(0.0, 1.0, 0.0);
+/// let blue = LumaFormula::Lightness.luma(0.0, 0.0, 1.0);
+/// assert!(green > 0.8 && blue < 0.35);
+/// assert_eq!(LumaFormula::Rec709.luma(0.5, 0.5, 0.5), 0.5);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LumaFormula {
+    /// The weights 0.3, 0.59 and 0.11 this crate has always used for the average brightness.
+    #[default]
+    Legacy,
+    /// Luma of ITU-R BT.601 (standard definition video, JPEG): 0.299, 0.587 and 0.114.
+    Rec601,
+    /// Luma of ITU-R BT.709 (HD video): 0.2126, 0.7152 and 0.0722 on the gamma encoded values.
+    Rec709,
+    /// The relative luminance `Y` of CIE XYZ: the BT.709 weights on linearised sRGB values.
+    RelativeLuminance,
+    /// The perceived lightness `L*` of CIE L\*a\*b\*, divided by 100.
+    Lightness,
+}
+
+impl LumaFormula {
+    pub const ALL: [LumaFormula; 5] = [
+        LumaFormula::Legacy,
+        LumaFormula::Rec601,
+        LumaFormula::Rec709,
+        LumaFormula::RelativeLuminance,
+        LumaFormula::Lightness,
+    ];
+
+    /// Returns the gray value of normalized red, green and blue values.
+    pub fn luma(&self, red: f32, green: f32, blue: f32) -> f32 {
+        match self {
+            LumaFormula::Legacy => (0.3 * red) + (0.59 * green) + (0.11 * blue),
+            LumaFormula::Rec601 => 0.299 * red + 0.587 * green + 0.114 * blue,
+            LumaFormula::Rec709 => weigh_rec709(red, green, blue),
+            LumaFormula::RelativeLuminance => relative_luminance(red, green, blue),
+            LumaFormula::Lightness => lightness(relative_luminance(red, green, blue)),
+        }
+    }
+}
+
+impl Display for LumaFormula {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LumaFormula::Legacy => "legacy",
+            LumaFormula::Rec601 => "rec601",
+            LumaFormula::Rec709 => "rec709",
+            LumaFormula::RelativeLuminance => "luminance",
+            LumaFormula::Lightness => "lightness",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for LumaFormula {
+    type Err = String;
+
+    /// Parses the names written by `Display`, e.g. `rec709`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        LumaFormula::ALL
+            .into_iter()
+            .find(|formula| formula.to_string() == name)
+            .ok_or_else(|| format!("Unknown luma formula '{name}'"))
+    }
+}
+
+fn weigh_rec709(red: f32, green: f32, blue: f32) -> f32 {
+    0.2126 * red + 0.7152 * green + 0.0722 * blue
+}
+
+/// Removes the gamma encoding of a normalized sRGB value.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn relative_luminance(red: f32, green: f32, blue: f32) -> f32 {
+    weigh_rec709(
+        srgb_to_linear(red),
+        srgb_to_linear(green),
+        srgb_to_linear(blue),
+    )
+}
+
+/// Returns `L* / 100` of a relative luminance.
+fn lightness(luminance: f32) -> f32 {
+    const EPSILON: f32 = 216.0 / 24_389.0;
+    const KAPPA: f32 = 24_389.0 / 27.0;
+    let lightness = if luminance > EPSILON {
+        116.0 * luminance.cbrt() - 16.0
+    } else {
+        KAPPA * luminance
+    };
+    (lightness / 100.0).clamp(0.0, 1.0)
+}
+
+impl<T: Sample> PictureView<'_, T> {
+    /// Computes the gray value of every pixel with the given formula. Alpha channels are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the colour model of the view is not known (`ColorType::Channels`).
+    pub fn to_grayscale(&self, formula: LumaFormula) -> Result<PictureF32, Box<dyn Error>> {
+        let gray_values = self.gray_intensities(formula).ok_or_else(|| {
+            format!(
+                "Can not compute the gray values of a picture with {}",
+                self.color_type()
+            )
+        })?;
+        Ok(Picture {
+            lines: self.lines(),
+            columns: self.columns(),
+            color_channel_count: 1,
+            color_type: ColorType::Gray,
+            data: gray_values.collect(),
+        })
+    }
+}
+
+impl<T: Sample> Picture<T> {
+    /// Computes the gray value of every pixel with the given formula, see
+    /// `PictureView::to_grayscale`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the colour model of the picture is not known (`ColorType::Channels`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use imsearch::color_type::ColorType;
+    /// use imsearch::grayscale::LumaFormula;
+    /// use imsearch::picture::PictureU8;
+    ///
+    /// let pic = PictureU8::new(2, 1, ColorType::Rgba, vec![255, 255, 255, 0, 0, 0, 0, 255]).unwrap();
+    ///
+    /// let gray = pic.to_grayscale(LumaFormula::Rec601).unwrap();
+    /// assert_eq!(gray.color_type, ColorType::Gray);
+    /// assert_eq!(gray.data, vec![1.0, 0.0]);
+    /// ```
+    pub fn to_grayscale(&self, formula: LumaFormula) -> Result<PictureF32, Box<dyn Error>> {
+        self.view().to_grayscale(formula)
+    }
+}
+
+/// Calculates the average gray value of a picture or view with the given formula, without
+/// collecting the gray value of every pixel first. It is NaN if the colour model of the picture is
+/// not known (`ColorType::Channels`).
+///
+/// `determine_avg_brightness` is the average with `LumaFormula::Legacy`; `LumaFormula::Lightness`
+/// follows the perceived lightness more closely.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::grayscale::{average_luma, LumaFormula};
+/// use imsearch::read_picture;
+///
+/// let bird = read_picture("src/tests/files/pictures_for_testing/bird.png");
+/// let lightness = average_luma(&bird, LumaFormula::Lightness);
+/// assert!((0.0..=1.0).contains(&lightness));
+/// ```
+pub fn average_luma<P: AsPictureView>(pic: &P, formula: LumaFormula) -> f32 {
+    let view = pic.as_view();
+    let Some(gray_values) = view.gray_intensities(formula) else {
+        return f32::NAN;
+    };
+    let mut sum_grey: f32 = 0.0;
+    let mut pixels: usize = 0;
+    for gray_value in gray_values {
+        sum_grey += gray_value;
+        pixels += 1;
+    }
+    sum_grey / pixels as f32
+}
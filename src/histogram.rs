@@ -10,6 +10,13 @@ pub struct Histogram {
 }
 
 pub const BIN_COUNT: u8 = 5; // only dividers of 255 work: 1, 3, 5, 17, 51, 85, 255
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Histogram {
     /// Creates a new empty histogram.
     ///
@@ -0,0 +1,106 @@
+use crate::datastore::{
+    read_stored_datastore_file, write_datastore_file, ChangeBuffer, Datastore, DatastoreLock,
+    Modification,
+};
+use crate::features::FeatureConfiguration;
+use crate::search_index::SearchIndex;
+use std::error::Error;
+
+/// A datastore in a JSON file, the format `write_data_to_filepath` writes.
+///
+/// The entries are read once when the datastore is opened. Changes are buffered in memory until
+/// `flush` or `modify` is called, which apply them to the current content of the file while
+/// holding the `DatastoreLock`, so changes made by other processes in the meantime are kept.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::datastore::Datastore;
+/// use imsearch::json_datastore::JsonDatastore;
+/// use imsearch::search_index::SearchIndex;
+///
+/// let datastore_path = std::env::temp_dir().join("imsearch_doc_json_datastore.json");
+/// let datastore_path = datastore_path.to_str().unwrap();
+///
+/// let mut datastore = JsonDatastore::open(datastore_path).unwrap();
+/// datastore
+///     .insert(SearchIndex::new("pictures/bird.png".to_string(), 0.4, vec![]))
+///     .unwrap();
+/// datastore.flush().unwrap();
+///
+/// let datastore = JsonDatastore::open(datastore_path).unwrap();
+/// assert!(datastore.get("pictures/bird.png").unwrap().is_some());
+/// ```
+#[derive(Debug)]
+pub struct JsonDatastore {
+    path: String,
+    features: FeatureConfiguration,
+    buffer: ChangeBuffer,
+}
+
+impl JsonDatastore {
+    /// Opens the JSON datastore at the given path. A file that does not exist yet is created by the
+    /// first `flush`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but can not be read (see `read_datastore_file`).
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let (features, entries) = read_stored_datastore_file(path)?;
+        Ok(JsonDatastore {
+            path: path.to_string(),
+            features,
+            buffer: ChangeBuffer::new(entries),
+        })
+    }
+}
+
+impl Datastore for JsonDatastore {
+    fn insert(&mut self, entry: SearchIndex) -> Result<(), Box<dyn Error>> {
+        self.buffer.insert(entry);
+        Ok(())
+    }
+
+    fn get(&self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        Ok(self.buffer.get(filepath))
+    }
+
+    fn remove(&mut self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        Ok(self.buffer.remove(filepath))
+    }
+
+    fn entries(&self) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        Ok(self.buffer.entries().to_vec())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.buffer.has_changes() {
+            return Ok(());
+        }
+        self.modify(&mut |_| Ok(()))
+    }
+
+    /// Applies the modification to the current content of the file while holding the
+    /// `DatastoreLock`.
+    fn modify(&mut self, modification: &mut Modification) -> Result<(), Box<dyn Error>> {
+        let _lock = DatastoreLock::acquire(&self.path)?;
+        let (features, mut entries) = read_stored_datastore_file(&self.path)?;
+        self.buffer.apply_to(&mut entries);
+        modification(&mut entries)?;
+        for entry in &mut entries {
+            features.keep_stored_descriptors(entry);
+        }
+        write_datastore_file(&self.path, &entries, &features)?;
+        self.features = features;
+        self.buffer = ChangeBuffer::new(entries);
+        Ok(())
+    }
+
+    fn len(&self) -> Result<usize, Box<dyn Error>> {
+        Ok(self.buffer.entries().len())
+    }
+
+    fn features(&self) -> FeatureConfiguration {
+        self.features.clone()
+    }
+}
@@ -1,25 +1,57 @@
 // Here all of the files for the library have to be added.
 // If they are added, they get executed when cargo run is called.
 
+pub mod ann_index;
+pub mod batch_search;
+pub mod binary_datastore;
+pub mod cli;
+pub mod color_moments;
+pub mod color_type;
 pub mod compare_pictures;
+pub mod correlogram;
 pub mod cosinus_similarity;
+pub mod datastore;
+pub mod edges;
 pub mod escape;
+pub mod features;
+pub mod feedback;
 pub mod file_handler;
+pub mod grayscale;
 pub mod histogram;
+pub mod json_datastore;
+pub mod memory_datastore;
+pub mod migration;
+pub mod multi_query;
+pub mod orientation;
 pub mod picture;
+pub mod picture_view;
+pub mod query;
+pub mod query_descriptor;
+pub mod region;
+pub mod search;
 pub mod search_index;
+pub mod shape;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_datastore;
+pub mod statistics;
+pub mod sync;
 mod tests;
+pub mod transform;
 pub mod user_input;
 pub mod with_threads;
 
-const DEFAULT_DATASTORE_FILEPATH: &str = "src/tests/files/DataStoreJSON/data.json";
+pub(crate) const DEFAULT_DATASTORE_FILEPATH: &str = "src/tests/files/DataStoreJSON/data.json";
 use std::env;
 use std::error::Error;
 use std::fs::File;
 
+use crate::color_type::ColorType;
 use crate::compare_pictures::{calculate_similarities, SimilarityInformation};
 use crate::cosinus_similarity::determine_similarity_of_search_index_histograms;
-use crate::picture::{AverageBrightness, PictureF32};
+use crate::file_handler::format_filepath;
+use crate::picture::{AverageBrightness, PictureF32, Sample};
+use crate::picture_view::AsPictureView;
+use crate::search::search;
 use crate::search_index::{generate_suchindex, generate_suchindex_to_file, SearchIndex};
 use crate::user_input::input;
 pub use {
@@ -55,29 +87,58 @@ pub use {
 ///
 /// This function panics if there are any errors while reading the image file or decoding its contents.
 pub fn read_picture(path: &str) -> PictureU8 {
+    try_read_picture(path).unwrap()
+}
+
+/// Reads an image file and returns the image data as a `PictureU8` struct.
+///
+/// This is the fallible counterpart of `read_picture`. Instead of panicking, it returns an error if the
+/// file can not be opened or is not a valid PNG.
+///
+/// # Arguments
+///
+/// * `path` - A string slice representing the path to the image file.
+///
+/// # Examples
+///
+/// ```
+/// use imsearch::try_read_picture;
+///
+/// assert!(try_read_picture("src/tests/files/pictures_for_testing/bird.png").is_ok());
+/// assert!(try_read_picture("src/tests/files/DataStoreJSON/data.json").is_err());
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the file can not be opened or decoded.
+pub fn try_read_picture(path: &str) -> Result<PictureU8, Box<dyn Error>> {
     //load picture
-    let decoder = png::Decoder::new(File::open(path).unwrap());
-    let mut reader = decoder.read_info().unwrap();
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    // palettes and gray values with fewer than 8 bits become 8-bit samples
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
     // Allocate the output buffer.
     let mut buf = vec![0; reader.output_buffer_size()];
     // Read the next frame. An APNG might contain multiple frames.
-    let info = reader.next_frame(&mut buf).unwrap(); // Example OutputInfo { width: 1078, height: 1830, color_type: Rgba, bit_depth: Eight, line_size: 4312 }
+    let info = reader.next_frame(&mut buf)?; // Example OutputInfo { width: 1078, height: 1830, color_type: Rgba, bit_depth: Eight, line_size: 4312 }
 
     // Grab the bytes of the image.
     let picture_data = &buf[..info.buffer_size()];
 
-    PictureU8 {
+    Ok(PictureU8 {
         lines: info.height,
         columns: info.width,
         color_channel_count: info.color_type.samples(),
+        color_type: ColorType::from_png(info.color_type),
         data: Vec::from(picture_data), //muss von &[u8] gecastet werden
-    }
+    })
 }
 
 /// Prints histograms of color channels using different bar symbols based on the number of color channels.
 ///
-/// This function takes a vector of histograms and prints each histogram in a separate section. The bar symbols used to represent
-/// the histogram bins vary based on the number of color channels present in the histograms.
+/// This function takes a vector of histograms and prints each histogram in a separate section. The
+/// colour type is guessed from the number of histograms (see `ColorType::from_channel_count`); use
+/// `print_diagrams` if it is known.
 ///
 /// # Arguments
 ///
@@ -104,7 +165,7 @@ pub fn read_picture(path: &str) -> PictureU8 {
 /// Output:
 /// ```text
 /// Division of the values in 6 bins.
-/// Histogram of color channel 0:
+/// Histogram of color channel 0 (Y):
 /// 0: ███
 /// 1: ████
 /// 2: █
@@ -112,48 +173,57 @@ pub fn read_picture(path: &str) -> PictureU8 {
 /// 4: ████
 /// 5: ███
 ///
-/// Histogram of color channel 1:
-/// 0: █
-/// 1: ████
-/// 2: ██
-/// 3: █
+/// Histogram of color channel 1 (A):
+/// 0: ▒
+/// 1: ▒▒▒▒
+/// 2: ▒▒
+/// 3: ▒
 /// 4:
-/// 5: █
+/// 5: ▒
 /// ```
 ///
 /// # Panics
 ///
 /// This function does not panic.
 pub fn print_all_diagrams(histograms: Vec<Histogram>) {
-    println!(
-        "Division of the values in {} bins.",
-        histograms[0].bins.len()
-    );
-    //color_channel_count: 1 -> █
-    //color_channel_count: 3 -> R, G, B
-    //color_channel_count: 4 -> R, G, B, ▒
-    for current_color_channel in 0..histograms.len() {
-        let bar_symbol = match histograms.len() {
-            1 => String::from("█"),
-            3 => match current_color_channel {
-                0 => red_escape("█"),
-                1 => green_escape("█"),
-                2 => blue_escape("█"),
-                _ => String::from("█"),
-            },
-            4 => match current_color_channel {
-                0 => red_escape("█"),
-                1 => green_escape("█"),
-                2 => blue_escape("█"),
-                3 => String::from("▒"),
-                _ => String::from("█"),
-            },
+    let color_type = ColorType::from_channel_count(histograms.len());
+    print_diagrams(&histograms, color_type);
+}
+
+/// Prints the histograms of a picture with the given colour type. Red, green and blue channels are
+/// drawn in their colour, alpha channels with `▒` and all other channels with `█`.
+///
+/// # Examples
+///
+/// ```
+/// use imsearch::color_type::ColorType;
+/// use imsearch::{get_histogram, print_diagrams, PictureU8};
+///
+/// let pic = PictureU8::new(2, 1, ColorType::GrayAlpha, vec![0, 255, 128, 255]).unwrap();
+/// print_diagrams(&get_histogram(&pic), pic.color_type);
+/// ```
+pub fn print_diagrams(histograms: &[Histogram], color_type: ColorType) {
+    let Some(first) = histograms.first() else {
+        return;
+    };
+    println!("Division of the values in {} bins.", first.bins.len());
+    let names = color_type.channel_names();
+    for (current_color_channel, histogram) in histograms.iter().enumerate() {
+        let name = names
+            .get(current_color_channel)
+            .map(String::as_str)
+            .unwrap_or_default();
+        let bar_symbol = match (color_type, name) {
+            (ColorType::Rgb | ColorType::Rgba, "R") => red_escape("█"),
+            (ColorType::Rgb | ColorType::Rgba, "G") => green_escape("█"),
+            (ColorType::Rgb | ColorType::Rgba, "B") => blue_escape("█"),
+            (_, "A") => String::from("▒"),
             _ => String::from("█"),
         };
 
-        println!("Histogram of color channel {current_color_channel}:");
+        println!("Histogram of color channel {current_color_channel} ({name}):");
 
-        histograms[current_color_channel].print_diagram(bar_symbol);
+        histogram.print_diagram(bar_symbol);
 
         println!();
     }
@@ -164,12 +234,13 @@ pub fn print_all_diagrams(histograms: Vec<Histogram>) {
 ///
 /// # Arguments
 ///
-/// * `pic` - A reference to a `Picture` trait object. These need to implement to_picture_u8()
-///         which is needed for this function.
+/// * `pic` - A picture or view of any sample type. Samples that are not `u8` are converted one by
+///   one to sort them into the bins.
 ///
 /// # Examples
 ///
 /// ```
+/// use imsearch::color_type::ColorType;
 /// use imsearch::get_histogram;
 /// use imsearch::picture::{PictureF32, PictureU8};
 ///
@@ -179,6 +250,7 @@ pub fn print_all_diagrams(histograms: Vec<Histogram>) {
 ///     columns: 3,
 ///     data: vec![0, 255, 25, 99], // Sample image data
 ///     color_channel_count: 2,
+///     color_type: ColorType::GrayAlpha,
 /// };
 /// // Create a sample PictureF32
 /// let picture_f32 = PictureF32 {
@@ -186,6 +258,7 @@ pub fn print_all_diagrams(histograms: Vec<Histogram>) {
 ///     columns: 3,
 ///     data: vec![0.0, 1.0, 0.1, 0.38], // Sample image data
 ///     color_channel_count: 2,
+///     color_type: ColorType::GrayAlpha,
 /// };
 ///
 /// let histograms_u8 = get_histogram(&picture_u8);
@@ -203,19 +276,15 @@ pub fn print_all_diagrams(histograms: Vec<Histogram>) {
 /// assert_eq!(histograms_f32[1].bins[1], 1);
 /// assert_eq!(histograms_f32[1].bins[4], 1);
 /// ```
-pub fn get_histogram(pic: &dyn Picture) -> Vec<Histogram> {
-    // convert any Picture-Object to PictureU8
-    let pic_u8 = pic.to_picture_u8();
-
-    let mut histograms: Vec<Histogram> = vec![Histogram::new(); pic_u8.color_channel_count];
+pub fn get_histogram<P: AsPictureView>(pic: &P) -> Vec<Histogram> {
+    let view = pic.as_view();
+    let mut histograms: Vec<Histogram> = vec![Histogram::new(); view.color_channel_count()];
 
     // komplette Daten durchiterieren, immer je Daten zu 1 Pixel ansehen (abhängig von color_channel_count)
-    let mut current_index: usize = 0;
-    while current_index < pic_u8.data.len() {
-        for i in 0..pic_u8.color_channel_count {
-            histograms[i].add_pixel_to_correct_bin(pic_u8.data[current_index + i]);
+    for pixel in view.pixel_samples() {
+        for (histogram, sample) in histograms.iter_mut().zip(pixel) {
+            histogram.add_pixel_to_correct_bin(sample.convert_to());
         }
-        current_index += pic_u8.color_channel_count;
     }
 
     histograms
@@ -239,11 +308,56 @@ pub fn get_datastore_path() -> Result<String, Box<dyn Error>> {
         Ok(path) => Ok(path),
         Err(_) => {
             //eprintln!("datastore_filepath was not set. Using default filepath. Error: {}", err);
-            Ok(DEFAULT_DATASTORE_FILEPATH.to_string())
+            Ok(default_datastore_path().to_string())
         }
     }
 }
 
+#[cfg(not(test))]
+fn default_datastore_path() -> &'static str {
+    DEFAULT_DATASTORE_FILEPATH
+}
+
+/// The unit tests use a copy of the default datastore, so they do not change the checked-in file.
+#[cfg(test)]
+fn default_datastore_path() -> &'static str {
+    tests::test_helpers::test_datastore_path()
+}
+
+/// Configures the folders which make up the picture library.
+///
+/// The folders are used by `sync_library` to keep the datastore up to date.
+///
+/// # Environment Variables
+///
+/// - `IMSEARCH_LIBRARY_ROOTS`: The library folders, separated like entries of the `PATH` variable.
+///
+/// # Errors
+///
+/// Returns an error if one of the folders contains the path separator.
+pub fn set_library_roots(roots: &[&str]) -> Result<(), Box<dyn Error>> {
+    let joined_roots = env::join_paths(roots)?;
+    env::set_var("IMSEARCH_LIBRARY_ROOTS", joined_roots);
+    Ok(())
+}
+
+/// Returns the configured folders of the picture library.
+///
+/// An empty `Vec` is returned if no folders were configured.
+///
+/// # Environment Variables
+///
+/// - `IMSEARCH_LIBRARY_ROOTS`: The library folders, separated like entries of the `PATH` variable.
+pub fn get_library_roots() -> Vec<String> {
+    match env::var_os("IMSEARCH_LIBRARY_ROOTS") {
+        Some(roots) => env::split_paths(&roots)
+            .filter_map(|root| root.to_str().map(format_filepath))
+            .filter(|root| !root.is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
 pub fn get_pictures_from_user() {
     //Input User: SearchPool
     loop {
@@ -373,7 +487,7 @@ pub fn get_average_brightness_of_two_pictures(path1: &str, path2: &str) -> f32 {
 /// It does not perform any input validation, so ensure that the search indexes are properly constructed and represent valid data.
 ///
 pub fn get_cosinus_similarity(search_index1: SearchIndex, search_index2: SearchIndex) -> f64 {
-    determine_similarity_of_search_index_histograms(search_index1, search_index2) * 100.0
+    determine_similarity_of_search_index_histograms(&search_index1, &search_index2) * 100.0
 }
 
 /// Retrieves the top five similar pictures based on a given picture path.
@@ -397,13 +511,12 @@ pub fn get_cosinus_similarity(search_index1: SearchIndex, search_index2: SearchI
 ///
 /// This function assumes that the picture file exists at the specified path and is in a supported format.
 /// It does not perform any input validation, so ensure that the path is valid and accessible.
-/// The function relies on the underlying implementation of `get_all_similar_pictures` to handle the safety and correctness of the similarity retrieval process.
+/// The function relies on `search`, use it directly for another number of pictures.
 ///
 pub fn get_top_five_similar_pictures(
     path: &str,
 ) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
-    let similar_pictures = get_all_similar_pictures(path)?;
-    Ok(similar_pictures.iter().take(5).cloned().collect())
+    search(path, 5)
 }
 
 /// Retrieves a list of all similar pictures based on a given picture path.
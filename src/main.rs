@@ -1,19 +1,28 @@
-use imsearch::search_index::{analyse_pictures, generate_suchindex_to_file};
-use imsearch::user_input::input_search_image;
+use imsearch::cli::{parse_command, run_command, USAGE};
+use imsearch::search_index::analyse_pictures;
+use imsearch::user_input::{input_search_image, refine_search};
 use imsearch::{
     get_average_brightness_of_picture, get_histogram, get_pictures_from_user,
-    get_top_five_similar_pictures, print_all_diagrams, print_calculated_similar_pictures,
-    read_picture, PictureU8,
+    get_top_five_similar_pictures, print_calculated_similar_pictures, print_diagrams, read_picture,
+    PictureU8,
 };
-
-// Example filepaths.
-const PICTURE_FILEPATH_BIRD: &str = "src/tests/files/pictures_for_testing/bird.png";
-const PICTURE_FILEPATH_FLOWER2: &str =
-    "src/tests/files/pictures_for_testing/flower_purple_1_modified.png";
+use std::{env, process};
 
 fn main() {
+    // Commands like `imsearch sync` or `imsearch prune` work on the datastore directly.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        let result = parse_command(&args)
+            .map_err(|message| format!("{message}\n\n{USAGE}").into())
+            .and_then(run_command);
+        if let Err(err) = result {
+            eprintln!("Error: {err}");
+            process::exit(1);
+        }
+        return;
+    }
 
-    analyse_pictures("src/tests/files/pictures_for_testing/");
+    analyse_pictures("src/tests/files/pictures_for_testing/").unwrap();
 
     // Asking the user to add elements to the picture library.
     // Later you can compare pictures to the library which was provided.
@@ -40,7 +49,7 @@ fn main() {
     // Calculate color histograms for a picture.
     let histograms = get_histogram(&pic_u8);
     // Histograms can also be printed to the console.
-    print_all_diagrams(histograms);
+    print_diagrams(&histograms, pic_u8.color_type);
 
     // Main usage of the library.
     // Compare a picture to the pictureLibrary (also user-generated).
@@ -49,4 +58,9 @@ fn main() {
         get_top_five_similar_pictures(picture_path.clone().as_str()).unwrap();
     //The most similar pictures can be printed to the console.
     print_calculated_similar_pictures(similar_five_pictures);
+
+    // The search can be refined by marking the results as relevant or irrelevant.
+    if let Err(err) = refine_search(&picture_path) {
+        eprintln!("Error: {err}");
+    }
 }
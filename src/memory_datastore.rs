@@ -0,0 +1,110 @@
+use crate::datastore::{Change, Datastore, Modification};
+use crate::features::FeatureConfiguration;
+use crate::search_index::SearchIndex;
+use std::error::Error;
+
+/// A datastore that only keeps its entries in memory.
+///
+/// It is meant for tests and for searching pictures that do not need to be stored.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::datastore::Datastore;
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::search_index::SearchIndex;
+///
+/// let mut datastore = MemoryDatastore::new();
+/// datastore
+///     .insert(SearchIndex::new("pictures/bird.png".to_string(), 0.4, vec![]))
+///     .unwrap();
+///
+/// assert_eq!(datastore.len().unwrap(), 1);
+/// assert!(datastore.remove("pictures/bird.png").unwrap().is_some());
+/// assert!(datastore.is_empty().unwrap());
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryDatastore {
+    entries: Vec<SearchIndex>,
+    features: FeatureConfiguration,
+}
+
+impl MemoryDatastore {
+    /// Creates an empty datastore.
+    pub fn new() -> Self {
+        MemoryDatastore::default()
+    }
+
+    /// Creates a datastore with the given entries. Later entries replace earlier entries with the
+    /// same filepath.
+    pub fn with_entries(entries: Vec<SearchIndex>) -> Self {
+        let mut datastore = MemoryDatastore::new();
+        for entry in entries {
+            Change::Insert(Box::new(entry)).apply(&mut datastore.entries);
+        }
+        datastore
+    }
+
+    /// Keeps the entries like a datastore with the given feature configuration, e.g.
+    /// `FeatureConfiguration::compact`. Descriptors it does not keep are removed from the entries,
+    /// see `FeatureConfiguration::keep_stored_descriptors`.
+    pub fn with_features(mut self, features: FeatureConfiguration) -> Self {
+        for entry in &mut self.entries {
+            features.keep_stored_descriptors(entry);
+        }
+        self.features = features;
+        self
+    }
+}
+
+impl Datastore for MemoryDatastore {
+    fn insert(&mut self, mut entry: SearchIndex) -> Result<(), Box<dyn Error>> {
+        self.features.keep_stored_descriptors(&mut entry);
+        Change::Insert(Box::new(entry)).apply(&mut self.entries);
+        Ok(())
+    }
+
+    fn get(&self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        Ok(self
+            .entries
+            .iter()
+            .find(|entry| entry.filepath == filepath)
+            .cloned())
+    }
+
+    fn remove(&mut self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.filepath == filepath);
+        Ok(position.map(|position| self.entries.remove(position)))
+    }
+
+    fn entries(&self) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        Ok(self.entries.clone())
+    }
+
+    /// There is no storage to write to.
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Nothing is changed if the modification fails.
+    fn modify(&mut self, modification: &mut Modification) -> Result<(), Box<dyn Error>> {
+        let mut entries = self.entries.clone();
+        modification(&mut entries)?;
+        for entry in &mut entries {
+            self.features.keep_stored_descriptors(entry);
+        }
+        self.entries = entries;
+        Ok(())
+    }
+
+    fn len(&self) -> Result<usize, Box<dyn Error>> {
+        Ok(self.entries.len())
+    }
+
+    fn features(&self) -> FeatureConfiguration {
+        self.features.clone()
+    }
+}
@@ -0,0 +1,246 @@
+use crate::color_type::ColorType;
+use crate::features::{DescriptorSet, FeatureConfiguration};
+use crate::file_handler::is_file;
+use crate::search_index::try_generate_suchindex;
+use serde_json::Value;
+use std::error::Error;
+
+/// The schema version of the datastores written by this version of the library.
+///
+/// History of the schema versions:
+///
+/// 1. A plain JSON array of entries without content hashes.
+/// 2. Entries have a `content_hash`. The entries are stored together with the schema version and
+///    the `FeatureConfiguration`.
+/// 3. Entries have the dimensions (`lines`, `columns`) and `file_size` of their picture and
+///    `tags`.
+/// 4. Entries have the descriptors of their `grid_cells`.
+/// 5. Entries have the `color_type` of their picture.
+/// 6. Entries have the perceived `lightness` of their picture.
+///
+/// New descriptors do not change the schema version, see `DESCRIPTOR_VERSION`.
+pub const SCHEMA_VERSION: u32 = 6;
+
+/// The feature configuration of datastores written before it was stored in the datastore.
+pub const LEGACY_FEATURES: FeatureConfiguration = FeatureConfiguration {
+    bin_count: 5,
+    color_channels: 4,
+    descriptor_version: 0,
+    descriptors: DescriptorSet::Full,
+};
+
+/// The upgrade of the stored entries of one schema version to the next.
+struct Migration {
+    /// Changes a stored entry in place. Only cheap changes of the JSON are made here, the
+    /// descriptors of the pictures are computed once after all upgrades (see `migrate_entries`).
+    upgrade: fn(&mut Value) -> Result<(), Box<dyn Error>>,
+    /// Whether the new version has fields that are computed from the pictures.
+    reindex: bool,
+}
+
+/// `MIGRATIONS[i]` upgrades the entries of schema version `i + 1` to schema version `i + 2`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize - 1] = [
+    // entries get a `content_hash`
+    Migration {
+        upgrade: add_content_hash,
+        reindex: true,
+    },
+    // entries get the dimensions and file size of their picture, missing pictures keep the
+    // defaults
+    Migration {
+        upgrade: keep_defaults,
+        reindex: true,
+    },
+    // entries get the descriptors of their grid cells, missing pictures keep no cells and are
+    // compared as a whole by region searches
+    Migration {
+        upgrade: keep_defaults,
+        reindex: true,
+    },
+    // entries get the colour type of their picture, gray pictures had no brightness before
+    Migration {
+        upgrade: add_color_type,
+        reindex: true,
+    },
+    // entries get the perceived lightness of their picture, missing pictures have none and do not
+    // match lightness filters
+    Migration {
+        upgrade: keep_defaults,
+        reindex: true,
+    },
+];
+
+/// Summary of a migration of the entries of a datastore, see `migrate_entries`.
+///
+/// # Fields
+///
+/// * `previous_version`: The schema version the entries had before.
+/// * `reindexed`: The number of entries whose descriptors were computed again.
+/// * `skipped`: Pairs of (filepath, reason) of entries that were kept with their old descriptors,
+///   because their picture can not be read.
+/// * `dropped`: Pairs of (filepath, reason) of entries that were removed, because their picture
+///   can not be read and their histograms can not be compared with the others.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MigrationSummary {
+    pub previous_version: u32,
+    pub reindexed: usize,
+    pub skipped: Vec<(String, String)>,
+    pub dropped: Vec<(String, String)>,
+}
+
+impl MigrationSummary {
+    pub fn print(&self) {
+        println!("______________________________");
+        println!("Indexed again: {}", self.reindexed);
+        println!("Skipped:       {}", self.skipped.len());
+        for (filepath, reason) in &self.skipped {
+            println!("  ~ {filepath}: {reason}");
+        }
+        println!("Dropped:       {}", self.dropped.len());
+        for (filepath, reason) in &self.dropped {
+            println!("  - {filepath}: {reason}");
+        }
+        println!("______________________________");
+    }
+}
+
+/// Upgrades the entries of a datastore to the current `SCHEMA_VERSION` and `FeatureConfiguration`.
+///
+/// The entries are handled as JSON values, because they can not be deserialized into the current
+/// `SearchIndex` before they are migrated. The upgrades for all versions between `from_version`
+/// and `SCHEMA_VERSION` are applied in order. If one of them added descriptors or the descriptors
+/// were computed with a different feature configuration, e.g. an older `DESCRIPTOR_VERSION`, the
+/// entries are computed again from their pictures afterwards, once.
+///
+/// Descriptors are only recomputed for pictures that can still be read. Other entries are migrated
+/// without them, or dropped if the histograms were computed with other bins or channels, because
+/// they could not be compared with the others. Both are listed in the returned `MigrationSummary`.
+///
+/// Computing the descriptors again reads every picture, so datastores are only migrated by the
+/// explicit migration functions, e.g. `migrate_datastore_at`, and never while they are read.
+///
+/// # Arguments
+///
+/// * `entries` - The stored entries.
+/// * `from_version` - The schema version of the stored entries.
+/// * `features` - The feature configuration the stored entries were computed with.
+///
+/// # Errors
+///
+/// Returns an error if `from_version` is not a known schema version or if an upgrade fails.
+pub fn migrate_entries(
+    mut entries: Vec<Value>,
+    from_version: u32,
+    features: &FeatureConfiguration,
+) -> Result<(Vec<Value>, MigrationSummary), Box<dyn Error>> {
+    if from_version == 0 || from_version > SCHEMA_VERSION {
+        return Err(format!("Unknown schema version {from_version}.").into());
+    }
+
+    let migrations = &MIGRATIONS[from_version as usize - 1..];
+    for migration in migrations {
+        for entry in &mut entries {
+            (migration.upgrade)(entry)?;
+        }
+    }
+
+    let mut summary = MigrationSummary {
+        previous_version: from_version,
+        ..MigrationSummary::default()
+    };
+    let current_features = features.current();
+    if *features != current_features || migrations.iter().any(|migration| migration.reindex) {
+        entries = reindex_entries(
+            entries,
+            &current_features,
+            features.has_same_histograms(&current_features),
+            &mut summary,
+        );
+    }
+    Ok((entries, summary))
+}
+
+/// Returns the filepath of a stored entry, if it has one.
+fn filepath_of(entry: &Value) -> Option<&str> {
+    entry.get("filepath").and_then(Value::as_str)
+}
+
+/// Computes the entry of the picture again with the descriptors of the `features`, if the picture
+/// still exists. The tags of the entry are kept.
+pub(crate) fn reindex_entry(
+    entry: &Value,
+    features: &FeatureConfiguration,
+) -> Option<Result<Value, Box<dyn Error>>> {
+    let filepath = filepath_of(entry)?;
+    if !is_file(filepath) {
+        return None;
+    }
+    Some(
+        try_generate_suchindex(filepath.to_string(), features).and_then(|mut search_index| {
+            if let Some(tags) = entry.get("tags") {
+                search_index.tags = serde_json::from_value(tags.clone())?;
+            }
+            Ok(serde_json::to_value(search_index)?)
+        }),
+    )
+}
+
+/// Computes all entries again. Entries whose pictures do not exist anymore or can not be read are
+/// kept as they are if `keep_stale` is set, and dropped otherwise. The summary lists them.
+fn reindex_entries(
+    entries: Vec<Value>,
+    features: &FeatureConfiguration,
+    keep_stale: bool,
+    summary: &mut MigrationSummary,
+) -> Vec<Value> {
+    let mut reindexed_entries = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let filepath = filepath_of(&entry)
+            .unwrap_or("an unknown picture")
+            .to_string();
+        let reason = match reindex_entry(&entry, features) {
+            Some(Ok(reindexed_entry)) => {
+                reindexed_entries.push(reindexed_entry);
+                summary.reindexed += 1;
+                continue;
+            }
+            Some(Err(err)) => err.to_string(),
+            None => "the picture does not exist anymore".to_string(),
+        };
+        if keep_stale {
+            reindexed_entries.push(entry);
+            summary.skipped.push((filepath, reason));
+        } else {
+            summary.dropped.push((filepath, reason));
+        }
+    }
+    reindexed_entries
+}
+
+/// Version 1 -> 2: entries get an empty `content_hash`, which counts as changed by a sync.
+fn add_content_hash(entry: &mut Value) -> Result<(), Box<dyn Error>> {
+    if let Value::Object(fields) = entry {
+        fields.insert("content_hash".to_string(), Value::String(String::new()));
+    }
+    Ok(())
+}
+
+/// Version 4 -> 5: entries get the colour type guessed from their number of histograms.
+fn add_color_type(entry: &mut Value) -> Result<(), Box<dyn Error>> {
+    let channels = entry
+        .get("histogram")
+        .and_then(Value::as_array)
+        .map_or(0, Vec::len);
+    if let (Value::Object(fields), true) = (entry, channels > 0) {
+        fields.insert(
+            "color_type".to_string(),
+            serde_json::to_value(ColorType::from_channel_count(channels))?,
+        );
+    }
+    Ok(())
+}
+
+/// The new fields have defaults, so stored entries need no change.
+fn keep_defaults(_entry: &mut Value) -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
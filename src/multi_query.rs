@@ -0,0 +1,257 @@
+use crate::compare_pictures::{
+    compute_similarity, similarity_upper_bound, Similarity, SimilarityInformation,
+};
+use crate::datastore::{open_datastore, Datastore};
+use crate::features::FeatureConfiguration;
+use crate::get_datastore_path;
+use crate::query_descriptor::{FeatureWeights, QueryDescriptor};
+use crate::search::{rank_by, SearchOptions};
+use crate::search_index::{try_generate_suchindex, SearchIndex};
+use std::error::Error;
+use std::str::FromStr;
+
+/// The `negative_weight` of `MultiQuery::new`.
+pub const DEFAULT_NEGATIVE_WEIGHT: f64 = 0.5;
+
+/// How the similarities to several query pictures are combined into one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FusionStrategy {
+    /// The entries are compared with a single combined query: the average of the normalized
+    /// histograms of the positives minus `negative_weight` times the average of those of the
+    /// negatives. Its brightness is the average brightness of the positives.
+    Centroid,
+    /// The similarity to the most similar positive minus `negative_weight` times the similarity
+    /// to the most similar negative. Finds pictures that are like any one of the positives.
+    MaxSimilarity,
+    /// The average similarity to the positives minus `negative_weight` times the average
+    /// similarity to the negatives.
+    AverageSimilarity,
+}
+
+impl FromStr for FusionStrategy {
+    type Err = String;
+
+    /// Parses the names used on the command line: `centroid`, `max` and `average`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "centroid" => Ok(FusionStrategy::Centroid),
+            "max" => Ok(FusionStrategy::MaxSimilarity),
+            "average" => Ok(FusionStrategy::AverageSimilarity),
+            _ => Err(format!(
+                "Unknown strategy '{name}', expected centroid, max or average"
+            )),
+        }
+    }
+}
+
+/// A query made of several pictures the results should be like (positives) and pictures they
+/// should not be like (negatives).
+///
+/// # Fields
+///
+/// * `positives`: The pictures the results should be similar to. At least one is needed.
+/// * `negatives`: The pictures the results should not be similar to.
+/// * `strategy`: How the similarities to the pictures are combined.
+/// * `negative_weight`: How much the similarity to the negatives lowers the similarity.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::multi_query::{FusionStrategy, MultiQuery};
+/// use imsearch::search::SearchOptions;
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let picture = |name: &str| {
+///     generate_suchindex(format!("src/tests/files/pictures_for_testing/{name}.png"))
+/// };
+/// let datastore = MemoryDatastore::with_entries(vec![
+///     picture("bird"),
+///     picture("flower_purple_1"),
+///     picture("flower_purple_2"),
+/// ]);
+///
+/// let query = MultiQuery::new(vec![picture("flower_purple_1_modified")])
+///     .with_negatives(vec![picture("bird")])
+///     .with_strategy(FusionStrategy::MaxSimilarity);
+/// let results = query.search_datastore(&datastore, &SearchOptions::top(1)).unwrap();
+/// assert_ne!(results[0].search_index().filename, "bird");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiQuery {
+    pub positives: Vec<SearchIndex>,
+    pub negatives: Vec<SearchIndex>,
+    pub strategy: FusionStrategy,
+    pub negative_weight: f64,
+}
+
+impl MultiQuery {
+    /// Creates a query for pictures similar to the positives, combined with
+    /// `FusionStrategy::Centroid` and the `DEFAULT_NEGATIVE_WEIGHT`.
+    pub fn new(positives: Vec<SearchIndex>) -> Self {
+        MultiQuery {
+            positives,
+            negatives: Vec::new(),
+            strategy: FusionStrategy::Centroid,
+            negative_weight: DEFAULT_NEGATIVE_WEIGHT,
+        }
+    }
+
+    /// Creates a query from the pictures at the given paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a picture can not be read.
+    pub fn from_pictures(
+        positives: &[String],
+        negatives: &[String],
+    ) -> Result<Self, Box<dyn Error>> {
+        let index = |paths: &[String]| -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+            paths
+                .iter()
+                .map(|path| try_generate_suchindex(path.clone(), &FeatureConfiguration::default()))
+                .collect()
+        };
+        Ok(MultiQuery::new(index(positives)?).with_negatives(index(negatives)?))
+    }
+
+    pub fn with_negatives(mut self, negatives: Vec<SearchIndex>) -> Self {
+        self.negatives = negatives;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: FusionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_negative_weight(mut self, negative_weight: f64) -> Self {
+        self.negative_weight = negative_weight;
+        self
+    }
+
+    /// Searches the configured datastore, see `search_datastore`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query has no positives or the datastore can not be read.
+    pub fn search(
+        &self,
+        options: &SearchOptions,
+    ) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+        let datastore = open_datastore(&get_datastore_path()?)?;
+        self.search_datastore(datastore.as_ref(), options)
+    }
+
+    /// Ranks the entries of the datastore by their combined similarity to the query and returns
+    /// the results selected by the `options`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query has no positives or the datastore can not be read.
+    pub fn search_datastore(
+        &self,
+        datastore: &dyn Datastore,
+        options: &SearchOptions,
+    ) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+        if self.positives.is_empty() {
+            return Err("A query needs at least one positive picture.".into());
+        }
+        let entries = datastore.entries()?;
+
+        Ok(match self.strategy {
+            FusionStrategy::Centroid => {
+                let centroid = self.centroid();
+                let weights = FeatureWeights::uniform(centroid.histograms.len());
+                rank_by(
+                    &entries,
+                    options,
+                    |entry| centroid.similarity_upper_bound(entry, &weights),
+                    |entry| centroid.score(entry, &weights),
+                )
+            }
+            FusionStrategy::MaxSimilarity | FusionStrategy::AverageSimilarity => rank_by(
+                &entries,
+                options,
+                |entry| self.fused_upper_bound(entry),
+                |entry| self.fuse(entry),
+            ),
+        })
+    }
+
+    /// Combines the similarities of the entry to every query picture, for all strategies except
+    /// `FusionStrategy::Centroid`.
+    ///
+    /// The cosine similarity and brightness similarity of the result are those of the most
+    /// similar positive.
+    fn fuse(&self, entry: &SearchIndex) -> Similarity {
+        let positives: Vec<Similarity> = self
+            .positives
+            .iter()
+            .map(|positive| compute_similarity(positive, entry))
+            .collect();
+        let negatives: Vec<f64> = self
+            .negatives
+            .iter()
+            .map(|negative| compute_similarity(negative, entry).similarity)
+            .collect();
+        let positive_similarities: Vec<f64> = positives
+            .iter()
+            .map(|positive| positive.similarity)
+            .collect();
+
+        let similarity =
+            self.combine(&positive_similarities) - self.negative_weight * self.combine(&negatives);
+        let best_positive = positives
+            .into_iter()
+            .max_by(|a, b| a.similarity.total_cmp(&b.similarity))
+            .expect("the query has at least one positive");
+        Similarity::new(
+            similarity,
+            best_positive.cosine_similarity,
+            best_positive.average_brightness,
+        )
+    }
+
+    /// Similarities are never negative, so with a `negative_weight` of at least 0 the negatives
+    /// can only lower the fused similarity. A negative weight raises it, at most by the weight
+    /// times the combined upper bounds of the negatives.
+    fn fused_upper_bound(&self, entry: &SearchIndex) -> f64 {
+        let upper_bounds = |pictures: &[SearchIndex]| -> Vec<f64> {
+            pictures
+                .iter()
+                .map(|picture| similarity_upper_bound(picture, entry))
+                .collect()
+        };
+        let positives = self.combine(&upper_bounds(&self.positives));
+        if self.negative_weight >= 0.0 {
+            positives
+        } else {
+            positives - self.negative_weight * self.combine(&upper_bounds(&self.negatives))
+        }
+    }
+
+    /// Combines the similarities to several pictures according to the strategy, 0 if there are
+    /// none.
+    fn combine(&self, similarities: &[f64]) -> f64 {
+        if similarities.is_empty() {
+            return 0.0;
+        }
+        match self.strategy {
+            FusionStrategy::MaxSimilarity => similarities.iter().copied().fold(f64::MIN, f64::max),
+            _ => similarities.iter().sum::<f64>() / similarities.len() as f64,
+        }
+    }
+
+    /// The average of the positives minus `negative_weight` times the average histograms of the
+    /// negatives, with the average brightness of the positives.
+    fn centroid(&self) -> QueryDescriptor {
+        let mut centroid = QueryDescriptor::centroid(&self.positives).unwrap_or_default();
+        if let Some(mut negative_centroid) = QueryDescriptor::centroid(&self.negatives) {
+            // the brightness of the negatives is not subtracted
+            negative_centroid.average_brightness = 0.0;
+            centroid.add_weighted(&negative_centroid, -self.negative_weight);
+        }
+        centroid
+    }
+}
@@ -0,0 +1,288 @@
+use crate::compare_pictures::{
+    compute_similarity, similarity_upper_bound, Similarity, SimilarityInformation,
+};
+use crate::datastore::{open_datastore, Datastore};
+use crate::features::DescriptorSet;
+use crate::get_datastore_path;
+use crate::region::{cell_search_index, GRID_SIZE};
+use crate::search::{rank_by, rank_stored_entries, SearchOptions};
+use crate::search_index::{try_generate_suchindex, SearchIndex};
+use crate::transform::{Flip, Rotation, Transform};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// One of the eight ways to turn and mirror a picture without distorting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Orientation {
+    Original,
+    /// Rotated 90 degrees clockwise.
+    Rotated90,
+    Rotated180,
+    /// Rotated 270 degrees clockwise, which is 90 degrees counterclockwise.
+    Rotated270,
+    /// Mirrored left to right.
+    FlippedHorizontally,
+    /// Mirrored top to bottom.
+    FlippedVertically,
+    /// Mirrored at the diagonal from the top left to the bottom right corner.
+    Transposed,
+    /// Mirrored at the diagonal from the top right to the bottom left corner.
+    AntiTransposed,
+}
+
+impl Orientation {
+    /// All orientations, for photos that may be turned or mirrored.
+    pub const ALL: [Orientation; 8] = [
+        Orientation::Original,
+        Orientation::Rotated90,
+        Orientation::Rotated180,
+        Orientation::Rotated270,
+        Orientation::FlippedHorizontally,
+        Orientation::FlippedVertically,
+        Orientation::Transposed,
+        Orientation::AntiTransposed,
+    ];
+
+    /// The orientations that only turn the picture.
+    pub const ROTATIONS: [Orientation; 4] = [
+        Orientation::Original,
+        Orientation::Rotated90,
+        Orientation::Rotated180,
+        Orientation::Rotated270,
+    ];
+
+    /// Turns and mirrors the picture into this orientation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use imsearch::color_type::ColorType;
+    /// use imsearch::orientation::Orientation;
+    /// use imsearch::PictureU8;
+    ///
+    /// let pic = PictureU8 {
+    ///     lines: 2,
+    ///     columns: 2,
+    ///     color_channel_count: 1,
+    ///     color_type: ColorType::Gray,
+    ///     data: vec![1, 2, 3, 4],
+    /// };
+    /// assert_eq!(Orientation::Transposed.apply(&pic).data, vec![1, 3, 2, 4]);
+    /// ```
+    pub fn apply<P: Transform + Clone>(&self, picture: &P) -> P {
+        match self {
+            Orientation::Original => picture.clone(),
+            Orientation::Rotated90 => picture.rotate(Rotation::Clockwise90),
+            Orientation::Rotated180 => picture.rotate(Rotation::Clockwise180),
+            Orientation::Rotated270 => picture.rotate(Rotation::Clockwise270),
+            Orientation::FlippedHorizontally => picture.flip(Flip::Horizontal),
+            Orientation::FlippedVertically => picture.flip(Flip::Vertical),
+            Orientation::Transposed => picture.rotate(Rotation::Clockwise90).flip(Flip::Horizontal),
+            Orientation::AntiTransposed => {
+                picture.rotate(Rotation::Clockwise90).flip(Flip::Vertical)
+            }
+        }
+    }
+
+    /// Returns the position a pixel at `line` and `column` of the picture in this orientation has
+    /// in the original picture of `size` x `size` pixels.
+    fn original_position(&self, line: u32, column: u32, size: u32) -> (u32, u32) {
+        let last = size - 1;
+        match self {
+            Orientation::Original => (line, column),
+            Orientation::Rotated90 => (last - column, line),
+            Orientation::Rotated180 => (last - line, last - column),
+            Orientation::Rotated270 => (column, last - line),
+            Orientation::FlippedHorizontally => (line, last - column),
+            Orientation::FlippedVertically => (last - line, column),
+            Orientation::Transposed => (column, line),
+            Orientation::AntiTransposed => (last - column, last - line),
+        }
+    }
+
+    /// Returns the grid cells of the picture in this orientation, computed from the grid cells of
+    /// the original picture: turning or mirroring a picture moves its cells, but does not change
+    /// their histograms and brightness. `None` if the cells are not a complete grid.
+    ///
+    /// The cells can be given as `GridCell`s or as anything computed from them in grid order.
+    pub(crate) fn oriented_cells<'a, T>(&self, cells: &'a [T]) -> Option<Vec<&'a T>> {
+        if cells.len() != (GRID_SIZE * GRID_SIZE) as usize {
+            return None;
+        }
+        let mut oriented = Vec::with_capacity(cells.len());
+        for line in 0..GRID_SIZE {
+            for column in 0..GRID_SIZE {
+                let (original_line, original_column) =
+                    self.original_position(line, column, GRID_SIZE);
+                oriented.push(&cells[(original_line * GRID_SIZE + original_column) as usize]);
+            }
+        }
+        Some(oriented)
+    }
+}
+
+impl Display for Orientation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Orientation::Original => "original",
+            Orientation::Rotated90 => "rotated 90° clockwise",
+            Orientation::Rotated180 => "rotated 180°",
+            Orientation::Rotated270 => "rotated 90° counterclockwise",
+            Orientation::FlippedHorizontally => "flipped horizontally",
+            Orientation::FlippedVertically => "flipped vertically",
+            Orientation::Transposed => "transposed",
+            Orientation::AntiTransposed => "anti-transposed",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Compares the entry with the query in the given orientation, taking the layout of the pictures
+/// into account.
+///
+/// The brightness and the histograms of a whole picture do not change when it is turned or
+/// mirrored, but its grid cells move. The similarity is the average of the similarity of the whole
+/// pictures (`compare_search_indexes`) and the average similarity of the grid cells at the same
+/// position. If the query or the entry have no complete grid, only the whole pictures are
+/// compared.
+pub fn compare_oriented(
+    query: &SearchIndex,
+    entry: &SearchIndex,
+    orientation: Orientation,
+) -> SimilarityInformation {
+    oriented_similarity(
+        &compute_similarity(query, entry),
+        &cell_indexes(query),
+        &cell_indexes(entry),
+        orientation,
+    )
+    .with_entry(entry.clone())
+}
+
+/// Same as `compare_oriented`, for the similarity of the whole pictures and the grid cells of the
+/// query and the entry as search indexes. Only the cells depend on the orientation, so a search
+/// computes the rest once per entry.
+fn oriented_similarity(
+    whole: &Similarity,
+    query_cells: &[SearchIndex],
+    entry_cells: &[SearchIndex],
+    orientation: Orientation,
+) -> Similarity {
+    let similarity = match orientation.oriented_cells(query_cells) {
+        Some(query_cells) if entry_cells.len() == query_cells.len() => {
+            let cell_similarity: f64 = query_cells
+                .iter()
+                .zip(entry_cells)
+                .map(|(query_cell, entry_cell)| {
+                    compute_similarity(query_cell, entry_cell).similarity
+                })
+                .sum::<f64>()
+                / query_cells.len() as f64;
+            (whole.similarity + cell_similarity) / 2.0
+        }
+        _ => whole.similarity,
+    };
+    Similarity::new(
+        similarity,
+        whole.cosine_similarity,
+        whole.average_brightness,
+    )
+    .with_orientation(orientation)
+}
+
+/// Returns the grid cells of the picture as search indexes, in grid order.
+fn cell_indexes(search_index: &SearchIndex) -> Vec<SearchIndex> {
+    search_index
+        .grid_cells
+        .iter()
+        .map(|cell| cell_search_index(search_index, cell))
+        .collect()
+}
+
+/// Searches the configured datastore for pictures similar to the picture at the given path in
+/// any orientation, see `search_oriented_datastore`.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read.
+pub fn search_invariant(
+    path: &str,
+    options: &SearchOptions,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    let query = try_generate_suchindex(path.to_string(), &datastore.features())?;
+    search_oriented_datastore(datastore.as_ref(), &query, &Orientation::ALL, options)
+}
+
+/// Ranks the entries of the datastore by their similarity to the query in the best of the given
+/// orientations (see `compare_oriented`) and returns the results selected by the `options`.
+/// Every result reports the orientation of the query that matched it best.
+///
+/// The entries of compact datastores (see `DescriptorSet::Compact`) keep neither histograms nor grid
+/// cells. Their descriptors do not depend on the orientation, so they are ranked with
+/// `rank_stored_entries` and report the first orientation.
+///
+/// # Errors
+///
+/// Returns an error if no orientation is given or the datastore can not be read.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::orientation::{search_oriented_datastore, Orientation};
+/// use imsearch::search::SearchOptions;
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let datastore = MemoryDatastore::with_entries(vec![bird.clone()]);
+///
+/// let results =
+///     search_oriented_datastore(&datastore, &bird, &Orientation::ALL, &SearchOptions::top(1))
+///         .unwrap();
+/// assert_eq!(results[0].orientation(), Some(Orientation::Original));
+/// ```
+pub fn search_oriented_datastore(
+    datastore: &dyn Datastore,
+    query: &SearchIndex,
+    orientations: &[Orientation],
+    options: &SearchOptions,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    if orientations.is_empty() {
+        return Err("At least one orientation of the query is needed.".into());
+    }
+    let entries = datastore.entries()?;
+    let features = datastore.features();
+    if features.descriptors == DescriptorSet::Compact {
+        return Ok(rank_stored_entries(query, &entries, &features, options)
+            .into_iter()
+            .map(|result| result.with_orientation(orientations[0]))
+            .collect());
+    }
+    let query_cells = cell_indexes(query);
+    Ok(rank_by(
+        &entries,
+        options,
+        // the grid cells can at most be equal
+        |entry| (similarity_upper_bound(query, entry) + 1.0) / 2.0,
+        |entry| {
+            let whole = compute_similarity(query, entry);
+            let entry_cells = cell_indexes(entry);
+            orientations
+                .iter()
+                .map(|orientation| {
+                    oriented_similarity(&whole, &query_cells, &entry_cells, *orientation)
+                })
+                // the first of equally good orientations wins, so an unchanged query is preferred
+                .reduce(|best, other| {
+                    if other.similarity > best.similarity {
+                        other
+                    } else {
+                        best
+                    }
+                })
+                .expect("there is at least one orientation")
+        },
+    ))
+}
@@ -1,118 +1,437 @@
-use crate::with_threads::{
-    convert_data_to_f32, convert_data_to_f32_with_threads, convert_data_to_u8,
-    convert_data_to_u8_with_threads,
-};
-use std::fmt::{Display, Formatter};
-
-/// The `Picture` trait represents a picture.
-pub trait Picture {
-    /// Converts the picture to `PictureU8`, which stores pixel values as `u8`.
-    fn to_picture_u8(&self) -> PictureU8;
+use crate::color_type::ColorType;
+use crate::grayscale::LumaFormula;
+use crate::picture_view::AsPictureView;
+use std::any::Any;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::slice::{ChunksExact, ChunksExactMut};
+use std::thread;
 
-    /// Converts the picture to `PictureF32`, which stores pixel values as `f32`.
-    fn to_picture_f32(&self) -> PictureF32;
+/// A value of a color channel of a picture.
+///
+/// Every sample type has a range from 0 to `MAX_VALUE` (full intensity). Conversions between
+/// sample types map these ranges onto each other, through the normalized range `[0.0, 1.0]`.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::picture::Sample;
+///
+/// assert_eq!(u16::MAX.to_normalized(), 1.0);
+/// assert_eq!(u8::from_normalized(0.5), 127);
+/// assert_eq!(0.25f64.to_normalized(), 0.25);
+/// ```
+pub trait Sample: Copy + Default + PartialOrd + Debug + Display + Send + Sync + 'static {
+    /// The value of full intensity: the largest value of integer types, `1.0` for floats.
+    const MAX_VALUE: Self;
+
+    /// Returns the value itself as `f32`, e.g. `255.0` for `255u8`.
+    fn to_f32(self) -> f32;
+
+    /// Converts an `f32` in the range of this type back to it. Integer types round the value and
+    /// clamp it to their range.
+    fn from_f32(value: f32) -> Self;
+
+    /// Maps the value to `[0.0, 1.0]`.
+    fn to_normalized(self) -> f32 {
+        self.to_f32() / Self::MAX_VALUE.to_f32()
+    }
+
+    /// Maps a value of `[0.0, 1.0]` to the range of this type. Integer types truncate the value
+    /// and clamp it to their range.
+    fn from_normalized(value: f32) -> Self;
+
+    /// Converts the value to another sample type. Values of the same type are kept as they are.
+    fn convert_to<U: Sample>(self) -> U {
+        match (&self as &dyn Any).downcast_ref::<U>() {
+            Some(same_type) => *same_type,
+            None => U::from_normalized(self.to_normalized()),
+        }
+    }
 }
 
-/// Represents a picture with pixel values stored as `u8`.
-#[derive(Debug)]
-pub struct PictureU8 {
+impl Sample for u8 {
+    const MAX_VALUE: Self = u8::MAX;
+
+    fn to_f32(self) -> f32 {
+        f32::from(self)
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value.round() as u8
+    }
+
+    fn from_normalized(value: f32) -> Self {
+        (value * 255.0) as u8
+    }
+}
+
+impl Sample for u16 {
+    const MAX_VALUE: Self = u16::MAX;
+
+    fn to_f32(self) -> f32 {
+        f32::from(self)
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value.round() as u16
+    }
+
+    fn from_normalized(value: f32) -> Self {
+        (value * f32::from(u16::MAX)) as u16
+    }
+}
+
+impl Sample for u32 {
+    const MAX_VALUE: Self = u32::MAX;
+
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value.round() as u32
+    }
+
+    fn to_normalized(self) -> f32 {
+        // in f64, because f32 can not tell the largest values of u32 apart
+        (f64::from(self) / f64::from(u32::MAX)) as f32
+    }
+
+    fn from_normalized(value: f32) -> Self {
+        (f64::from(value) * f64::from(u32::MAX)) as u32
+    }
+}
+
+impl Sample for f32 {
+    const MAX_VALUE: Self = 1.0;
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    fn to_normalized(self) -> f32 {
+        self
+    }
+
+    fn from_normalized(value: f32) -> Self {
+        value
+    }
+}
+
+impl Sample for f64 {
+    const MAX_VALUE: Self = 1.0;
+
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    fn from_f32(value: f32) -> Self {
+        f64::from(value)
+    }
+
+    fn to_normalized(self) -> f32 {
+        self as f32
+    }
+
+    fn from_normalized(value: f32) -> Self {
+        f64::from(value)
+    }
+}
+
+/// Represents a picture whose pixel values are stored as samples of type `T`.
+///
+/// The samples are stored line by line, each pixel with `color_channel_count` samples in the
+/// colour model given by `color_type`.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::picture::{Picture, PictureF32};
+///
+/// let pic_u16: Picture<u16> = Picture {
+///     lines: 1,
+///     columns: 2,
+///     color_channel_count: 1,
+///     color_type: ColorType::Gray,
+///     data: vec![0, u16::MAX],
+/// };
+///
+/// let pic_f32: PictureF32 = pic_u16.convert();
+/// assert_eq!(pic_f32.data, vec![0.0, 1.0]);
+/// assert_eq!(pic_u16.to_picture_u8().data, vec![0, 255]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Picture<T> {
     pub lines: u32,   //height
     pub columns: u32, //width
     pub color_channel_count: usize,
-    pub data: Vec<u8>, // values from 0 to 255 (both included)
+    pub color_type: ColorType,
+    pub data: Vec<T>,
 }
 
+/// A picture with values from 0 to 255 (both included).
+pub type PictureU8 = Picture<u8>;
+pub type PictureU16 = Picture<u16>;
+pub type PictureU32 = Picture<u32>;
+/// A picture with values from 0.0 to 1.0 (both included).
+pub type PictureF32 = Picture<f32>;
+pub type PictureF64 = Picture<f64>;
+
 const USE_THREADS_FOR_CONVERSION: bool = true;
-impl Picture for PictureU8 {
-    fn to_picture_u8(&self) -> PictureU8 {
-        PictureU8 {
+const THREAD_COUNT: usize = 4;
+const THREAD_THRESHOLD: usize = 200;
+
+impl<T: Sample> Picture<T> {
+    /// Converts the picture to another sample type, mapping the range of `T` to the range of `U`.
+    /// Converting to the same sample type copies the picture.
+    pub fn convert<U: Sample>(&self) -> Picture<U> {
+        if let Some(same_type) = (self as &dyn Any).downcast_ref::<Picture<U>>() {
+            return same_type.clone();
+        }
+        Picture {
             lines: self.lines,
             columns: self.columns,
             color_channel_count: self.color_channel_count,
-            data: self.data.clone(),
+            color_type: self.color_type,
+            data: convert_samples(&self.data),
         }
     }
-    fn to_picture_f32(&self) -> PictureF32 {
-        let new_data: Vec<f32>;
-        if USE_THREADS_FOR_CONVERSION {
-            new_data = convert_data_to_f32_with_threads(&self.data);
-        } else {
-            new_data = convert_data_to_f32(&self.data);
-        };
 
-        PictureF32 {
-            lines: self.lines,
-            columns: self.columns,
-            color_channel_count: self.color_channel_count,
-            data: new_data,
+    /// Returns the picture with samples of type `U`: borrowed if it already has them, converted
+    /// otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use imsearch::color_type::ColorType;
+    /// use imsearch::picture::PictureU8;
+    /// use std::borrow::Cow;
+    ///
+    /// let pic = PictureU8 {
+    ///     lines: 1,
+    ///     columns: 1,
+    ///     color_channel_count: 1,
+    ///     color_type: ColorType::Gray,
+    ///     data: vec![255],
+    /// };
+    /// assert!(matches!(pic.converted::<u8>(), Cow::Borrowed(_)));
+    /// assert_eq!(pic.converted::<f32>().data, vec![1.0]);
+    /// ```
+    pub fn converted<U: Sample>(&self) -> Cow<'_, Picture<U>> {
+        match (self as &dyn Any).downcast_ref::<Picture<U>>() {
+            Some(same_type) => Cow::Borrowed(same_type),
+            None => Cow::Owned(self.convert()),
         }
     }
-}
 
-impl Display for PictureU8 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "( lines: {}, columns: {}, color_channel_count: {} , Anzahl Pixel: {})",
-            self.lines,
-            self.columns,
-            self.color_channel_count,
-            (self.data.len() / self.color_channel_count)
-        )
+    /// Converts the picture to `PictureU8`, which stores pixel values as `u8`.
+    pub fn to_picture_u8(&self) -> PictureU8 {
+        self.convert()
+    }
+
+    /// Converts the picture to `PictureF32`, which stores pixel values as `f32`.
+    pub fn to_picture_f32(&self) -> PictureF32 {
+        self.convert()
     }
 }
-/// Represents a picture with pixel values stored as `f32`.
-#[derive(Debug, Clone)]
-pub struct PictureF32 {
-    pub lines: u32,   //height
-    pub columns: u32, //width
-    pub color_channel_count: usize,
-    pub data: Vec<f32>, // values from 0.0 to 1.0 (both included)
-}
 
-impl Picture for PictureF32 {
-    fn to_picture_u8(&self) -> PictureU8 {
-        let new_data: Vec<u8>;
-        if USE_THREADS_FOR_CONVERSION {
-            new_data = convert_data_to_u8_with_threads(&self.data);
-        } else {
-            new_data = convert_data_to_u8(&self.data);
+impl<T: Sample> Picture<T> {
+    /// Creates a picture and checks that `data` has exactly one sample per channel of every
+    /// pixel. The number of channels follows from the colour type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length of `data` does not match the size of the picture.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use imsearch::color_type::ColorType;
+    /// use imsearch::picture::PictureU8;
+    ///
+    /// assert!(PictureU8::new(2, 1, ColorType::Rgb, vec![0; 6]).is_ok());
+    /// assert!(PictureU8::new(2, 1, ColorType::Rgb, vec![0; 5]).is_err());
+    /// ```
+    pub fn new(
+        columns: u32,
+        lines: u32,
+        color_type: ColorType,
+        data: Vec<T>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let picture = Picture {
+            lines,
+            columns,
+            color_channel_count: color_type.channel_count(),
+            color_type,
+            data,
+        };
+        picture.validate()?;
+        Ok(picture)
+    }
+
+    /// Checks that `data.len() == lines * columns * color_channel_count`, that there is at
+    /// least one channel and that the colour type has `color_channel_count` channels. The fields
+    /// are public, so pictures built by hand may not be valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the mismatch.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.color_channel_count == 0 {
+            return Err("A picture needs at least one color channel".into());
         }
+        if self.color_type.channel_count() != self.color_channel_count {
+            return Err(format!(
+                "The colour type {} has {} channels, but the picture has {}",
+                self.color_type,
+                self.color_type.channel_count(),
+                self.color_channel_count
+            )
+            .into());
+        }
+        let expected = self.lines as usize * self.columns as usize * self.color_channel_count;
+        if self.data.len() != expected {
+            return Err(format!(
+                "A picture of {}x{} pixels with {} channels needs {expected} samples, but has {}",
+                self.columns,
+                self.lines,
+                self.color_channel_count,
+                self.data.len()
+            )
+            .into());
+        }
+        Ok(())
+    }
 
-        PictureU8 {
-            lines: self.lines,
-            columns: self.columns,
-            color_channel_count: self.color_channel_count,
-            data: new_data,
+    /// Returns the index of the first sample of the pixel, `None` if it is outside of the
+    /// picture.
+    fn pixel_index(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.columns || y >= self.lines {
+            return None;
         }
+        let index = (y as usize * self.columns as usize + x as usize) * self.color_channel_count;
+        (index + self.color_channel_count <= self.data.len()).then_some(index)
     }
 
-    fn to_picture_f32(&self) -> PictureF32 {
-        PictureF32 {
-            lines: self.lines,
-            columns: self.columns,
-            color_channel_count: self.color_channel_count,
-            data: self.data.clone(),
+    /// Returns the samples of the pixel in column `x` and line `y`, `None` if it is outside of
+    /// the picture.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use imsearch::color_type::ColorType;
+    /// use imsearch::picture::PictureU8;
+    ///
+    /// let mut pic = PictureU8::new(2, 2, ColorType::Rgb, vec![0; 12]).unwrap();
+    /// pic.set_pixel(1, 0, &[255, 128, 0]).unwrap();
+    ///
+    /// assert_eq!(pic.get_pixel(1, 0), Some(&[255, 128, 0][..]));
+    /// assert_eq!(pic.get_pixel(2, 0), None);
+    /// assert_eq!(pic.channel(0).unwrap().collect::<Vec<_>>(), vec![0, 255, 0, 0]);
+    /// ```
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<&[T]> {
+        let index = self.pixel_index(x, y)?;
+        Some(&self.data[index..index + self.color_channel_count])
+    }
+
+    /// Returns the samples of the pixel in column `x` and line `y` to change them, `None` if it
+    /// is outside of the picture.
+    pub fn get_pixel_mut(&mut self, x: u32, y: u32) -> Option<&mut [T]> {
+        let index = self.pixel_index(x, y)?;
+        Some(&mut self.data[index..index + self.color_channel_count])
+    }
+
+    /// Replaces the samples of the pixel in column `x` and line `y`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pixel is outside of the picture or `samples` does not have one
+    /// sample per color channel.
+    pub fn set_pixel(&mut self, x: u32, y: u32, samples: &[T]) -> Result<(), Box<dyn Error>> {
+        if samples.len() != self.color_channel_count {
+            return Err(format!(
+                "A pixel has {} samples, but {} were given",
+                self.color_channel_count,
+                samples.len()
+            )
+            .into());
+        }
+        let (columns, lines) = (self.columns, self.lines);
+        let pixel = self.get_pixel_mut(x, y).ok_or_else(|| {
+            format!("The pixel ({x}, {y}) is outside of the picture of {columns}x{lines} pixels")
+        })?;
+        pixel.copy_from_slice(samples);
+        Ok(())
+    }
+
+    /// Returns the samples of every pixel, line by line.
+    pub fn pixels(&self) -> ChunksExact<'_, T> {
+        self.data.chunks_exact(self.color_channel_count.max(1))
+    }
+
+    /// Returns the samples of every pixel to change them, line by line.
+    pub fn pixels_mut(&mut self) -> ChunksExactMut<'_, T> {
+        self.data.chunks_exact_mut(self.color_channel_count.max(1))
+    }
+
+    /// Returns the samples of every line, from top to bottom.
+    pub fn rows(&self) -> ChunksExact<'_, T> {
+        self.data
+            .chunks_exact((self.columns as usize * self.color_channel_count).max(1))
+    }
+
+    /// Returns the samples of one color channel of every pixel, `None` if the picture has no such
+    /// channel.
+    pub fn channel(&self, channel: usize) -> Option<impl Iterator<Item = T> + '_> {
+        if channel >= self.color_channel_count {
+            return None;
         }
+        Some(self.pixels().map(move |pixel| pixel[channel]))
     }
 }
 
-// FIXME: duplicate code
-impl Display for PictureF32 {
+/// Converts the samples, with threads for larger pictures.
+fn convert_samples<T: Sample, U: Sample>(data: &[T]) -> Vec<U> {
+    let convert =
+        |samples: &[T]| -> Vec<U> { samples.iter().map(|sample| sample.convert_to()).collect() };
+    if !USE_THREADS_FOR_CONVERSION || data.len() < THREAD_COUNT * THREAD_THRESHOLD {
+        return convert(data);
+    }
+    let chunk_size = data.len().div_ceil(THREAD_COUNT);
+    thread::scope(|scope| {
+        let handles: Vec<_> = data
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || convert(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+impl<T> Display for Picture<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "( lines: {}, columns: {}, color_channel_count: {} , Anzahl Pixel: {})",
+            "( lines: {}, columns: {}, color_channel_count: {}, color_type: {} , Anzahl Pixel: {})",
             self.lines,
             self.columns,
             self.color_channel_count,
+            self.color_type,
             (self.data.len() / self.color_channel_count)
         )
     }
 }
 
 /// Trait for calculating the average brightness of an image.
-
 pub trait AverageBrightness {
     /// Calculates the gray intensity value for a single pixel given the red, green, and blue color values.
     ///
@@ -146,11 +465,11 @@ pub trait AverageBrightness {
     /// # Returns
     ///
     /// The average brightness of the image.
-    fn average_brightness(&self, grayray: &Vec<f32>) -> f32;
+    fn average_brightness(&self, grayray: &[f32]) -> f32;
 }
 
-///the implement block is implemented for everything that implements Picture.
-impl<T: Picture> AverageBrightness for T {
+///the implement block is implemented for pictures and views of every sample type.
+impl<P: AsPictureView> AverageBrightness for P {
     /// Calculates the gray intensity value for a single pixel based on the provided RGB color values.
     /// The formula used to calculate the gray intensity is specified in the task description.
     ///
@@ -169,31 +488,20 @@ impl<T: Picture> AverageBrightness for T {
         green_colour_val: f32,
         blue_colour_val: f32,
     ) -> f32 {
-        (0.3 * red_colour_val) + (0.59 * green_colour_val) + (0.11 * blue_colour_val)
+        LumaFormula::Legacy.luma(red_colour_val, green_colour_val, blue_colour_val)
     }
     /// Calculates the gray intensity values for all pixels in the image and returns them as an array.
-    ///this function only uses the first tree channels (R,G,B) and skips channels bigger than 3
+    ///Gray pictures use their gray channel, the other colour types are converted to RGB first.
+    ///The array is empty if the colour model is not known (`ColorType::Channels`).
     ///
     /// # Returns
     ///
     /// An array containing the gray intensity values for all pixels in the image.
     fn gray_intensity_array(&self) -> Vec<f32> {
-        let pic_f32 = &self.to_picture_f32();
-
-        let mut grayray: Vec<f32> = Vec::new();
-        let mut count_colour: usize = 0;
-
-        if pic_f32.color_channel_count >= 3 {
-            while count_colour < pic_f32.data.len() {
-                let r = pic_f32.data[count_colour];
-                let g = pic_f32.data[count_colour + 1];
-                let b = pic_f32.data[count_colour + 2];
-
-                grayray.push(pic_f32.gray_intensity_single_val(r, g, b));
-                count_colour += pic_f32.color_channel_count;
-            }
-        }
-        grayray
+        self.as_view()
+            .gray_intensities(LumaFormula::Legacy)
+            .map(|intensities| intensities.collect())
+            .unwrap_or_default()
     }
 
     /// Calculates the average brightness of the image based on the provided gray intensity values.
@@ -205,17 +513,16 @@ impl<T: Picture> AverageBrightness for T {
     /// # Returns
     ///
     /// The average brightness of the image
-    fn average_brightness(&self, grayray: &Vec<f32>) -> f32 {
+    fn average_brightness(&self, grayray: &[f32]) -> f32 {
         let mut sum_grey: f32 = 0.0;
         let pixels = grayray.len() as f32;
         let mut count: usize = 0;
 
         while count < pixels as usize {
-            sum_grey = sum_grey + grayray[count];
+            sum_grey += grayray[count];
             count += 1;
         }
 
-        let averagebrightness = sum_grey / pixels;
-        return averagebrightness;
+        sum_grey / pixels
     }
 }
@@ -0,0 +1,282 @@
+use crate::color_type::ColorType;
+use crate::grayscale::LumaFormula;
+use crate::picture::{Picture, Sample};
+use crate::region::Rect;
+use std::error::Error;
+
+/// A borrowed picture: the samples of a `Picture` or of any other buffer, without copying them.
+///
+/// The lines of a view don't have to follow each other directly: `stride` is the number of
+/// samples from the start of one line to the start of the next, so a view can show a region of a
+/// larger picture (see `sub_view`).
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::picture_view::PictureView;
+/// use imsearch::region::Rect;
+///
+/// // a gray picture of 3x2 pixels
+/// let data = [1u8, 2, 3, 4, 5, 6];
+/// let view = PictureView::new(&data, 3, 2, ColorType::Gray).unwrap();
+///
+/// let right = view.sub_view(&Rect::new(1, 0, 2, 2)).unwrap();
+/// assert_eq!(right.stride(), 3);
+/// assert_eq!(right.rows().collect::<Vec<_>>(), vec![&[2, 3][..], &[5, 6][..]]);
+/// assert_eq!(right.to_picture().data, vec![2, 3, 5, 6]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PictureView<'a, T> {
+    lines: u32,
+    columns: u32,
+    color_channel_count: usize,
+    color_type: ColorType,
+    stride: usize,
+    data: &'a [T],
+}
+
+impl<'a, T: Sample> PictureView<'a, T> {
+    /// Views a buffer whose lines follow each other directly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small for the picture.
+    pub fn new(
+        data: &'a [T],
+        columns: u32,
+        lines: u32,
+        color_type: ColorType,
+    ) -> Result<Self, Box<dyn Error>> {
+        let stride = columns as usize * color_type.channel_count();
+        PictureView::with_stride(data, columns, lines, color_type, stride)
+    }
+
+    /// Views a buffer whose lines start `stride` samples after each other.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stride is shorter than a line or the buffer is too small for the
+    /// picture.
+    pub fn with_stride(
+        data: &'a [T],
+        columns: u32,
+        lines: u32,
+        color_type: ColorType,
+        stride: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let color_channel_count = color_type.channel_count();
+        let line_length = columns as usize * color_channel_count;
+        if stride < line_length {
+            return Err(format!(
+                "A stride of {stride} samples is shorter than a line of {line_length} samples"
+            )
+            .into());
+        }
+        let needed = match lines {
+            0 => 0,
+            _ => (lines as usize - 1) * stride + line_length,
+        };
+        if data.len() < needed {
+            return Err(format!(
+                "A picture of {columns}x{lines} pixels needs {needed} samples, but there are only {}",
+                data.len()
+            )
+            .into());
+        }
+        Ok(PictureView {
+            lines,
+            columns,
+            color_channel_count,
+            color_type,
+            stride,
+            data: &data[..needed],
+        })
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn color_channel_count(&self) -> usize {
+        self.color_channel_count
+    }
+
+    pub fn color_type(&self) -> ColorType {
+        self.color_type
+    }
+
+    /// Returns the number of samples from the start of one line to the start of the next.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Returns `true` if the lines follow each other directly, like in a `Picture`.
+    pub fn is_contiguous(&self) -> bool {
+        self.stride == self.columns as usize * self.color_channel_count
+    }
+
+    /// Returns the samples of a line.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the line is outside of the view.
+    pub fn row(&self, line: u32) -> &'a [T] {
+        assert!(line < self.lines, "line {line} is outside of the view");
+        let start = line as usize * self.stride;
+        &self.data[start..start + self.columns as usize * self.color_channel_count]
+    }
+
+    /// Returns the samples of every line, from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> + '_ {
+        (0..self.lines).map(move |line| self.row(line))
+    }
+
+    /// Returns the samples of the pixel in column `x` and line `y` of the view, `None` if it is
+    /// outside of the view.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<&'a [T]> {
+        if x >= self.columns || y >= self.lines {
+            return None;
+        }
+        let start = y as usize * self.stride + x as usize * self.color_channel_count;
+        self.data.get(start..start + self.color_channel_count)
+    }
+
+    /// Returns the samples of one color channel of every pixel, `None` if the view has no such
+    /// channel.
+    pub fn channel(&self, channel: usize) -> Option<impl Iterator<Item = T> + '_> {
+        if channel >= self.color_channel_count {
+            return None;
+        }
+        Some(self.pixel_samples().map(move |pixel| pixel[channel]))
+    }
+
+    /// Returns the samples of every pixel, line by line.
+    pub fn pixel_samples(&self) -> impl Iterator<Item = &'a [T]> + '_ {
+        let channels = self.color_channel_count.max(1);
+        self.slices()
+            .flat_map(move |slice| slice.chunks_exact(channels))
+    }
+
+    /// Returns the viewed samples in as few slices as possible: all at once if the lines follow
+    /// each other directly, line by line otherwise.
+    fn slices(&self) -> impl Iterator<Item = &'a [T]> + '_ {
+        let (count, length) = if self.is_contiguous() {
+            (1, self.data.len())
+        } else {
+            (
+                self.lines as usize,
+                self.columns as usize * self.color_channel_count,
+            )
+        };
+        (0..count).map(move |slice| &self.data[slice * self.stride..slice * self.stride + length])
+    }
+
+    /// Returns the normalized gray value of every pixel with the given formula, `None` if the
+    /// colour model of the view is not known (`ColorType::Channels`).
+    pub(crate) fn gray_intensities(
+        &self,
+        formula: LumaFormula,
+    ) -> Option<impl Iterator<Item = f32> + '_> {
+        if matches!(self.color_type, ColorType::Channels(_)) {
+            return None;
+        }
+        let color_type = self.color_type;
+        let mut normalized = Vec::with_capacity(self.color_channel_count);
+        Some(self.pixel_samples().map(move |pixel| {
+            normalized.clear();
+            normalized.extend(pixel.iter().map(|sample| sample.to_normalized()));
+            color_type
+                .gray_of(&normalized, formula)
+                .expect("the colour model is known")
+        }))
+    }
+
+    /// Views a region of this view without copying it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the region is empty or does not lie inside the view.
+    pub fn sub_view(&self, region: &Rect) -> Result<PictureView<'a, T>, Box<dyn Error>> {
+        if !region.fits_into(self.columns, self.lines) {
+            return Err(format!(
+                "The region {region} does not lie inside the picture of {}x{} pixels",
+                self.columns, self.lines
+            )
+            .into());
+        }
+        let start = region.y as usize * self.stride + region.x as usize * self.color_channel_count;
+        let end = start
+            + (region.height as usize - 1) * self.stride
+            + region.width as usize * self.color_channel_count;
+        Ok(PictureView {
+            lines: region.height,
+            columns: region.width,
+            color_channel_count: self.color_channel_count,
+            color_type: self.color_type,
+            stride: self.stride,
+            data: &self.data[start..end],
+        })
+    }
+
+    /// Copies the viewed samples into a new picture.
+    pub fn to_picture(&self) -> Picture<T> {
+        self.convert()
+    }
+
+    /// Copies the viewed samples into a new picture of another sample type, see
+    /// `Picture::convert`.
+    pub fn convert<U: Sample>(&self) -> Picture<U> {
+        Picture {
+            lines: self.lines,
+            columns: self.columns,
+            color_channel_count: self.color_channel_count,
+            color_type: self.color_type,
+            data: self
+                .slices()
+                .flat_map(|slice| slice.iter().map(|sample| sample.convert_to::<U>()))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Sample> Picture<T> {
+    /// Views the whole picture without copying it. The view covers all of `data`.
+    pub fn view(&self) -> PictureView<'_, T> {
+        PictureView {
+            lines: self.lines,
+            columns: self.columns,
+            color_channel_count: self.color_channel_count,
+            color_type: self.color_type,
+            stride: self.columns as usize * self.color_channel_count,
+            data: &self.data,
+        }
+    }
+}
+
+/// Pictures and views, so functions that only read the samples can take both.
+pub trait AsPictureView {
+    type Sample: Sample;
+
+    fn as_view(&self) -> PictureView<'_, Self::Sample>;
+}
+
+impl<T: Sample> AsPictureView for Picture<T> {
+    type Sample = T;
+
+    fn as_view(&self) -> PictureView<'_, T> {
+        self.view()
+    }
+}
+
+impl<T: Sample> AsPictureView for PictureView<'_, T> {
+    type Sample = T;
+
+    fn as_view(&self) -> PictureView<'_, T> {
+        *self
+    }
+}
@@ -0,0 +1,283 @@
+use crate::color_type::ColorType;
+use crate::compare_pictures::SimilarityInformation;
+use crate::datastore::Datastore;
+use crate::features::FeatureConfiguration;
+use crate::search::{rank_stored_entries, SearchOptions};
+use crate::search_index::{try_generate_suchindex, SearchIndex};
+use crate::statistics::Statistic;
+use std::error::Error;
+use std::ops::RangeInclusive;
+
+/// Tolerance for rounding errors when the brightness range is derived from `min_similarity`.
+const BRIGHTNESS_TOLERANCE: f32 = 1e-6;
+
+/// A query that combines a similarity threshold with range filters on the metadata of the
+/// entries.
+///
+/// All filters must match for an entry to be returned. Filters that are not set match every entry.
+/// `run` ranks the matching entries by their similarity to the picture given with `similar_to`,
+/// `matching_entries` returns them without ranking.
+///
+/// The brightness range, narrowed down by `min_similarity`, is handed to
+/// `Datastore::entries_in_brightness_range`, so backends with an index on the brightness only read
+/// the entries that can match.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::query::Query;
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let datastore = MemoryDatastore::with_entries(vec![bird.clone()]);
+///
+/// let similar_pictures = Query::new()
+///     .similar_to(bird)
+///     .min_similarity(0.9)
+///     .color_channels(3..=4)
+///     .run(&datastore)
+///     .unwrap();
+/// assert_eq!(similar_pictures.len(), 1);
+///
+/// let dark_pictures = Query::new()
+///     .brightness(0.2..=0.4)
+///     .matching_entries(&datastore)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    similar_to: Option<SearchIndex>,
+    min_similarity: Option<f64>,
+    brightness: Option<RangeInclusive<f32>>,
+    lightness: Option<RangeInclusive<f32>>,
+    lines: Option<RangeInclusive<u32>>,
+    columns: Option<RangeInclusive<u32>>,
+    color_channels: Option<RangeInclusive<usize>>,
+    color_types: Vec<ColorType>,
+    statistics: Vec<(Statistic, RangeInclusive<f32>)>,
+    file_size: Option<RangeInclusive<u64>>,
+    tags: Vec<String>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl Query {
+    /// Creates a query that matches every entry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ranks the results by their similarity to the given entry.
+    pub fn similar_to(mut self, query: SearchIndex) -> Self {
+        self.similar_to = Some(query);
+        self
+    }
+
+    /// Ranks the results by their similarity to the picture at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the picture can not be read.
+    pub fn similar_to_picture(self, path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(self.similar_to(try_generate_suchindex(
+            path.to_string(),
+            &FeatureConfiguration::default(),
+        )?))
+    }
+
+    /// Drops results that are less similar than `min_similarity`. Only used by `run`.
+    pub fn min_similarity(mut self, min_similarity: f64) -> Self {
+        self.min_similarity = Some(min_similarity);
+        self
+    }
+
+    /// Only matches entries whose average brightness lies within the range.
+    pub fn brightness(mut self, range: RangeInclusive<f32>) -> Self {
+        self.brightness = Some(range);
+        self
+    }
+
+    /// Only matches entries whose perceived lightness (`SearchIndex::lightness`) lies within the
+    /// range. Unlike the brightness, it follows how light people perceive the colours. Entries
+    /// without a lightness do not match.
+    pub fn lightness(mut self, range: RangeInclusive<f32>) -> Self {
+        self.lightness = Some(range);
+        self
+    }
+
+    /// Only matches pictures whose number of lines (their height) lies within the range.
+    pub fn lines(mut self, range: RangeInclusive<u32>) -> Self {
+        self.lines = Some(range);
+        self
+    }
+
+    /// Only matches pictures whose number of columns (their width) lies within the range.
+    pub fn columns(mut self, range: RangeInclusive<u32>) -> Self {
+        self.columns = Some(range);
+        self
+    }
+
+    /// Only matches pictures whose number of colour channels lies within the range.
+    pub fn color_channels(mut self, range: RangeInclusive<usize>) -> Self {
+        self.color_channels = Some(range);
+        self
+    }
+
+    /// Only matches pictures of the given colour type. Can be given several times, then any of
+    /// the colour types matches.
+    pub fn color_type(mut self, color_type: ColorType) -> Self {
+        self.color_types.push(color_type);
+        self
+    }
+
+    /// Only matches entries whose statistic (see `ImageStatistics::get`) lies within the range.
+    /// Can be given several times, then all statistics must match. Entries without statistics do
+    /// not match.
+    pub fn statistic(mut self, statistic: Statistic, range: RangeInclusive<f32>) -> Self {
+        self.statistics.push((statistic, range));
+        self
+    }
+
+    /// Only matches pictures whose file size in bytes lies within the range.
+    pub fn file_size(mut self, range: RangeInclusive<u64>) -> Self {
+        self.file_size = Some(range);
+        self
+    }
+
+    /// Only matches entries with the given tag. Can be given several times, then all tags are
+    /// required.
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Returns at most `limit` results. By default all matching results are returned.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skips the first `offset` results.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Returns `true` if the entry matches all metadata filters of the query.
+    ///
+    /// The similarity threshold is not checked, because it needs the histograms to be compared.
+    pub fn matches(&self, entry: &SearchIndex) -> bool {
+        fn within<T: PartialOrd>(range: &Option<RangeInclusive<T>>, value: &T) -> bool {
+            range.as_ref().is_none_or(|range| range.contains(value))
+        }
+
+        within(&self.brightness, &entry.average_brightness)
+            && self.lightness.as_ref().is_none_or(|range| {
+                entry
+                    .lightness
+                    .is_some_and(|lightness| range.contains(&lightness))
+            })
+            && within(&self.lines, &entry.lines)
+            && within(&self.columns, &entry.columns)
+            && within(&self.color_channels, &entry.histogram.len())
+            && (self.color_types.is_empty() || self.color_types.contains(&entry.color_type))
+            && within(&self.file_size, &entry.file_size)
+            && self.tags.iter().all(|tag| entry.tags.contains(tag))
+            && self.statistics.iter().all(|(statistic, range)| {
+                entry
+                    .statistics
+                    .as_ref()
+                    .and_then(|statistics| statistics.get(*statistic))
+                    .is_some_and(|value| range.contains(&value))
+            })
+    }
+
+    /// Returns the entries of the datastore that match all metadata filters, in the order of the
+    /// datastore and paged by `limit` and `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the datastore can not be read.
+    pub fn matching_entries(
+        &self,
+        datastore: &dyn Datastore,
+    ) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        let bounds = self
+            .brightness
+            .as_ref()
+            .map(|range| (*range.start(), *range.end()));
+        Ok(candidates(datastore, bounds)?
+            .into_iter()
+            .filter(|entry| self.matches(entry))
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Ranks the matching entries of the datastore by their similarity to the picture given with
+    /// `similar_to`, the most similar first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no picture to compare with was given or if the datastore can not be
+    /// read.
+    pub fn run(
+        &self,
+        datastore: &dyn Datastore,
+    ) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+        let Some(query) = &self.similar_to else {
+            return Err("The query has no picture to compare with. Use similar_to first.".into());
+        };
+        let entries: Vec<SearchIndex> = candidates(datastore, self.brightness_bounds(query))?
+            .into_iter()
+            .filter(|entry| self.matches(entry))
+            .collect();
+        let options = SearchOptions {
+            limit: self.limit.unwrap_or(usize::MAX),
+            offset: self.offset,
+            min_similarity: self.min_similarity,
+            approximate: None,
+        };
+        Ok(rank_stored_entries(
+            query,
+            &entries,
+            &datastore.features(),
+            &options,
+        ))
+    }
+
+    /// Intersects the brightness filter with the brightness range that `min_similarity` allows.
+    ///
+    /// The similarity is the average of `1 - ` the brightness difference and a cosine similarity
+    /// of at most 1, so a similarity of at least `s` needs a brightness difference of at most
+    /// `2 - 2s`.
+    fn brightness_bounds(&self, query: &SearchIndex) -> Option<(f32, f32)> {
+        let mut bounds = self
+            .brightness
+            .as_ref()
+            .map(|range| (*range.start(), *range.end()));
+
+        if let Some(min_similarity) = self.min_similarity {
+            let max_difference = (2.0 - 2.0 * min_similarity) as f32 + BRIGHTNESS_TOLERANCE;
+            let (min, max) = bounds.unwrap_or((f32::NEG_INFINITY, f32::INFINITY));
+            bounds = Some((
+                min.max(query.average_brightness - max_difference),
+                max.min(query.average_brightness + max_difference),
+            ));
+        }
+        bounds
+    }
+}
+
+/// Reads the entries whose brightness lies within the bounds, or all entries without bounds.
+fn candidates(
+    datastore: &dyn Datastore,
+    bounds: Option<(f32, f32)>,
+) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+    match bounds {
+        Some((min, max)) if min > max => Ok(Vec::new()),
+        Some((min, max)) => datastore.entries_in_brightness_range(min, max),
+        None => datastore.entries(),
+    }
+}
@@ -0,0 +1,437 @@
+use crate::color_moments::{compare_color_moment_features, ColorMoments};
+use crate::compare_pictures::{Similarity, SimilarityInformation};
+use crate::correlogram::{compare_correlogram_values, AutoCorrelogram};
+use crate::cosinus_similarity::{
+    compute_cosine_similarity, get_normalized_histogram_of_search_index,
+};
+use crate::search_index::SearchIndex;
+use crate::shape::{compare_shape_features, ShapeDescriptor};
+use crate::statistics::{compare_feature_vectors, ImageStatistics};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The weights of the features that make up the similarity of two pictures.
+///
+/// The similarity is the weighted average of the brightness similarity (`1 - ` the difference of
+/// the average brightness), the cosine similarity of each color channel's histogram, the
+/// similarity of the image statistics (see `compare_statistics`), the similarity of the colour
+/// moments (see `compare_color_moments`), the similarity of the colour auto-correlograms (see
+/// `compare_auto_correlograms`) and the similarity of the shapes (see `compare_shapes`).
+///
+/// # Fields
+///
+/// * `brightness`: The weight of the brightness similarity.
+/// * `histogram_channels`: The weight of the histogram of each color channel.
+/// * `statistics`: The weight of the statistics similarity. Entries without statistics count as
+///   not similar.
+/// * `color_moments`: The weight of the colour moments similarity. Entries without colour moments
+///   count as not similar.
+/// * `correlogram`: The weight of the correlogram similarity. Entries without a correlogram count
+///   as not similar.
+/// * `shape`: The weight of the shape similarity. Entries without a shape descriptor count as not
+///   similar.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::query_descriptor::FeatureWeights;
+///
+/// // the weights of `compare_search_indexes`: brightness and histograms count equally
+/// let weights = FeatureWeights::uniform(4);
+/// assert_eq!(weights.brightness, 1.0);
+/// assert_eq!(weights.histogram_channels, vec![0.25; 4]);
+///
+/// // the statistics count as much as the brightness
+/// let weights = FeatureWeights::uniform(4).with_statistics(1.0);
+/// assert_eq!(weights.total(), 3.0);
+///
+/// // the colour moments instead of the histograms
+/// let weights = FeatureWeights::compact(4);
+/// assert_eq!(weights.histogram_channels, vec![0.0; 4]);
+/// assert_eq!(weights.color_moments, 1.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureWeights {
+    pub brightness: f64,
+    pub histogram_channels: Vec<f64>,
+    pub statistics: f64,
+    pub color_moments: f64,
+    pub correlogram: f64,
+    pub shape: f64,
+}
+
+impl FeatureWeights {
+    /// Weighs the brightness like all histograms together, which is the similarity computed by
+    /// `compare_search_indexes`. The statistics are not used.
+    pub fn uniform(channel_count: usize) -> Self {
+        FeatureWeights {
+            brightness: 1.0,
+            histogram_channels: vec![1.0 / channel_count as f64; channel_count],
+            statistics: 0.0,
+            color_moments: 0.0,
+            correlogram: 0.0,
+            shape: 0.0,
+        }
+    }
+
+    /// Weighs the brightness like the colour moments and ignores the histograms, which are not
+    /// compared at all then. This trades a little accuracy for speed in very large datastores,
+    /// and is the only way to search compact ones (see `DescriptorSet::Compact`).
+    pub fn compact(channel_count: usize) -> Self {
+        FeatureWeights {
+            histogram_channels: vec![0.0; channel_count],
+            ..FeatureWeights::uniform(channel_count)
+        }
+        .with_color_moments(1.0)
+    }
+
+    /// Uses the similarity of the image statistics with the given weight.
+    pub fn with_statistics(mut self, weight: f64) -> Self {
+        self.statistics = weight;
+        self
+    }
+
+    /// Uses the similarity of the colour moments with the given weight.
+    pub fn with_color_moments(mut self, weight: f64) -> Self {
+        self.color_moments = weight;
+        self
+    }
+
+    /// Uses the similarity of the colour auto-correlograms with the given weight.
+    pub fn with_correlogram(mut self, weight: f64) -> Self {
+        self.correlogram = weight;
+        self
+    }
+
+    /// Uses the similarity of the shapes with the given weight.
+    pub fn with_shape(mut self, weight: f64) -> Self {
+        self.shape = weight;
+        self
+    }
+
+    /// Returns the sum of all weights.
+    pub fn total(&self) -> f64 {
+        self.brightness
+            + self.histogram_channels.iter().sum::<f64>()
+            + self.statistics
+            + self.color_moments
+            + self.correlogram
+            + self.shape
+    }
+}
+
+/// Predefined `FeatureWeights` for different kinds of pictures.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::query_descriptor::{FeatureWeights, SearchProfile};
+///
+/// let profile: SearchProfile = "shape".parse().unwrap();
+/// let weights = profile.weights(4);
+/// assert!(weights.shape > 0.5 * weights.total());
+/// assert_eq!(SearchProfile::default().weights(4), FeatureWeights::uniform(4));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchProfile {
+    /// The brightness and the histograms, like `compare_search_indexes`.
+    #[default]
+    Colour,
+    /// The brightness and the colour moments, see `FeatureWeights::compact`.
+    Compact,
+    /// Mostly the shapes of the edges, for line art and logos whose colours barely matter. The
+    /// brightness and the histograms together count a fifth.
+    Shape,
+    /// The colours and where they are: the colour auto-correlograms count as much as the
+    /// brightness and the histograms together, so pictures with a similar colour layout rank
+    /// higher.
+    Structure,
+}
+
+impl SearchProfile {
+    /// Returns the weights of the profile for pictures with the given number of color channels.
+    pub fn weights(&self, channel_count: usize) -> FeatureWeights {
+        match self {
+            SearchProfile::Colour => FeatureWeights::uniform(channel_count),
+            SearchProfile::Compact => FeatureWeights::compact(channel_count),
+            SearchProfile::Shape => FeatureWeights {
+                brightness: 0.125,
+                histogram_channels: vec![0.125 / channel_count as f64; channel_count],
+                ..FeatureWeights::uniform(channel_count)
+            }
+            .with_shape(1.0),
+            SearchProfile::Structure => FeatureWeights {
+                brightness: 0.5,
+                histogram_channels: vec![0.5 / channel_count as f64; channel_count],
+                ..FeatureWeights::uniform(channel_count)
+            }
+            .with_correlogram(1.0),
+        }
+    }
+}
+
+impl Display for SearchProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SearchProfile::Colour => "colour",
+            SearchProfile::Compact => "compact",
+            SearchProfile::Shape => "shape",
+            SearchProfile::Structure => "structure",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for SearchProfile {
+    type Err = String;
+
+    /// Parses the names used on the command line: `colour`, `compact`, `shape` and `structure`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "colour" => Ok(SearchProfile::Colour),
+            "compact" => Ok(SearchProfile::Compact),
+            "shape" => Ok(SearchProfile::Shape),
+            "structure" => Ok(SearchProfile::Structure),
+            _ => Err(format!(
+                "Unknown profile '{name}', expected colour, compact, shape or structure"
+            )),
+        }
+    }
+}
+
+/// The descriptor a query is compared with: normalized histograms, an average brightness, and the
+/// image statistics, colour moments, correlogram values and shape as feature vectors (empty if the
+/// query has none).
+///
+/// Unlike a `SearchIndex`, the histograms are not counts, so they can be averaged over several
+/// pictures and combined with negative weights.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::compare_pictures::compare_search_indexes;
+/// use imsearch::query_descriptor::{FeatureWeights, QueryDescriptor};
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let flower =
+///     generate_suchindex("src/tests/files/pictures_for_testing/flower_purple_1.png".to_string());
+///
+/// let descriptor = QueryDescriptor::from_search_index(&bird);
+/// let weights = FeatureWeights::uniform(bird.histogram.len());
+/// let similarity = descriptor.compare(&flower, &weights).similarity();
+/// assert!((similarity - compare_search_indexes(&bird, &flower).similarity()).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QueryDescriptor {
+    pub histograms: Vec<Vec<f64>>,
+    pub average_brightness: f32,
+    pub statistics: Vec<f64>,
+    pub color_moments: Vec<f64>,
+    pub correlogram: Vec<f64>,
+    pub shape: Vec<f64>,
+}
+
+impl QueryDescriptor {
+    pub fn from_search_index(search_index: &SearchIndex) -> Self {
+        QueryDescriptor {
+            histograms: get_normalized_histogram_of_search_index(search_index),
+            average_brightness: search_index.average_brightness,
+            statistics: statistics_features(search_index),
+            color_moments: color_moments_features(search_index),
+            correlogram: correlogram_features(search_index),
+            shape: shape_features(search_index),
+        }
+    }
+
+    /// Averages the descriptors of the entries, `None` if there are no entries.
+    pub fn centroid(entries: &[SearchIndex]) -> Option<Self> {
+        let (first, rest) = entries.split_first()?;
+        let mut centroid = QueryDescriptor::from_search_index(first);
+        for entry in rest {
+            centroid.add_weighted(&QueryDescriptor::from_search_index(entry), 1.0);
+        }
+        centroid.scale(1.0 / entries.len() as f64);
+        Some(centroid)
+    }
+
+    /// Adds `weight` times the histograms, brightness and feature vectors of `other`. Channels,
+    /// bins and features that only one of the descriptors has are left as they are.
+    pub fn add_weighted(&mut self, other: &QueryDescriptor, weight: f64) {
+        for (channel, other_channel) in self.histograms.iter_mut().zip(&other.histograms) {
+            for (bin, other_bin) in channel.iter_mut().zip(other_channel) {
+                *bin += weight * other_bin;
+            }
+        }
+        self.average_brightness += (weight * f64::from(other.average_brightness)) as f32;
+        for (feature, other_feature) in self
+            .statistics
+            .iter_mut()
+            .zip(&other.statistics)
+            .chain(self.color_moments.iter_mut().zip(&other.color_moments))
+            .chain(self.correlogram.iter_mut().zip(&other.correlogram))
+            .chain(self.shape.iter_mut().zip(&other.shape))
+        {
+            *feature += weight * other_feature;
+        }
+    }
+
+    /// Multiplies the histograms, brightness and feature vectors by `factor`.
+    pub fn scale(&mut self, factor: f64) {
+        for bin in self
+            .histograms
+            .iter_mut()
+            .flatten()
+            .chain(self.statistics.iter_mut())
+            .chain(self.color_moments.iter_mut())
+            .chain(self.correlogram.iter_mut())
+            .chain(self.shape.iter_mut())
+        {
+            *bin *= factor;
+        }
+        self.average_brightness = (f64::from(self.average_brightness) * factor) as f32;
+    }
+
+    /// Returns the brightness similarity and the cosine similarity of each color channel of the
+    /// entry. Channels the entry does not have are 0.
+    pub fn feature_similarities(&self, entry: &SearchIndex) -> (f32, Vec<f64>) {
+        let brightness = self.brightness_similarity(entry);
+        let entry_histograms = get_normalized_histogram_of_search_index(entry);
+        let channels = self
+            .histograms
+            .iter()
+            .enumerate()
+            .map(|(channel, histogram)| match entry_histograms.get(channel) {
+                Some(entry_histogram) if entry_histogram.len() == histogram.len() => {
+                    compute_cosine_similarity(histogram, entry_histogram)
+                }
+                _ => 0.0,
+            })
+            .collect();
+        (brightness, channels)
+    }
+
+    /// Returns the similarity of the entry's statistics to the descriptor's, 0 if one of them has
+    /// none.
+    pub fn statistics_similarity(&self, entry: &SearchIndex) -> f64 {
+        compare_feature_vectors(&self.statistics, &statistics_features(entry)).unwrap_or(0.0)
+    }
+
+    /// Returns the similarity of the entry's colour moments to the descriptor's, 0 if one of them
+    /// has none.
+    pub fn color_moments_similarity(&self, entry: &SearchIndex) -> f64 {
+        compare_color_moment_features(&self.color_moments, &color_moments_features(entry))
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the similarity of the entry's correlogram to the descriptor's, 0 if one of them has
+    /// none.
+    pub fn correlogram_similarity(&self, entry: &SearchIndex) -> f64 {
+        compare_correlogram_values(&self.correlogram, &correlogram_features(entry)).unwrap_or(0.0)
+    }
+
+    /// Returns the similarity of the entry's shape to the descriptor's, 0 if one of them has none.
+    pub fn shape_similarity(&self, entry: &SearchIndex) -> f64 {
+        compare_shape_features(&self.shape, &shape_features(entry)).unwrap_or(0.0)
+    }
+
+    /// Computes the weighted similarity of the entry to the descriptor. Features without weight
+    /// are not compared.
+    ///
+    /// The `cosine_similarity` of the result is the weighted average of the channels' cosine
+    /// similarities.
+    pub fn compare(&self, entry: &SearchIndex, weights: &FeatureWeights) -> SimilarityInformation {
+        self.score(entry, weights).with_entry(entry.clone())
+    }
+
+    /// Same as `compare`, but without a copy of the entry.
+    pub(crate) fn score(&self, entry: &SearchIndex, weights: &FeatureWeights) -> Similarity {
+        let channel_weight: f64 = weights.histogram_channels.iter().sum();
+        let (brightness, weighted_cosine) = if channel_weight > 0.0 {
+            let (brightness, channels) = self.feature_similarities(entry);
+            let weighted_cosine: f64 = channels
+                .iter()
+                .zip(&weights.histogram_channels)
+                .map(|(similarity, weight)| similarity * weight)
+                .sum();
+            (brightness, weighted_cosine)
+        } else {
+            (self.brightness_similarity(entry), 0.0)
+        };
+        let statistics = if weights.statistics > 0.0 {
+            weights.statistics * self.statistics_similarity(entry)
+        } else {
+            0.0
+        };
+        let color_moments = if weights.color_moments > 0.0 {
+            weights.color_moments * self.color_moments_similarity(entry)
+        } else {
+            0.0
+        };
+        let correlogram = if weights.correlogram > 0.0 {
+            weights.correlogram * self.correlogram_similarity(entry)
+        } else {
+            0.0
+        };
+        let shape = if weights.shape > 0.0 {
+            weights.shape * self.shape_similarity(entry)
+        } else {
+            0.0
+        };
+        let similarity = (weights.brightness * f64::from(brightness)
+            + weighted_cosine
+            + statistics
+            + color_moments
+            + correlogram
+            + shape)
+            / weights.total();
+        let cosine_similarity = if channel_weight > 0.0 {
+            weighted_cosine / channel_weight
+        } else {
+            0.0
+        };
+        Similarity::new(similarity, cosine_similarity, brightness)
+    }
+
+    /// Returns an upper bound of `compare` that only depends on the average brightness.
+    pub fn similarity_upper_bound(&self, entry: &SearchIndex, weights: &FeatureWeights) -> f64 {
+        let brightness = self.brightness_similarity(entry);
+        (weights.total() - weights.brightness * f64::from(1.0 - brightness)) / weights.total()
+    }
+
+    fn brightness_similarity(&self, entry: &SearchIndex) -> f32 {
+        1.0 - (self.average_brightness - entry.average_brightness).abs()
+    }
+}
+
+fn color_moments_features(search_index: &SearchIndex) -> Vec<f64> {
+    search_index
+        .color_moments
+        .as_ref()
+        .map(ColorMoments::feature_vector)
+        .unwrap_or_default()
+}
+
+fn correlogram_features(search_index: &SearchIndex) -> Vec<f64> {
+    search_index
+        .correlogram
+        .as_ref()
+        .map(AutoCorrelogram::feature_vector)
+        .unwrap_or_default()
+}
+
+fn shape_features(search_index: &SearchIndex) -> Vec<f64> {
+    search_index
+        .shape
+        .as_ref()
+        .map(ShapeDescriptor::feature_vector)
+        .unwrap_or_default()
+}
+
+fn statistics_features(search_index: &SearchIndex) -> Vec<f64> {
+    search_index
+        .statistics
+        .as_ref()
+        .map(ImageStatistics::feature_vector)
+        .unwrap_or_default()
+}
@@ -0,0 +1,329 @@
+use crate::color_moments::compute_color_moments;
+use crate::compare_pictures::{
+    brightness_upper_bound, compute_similarity, similarity_upper_bound, Similarity,
+    SimilarityInformation,
+};
+use crate::correlogram::{compute_auto_correlogram, CORRELOGRAM_DISTANCES, CORRELOGRAM_LEVELS};
+use crate::datastore::{open_datastore, Datastore};
+use crate::get_datastore_path;
+use crate::grayscale::{average_luma, LumaFormula};
+use crate::histogram::Histogram;
+use crate::picture::PictureU8;
+use crate::search::{rank_with_details_by, search_datastore, SearchOptions};
+use crate::search_index::{determine_avg_brightness, SearchIndex};
+use crate::shape::compute_shape_descriptor;
+use crate::statistics::compute_statistics;
+use crate::{get_histogram, try_read_picture};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The number of rows and columns of the grid whose cells are stored with every entry.
+pub const GRID_SIZE: u32 = 3;
+
+/// A rectangular region of a picture in pixels. `x` counts the columns, `y` the lines, both start
+/// at the top left corner.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::region::Rect;
+///
+/// let logo: Rect = "10,20,64,32".parse().unwrap();
+/// assert_eq!(logo, Rect::new(10, 20, 64, 32));
+/// assert_eq!(logo.to_string(), "10,20,64,32");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns `true` if the region is not empty and lies completely inside a picture of the
+    /// given size.
+    pub fn fits_into(&self, columns: u32, lines: u32) -> bool {
+        self.width > 0
+            && self.height > 0
+            && u64::from(self.x) + u64::from(self.width) <= u64::from(columns)
+            && u64::from(self.y) + u64::from(self.height) <= u64::from(lines)
+    }
+}
+
+impl Display for Rect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl FromStr for Rect {
+    type Err = String;
+
+    /// Parses `x,y,width,height`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let numbers: Vec<u32> = value
+            .split(',')
+            .map(|number| number.trim().parse::<u32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("'{value}' is not a region, expected x,y,width,height"))?;
+        match numbers.as_slice() {
+            [x, y, width, height] => Ok(Rect::new(*x, *y, *width, *height)),
+            _ => Err(format!(
+                "'{value}' is not a region, expected x,y,width,height"
+            )),
+        }
+    }
+}
+
+/// The descriptors of one cell of the grid that is stored with every entry.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct GridCell {
+    pub region: Rect,
+    pub average_brightness: f32,
+    pub histogram: Vec<Histogram>,
+}
+
+/// A picture whose region matches a query, see `search_region_in_cells`.
+///
+/// # Fields
+///
+/// * `similarity`: The similarity of the best matching cell; its `search_index` is the entry of
+///   the whole picture.
+/// * `region`: The best matching cell of the picture.
+#[derive(Debug, Clone)]
+pub struct RegionMatch {
+    pub similarity: SimilarityInformation,
+    pub region: Rect,
+}
+
+impl RegionMatch {
+    pub fn print(&self) {
+        self.similarity.print();
+        println!("Matching region:      {}", self.region);
+    }
+}
+
+/// Computes the descriptors of a region of a picture.
+///
+/// # Errors
+///
+/// Returns an error if the region does not lie inside the picture.
+pub fn describe_region(
+    picture: &PictureU8,
+    region: &Rect,
+) -> Result<(f32, Vec<Histogram>), Box<dyn Error>> {
+    let cell = picture.view().sub_view(region)?;
+    Ok((determine_avg_brightness(&cell), get_histogram(&cell)))
+}
+
+/// Splits the picture into a `GRID_SIZE` x `GRID_SIZE` grid and computes the descriptors of every
+/// cell. The last row and column take the remaining pixels; pictures smaller than the grid get
+/// fewer cells.
+pub fn compute_grid_cells(picture: &PictureU8) -> Vec<GridCell> {
+    let cell_width = picture.columns / GRID_SIZE;
+    let cell_height = picture.lines / GRID_SIZE;
+    if cell_width == 0 || cell_height == 0 {
+        return Vec::new();
+    }
+
+    let mut cells = Vec::new();
+    for row in 0..GRID_SIZE {
+        for column in 0..GRID_SIZE {
+            let x = column * cell_width;
+            let y = row * cell_height;
+            let width = if column == GRID_SIZE - 1 {
+                picture.columns - x
+            } else {
+                cell_width
+            };
+            let height = if row == GRID_SIZE - 1 {
+                picture.lines - y
+            } else {
+                cell_height
+            };
+            let region = Rect::new(x, y, width, height);
+            let (average_brightness, histogram) =
+                describe_region(picture, &region).expect("grid cells lie inside the picture");
+            cells.push(GridCell {
+                region,
+                average_brightness,
+                histogram,
+            });
+        }
+    }
+    cells
+}
+
+/// Generates the search index of a region of the picture at the given path. Its `lines` and
+/// `columns` are the size of the region.
+///
+/// # Errors
+///
+/// Returns an error if the picture can not be read or the region does not lie inside it.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::region::{region_search_index, Rect};
+///
+/// let top_left = region_search_index(
+///     "src/tests/files/pictures_for_testing/bird.png",
+///     &Rect::new(0, 0, 100, 50),
+/// )
+/// .unwrap();
+/// assert_eq!((top_left.columns, top_left.lines), (100, 50));
+/// ```
+pub fn region_search_index(path: &str, region: &Rect) -> Result<SearchIndex, Box<dyn Error>> {
+    let picture = try_read_picture(path)?;
+    let (average_brightness, histogram) = describe_region(&picture, region)?;
+    let mut search_index = SearchIndex::new(path.to_string(), average_brightness, histogram);
+    search_index.lines = region.height;
+    search_index.columns = region.width;
+    search_index.color_type = picture.color_type;
+    let cell = picture.view().sub_view(region)?;
+    search_index.lightness = Some(average_luma(&cell, LumaFormula::Lightness));
+    search_index.statistics = compute_statistics(&cell).ok();
+    search_index.color_moments = compute_color_moments(&cell).ok();
+    search_index.correlogram =
+        compute_auto_correlogram(&cell, CORRELOGRAM_LEVELS, &CORRELOGRAM_DISTANCES).ok();
+    search_index.shape = compute_shape_descriptor(&cell).ok();
+    Ok(search_index)
+}
+
+/// Searches the configured datastore for pictures that are similar to a region of the picture at
+/// the given path. The region is compared with the stored pictures as a whole.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read, or the region does not lie
+/// inside the picture.
+pub fn search_region(
+    path: &str,
+    region: &Rect,
+    options: &SearchOptions,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    let query = region_search_index(path, region)?;
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    search_datastore(datastore.as_ref(), &query, options)
+}
+
+/// Searches the configured datastore for pictures that contain a region similar to a region of
+/// the picture at the given path, see `search_region_in_cells`.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read, or the region does not lie
+/// inside the picture.
+pub fn search_region_anywhere(
+    path: &str,
+    region: &Rect,
+    options: &SearchOptions,
+) -> Result<Vec<RegionMatch>, Box<dyn Error>> {
+    let query = region_search_index(path, region)?;
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    search_region_in_cells(datastore.as_ref(), &query, options)
+}
+
+/// Ranks the entries of the datastore by the similarity of their best matching grid cell to the
+/// query, so a region can be found anywhere in the stored pictures.
+///
+/// Entries without grid cells (pictures smaller than the grid or entries that were not indexed
+/// again since grid cells were added) are compared as a whole.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::datastore::Datastore;
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::region::{region_search_index, search_region_in_cells};
+/// use imsearch::search::SearchOptions;
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = "src/tests/files/pictures_for_testing/bird.png";
+/// let datastore = MemoryDatastore::with_entries(vec![generate_suchindex(bird.to_string())]);
+///
+/// // the top left grid cell of the bird is found again
+/// let cell = datastore.get(bird).unwrap().unwrap().grid_cells[0].region;
+/// let query = region_search_index(bird, &cell).unwrap();
+/// let matches = search_region_in_cells(&datastore, &query, &SearchOptions::top(1)).unwrap();
+/// assert_eq!(matches[0].region, cell);
+/// ```
+pub fn search_region_in_cells(
+    datastore: &dyn Datastore,
+    query: &SearchIndex,
+    options: &SearchOptions,
+) -> Result<Vec<RegionMatch>, Box<dyn Error>> {
+    let entries = datastore.entries()?;
+    let ranked = rank_with_details_by(
+        &entries,
+        options,
+        |entry| {
+            if entry.grid_cells.is_empty() {
+                return similarity_upper_bound(query, entry);
+            }
+            entry
+                .grid_cells
+                .iter()
+                .map(|cell| {
+                    brightness_upper_bound(query.average_brightness, cell.average_brightness)
+                })
+                .fold(f64::NEG_INFINITY, f64::max)
+        },
+        |entry| best_cell(query, entry),
+    );
+    Ok(ranked
+        .into_iter()
+        .map(|(similarity, region)| RegionMatch { similarity, region })
+        .collect())
+}
+
+/// Returns the grid cells of the entry as search indexes, or the whole entry if it has no cells.
+fn cell_indexes(entry: &SearchIndex) -> Vec<(Rect, SearchIndex)> {
+    if entry.grid_cells.is_empty() {
+        let whole_picture = Rect::new(0, 0, entry.columns, entry.lines);
+        return vec![(whole_picture, entry.clone())];
+    }
+    entry
+        .grid_cells
+        .iter()
+        .map(|cell| (cell.region, cell_search_index(entry, cell)))
+        .collect()
+}
+
+/// Returns a grid cell of the entry as a search index, so it can be compared like a picture.
+pub(crate) fn cell_search_index(entry: &SearchIndex, cell: &GridCell) -> SearchIndex {
+    let mut cell_index = SearchIndex::new(
+        entry.filepath.clone(),
+        cell.average_brightness,
+        cell.histogram.clone(),
+    );
+    cell_index.columns = cell.region.width;
+    cell_index.lines = cell.region.height;
+    cell_index.color_type = entry.color_type;
+    cell_index
+}
+
+/// Returns the similarity of the best matching cell, reported for the whole entry, and its region.
+fn best_cell(query: &SearchIndex, entry: &SearchIndex) -> (Similarity, Rect) {
+    cell_indexes(entry)
+        .into_iter()
+        .map(|(region, cell)| (compute_similarity(query, &cell), region))
+        .max_by(|(a, _), (b, _)| a.similarity.total_cmp(&b.similarity))
+        .expect("every entry has at least one cell")
+}
@@ -0,0 +1,442 @@
+use crate::ann_index::{load_ann_index, AnnIndex, AnnSearchOptions};
+use crate::compare_pictures::{
+    compute_similarity, similarity_upper_bound, Similarity, SimilarityInformation,
+};
+use crate::datastore::{open_datastore, Datastore};
+use crate::features::{DescriptorSet, FeatureConfiguration};
+use crate::get_datastore_path;
+use crate::query_descriptor::{FeatureWeights, QueryDescriptor, SearchProfile};
+use crate::search_index::{try_generate_suchindex, SearchIndex};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::error::Error;
+
+/// Selects which results of a search are returned.
+///
+/// The results are ranked by their similarity, the most similar first (ties are ordered by
+/// filepath). Of this ranking, `limit` results starting at `offset` are returned, so a UI can page
+/// through the results with the same `limit` and an increasing `offset`.
+///
+/// # Fields
+///
+/// * `limit`: The maximum number of returned results.
+/// * `offset`: The number of best results that are skipped.
+/// * `min_similarity`: Results that are less similar than this are dropped.
+/// * `approximate`: If set, `search` and `search_with_options` only compare the candidates that the
+///   ANN index of the datastore finds (see `ann_index`) instead of every entry. This trades recall
+///   for speed, the results are looked up by their filepath with `Datastore::get`.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::ann_index::AnnSearchOptions;
+/// use imsearch::search::SearchOptions;
+///
+/// let second_page = SearchOptions::top(20).with_offset(20).with_min_similarity(0.8);
+/// assert_eq!(second_page.limit, 20);
+///
+/// let fast = SearchOptions::top(5).with_approximate(AnnSearchOptions::default());
+/// assert!(fast.approximate.is_some());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchOptions {
+    pub limit: usize,
+    pub offset: usize,
+    pub min_similarity: Option<f64>,
+    pub approximate: Option<AnnSearchOptions>,
+}
+
+impl Default for SearchOptions {
+    /// Returns the five most similar pictures, like `get_top_five_similar_pictures`.
+    fn default() -> Self {
+        SearchOptions::top(5)
+    }
+}
+
+impl SearchOptions {
+    /// Returns the `k` most similar pictures.
+    pub fn top(k: usize) -> Self {
+        SearchOptions {
+            limit: k,
+            offset: 0,
+            min_similarity: None,
+            approximate: None,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_min_similarity(mut self, min_similarity: f64) -> Self {
+        self.min_similarity = Some(min_similarity);
+        self
+    }
+
+    pub fn with_approximate(mut self, options: AnnSearchOptions) -> Self {
+        self.approximate = Some(options);
+        self
+    }
+}
+
+/// Finds the `k` pictures of the configured datastore most similar to the picture at the given
+/// path.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::search::search;
+///
+/// let similar_pictures = search("src/tests/files/pictures_for_testing/bird.png", 3).unwrap();
+/// assert!(similar_pictures.len() <= 3);
+/// ```
+pub fn search(path: &str, k: usize) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    search_with_options(path, &SearchOptions::top(k))
+}
+
+/// Same as `search`, but the returned results are selected by the `options`.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read.
+pub fn search_with_options(
+    path: &str,
+    options: &SearchOptions,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    let datastore_path = get_datastore_path()?;
+    let datastore = open_datastore(&datastore_path)?;
+    let query = try_generate_suchindex(path.to_string(), &datastore.features())?;
+    match &options.approximate {
+        // the ANN index compares histograms, which compact datastores do not store
+        Some(_) if datastore.features().descriptors == DescriptorSet::Full => {
+            let index = load_ann_index(&datastore_path)?;
+            search_datastore_with_index(datastore.as_ref(), &index, &query, options)
+        }
+        _ => search_datastore(datastore.as_ref(), &query, options),
+    }
+}
+
+/// Same as `search_with_options`, but the similarity uses the weights of the `profile`, e.g.
+/// `SearchProfile::Shape` for line art. The ANN index only knows the similarity of
+/// `SearchProfile::Colour`, so `options.approximate` is ignored for the other profiles.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read.
+pub fn search_with_profile(
+    path: &str,
+    options: &SearchOptions,
+    profile: SearchProfile,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    if profile == SearchProfile::Colour {
+        return search_with_options(path, options);
+    }
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    let query = try_generate_suchindex(path.to_string(), &datastore.features())?;
+    let weights = profile.weights(query.histogram.len());
+    Ok(rank_entries_weighted(
+        &query,
+        &datastore.entries()?,
+        &weights,
+        options,
+    ))
+}
+
+/// Searches the given datastore for the pictures most similar to the query, see
+/// `rank_stored_entries`.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read.
+pub fn search_datastore(
+    datastore: &dyn Datastore,
+    query: &SearchIndex,
+    options: &SearchOptions,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    let entries = datastore.entries()?;
+    Ok(rank_stored_entries(
+        query,
+        &entries,
+        &datastore.features(),
+        options,
+    ))
+}
+
+/// Ranks entries that a datastore with the given feature configuration stores by their similarity
+/// to the query, like `rank_entries`. The entries of compact datastores (see
+/// `DescriptorSet::Compact`) have no histograms, so they are ranked with `FeatureWeights::compact`
+/// instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::features::FeatureConfiguration;
+/// use imsearch::search::{rank_stored_entries, SearchOptions};
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let mut bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let mut flower =
+///     generate_suchindex("src/tests/files/pictures_for_testing/flower_purple_1.png".to_string());
+/// let compact = FeatureConfiguration::compact();
+/// compact.keep_stored_descriptors(&mut bird);
+/// compact.keep_stored_descriptors(&mut flower);
+///
+/// let entries = [flower, bird.clone()];
+/// let results = rank_stored_entries(&bird, &entries, &compact, &SearchOptions::top(1));
+/// assert_eq!(results[0].search_index().filepath, bird.filepath);
+/// ```
+pub fn rank_stored_entries(
+    query: &SearchIndex,
+    entries: &[SearchIndex],
+    features: &FeatureConfiguration,
+    options: &SearchOptions,
+) -> Vec<SimilarityInformation> {
+    match features.descriptors {
+        DescriptorSet::Full => rank_entries(query, entries, options),
+        DescriptorSet::Compact => {
+            let weights = FeatureWeights::compact(query.histogram.len());
+            rank_entries_weighted(query, entries, &weights, options)
+        }
+    }
+}
+
+/// Searches the given datastore for the pictures most similar to the query, but only compares the
+/// candidates the ANN `index` of the datastore finds with `options.approximate` (the default
+/// `AnnSearchOptions` if it is not set). The results are looked up with `Datastore::get`, so a
+/// datastore that answers it without reading every entry (e.g. `SqliteDatastore`) is never
+/// scanned.
+///
+/// The similarity is the same as the one of `search_datastore`, but entries the index misses are
+/// not returned.
+///
+/// # Errors
+///
+/// Returns an error if a result can not be read from the datastore.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::ann_index::AnnIndex;
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::search::{search_datastore_with_index, SearchOptions};
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let datastore = MemoryDatastore::with_entries(vec![bird.clone()]);
+/// let index = AnnIndex::build(&[bird.clone()]);
+///
+/// let results =
+///     search_datastore_with_index(&datastore, &index, &bird, &SearchOptions::top(1)).unwrap();
+/// assert_eq!(results[0].search_index().filepath, bird.filepath);
+/// ```
+pub fn search_datastore_with_index(
+    datastore: &dyn Datastore,
+    index: &AnnIndex,
+    query: &SearchIndex,
+    options: &SearchOptions,
+) -> Result<Vec<SimilarityInformation>, Box<dyn Error>> {
+    let capacity = options.offset.saturating_add(options.limit);
+    let ann_options = options.approximate.clone().unwrap_or_default();
+    let min_similarity = options.min_similarity.unwrap_or(f64::NEG_INFINITY);
+
+    let mut results = Vec::new();
+    for found in index.search(query, capacity, &ann_options) {
+        if found.similarity < min_similarity {
+            continue;
+        }
+        if let Some(entry) = datastore.get(&found.filepath)? {
+            results.push(SimilarityInformation::new(
+                found.similarity,
+                entry,
+                found.cosine_similarity,
+                found.brightness_similarity as f32,
+            ));
+        }
+    }
+    Ok(results.into_iter().skip(options.offset).collect())
+}
+
+/// A result ordered by its rank: a larger value is a worse result. The last field holds what
+/// the comparison found out besides the similarity, e.g. the matching region.
+struct RankedResult<'a, D>(Similarity, &'a SearchIndex, D);
+
+impl<D> RankedResult<'_, D> {
+    fn rank_cmp(&self, other: &Self) -> Ordering {
+        other
+            .0
+            .similarity
+            .total_cmp(&self.0.similarity)
+            .then_with(|| self.1.filepath.cmp(&other.1.filepath))
+    }
+}
+
+impl<D> PartialEq for RankedResult<'_, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank_cmp(other) == Ordering::Equal
+    }
+}
+
+impl<D> Eq for RankedResult<'_, D> {}
+
+impl<D> PartialOrd for RankedResult<'_, D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<D> Ord for RankedResult<'_, D> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank_cmp(other)
+    }
+}
+
+/// Ranks the entries by their similarity to the query and returns the results selected by the
+/// `options`.
+///
+/// Instead of sorting all entries, only the best `offset + limit` results are kept in a bounded
+/// heap. Entries whose similarity can not reach the worst kept result or `min_similarity`, judged
+/// by their average brightness alone, are skipped without comparing their histograms.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::search::{rank_entries, SearchOptions};
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let flower =
+///     generate_suchindex("src/tests/files/pictures_for_testing/flower_purple_1.png".to_string());
+///
+/// let results = rank_entries(&bird, &[flower, bird.clone()], &SearchOptions::top(1));
+/// assert_eq!(results[0].search_index().filepath, bird.filepath);
+/// ```
+pub fn rank_entries(
+    query: &SearchIndex,
+    entries: &[SearchIndex],
+    options: &SearchOptions,
+) -> Vec<SimilarityInformation> {
+    rank_by(
+        entries,
+        options,
+        |entry| similarity_upper_bound(query, entry),
+        |entry| compute_similarity(query, entry),
+    )
+}
+
+/// Same as `rank_entries`, but the similarity weighs the features of the query with the given
+/// `weights`, e.g. `FeatureWeights::compact` to compare only the brightness and the colour moments.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::query_descriptor::FeatureWeights;
+/// use imsearch::search::{rank_entries_weighted, SearchOptions};
+/// use imsearch::search_index::generate_suchindex;
+///
+/// let bird = generate_suchindex("src/tests/files/pictures_for_testing/bird.png".to_string());
+/// let flower =
+///     generate_suchindex("src/tests/files/pictures_for_testing/flower_purple_1.png".to_string());
+///
+/// let weights = FeatureWeights::compact(bird.histogram.len());
+/// let entries = [flower, bird.clone()];
+/// let results = rank_entries_weighted(&bird, &entries, &weights, &SearchOptions::top(1));
+/// assert_eq!(results[0].search_index().filepath, bird.filepath);
+/// ```
+pub fn rank_entries_weighted(
+    query: &SearchIndex,
+    entries: &[SearchIndex],
+    weights: &FeatureWeights,
+    options: &SearchOptions,
+) -> Vec<SimilarityInformation> {
+    let descriptor = QueryDescriptor::from_search_index(query);
+    rank_by(
+        entries,
+        options,
+        |entry| descriptor.similarity_upper_bound(entry, weights),
+        |entry| descriptor.score(entry, weights),
+    )
+}
+
+/// Ranks the entries by the similarity `compare` computes and returns the results selected by
+/// the `options`, see `rank_entries`.
+///
+/// `upper_bound` must never be less than the similarity `compare` returns for the same entry, it
+/// is used to skip entries without comparing them. Only the entries of the returned results are
+/// copied.
+pub(crate) fn rank_by<B, C>(
+    entries: &[SearchIndex],
+    options: &SearchOptions,
+    upper_bound: B,
+    compare: C,
+) -> Vec<SimilarityInformation>
+where
+    B: Fn(&SearchIndex) -> f64,
+    C: Fn(&SearchIndex) -> Similarity,
+{
+    rank_with_details_by(entries, options, upper_bound, |entry| (compare(entry), ()))
+        .into_iter()
+        .map(|(result, ())| result)
+        .collect()
+}
+
+/// Same as `rank_by`, but `compare` also returns details of the comparison that are kept with
+/// each result.
+pub(crate) fn rank_with_details_by<B, C, D>(
+    entries: &[SearchIndex],
+    options: &SearchOptions,
+    upper_bound: B,
+    compare: C,
+) -> Vec<(SimilarityInformation, D)>
+where
+    B: Fn(&SearchIndex) -> f64,
+    C: Fn(&SearchIndex) -> (Similarity, D),
+{
+    let capacity = options.offset.saturating_add(options.limit);
+    if capacity == 0 {
+        return Vec::new();
+    }
+    let min_similarity = options.min_similarity.unwrap_or(f64::NEG_INFINITY);
+
+    // a max-heap of the ranks, so the worst kept result is on top
+    let mut best: BinaryHeap<RankedResult<D>> = BinaryHeap::new();
+    for entry in entries {
+        let upper_bound = upper_bound(entry);
+        if upper_bound < min_similarity {
+            continue;
+        }
+        if best.len() == capacity
+            && best
+                .peek()
+                .is_some_and(|worst| upper_bound < worst.0.similarity)
+        {
+            continue;
+        }
+
+        let (similarity, details) = compare(entry);
+        let result = RankedResult(similarity, entry, details);
+        if result.0.similarity < min_similarity {
+            continue;
+        }
+        if best.len() < capacity {
+            best.push(result);
+        } else if best.peek().is_some_and(|worst| result < *worst) {
+            best.pop();
+            best.push(result);
+        }
+    }
+
+    let mut ranked: Vec<RankedResult<D>> = best.into_vec();
+    ranked.sort();
+    ranked
+        .into_iter()
+        .skip(options.offset)
+        .map(|RankedResult(similarity, entry, details)| {
+            (similarity.with_entry(entry.clone()), details)
+        })
+        .collect()
+}
@@ -1,6 +1,19 @@
-use crate::file_handler::{extract_filename, format_filepath, is_directory, is_file};
-use crate::picture::{AverageBrightness, Picture};
-use crate::{get_datastore_path, get_histogram, read_picture, Histogram, PictureU8};
+use crate::color_moments::{compute_color_moments, ColorMoments};
+use crate::color_type::ColorType;
+use crate::correlogram::{
+    compute_auto_correlogram, AutoCorrelogram, CORRELOGRAM_DISTANCES, CORRELOGRAM_LEVELS,
+};
+use crate::datastore::{modify_datastore, open_datastore, write_datastore_file, Datastore};
+use crate::features::{DescriptorSet, FeatureConfiguration};
+use crate::file_handler::{
+    compute_file_hash, extract_filename, format_filepath, is_directory, is_file,
+};
+use crate::grayscale::{average_luma, LumaFormula};
+use crate::picture_view::AsPictureView;
+use crate::region::{compute_grid_cells, GridCell};
+use crate::shape::{compute_shape_descriptor, ShapeDescriptor};
+use crate::statistics::{compute_statistics, ImageStatistics};
+use crate::{get_datastore_path, get_histogram, try_read_picture, Histogram, PictureU8};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs;
@@ -8,7 +21,7 @@ use std::fs;
 /// Represents a search index containing information about a file.
 ///
 /// The `SearchIndex` struct is used to save information from various functions to the drive.
-/// It includes fields for the filepath, filename, average brightness, and histogram.
+/// It includes fields for the filepath, filename, average brightness, histogram and content hash.
 ///
 /// # Fields
 ///
@@ -16,6 +29,26 @@ use std::fs;
 /// * `filename`: The filename of the indexed file.
 /// * `average_brightness`: The average brightness value of the indexed file.
 /// * `histogram`: The histogram data of the indexed file.
+/// * `content_hash`: The hash of the file's content at indexing time (see `compute_file_hash`).
+///   It is empty if the hash is unknown, e.g. for entries written by older versions.
+/// * `lines`: The height of the picture in pixels.
+/// * `columns`: The width of the picture in pixels.
+/// * `file_size`: The size of the picture file in bytes.
+/// * `tags`: Tags assigned by the user (see `set_tags`). They are kept when the picture is indexed
+///   again.
+/// * `grid_cells`: The descriptors of the cells of a grid over the picture, so regions of other
+///   pictures can be matched anywhere in it (see `search_region_in_cells`).
+/// * `color_type`: The colour model of the picture, which gives the meaning of the histograms.
+/// * `lightness`: The average perceived lightness of the picture (`LumaFormula::Lightness`), `None`
+///   for entries written before it was stored.
+/// * `statistics`: Global statistics of the picture (see `compute_statistics`), `None` for entries
+///   written before they were stored and pictures whose colour model is not known.
+/// * `color_moments`: The compact colour moments of the picture (see `compute_color_moments`),
+///   `None` like the `statistics`.
+/// * `correlogram`: The colour auto-correlogram of the picture at `CORRELOGRAM_DISTANCES` (see
+///   `compute_auto_correlogram`), `None` like the `statistics`.
+/// * `shape`: The edge directions and Hu moments of the picture (see `compute_shape_descriptor`),
+///   `None` like the `statistics`.
 ///
 /// # Examples
 ///
@@ -48,12 +81,36 @@ use std::fs;
 ///     println!("Item: {:?}", item);
 /// }
 /// ```
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct SearchIndex {
     pub filepath: String,
     pub filename: String,
     pub average_brightness: f32,
     pub histogram: Vec<Histogram>,
+    #[serde(default)]
+    pub content_hash: String,
+    #[serde(default)]
+    pub lines: u32,
+    #[serde(default)]
+    pub columns: u32,
+    #[serde(default)]
+    pub file_size: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub grid_cells: Vec<GridCell>,
+    #[serde(default)]
+    pub color_type: ColorType,
+    #[serde(default)]
+    pub lightness: Option<f32>,
+    #[serde(default)]
+    pub statistics: Option<ImageStatistics>,
+    #[serde(default)]
+    pub color_moments: Option<ColorMoments>,
+    #[serde(default)]
+    pub correlogram: Option<AutoCorrelogram>,
+    #[serde(default)]
+    pub shape: Option<ShapeDescriptor>,
 }
 
 impl SearchIndex {
@@ -64,6 +121,8 @@ impl SearchIndex {
     /// The `histogram` argument represents the histogram data of the indexed file.
     ///
     /// The `filename` field is automatically extracted from the `filepath`.
+    /// The `content_hash`, the dimensions, the file size and the grid cells are left empty,
+    /// `generate_suchindex` fills them in.
     ///
     /// # Examples
     ///
@@ -88,6 +147,7 @@ impl SearchIndex {
             filename: extract_filename(filepath),
             average_brightness,
             histogram,
+            ..Default::default()
         }
     }
 }
@@ -116,22 +176,21 @@ impl IntoIterator for SearchIndex {
     }
 }
 
-/// Writes the provided data to a JSON file.
+/// Writes the provided data to the configured datastore.
 ///
 /// This function takes the provided data, which can be either a `Vec<SearchIndex>` or a single
-/// `SearchIndex` item, and appends it to an existing JSON file located at the datastore path.
-/// If the file does not exist, a new file will be created. The data is serialized as JSON using
-/// the `serde_json` crate and written to the file in a pretty-printed format.
+/// `SearchIndex` item, and adds it to the datastore located at the datastore path (see
+/// `open_datastore`). If the file does not exist, a new file will be created.
 ///
 /// # Arguments
 ///
-/// * `data` - The data to be written to the file. It can be either a `Vec<SearchIndex>` or a single
-///            `SearchIndex` item. The data will be appended to the existing content of the file.
+/// * `data` - The data to be written to the datastore. It can be either a `Vec<SearchIndex>` or a
+///   single `SearchIndex` item. Existing entries of the same filepaths are replaced.
 ///
 /// # Errors
 ///
-/// This function returns an error if there is a problem reading the existing data from the file,
-/// serializing the combined data, or writing the data to the file.
+/// This function returns an error if there is a problem opening the datastore or writing the data
+/// to it.
 ///
 /// # Examples
 ///
@@ -140,9 +199,12 @@ impl IntoIterator for SearchIndex {
 /// ```rust
 /// use std::error::Error;
 /// use imsearch::search_index::{SearchIndex, write_data_to_file};
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_write_data_to_file.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
 ///
 /// let search_index = SearchIndex {
-///  /* ... */ filepath: "".to_string(),filename: "".to_string(),average_brightness: 0.0 , histogram: vec![],};
+///  /* ... */ filepath: "".to_string(),filename: "".to_string(),average_brightness: 0.0 , histogram: vec![], ..Default::default()};
 /// if let Err(err) = write_data_to_file(search_index) {
 ///     eprintln!("Error writing data to file: {}", err);
 /// }
@@ -153,6 +215,9 @@ impl IntoIterator for SearchIndex {
 /// ```rust
 /// use std::error::Error;
 /// use imsearch::search_index::{SearchIndex, write_data_to_file};
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_write_data_to_files.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
 ///
 /// let search_indices: Vec<SearchIndex> = vec![/* ... */];
 /// if let Err(err) = write_data_to_file(search_indices) {
@@ -160,32 +225,80 @@ impl IntoIterator for SearchIndex {
 /// }
 /// ```
 pub fn write_data_to_file<T>(data: T) -> Result<(), Box<dyn Error>>
-    where
-        T: IntoIterator<Item = SearchIndex>,
+where
+    T: IntoIterator<Item = SearchIndex>,
 {
-    let datastore_filepath = get_datastore_path()?;
-
-    let mut datastore_data: Vec<SearchIndex> = read_data_from_datastore()?;
-
-    for item in data {
-        datastore_data.push(item);
-    }
+    let mut datastore = open_datastore(&get_datastore_path()?)?;
+    datastore.insert_all(data.into_iter().collect())?;
+    datastore.flush()
+}
 
-    let data_str = serde_json::to_string_pretty(&datastore_data)?;
-    fs::write(datastore_filepath, data_str)?;
+/// Replaces the whole content of the datastore with the provided data.
+///
+/// In contrast to `write_data_to_file`, the existing entries are not kept. This is used by
+/// operations that change or remove existing entries, like `sync_datastore`.
+///
+/// # Arguments
+///
+/// * `data` - The complete new content of the datastore.
+///
+/// # Errors
+///
+/// This function returns an error if the datastore can not be opened or written.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::search_index::{read_data_from_datastore, replace_datastore_data, SearchIndex};
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_replace_datastore_data.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
+///
+/// let data: Vec<SearchIndex> = read_data_from_datastore().unwrap();
+/// replace_datastore_data(&data).unwrap();
+/// ```
+pub fn replace_datastore_data(data: &[SearchIndex]) -> Result<(), Box<dyn Error>> {
+    modify_datastore(|entries| {
+        *entries = data.to_vec();
+        Ok(())
+    })
+}
 
-    Ok(())
+/// Writes the provided data to the JSON file at the given path, replacing its content.
+///
+/// The entries are stored together with the current `SCHEMA_VERSION` and the default
+/// `FeatureConfiguration` (see `encode_datastore_file`), so all their descriptors are kept.
+///
+/// The file is replaced atomically with `write_file_atomically`, so a crash while writing never
+/// leaves a truncated file behind. The previous content is kept as a backup (see `backup_path`),
+/// which `recover_datastore` uses.
+///
+/// This function does not take the `DatastoreLock`, callers that read and write the datastore
+/// should hold it (see `JsonDatastore`).
+///
+/// # Arguments
+///
+/// * `filepath` - The path to the file which is written.
+/// * `data` - The complete new content of the file.
+///
+/// # Errors
+///
+/// This function returns an error if the data can not be serialized or written to the file.
+pub fn write_data_to_filepath(filepath: &str, data: &[SearchIndex]) -> Result<(), Box<dyn Error>> {
+    write_datastore_file(filepath, data, &FeatureConfiguration::default())
 }
 
-/// Reads data from the datastore file and deserializes it into a vector of type T.
+/// Reads all entries of the configured datastore (see `open_datastore`) and converts them into a
+/// vector of type T.
 ///
 /// # Errors
 ///
 /// This function can return an error in the following situations:
 ///
 /// - If retrieving the datastore path using `get_datastore_path` fails.
-/// - If reading the contents of the datastore file using `fs::read_to_string` fails.
-/// - If deserializing the JSON data using `serde_json::from_str` fails.
+/// - If reading the contents of the datastore file fails.
+/// - If the data can not be deserialized. The error is a `DatastoreError::Corrupted` in this
+///   case, use `recover_datastore` to restore the datastore.
 ///
 /// # Arguments
 ///
@@ -193,13 +306,15 @@ pub fn write_data_to_file<T>(data: T) -> Result<(), Box<dyn Error>>
 ///
 /// # Generic Parameters
 ///
-/// - `T`: The type to deserialize the data into. It must implement the `Deserialize` trait from `serde`.
+/// - `T`: The type to deserialize the entries into. It must implement the `Deserialize` trait from
+///   `serde`. The entries are converted through their JSON representation, so `T` may also be a
+///   type with only some of the fields of `SearchIndex`.
 ///
 /// # Returns
 ///
-/// This function returns a `Result` containing the deserialized data as a vector of type T on success,
-/// or a boxed error trait object (`Box<dyn Error>`) on failure. If deserialization fails, an empty vector is returned
-/// and the error is logged to the console without panicking.
+/// This function returns a `Result` containing the data as a vector of type T on success,
+/// or a boxed error trait object (`Box<dyn Error>`) on failure. A datastore file that does not exist
+/// yet or is empty yields an empty vector.
 ///
 /// # Example
 ///
@@ -220,16 +335,13 @@ pub fn read_data_from_datastore<T>() -> Result<Vec<T>, Box<dyn Error>>
 where
     T: for<'de> Deserialize<'de>,
 {
-    let datastore_path = get_datastore_path()?;
+    let entries = read_datastore_entries()?;
+    Ok(serde_json::from_value(serde_json::to_value(entries)?)?)
+}
 
-    let data_str = fs::read_to_string(datastore_path)?;
-    match serde_json::from_str(&data_str) {
-        Ok(data) => Ok(data),
-        Err(err) => {
-            eprintln!("Error: {}", err);
-            Ok(Vec::new())
-        }
-    }
+/// Reads all entries of the configured datastore, see `read_data_from_datastore`.
+fn read_datastore_entries() -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+    open_datastore(&get_datastore_path()?)?.entries()
 }
 /// Reads data from a file and deserializes it into a vector of a given type.
 ///
@@ -288,6 +400,9 @@ where
 /// # use std::error::Error;
 /// # use imsearch::search_index::generate_suchindex_to_file;
 /// # const PICTURE_FILEPATH: &str = "src/tests/files/pictures_for_testing/bird.png";
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_generate_suchindex_to_file.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
 ///
 /// # fn main(){
 ///     generate_suchindex_to_file(PICTURE_FILEPATH.to_string()).unwrap();
@@ -298,7 +413,8 @@ where
 ///
 /// Returns an error if there was any problem reading the picture file or writing the search index to the data file.
 pub fn generate_suchindex_to_file(filepath: String) -> Result<(), Box<dyn Error>> {
-    let search_index = generate_suchindex(filepath);
+    let features = open_datastore(&get_datastore_path()?)?.features();
+    let search_index = try_generate_suchindex(filepath, &features)?;
     if !search_index_exists(&search_index)? {
         write_data_to_file(search_index)?;
     }
@@ -326,7 +442,34 @@ pub fn generate_suchindex_to_file(filepath: String) -> Result<(), Box<dyn Error>
 /// analyse_pictures("/path/to/picture.png").expect("analysing pictures failed");
 /// ```
 pub fn analyse_pictures(path: &str) -> Result<(), Box<dyn Error>> {
-    if is_directory(path) {
+    let mut datastore = open_datastore(&get_datastore_path()?)?;
+    analyse_pictures_into(datastore.as_mut(), path)?;
+    datastore.flush()
+}
+
+/// Same as `analyse_pictures`, but adds the entries to the given datastore instead of the
+/// configured one. The datastore is not flushed.
+///
+/// # Returns
+///
+/// The number of pictures that were added.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::datastore::Datastore;
+/// use imsearch::memory_datastore::MemoryDatastore;
+/// use imsearch::search_index::analyse_pictures_into;
+///
+/// let mut datastore = MemoryDatastore::new();
+/// analyse_pictures_into(&mut datastore, "src/tests/files/pictures_for_testing/bird.png").unwrap();
+/// assert_eq!(datastore.len().unwrap(), 1);
+/// ```
+pub fn analyse_pictures_into(
+    datastore: &mut dyn Datastore,
+    path: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let filepaths = if is_directory(path) {
         let entries = match fs::read_dir(path) {
             Ok(entries) => entries,
             Err(err) => {
@@ -334,20 +477,27 @@ pub fn analyse_pictures(path: &str) -> Result<(), Box<dyn Error>> {
                 return Err(Box::new(err));
             }
         };
-        for entry in entries.filter_map(|entry| entry.ok()) {
-            let entry_path = entry.path();
-            if let Some(file_path) = entry_path.to_str() {
-                if is_file(file_path) && !search_index_path_exists(file_path)? {
-                    generate_suchindex_to_file(format_filepath(file_path)).unwrap();
-                }
-            }
-        }
-    } else if is_file(path) && !search_index_path_exists(path)? {
-        generate_suchindex_to_file(path.to_string()).unwrap();
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.path().to_str().map(format_filepath))
+            .filter(|filepath| is_file(filepath))
+            .collect()
+    } else if is_file(path) {
+        vec![format_filepath(path)]
     } else {
         eprintln!("Invalid path: {}", path);
+        Vec::new()
+    };
+
+    let features = datastore.features();
+    let mut added_count = 0;
+    for filepath in filepaths {
+        if datastore.get(&filepath)?.is_none() {
+            datastore.insert(try_generate_suchindex(filepath, &features)?)?;
+            added_count += 1;
+        }
     }
-    Ok(())
+    Ok(added_count)
 }
 
 /// Checks if a given `SearchIndex` exists in the datastore.
@@ -362,6 +512,7 @@ pub fn analyse_pictures(path: &str) -> Result<(), Box<dyn Error>> {
 /// If the search operation is successful, the `Result` will contain a boolean value:
 /// - `Ok(true)` if the `search_index_element` exists in the datastore.
 /// - `Ok(false)` if the `search_index_element` does not exist in the datastore.
+///
 /// If any error occurs during the data retrieval or search operation, an `Err` variant will be returned
 /// containing a `Box<dyn Error>` trait object.
 ///
@@ -390,17 +541,15 @@ pub fn analyse_pictures(path: &str) -> Result<(), Box<dyn Error>> {
 /// }
 ///
 /// // Example usage
-/// fn main() {
-///     let filepath = "/path/to/file.png".to_string();
-///     let average_brightness = 6.9;
-///     let histogram = vec![/* Histogram data */];
-///     let search_index = SearchIndex::new(filepath, average_brightness, histogram);
-///
-///     match search_index_exists(search_index) {
-///         Ok(true) => println!("Search index exists in the datastore."),
-///         Ok(false) => println!("Search index does not exist in the datastore."),
-///         Err(err) => eprintln!("Error occurred: {}", err),
-///     }
+/// let filepath = "/path/to/file.png".to_string();
+/// let average_brightness = 6.9;
+/// let histogram = vec![/* Histogram data */];
+/// let search_index = SearchIndex::new(filepath, average_brightness, histogram);
+///
+/// match search_index_exists(search_index) {
+///     Ok(true) => println!("Search index exists in the datastore."),
+///     Ok(false) => println!("Search index does not exist in the datastore."),
+///     Err(err) => eprintln!("Error occurred: {}", err),
 /// }
 /// ```
 ///
@@ -409,38 +558,98 @@ pub fn analyse_pictures(path: &str) -> Result<(), Box<dyn Error>> {
 /// the existence of the search index. The function can be used by passing a `SearchIndex` instance
 /// to check its existence in the datastore.
 pub fn search_index_exists(search_index_element: &SearchIndex) -> Result<bool, Box<dyn Error>> {
-    let stored_data: Vec<SearchIndex> = read_data_from_datastore()?;
+    let stored_data = read_datastore_entries()?;
 
     // Check if the search_index_element is present in the stored_data
-    let found = stored_data
-        .iter()
-        .any(|stored_element| *stored_element == *search_index_element);
+    let found = stored_data.contains(search_index_element);
 
     Ok(found)
 }
 
+/// Generates a `SearchIndex` with all descriptors for the picture at the given filepath.
+///
+/// # Panics
+///
+/// Panics if the picture can not be read. Use `try_generate_suchindex` to handle such errors.
 pub fn generate_suchindex(filepath: String) -> SearchIndex {
-    let pic_u8: PictureU8 = read_picture(&filepath);
-    let histograms = get_histogram(&pic_u8);
+    try_generate_suchindex(filepath, &FeatureConfiguration::default()).unwrap()
+}
+
+/// Generates a `SearchIndex` for the picture at the given filepath, including its content hash.
+///
+/// Only the descriptors that datastores with the given feature configuration keep are computed (see
+/// `FeatureConfiguration::keep_stored_descriptors`), so entries and queries of compact datastores
+/// skip the histograms and the other large descriptors.
+///
+/// # Errors
+///
+/// Returns an error if the picture can not be read or decoded.
+///
+/// # Examples
+///
+/// ```
+/// # use imsearch::features::FeatureConfiguration;
+/// # use imsearch::search_index::try_generate_suchindex;
+/// let filepath = "src/tests/files/pictures_for_testing/bird.png".to_string();
+/// let search_index = try_generate_suchindex(filepath.clone(), &FeatureConfiguration::default())
+///     .unwrap();
+/// assert_eq!(search_index.filename, "bird");
+/// assert!(!search_index.content_hash.is_empty());
+/// assert!(search_index.lines > 0 && search_index.columns > 0);
+///
+/// let compact = try_generate_suchindex(filepath, &FeatureConfiguration::compact()).unwrap();
+/// assert!(compact.histogram.is_empty() && compact.color_moments.is_some());
+/// ```
+pub fn try_generate_suchindex(
+    filepath: String,
+    features: &FeatureConfiguration,
+) -> Result<SearchIndex, Box<dyn Error>> {
+    let pic_u8: PictureU8 = try_read_picture(&filepath)?;
+    let full = features.descriptors == DescriptorSet::Full;
+    let histograms = if full {
+        get_histogram(&pic_u8)
+    } else {
+        Vec::new()
+    };
     let average_brightness = determine_avg_brightness(&pic_u8);
+    let content_hash = compute_file_hash(&filepath)?;
 
-    SearchIndex::new(filepath, average_brightness, histograms)
-}
+    let file_size = fs::metadata(&filepath)?.len();
 
-pub fn determine_avg_brightness(pic_u8: &dyn Picture) -> f32 {
-    let pic_f32 = pic_u8.to_picture_f32();
-    let grayray = pic_f32.gray_intensity_array();
-    pic_f32.average_brightness(&grayray)
+    let mut search_index = SearchIndex::new(filepath, average_brightness, histograms);
+    search_index.content_hash = content_hash;
+    search_index.lines = pic_u8.lines;
+    search_index.columns = pic_u8.columns;
+    search_index.file_size = file_size;
+    search_index.color_type = pic_u8.color_type;
+    search_index.lightness = Some(average_luma(&pic_u8, LumaFormula::Lightness));
+    search_index.color_moments = compute_color_moments(&pic_u8).ok();
+    if full {
+        search_index.grid_cells = compute_grid_cells(&pic_u8);
+        search_index.statistics = compute_statistics(&pic_u8).ok();
+        search_index.correlogram =
+            compute_auto_correlogram(&pic_u8, CORRELOGRAM_LEVELS, &CORRELOGRAM_DISTANCES).ok();
+        search_index.shape = compute_shape_descriptor(&pic_u8).ok();
+    }
+    Ok(search_index)
 }
-pub fn search_index_path_exists(path: &str) -> Result<bool, Box<dyn Error>> {
 
-    let item = generate_suchindex(path.to_string());
+/// Calculates the average brightness of a picture or view, like `AverageBrightness` but without
+/// collecting the gray intensity of every pixel first. It is NaN if the colour model of the picture
+/// is not known (`ColorType::Channels`). See `average_luma` for other gray formulas.
+pub fn determine_avg_brightness<P: AsPictureView>(pic: &P) -> f32 {
+    average_luma(pic, LumaFormula::Legacy)
+}
 
-    if search_index_exists(&item)?{return Ok(true)}
-    // // Check if the search_index_element is present in the stored_data
-    // let found = stored_data
-    //     .iter()
-    //     .any(|stored_element| *stored_element.filepath == path.to_string());
+/// Checks whether a `SearchIndex` for the given filepath is already stored in the datastore.
+///
+/// Only the filepath is compared, so a file that changed since it was indexed still counts as
+/// existing. Use `sync_datastore` to bring such entries up to date.
+pub fn search_index_path_exists(path: &str) -> Result<bool, Box<dyn Error>> {
+    let path = format_filepath(path);
+    let stored_data = read_datastore_entries()?;
 
-    Ok(false)
-}
\ No newline at end of file
+    Ok(stored_data
+        .iter()
+        .any(|stored_element| stored_element.filepath == path))
+}
@@ -0,0 +1,261 @@
+use crate::edges::CANNY_THRESHOLDS;
+use crate::picture::PictureU8;
+use crate::picture_view::AsPictureView;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::f32::consts::PI;
+
+/// The number of bins of the edge direction histogram. Edges have no front and back, so the bins
+/// split the angles from 0 to 180 degrees.
+pub const EDGE_DIRECTION_BINS: usize = 8;
+
+/// The number of normalized central moments multiplied in each of the Hu moments.
+const HU_MOMENT_DEGREES: [f64; 7] = [1.0, 2.0, 2.0, 2.0, 4.0, 3.0, 4.0];
+
+/// Describes the shapes in a picture by its edges (see `PictureView::canny_edges`), independent of
+/// their colours.
+///
+/// # Fields
+///
+/// * `edge_directions`: The fraction of the edge pixels in each of `EDGE_DIRECTION_BINS`
+///   directions. They are all 0 if the picture has no edges.
+/// * `edge_density`: The fraction of the pixels that are edges.
+/// * `hu_moments`: The seven moments of Hu of the edge pixels (see `outline_hu_moments`), which do
+///   not change when the shape is moved, scaled or rotated.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ShapeDescriptor {
+    pub edge_directions: Vec<f32>,
+    pub edge_density: f32,
+    pub hu_moments: [f32; 7],
+}
+
+impl ShapeDescriptor {
+    /// Returns the edge directions, the edge density and the Hu moments. The moments are
+    /// products of up to four normalized central moments, so they are brought to the same scale
+    /// with the matching root (keeping their sign).
+    pub(crate) fn feature_vector(&self) -> Vec<f64> {
+        let mut features: Vec<f64> = self
+            .edge_directions
+            .iter()
+            .map(|fraction| f64::from(*fraction))
+            .collect();
+        features.push(f64::from(self.edge_density));
+        features.extend(
+            self.hu_moments
+                .iter()
+                .zip(HU_MOMENT_DEGREES)
+                .map(|(moment, degree)| {
+                    let moment = f64::from(*moment);
+                    moment.signum() * moment.abs().powf(1.0 / degree)
+                }),
+        );
+        features
+    }
+}
+
+/// Computes the histogram of the directions of the edges in an edge map, see `ShapeDescriptor`.
+///
+/// `edges` is an edge map (non-zero pixels are edges) and `direction` the gradient directions of
+/// its pixels (see `Gradients`).
+fn edge_direction_histogram(edges: &PictureU8, direction: &[f32]) -> Vec<f32> {
+    let mut histogram = vec![0.0; EDGE_DIRECTION_BINS];
+    let mut edge_count = 0;
+    for (edge, direction) in edges.data.iter().zip(direction) {
+        if *edge == 0 {
+            continue;
+        }
+        let bin = (direction.rem_euclid(PI) / PI * EDGE_DIRECTION_BINS as f32) as usize;
+        histogram[bin.min(EDGE_DIRECTION_BINS - 1)] += 1.0;
+        edge_count += 1;
+    }
+    if edge_count > 0 {
+        for fraction in &mut histogram {
+            *fraction /= edge_count as f32;
+        }
+    }
+    histogram
+}
+
+/// Computes the seven moments of Hu of the non-zero pixels of a picture, all 0 if there are none.
+///
+/// The pixels are treated as the area of a shape. For the outline of a shape, like an edge map,
+/// use `outline_hu_moments`.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::picture::PictureU8;
+/// use imsearch::shape::hu_moments;
+///
+/// // a horizontal and a vertical bar of the same size
+/// let horizontal = [vec![0; 10], vec![255; 5], vec![0; 10]].concat();
+/// let horizontal = PictureU8::new(5, 5, ColorType::Gray, horizontal).unwrap();
+/// let vertical = PictureU8::new(5, 5, ColorType::Gray, [0, 0, 255, 0, 0].repeat(5)).unwrap();
+///
+/// let (horizontal, vertical) = (hu_moments(&horizontal), hu_moments(&vertical));
+/// assert!(horizontal[0] > 0.0);
+/// for (horizontal, vertical) in horizontal.iter().zip(vertical) {
+///     assert!((horizontal - vertical).abs() < 1e-12);
+/// }
+/// ```
+pub fn hu_moments(shape: &PictureU8) -> [f64; 7] {
+    compute_hu_moments(shape, 2.0)
+}
+
+/// Computes the seven moments of Hu of the non-zero pixels of a picture like `hu_moments`, but
+/// treats them as the outline of a shape: the central moments are normalized by the length of
+/// the outline instead of the area, so they do not change when the outline is scaled either.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::picture::PictureU8;
+/// use imsearch::shape::outline_hu_moments;
+///
+/// /// The outline of a square from `from` to `to` (exclusive) in a picture of `size` x `size`.
+/// fn square(size: usize, from: usize, to: usize) -> PictureU8 {
+///     let mut data = vec![0; size * size];
+///     for i in from..to {
+///         for (x, y) in [(i, from), (i, to - 1), (from, i), (to - 1, i)] {
+///             data[y * size + x] = 255;
+///         }
+///     }
+///     PictureU8::new(size as u32, size as u32, ColorType::Gray, data).unwrap()
+/// }
+///
+/// let small = outline_hu_moments(&square(32, 4, 12));
+/// let large = outline_hu_moments(&square(32, 4, 28));
+/// assert!((small[0] - large[0]).abs() / large[0] < 0.1);
+/// ```
+pub fn outline_hu_moments(shape: &PictureU8) -> [f64; 7] {
+    compute_hu_moments(shape, 1.0)
+}
+
+/// Computes the moments of Hu with central moments of order `(p, q)` normalized by the count of
+/// the pixels to the power of `(p + q) / dimension + 1`.
+fn compute_hu_moments(shape: &PictureU8, dimension: f64) -> [f64; 7] {
+    let columns = shape.columns as usize;
+    let points: Vec<(f64, f64)> = shape
+        .pixels()
+        .enumerate()
+        .filter(|(_, pixel)| pixel.iter().any(|sample| *sample != 0))
+        .map(|(index, _)| ((index % columns) as f64, (index / columns) as f64))
+        .collect();
+    if points.is_empty() {
+        return [0.0; 7];
+    }
+    let count = points.len() as f64;
+    let center_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let center_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+    // the normalized central moment of order (p, q)
+    let eta = |p: i32, q: i32| {
+        let central: f64 = points
+            .iter()
+            .map(|(x, y)| (x - center_x).powi(p) * (y - center_y).powi(q))
+            .sum();
+        central / count.powf(1.0 + f64::from(p + q) / dimension)
+    };
+    let (n20, n02, n11) = (eta(2, 0), eta(0, 2), eta(1, 1));
+    let (n30, n03, n21, n12) = (eta(3, 0), eta(0, 3), eta(2, 1), eta(1, 2));
+
+    let (a, b) = (n30 + n12, n21 + n03);
+    [
+        n20 + n02,
+        (n20 - n02).powi(2) + 4.0 * n11 * n11,
+        (n30 - 3.0 * n12).powi(2) + (3.0 * n21 - n03).powi(2),
+        a * a + b * b,
+        (n30 - 3.0 * n12) * a * (a * a - 3.0 * b * b)
+            + (3.0 * n21 - n03) * b * (3.0 * a * a - b * b),
+        (n20 - n02) * (a * a - b * b) + 4.0 * n11 * a * b,
+        (3.0 * n21 - n03) * a * (a * a - 3.0 * b * b)
+            - (n30 - 3.0 * n12) * b * (3.0 * a * a - b * b),
+    ]
+}
+
+/// Computes the shape descriptor of a picture or view from its Canny edges with the
+/// `CANNY_THRESHOLDS`.
+///
+/// # Errors
+///
+/// Returns an error if the picture has no pixels or its colour model is not known
+/// (`ColorType::Channels`).
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::read_picture;
+/// use imsearch::shape::{compute_shape_descriptor, EDGE_DIRECTION_BINS};
+///
+/// let bird = read_picture("src/tests/files/pictures_for_testing/bird.png");
+/// let shape = compute_shape_descriptor(&bird).unwrap();
+///
+/// assert_eq!(shape.edge_directions.len(), EDGE_DIRECTION_BINS);
+/// assert!(shape.edge_density > 0.0 && shape.edge_density < 1.0);
+/// ```
+pub fn compute_shape_descriptor<P: AsPictureView>(
+    pic: &P,
+) -> Result<ShapeDescriptor, Box<dyn Error>> {
+    let view = pic.as_view();
+    let pixel_count = view.columns() as usize * view.lines() as usize;
+    if pixel_count == 0 {
+        return Err("Can not compute the shape of a picture without pixels".into());
+    }
+    let (low, high) = CANNY_THRESHOLDS;
+    let (edges, gradients) = view.canny(low, high)?;
+    let edge_count = edges.data.iter().filter(|edge| **edge != 0).count();
+    Ok(ShapeDescriptor {
+        edge_directions: edge_direction_histogram(&edges, &gradients.direction),
+        edge_density: edge_count as f32 / pixel_count as f32,
+        hu_moments: outline_hu_moments(&edges).map(|moment| moment as f32),
+    })
+}
+
+/// Returns the similarity of two shape descriptors, see `compare_shape_features`.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::read_picture;
+/// use imsearch::shape::{compare_shapes, compute_shape_descriptor};
+///
+/// let bird = compute_shape_descriptor(&read_picture("src/tests/files/pictures_for_testing/bird.png"))
+///     .unwrap();
+/// assert_eq!(compare_shapes(&bird, &bird), 1.0);
+/// ```
+pub fn compare_shapes(a: &ShapeDescriptor, b: &ShapeDescriptor) -> f64 {
+    compare_shape_features(&a.feature_vector(), &b.feature_vector()).unwrap_or(0.0)
+}
+
+/// Compares the feature vectors of two shape descriptors, `None` if their lengths differ.
+///
+/// The similarity weighs the edge directions (`1 - ` half their absolute difference) and the Hu
+/// moments (`1 - ` their absolute difference relative to their sum) twice as much as the edge
+/// density (`1 - ` its difference).
+pub(crate) fn compare_shape_features(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() || a.len() < 8 {
+        return None;
+    }
+    let directions = a.len() - 8;
+    let difference = |range: std::ops::Range<usize>| {
+        a[range.clone()]
+            .iter()
+            .zip(&b[range])
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f64>()
+    };
+    let direction_similarity = 1.0 - difference(0..directions) / 2.0;
+    let density_similarity = 1.0 - difference(directions..directions + 1);
+    let moment_sum: f64 = a[directions + 1..]
+        .iter()
+        .chain(&b[directions + 1..])
+        .map(|moment| moment.abs())
+        .sum();
+    let moment_similarity = if moment_sum == 0.0 {
+        1.0
+    } else {
+        1.0 - difference(directions + 1..a.len()) / moment_sum
+    };
+    Some((2.0 * direction_similarity + density_similarity + 2.0 * moment_similarity) / 5.0)
+}
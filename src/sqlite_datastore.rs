@@ -0,0 +1,448 @@
+use crate::datastore::{
+    needs_migration, read_stored_datastore_file, Datastore, DatastoreError, DatastoreLock,
+    Modification, LOCK_TIMEOUT,
+};
+use crate::features::{DescriptorSet, FeatureConfiguration};
+use crate::migration::{migrate_entries, MigrationSummary, SCHEMA_VERSION};
+use crate::search_index::{write_data_to_filepath, SearchIndex};
+use rusqlite::{params, Connection, OptionalExtension, Params, Transaction, TransactionBehavior};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+
+// Every entry is stored as JSON in the `entry` column, so fields added to `SearchIndex` later are
+// kept without changing the table. The columns next to it repeat the fields that are filtered by
+// and are indexed. The schema version is stored as `PRAGMA user_version`, the feature
+// configuration as JSON in the metadata table.
+const CREATE_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS search_index (
+    filepath TEXT PRIMARY KEY NOT NULL,
+    filename TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    average_brightness REAL NOT NULL,
+    entry TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS search_index_content_hash ON search_index (content_hash);
+CREATE INDEX IF NOT EXISTS search_index_average_brightness ON search_index (average_brightness);
+CREATE TABLE IF NOT EXISTS metadata (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);";
+
+const INSERT_ENTRY: &str = "INSERT OR REPLACE INTO search_index
+    (filepath, filename, content_hash, average_brightness, entry)
+    VALUES (?1, ?2, ?3, ?4, ?5)";
+
+/// The magic string at the start of every SQLite database file.
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+/// File extensions of datastores that are created as SQLite databases.
+pub const SQLITE_EXTENSIONS: [&str; 3] = [".sqlite", ".sqlite3", ".db"];
+
+/// A datastore in an embedded SQLite database.
+///
+/// The entries are stored in the table `search_index` with the indexed columns `filepath`
+/// (primary key), `content_hash` and `average_brightness`, so they can be filtered with SQL (see
+/// `select_where`) before their similarity is computed.
+///
+/// Several processes can use the same database at the same time: it is opened in write-ahead log
+/// mode and waits up to `LOCK_TIMEOUT` for other writers. Every change is committed immediately.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::datastore::Datastore;
+/// use imsearch::search_index::SearchIndex;
+/// use imsearch::sqlite_datastore::SqliteDatastore;
+///
+/// let mut datastore = SqliteDatastore::open_in_memory().unwrap();
+/// datastore
+///     .insert(SearchIndex::new("pictures/bird.png".to_string(), 0.4, vec![]))
+///     .unwrap();
+/// datastore
+///     .insert(SearchIndex::new("pictures/night.png".to_string(), 0.1, vec![]))
+///     .unwrap();
+///
+/// let dark_pictures = datastore
+///     .select_where("average_brightness < ?1", [0.2])
+///     .unwrap();
+/// assert_eq!(dark_pictures.len(), 1);
+/// assert_eq!(dark_pictures[0].filename, "night");
+/// ```
+#[derive(Debug)]
+pub struct SqliteDatastore {
+    path: String,
+    connection: Connection,
+}
+
+impl SqliteDatastore {
+    /// Opens the SQLite datastore at the given path and creates it if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DatastoreError::NeedsMigration` if the datastore was written by an older version
+    /// of this library (see `migrate_sqlite_datastore`), `DatastoreError::UnsupportedVersion` if
+    /// it was written by a newer version, and an error if the database can not be opened.
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::open_with(path, false)?.0)
+    }
+
+    /// Opens a new, empty datastore that only lives in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can not be created.
+    pub fn open_in_memory() -> Result<Self, Box<dyn Error>> {
+        let mut datastore = SqliteDatastore {
+            path: ":memory:".to_string(),
+            connection: Connection::open_in_memory()?,
+        };
+        datastore.initialize(false)?;
+        Ok(datastore)
+    }
+
+    /// Opens the datastore and migrates it if `migrate` is set, see `initialize`.
+    fn open_with(
+        path: &str,
+        migrate: bool,
+    ) -> Result<(Self, Option<MigrationSummary>), Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(LOCK_TIMEOUT)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        let mut datastore = SqliteDatastore {
+            path: path.to_string(),
+            connection,
+        };
+        let summary = datastore.initialize(migrate)?;
+        Ok((datastore, summary))
+    }
+
+    /// Creates the tables of a new datastore. An outdated datastore is migrated if `migrate` is
+    /// set, and returns `DatastoreError::NeedsMigration` otherwise.
+    fn initialize(&mut self, migrate: bool) -> Result<Option<MigrationSummary>, Box<dyn Error>> {
+        let path = self.path.clone();
+        // the immediate transaction keeps other processes from initializing at the same time
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        transaction.execute_batch(CREATE_TABLES)?;
+
+        let schema_version: u32 =
+            transaction.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let features: Option<String> = transaction
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'features'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        if schema_version > SCHEMA_VERSION {
+            return Err(Box::new(DatastoreError::UnsupportedVersion {
+                path,
+                found: schema_version,
+                supported: SCHEMA_VERSION,
+            }));
+        }
+
+        let current_features = FeatureConfiguration::default();
+        let summary = match (schema_version, features) {
+            // a new database
+            (0, None) => None,
+            (schema_version, Some(features)) => {
+                let features: FeatureConfiguration = serde_json::from_str(&features)?;
+                if schema_version == SCHEMA_VERSION && features == current_features {
+                    return Ok(None);
+                }
+                if !migrate {
+                    return Err(needs_migration(&path));
+                }
+                Some(migrate_rows(&transaction, schema_version, &features)?)
+            }
+            (_, None) => {
+                return Err(Box::new(DatastoreError::Corrupted {
+                    path,
+                    reason: "the feature configuration is missing".to_string(),
+                }))
+            }
+        };
+
+        transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('features', ?1)",
+            [serde_json::to_string(&current_features)?],
+        )?;
+        transaction.commit()?;
+        Ok(summary)
+    }
+
+    /// Returns the entries that match an SQL condition on the columns of the `search_index` table
+    /// (`filepath`, `filename`, `content_hash` and `average_brightness`).
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The SQL expression used as `WHERE` clause. Values should be passed as
+    ///   parameters (`?1`, `?2`, ...) and never be formatted into the condition.
+    /// * `params` - The values of the parameters of the condition.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the condition is not valid SQL or the entries can not be read.
+    pub fn select_where<P: Params>(
+        &self,
+        condition: &str,
+        params: P,
+    ) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        let query = format!("SELECT entry FROM search_index WHERE {condition} ORDER BY rowid");
+        self.query_entries(&query, params)
+    }
+
+    /// Returns all entries of pictures with the given content hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entries can not be read.
+    pub fn find_by_content_hash(
+        &self,
+        content_hash: &str,
+    ) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        self.select_where("content_hash = ?1", [content_hash])
+    }
+
+    fn query_entries<P: Params>(
+        &self,
+        query: &str,
+        params: P,
+    ) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        query_rows(&self.connection, query, params)
+    }
+}
+
+impl Datastore for SqliteDatastore {
+    fn insert(&mut self, entry: SearchIndex) -> Result<(), Box<dyn Error>> {
+        insert_row(&self.connection, &entry)
+    }
+
+    fn get(&self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        Ok(self
+            .query_entries(
+                "SELECT entry FROM search_index WHERE filepath = ?1",
+                [filepath],
+            )?
+            .pop())
+    }
+
+    fn remove(&mut self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        let entry: Option<String> = self
+            .connection
+            .query_row(
+                "DELETE FROM search_index WHERE filepath = ?1 RETURNING entry",
+                [filepath],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match entry {
+            Some(entry) => Ok(Some(serde_json::from_str(&entry)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn entries(&self) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        self.query_entries("SELECT entry FROM search_index ORDER BY rowid", [])
+    }
+
+    /// Uses the index on the average brightness instead of reading every entry.
+    fn entries_in_brightness_range(
+        &self,
+        min: f32,
+        max: f32,
+    ) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        self.select_where(
+            "average_brightness BETWEEN ?1 AND ?2",
+            [f64::from(min), f64::from(max)],
+        )
+    }
+
+    /// Every change is committed immediately, so there is nothing left to write.
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Applies the modification in a single transaction. Only the rows of removed and changed
+    /// entries are written.
+    fn modify(&mut self, modification: &mut Modification) -> Result<(), Box<dyn Error>> {
+        // the immediate transaction keeps other processes from writing in the meantime
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let stored_entries = query_rows(
+            &transaction,
+            "SELECT entry FROM search_index ORDER BY rowid",
+            [],
+        )?;
+        let mut entries = stored_entries.clone();
+        modification(&mut entries)?;
+
+        let kept_filepaths: HashSet<&str> = entries
+            .iter()
+            .map(|entry| entry.filepath.as_str())
+            .collect();
+        for stored_entry in &stored_entries {
+            if !kept_filepaths.contains(stored_entry.filepath.as_str()) {
+                transaction.execute(
+                    "DELETE FROM search_index WHERE filepath = ?1",
+                    [&stored_entry.filepath],
+                )?;
+            }
+        }
+        let stored_by_filepath: HashMap<&str, &SearchIndex> = stored_entries
+            .iter()
+            .map(|entry| (entry.filepath.as_str(), entry))
+            .collect();
+        for entry in &entries {
+            if stored_by_filepath.get(entry.filepath.as_str()) != Some(&entry) {
+                insert_row(&transaction, entry)?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Stores all given entries in a single transaction.
+    fn insert_all(&mut self, entries: Vec<SearchIndex>) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.transaction()?;
+        for entry in &entries {
+            insert_row(&transaction, entry)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn len(&self) -> Result<usize, Box<dyn Error>> {
+        let count: i64 =
+            self.connection
+                .query_row("SELECT COUNT(*) FROM search_index", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+}
+
+fn query_rows<P: Params>(
+    connection: &Connection,
+    query: &str,
+    params: P,
+) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+    let mut statement = connection.prepare(query)?;
+    let rows = statement.query_map(params, |row| row.get::<_, String>(0))?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        entries.push(serde_json::from_str(&row?)?);
+    }
+    Ok(entries)
+}
+
+fn insert_row(connection: &Connection, entry: &SearchIndex) -> Result<(), Box<dyn Error>> {
+    connection.execute(
+        INSERT_ENTRY,
+        params![
+            entry.filepath,
+            entry.filename,
+            entry.content_hash,
+            entry.average_brightness,
+            serde_json::to_string(entry)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// Migrates all rows of the `search_index` table, see `migrate_entries`.
+fn migrate_rows(
+    transaction: &Transaction,
+    schema_version: u32,
+    features: &FeatureConfiguration,
+) -> Result<MigrationSummary, Box<dyn Error>> {
+    let mut stored_entries: Vec<Value> = Vec::new();
+    {
+        let mut statement = transaction.prepare("SELECT entry FROM search_index ORDER BY rowid")?;
+        for row in statement.query_map([], |row| row.get::<_, String>(0))? {
+            stored_entries.push(serde_json::from_str(&row?)?);
+        }
+    }
+
+    let (entries, summary) = migrate_entries(stored_entries, schema_version, features)?;
+    let entries: Vec<SearchIndex> = serde_json::from_value(Value::Array(entries))?;
+
+    transaction.execute("DELETE FROM search_index", [])?;
+    for entry in &entries {
+        insert_row(transaction, entry)?;
+    }
+    Ok(summary)
+}
+
+/// Returns `true` if the file at the given path is an SQLite database.
+pub fn is_sqlite_datastore(path: &str) -> bool {
+    let mut magic = [0; 16];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| magic == *SQLITE_MAGIC)
+        .unwrap_or(false)
+}
+
+/// Returns `true` if the path has one of the `SQLITE_EXTENSIONS`.
+pub fn has_sqlite_extension(path: &str) -> bool {
+    SQLITE_EXTENSIONS
+        .iter()
+        .any(|extension| path.ends_with(extension))
+}
+
+/// Migrates the SQLite datastore at the given path to the current `SCHEMA_VERSION` and
+/// `FeatureConfiguration`.
+///
+/// # Returns
+///
+/// What was migrated, or `None` if the datastore was already up to date.
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be opened or migrated.
+pub fn migrate_sqlite_datastore(path: &str) -> Result<Option<MigrationSummary>, Box<dyn Error>> {
+    Ok(SqliteDatastore::open_with(path, true)?.1)
+}
+
+/// Copies all entries of a JSON datastore into an SQLite datastore.
+///
+/// # Returns
+///
+/// The number of converted entries.
+///
+/// # Errors
+///
+/// Returns an error if the JSON datastore can not be read or is compact, because SQLite
+/// datastores always keep all descriptors, or if the SQLite datastore can not be written.
+pub fn convert_json_to_sqlite(json_path: &str, sqlite_path: &str) -> Result<usize, Box<dyn Error>> {
+    let (features, entries): (_, Vec<SearchIndex>) = read_stored_datastore_file(json_path)?;
+    if features.descriptors != DescriptorSet::Full {
+        return Err(format!("{json_path} is compact, it can not be converted to SQLite.").into());
+    }
+    let entry_count = entries.len();
+    SqliteDatastore::open(sqlite_path)?.insert_all(entries)?;
+    Ok(entry_count)
+}
+
+/// Writes all entries of an SQLite datastore to a JSON datastore.
+///
+/// # Returns
+///
+/// The number of converted entries.
+///
+/// # Errors
+///
+/// Returns an error if the SQLite datastore can not be read or the JSON datastore can not be
+/// written.
+pub fn convert_sqlite_to_json(sqlite_path: &str, json_path: &str) -> Result<usize, Box<dyn Error>> {
+    let entries = SqliteDatastore::open(sqlite_path)?.entries()?;
+    let _lock = DatastoreLock::acquire(json_path)?;
+    write_data_to_filepath(json_path, &entries)?;
+    Ok(entries.len())
+}
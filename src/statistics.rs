@@ -0,0 +1,330 @@
+use crate::color_type::ColorType;
+use crate::grayscale::LumaFormula;
+use crate::picture::Sample;
+use crate::picture_view::AsPictureView;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// The percentiles stored for every channel, as fractions of the pixels.
+pub const PERCENTILES: [f32; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// The number of levels values are sorted into for the percentiles and the entropy.
+const LEVELS: usize = 256;
+
+/// The statistics of the normalized values of one channel of a picture.
+///
+/// # Fields
+///
+/// * `percentiles`: The values below which the fractions of `PERCENTILES` of the pixels lie,
+///   rounded to 1/255.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct ChannelStatistics {
+    pub mean: f32,
+    pub standard_deviation: f32,
+    pub skewness: f32,
+    pub min: f32,
+    pub max: f32,
+    pub percentiles: Vec<f32>,
+}
+
+impl ChannelStatistics {
+    /// Returns the value below which half of the pixels lie.
+    pub fn median(&self) -> f32 {
+        self.percentiles[2]
+    }
+}
+
+/// Global statistics of a picture, see `compute_statistics`.
+///
+/// # Fields
+///
+/// * `channels`: The statistics of every channel, in the order of the picture's channels.
+/// * `rms_contrast`: The standard deviation of the gray values.
+/// * `michelson_contrast`: `(max - min) / (max + min)` of the gray values.
+/// * `colourfulness`: The colourfulness of Hasler and Süsstrunk on normalized values, 0 for gray
+///   pictures. Very colourful pictures reach about 0.5.
+/// * `entropy`: The Shannon entropy of the gray values in bits, from 0 (one gray value) to 8.
+///
+/// The gray values are computed with `LumaFormula::Legacy`, like the average brightness.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct ImageStatistics {
+    pub channels: Vec<ChannelStatistics>,
+    pub rms_contrast: f32,
+    pub michelson_contrast: f32,
+    pub colourfulness: f32,
+    pub entropy: f32,
+}
+
+/// A single value of the `ImageStatistics`, e.g. to filter entries by it (see
+/// `Query::statistic`). Per-channel statistics name the channel by its index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Statistic {
+    Mean(usize),
+    StandardDeviation(usize),
+    Skewness(usize),
+    Min(usize),
+    Max(usize),
+    Median(usize),
+    RmsContrast,
+    MichelsonContrast,
+    Colourfulness,
+    Entropy,
+}
+
+impl Display for Statistic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Statistic::Mean(channel) => write!(f, "mean of channel {channel}"),
+            Statistic::StandardDeviation(channel) => {
+                write!(f, "standard deviation of channel {channel}")
+            }
+            Statistic::Skewness(channel) => write!(f, "skewness of channel {channel}"),
+            Statistic::Min(channel) => write!(f, "minimum of channel {channel}"),
+            Statistic::Max(channel) => write!(f, "maximum of channel {channel}"),
+            Statistic::Median(channel) => write!(f, "median of channel {channel}"),
+            Statistic::RmsContrast => write!(f, "RMS contrast"),
+            Statistic::MichelsonContrast => write!(f, "Michelson contrast"),
+            Statistic::Colourfulness => write!(f, "colourfulness"),
+            Statistic::Entropy => write!(f, "entropy"),
+        }
+    }
+}
+
+impl ImageStatistics {
+    /// Returns the value of the statistic, `None` if the picture has no such channel.
+    pub fn get(&self, statistic: Statistic) -> Option<f32> {
+        let channel = |channel: usize| self.channels.get(channel);
+        match statistic {
+            Statistic::Mean(index) => channel(index).map(|channel| channel.mean),
+            Statistic::StandardDeviation(index) => {
+                channel(index).map(|channel| channel.standard_deviation)
+            }
+            Statistic::Skewness(index) => channel(index).map(|channel| channel.skewness),
+            Statistic::Min(index) => channel(index).map(|channel| channel.min),
+            Statistic::Max(index) => channel(index).map(|channel| channel.max),
+            Statistic::Median(index) => channel(index).map(ChannelStatistics::median),
+            Statistic::RmsContrast => Some(self.rms_contrast),
+            Statistic::MichelsonContrast => Some(self.michelson_contrast),
+            Statistic::Colourfulness => Some(self.colourfulness),
+            Statistic::Entropy => Some(self.entropy),
+        }
+    }
+
+    /// Returns the statistics as values of about `[0.0, 1.0]`, so they can be compared with each
+    /// other: the skewness is squashed, the RMS contrast doubled and the entropy divided by 8.
+    pub(crate) fn feature_vector(&self) -> Vec<f64> {
+        let mut features = Vec::new();
+        for channel in &self.channels {
+            features.extend([
+                channel.mean,
+                channel.standard_deviation,
+                0.5 + 0.5 * channel.skewness / (1.0 + channel.skewness.abs()),
+                channel.min,
+                channel.max,
+            ]);
+            features.extend(&channel.percentiles);
+        }
+        features.extend([
+            (2.0 * self.rms_contrast).min(1.0),
+            self.michelson_contrast,
+            (2.0 * self.colourfulness).min(1.0),
+            self.entropy / 8.0,
+        ]);
+        features.into_iter().map(f64::from).collect()
+    }
+}
+
+/// Returns the similarity of two pictures' statistics: `1 - ` the mean difference of their
+/// features (see `ImageStatistics`), `None` if the pictures have a different number of channels.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::read_picture;
+/// use imsearch::statistics::{compare_statistics, compute_statistics};
+///
+/// let bird = compute_statistics(&read_picture("src/tests/files/pictures_for_testing/bird.png"))
+///     .unwrap();
+/// assert_eq!(compare_statistics(&bird, &bird), Some(1.0));
+/// ```
+pub fn compare_statistics(a: &ImageStatistics, b: &ImageStatistics) -> Option<f64> {
+    compare_feature_vectors(&a.feature_vector(), &b.feature_vector())
+}
+
+/// Returns `1 - ` the mean difference of two feature vectors, `None` if their lengths differ or
+/// they are empty.
+pub(crate) fn compare_feature_vectors(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let difference: f64 = a.iter().zip(b).map(|(a, b)| (a - b).abs()).sum();
+    Some(1.0 - difference / a.len() as f64)
+}
+
+/// The running sums of one set of values.
+#[derive(Clone)]
+struct Moments {
+    sum: f64,
+    sum_of_squares: f64,
+    sum_of_cubes: f64,
+    min: f32,
+    max: f32,
+    levels: Vec<u64>,
+}
+
+impl Moments {
+    fn new() -> Self {
+        Moments {
+            sum: 0.0,
+            sum_of_squares: 0.0,
+            sum_of_cubes: 0.0,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            levels: vec![0; LEVELS],
+        }
+    }
+
+    fn add(&mut self, value: f32) {
+        let value_f64 = f64::from(value);
+        self.sum += value_f64;
+        self.sum_of_squares += value_f64 * value_f64;
+        self.sum_of_cubes += value_f64 * value_f64 * value_f64;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        let level = (value.clamp(0.0, 1.0) * (LEVELS - 1) as f32).round() as usize;
+        self.levels[level] += 1;
+    }
+
+    /// Returns the mean, the standard deviation and the skewness of `count` values.
+    fn mean_deviation_skewness(&self, count: f64) -> (f64, f64, f64) {
+        let mean = self.sum / count;
+        let mean_of_squares = self.sum_of_squares / count;
+        let variance = (mean_of_squares - mean * mean).max(0.0);
+        let third_moment =
+            self.sum_of_cubes / count - 3.0 * mean * mean_of_squares + 2.0 * mean * mean * mean;
+        let deviation = variance.sqrt();
+        let skewness = if deviation > 1e-6 {
+            third_moment / (deviation * deviation * deviation)
+        } else {
+            0.0
+        };
+        (mean, deviation, skewness)
+    }
+
+    /// Returns the level below which the fraction of the `count` values lies.
+    fn percentile(&self, fraction: f32, count: u64) -> f32 {
+        let needed = ((f64::from(fraction) * count as f64).ceil() as u64).max(1);
+        let mut cumulative = 0;
+        for (level, level_count) in self.levels.iter().enumerate() {
+            cumulative += level_count;
+            if cumulative >= needed {
+                return level as f32 / (LEVELS - 1) as f32;
+            }
+        }
+        1.0
+    }
+
+    fn entropy(&self, count: u64) -> f64 {
+        self.levels
+            .iter()
+            .filter(|level_count| **level_count > 0)
+            .map(|level_count| {
+                let probability = *level_count as f64 / count as f64;
+                -probability * probability.log2()
+            })
+            .sum()
+    }
+
+    fn channel_statistics(&self, count: u64) -> ChannelStatistics {
+        let (mean, deviation, skewness) = self.mean_deviation_skewness(count as f64);
+        ChannelStatistics {
+            mean: mean as f32,
+            standard_deviation: deviation as f32,
+            skewness: skewness as f32,
+            min: self.min,
+            max: self.max,
+            percentiles: PERCENTILES
+                .iter()
+                .map(|fraction| self.percentile(*fraction, count))
+                .collect(),
+        }
+    }
+}
+
+/// Computes the statistics of a picture or view in a single pass over its pixels.
+///
+/// # Errors
+///
+/// Returns an error if the picture has no pixels or its colour model is not known
+/// (`ColorType::Channels`), because the gray values and the colourfulness need it.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::picture::PictureU8;
+/// use imsearch::statistics::compute_statistics;
+///
+/// let pic = PictureU8::new(2, 1, ColorType::Gray, vec![0, 255]).unwrap();
+/// let statistics = compute_statistics(&pic).unwrap();
+///
+/// assert_eq!(statistics.channels[0].mean, 0.5);
+/// assert_eq!(statistics.michelson_contrast, 1.0);
+/// assert_eq!(statistics.entropy, 1.0);
+/// assert_eq!(statistics.colourfulness, 0.0);
+/// ```
+pub fn compute_statistics<P: AsPictureView>(pic: &P) -> Result<ImageStatistics, Box<dyn Error>> {
+    let view = pic.as_view();
+    let color_type = view.color_type();
+    if matches!(color_type, ColorType::Channels(_)) {
+        return Err(
+            format!("Can not compute the statistics of a picture with {color_type}").into(),
+        );
+    }
+    let mut channels = vec![Moments::new(); view.color_channel_count()];
+    let mut gray = Moments::new();
+    let mut red_green = Moments::new();
+    let mut yellow_blue = Moments::new();
+    let mut normalized = Vec::with_capacity(view.color_channel_count());
+    let mut count: u64 = 0;
+    for pixel in view.pixel_samples() {
+        normalized.clear();
+        normalized.extend(pixel.iter().map(|sample| sample.to_normalized()));
+        for (moments, value) in channels.iter_mut().zip(&normalized) {
+            moments.add(*value);
+        }
+        let [red, green, blue, _] = color_type
+            .to_rgba(&normalized)
+            .expect("the colour model is known");
+        gray.add(LumaFormula::Legacy.luma(red, green, blue));
+        red_green.add(red - green);
+        yellow_blue.add(0.5 * (red + green) - blue);
+        count += 1;
+    }
+    if count == 0 {
+        return Err("Can not compute the statistics of a picture without pixels".into());
+    }
+
+    let (_, rms_contrast, _) = gray.mean_deviation_skewness(count as f64);
+    let michelson_contrast = if gray.max + gray.min > 0.0 {
+        (gray.max - gray.min) / (gray.max + gray.min)
+    } else {
+        0.0
+    };
+    let (red_green_mean, red_green_deviation, _) = red_green.mean_deviation_skewness(count as f64);
+    let (yellow_blue_mean, yellow_blue_deviation, _) =
+        yellow_blue.mean_deviation_skewness(count as f64);
+    let colourfulness = red_green_deviation.hypot(yellow_blue_deviation)
+        + 0.3 * red_green_mean.hypot(yellow_blue_mean);
+    Ok(ImageStatistics {
+        channels: channels
+            .iter()
+            .map(|moments| moments.channel_statistics(count))
+            .collect(),
+        rms_contrast: rms_contrast as f32,
+        michelson_contrast,
+        colourfulness: colourfulness as f32,
+        entropy: gray.entropy(count) as f32,
+    })
+}
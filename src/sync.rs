@@ -0,0 +1,341 @@
+use crate::datastore::{modify_datastore, open_datastore};
+use crate::features::FeatureConfiguration;
+use crate::file_handler::{collect_picture_files, compute_file_hash, extract_filename, is_file};
+use crate::search_index::{try_generate_suchindex, SearchIndex};
+use crate::{get_datastore_path, get_library_roots};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+/// Summary of the changes a sync applied to the datastore.
+///
+/// # Fields
+///
+/// * `added`: Filepaths of pictures which were indexed for the first time.
+/// * `updated`: Filepaths of pictures whose content changed and which were indexed again.
+/// * `moved`: Pairs of (old filepath, new filepath) of pictures which were found at a new location.
+/// * `removed`: Filepaths of entries which were removed because their file disappeared.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SyncSummary {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub moved: Vec<(String, String)>,
+    pub removed: Vec<String>,
+}
+
+impl SyncSummary {
+    /// Returns `true` if the sync did not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.updated.is_empty()
+            && self.moved.is_empty()
+            && self.removed.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!("______________________________");
+        println!("Added:    {}", self.added.len());
+        for filepath in &self.added {
+            println!("  + {filepath}");
+        }
+        println!("Updated:  {}", self.updated.len());
+        for filepath in &self.updated {
+            println!("  ~ {filepath}");
+        }
+        println!("Moved:    {}", self.moved.len());
+        for (old_filepath, new_filepath) in &self.moved {
+            println!("  > {old_filepath} -> {new_filepath}");
+        }
+        println!("Removed:  {}", self.removed.len());
+        for filepath in &self.removed {
+            println!("  - {filepath}");
+        }
+        println!("______________________________");
+    }
+}
+
+/// Synchronizes the datastore with the library folders configured via `set_library_roots`.
+///
+/// # Errors
+///
+/// Returns an error if no library folders are configured or if `sync_datastore` fails.
+pub fn sync_library() -> Result<SyncSummary, Box<dyn Error>> {
+    let roots = get_library_roots();
+    if roots.is_empty() {
+        return Err("No library folders configured. Use set_library_roots first.".into());
+    }
+    let roots: Vec<&str> = roots.iter().map(String::as_str).collect();
+    sync_datastore(&roots)
+}
+
+/// Synchronizes the datastore with the pictures found in the given folders.
+///
+/// The stored entries are brought up to date with `sync_entries` without holding the lock of the
+/// datastore, because that reads and indexes pictures. Only applying the result locks it, see
+/// `apply_sync`: entries another process changed in the meantime keep that change.
+///
+/// # Arguments
+///
+/// * `roots` - The folders which are walked (including their subfolders).
+///
+/// # Errors
+///
+/// Returns an error if the datastore can not be read or written, or if a folder can not be walked.
+///
+/// # Examples
+///
+/// ```rust
+/// # use imsearch::sync::sync_datastore;
+/// # let datastore_path = std::env::temp_dir().join("imsearch_doc_sync_datastore.json");
+/// # std::fs::copy("src/tests/files/DataStoreJSON/data.json", &datastore_path).unwrap();
+/// # imsearch::set_datastore_filepath(datastore_path.to_str().unwrap());
+/// let summary = sync_datastore(&["src/tests/files/pictures_for_testing"]).unwrap();
+/// summary.print();
+/// ```
+pub fn sync_datastore(roots: &[&str]) -> Result<SyncSummary, Box<dyn Error>> {
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    let snapshot = datastore.entries()?;
+    let (synced_entries, summary) = sync_entries(snapshot.clone(), roots, &datastore.features())?;
+    modify_datastore(|entries| Ok(apply_sync(entries, &snapshot, synced_entries, summary)))
+}
+
+/// Applies the result of `sync_entries` for `snapshot` to the current entries of the datastore.
+///
+/// A change is only applied to an entry that still has the content hash it had in the snapshot.
+/// Entries another process changed, removed or added since the snapshot was read are kept as they
+/// are now, and the changes that were not applied are left out of the returned summary. Updated
+/// and moved entries keep their current tags.
+///
+/// # Arguments
+///
+/// * `entries` - The current entries of the datastore.
+/// * `snapshot` - The entries `sync_entries` was called with.
+/// * `synced_entries` - The entries `sync_entries` returned.
+/// * `summary` - The summary `sync_entries` returned.
+///
+/// # Returns
+///
+/// The summary of the changes that were applied.
+pub(crate) fn apply_sync(
+    entries: &mut Vec<SearchIndex>,
+    snapshot: &[SearchIndex],
+    synced_entries: Vec<SearchIndex>,
+    summary: SyncSummary,
+) -> SyncSummary {
+    let mut snapshot_hashes: HashMap<&str, &str> = HashMap::new();
+    for entry in snapshot {
+        // like `sync_entries`, the first of duplicate entries counts
+        snapshot_hashes
+            .entry(entry.filepath.as_str())
+            .or_insert(entry.content_hash.as_str());
+    }
+    let updated: HashSet<&str> = summary.updated.iter().map(String::as_str).collect();
+    let moved: HashMap<&str, &str> = summary
+        .moved
+        .iter()
+        .map(|(old_filepath, new_filepath)| (old_filepath.as_str(), new_filepath.as_str()))
+        .collect();
+    let mut synced_entries: HashMap<String, SearchIndex> = synced_entries
+        .into_iter()
+        .map(|entry| (entry.filepath.clone(), entry))
+        .collect();
+
+    let mut applied: HashSet<String> = HashSet::new();
+    let mut current_filepaths: HashSet<String> = HashSet::new();
+    let mut moved_entries: Vec<(SearchIndex, String)> = Vec::new();
+    let mut result = Vec::with_capacity(entries.len());
+    for entry in std::mem::take(entries) {
+        let first = current_filepaths.insert(entry.filepath.clone());
+        let unchanged = snapshot_hashes
+            .get(entry.filepath.as_str())
+            .is_some_and(|hash| *hash == entry.content_hash);
+        if !unchanged {
+            result.push(entry);
+            continue;
+        }
+        if !first {
+            // a duplicate, which the sync removed
+            continue;
+        }
+        applied.insert(entry.filepath.clone());
+        if let Some(new_filepath) = moved.get(entry.filepath.as_str()) {
+            moved_entries.push((entry, new_filepath.to_string()));
+            continue;
+        }
+        match synced_entries.remove(&entry.filepath) {
+            Some(mut updated_entry) if updated.contains(entry.filepath.as_str()) => {
+                updated_entry.tags = entry.tags;
+                result.push(updated_entry);
+            }
+            Some(_) => result.push(entry),
+            // the file disappeared
+            None => {}
+        }
+    }
+
+    let mut applied_summary = SyncSummary {
+        updated: summary
+            .updated
+            .iter()
+            .filter(|filepath| applied.contains(*filepath))
+            .cloned()
+            .collect(),
+        removed: summary
+            .removed
+            .iter()
+            .filter(|filepath| applied.contains(*filepath))
+            .cloned()
+            .collect(),
+        ..SyncSummary::default()
+    };
+    for (mut entry, new_filepath) in moved_entries {
+        // another process may have indexed the new filepath in the meantime
+        if current_filepaths.contains(&new_filepath) {
+            continue;
+        }
+        applied_summary
+            .moved
+            .push((entry.filepath.clone(), new_filepath.clone()));
+        entry.filename = extract_filename(new_filepath.clone());
+        entry.filepath = new_filepath;
+        result.push(entry);
+    }
+    for filepath in &summary.added {
+        if current_filepaths.contains(filepath) {
+            continue;
+        }
+        if let Some(new_entry) = synced_entries.remove(filepath) {
+            applied_summary.added.push(filepath.clone());
+            result.push(new_entry);
+        }
+    }
+
+    *entries = result;
+    applied_summary
+}
+
+/// Brings the given entries up to date with the pictures found in the given folders.
+///
+/// - Pictures without an entry are indexed and reported as added.
+/// - Entries whose file content changed (their content hash differs) are indexed again and reported
+///   as updated, keeping their tags. Entries without a content hash count as changed.
+/// - Entries whose file disappeared, but whose content is found under a new filepath, keep their
+///   descriptors and get the new filepath. They are reported as moved.
+/// - Entries whose file disappeared otherwise are reported as removed. So are duplicate entries of
+///   the same filepath.
+///
+/// Entries of files outside of the given folders are kept as long as their file exists.
+///
+/// # Arguments
+///
+/// * `entries` - The entries currently stored in the datastore.
+/// * `roots` - The folders which are walked (including their subfolders).
+/// * `features` - The feature configuration of the datastore, which selects the descriptors that
+///   are computed (see `try_generate_suchindex`).
+///
+/// # Returns
+///
+/// The updated entries together with a summary of the changes.
+///
+/// # Errors
+///
+/// Returns an error if a folder can not be walked. Pictures which can not be read are skipped and
+/// reported on the console.
+pub fn sync_entries(
+    entries: Vec<SearchIndex>,
+    roots: &[&str],
+    features: &FeatureConfiguration,
+) -> Result<(Vec<SearchIndex>, SyncSummary), Box<dyn Error>> {
+    let mut summary = SyncSummary::default();
+
+    // --- current state of the library folders ---
+    let mut files_on_disk: Vec<(String, String)> = Vec::new();
+    let mut hashes_on_disk: HashMap<String, String> = HashMap::new();
+    for root in roots {
+        for filepath in collect_picture_files(root)? {
+            if hashes_on_disk.contains_key(&filepath) {
+                // folders may overlap
+                continue;
+            }
+            match compute_file_hash(&filepath) {
+                Ok(hash) => {
+                    hashes_on_disk.insert(filepath.clone(), hash.clone());
+                    files_on_disk.push((filepath, hash));
+                }
+                Err(err) => eprintln!("Error reading {filepath}: {err}"),
+            }
+        }
+    }
+
+    // --- compare the stored entries with the files ---
+    let mut synced_entries: Vec<SearchIndex> = Vec::new();
+    let mut stored_filepaths: HashSet<String> = HashSet::new();
+    let mut missing_entries: Vec<SearchIndex> = Vec::new();
+
+    for entry in entries {
+        if !stored_filepaths.insert(entry.filepath.clone()) {
+            summary.removed.push(entry.filepath);
+            continue;
+        }
+        match hashes_on_disk.get(&entry.filepath) {
+            Some(hash) if *hash == entry.content_hash => synced_entries.push(entry),
+            Some(_) => match try_generate_suchindex(entry.filepath.clone(), features) {
+                Ok(mut updated_entry) => {
+                    updated_entry.tags = entry.tags.clone();
+                    summary.updated.push(entry.filepath);
+                    synced_entries.push(updated_entry);
+                }
+                Err(err) => {
+                    eprintln!("Error indexing {}: {err}", entry.filepath);
+                    synced_entries.push(entry);
+                }
+            },
+            None if is_file(&entry.filepath) => synced_entries.push(entry),
+            None => missing_entries.push(entry),
+        }
+    }
+
+    // --- files without an entry are either moved or new ---
+    let unindexed_files: Vec<(String, String)> = files_on_disk
+        .into_iter()
+        .filter(|(filepath, _)| !stored_filepaths.contains(filepath))
+        .collect();
+    let mut unindexed_by_hash: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (filepath, hash) in &unindexed_files {
+        unindexed_by_hash.entry(hash).or_default().push(filepath);
+    }
+
+    let mut moved_filepaths: HashSet<String> = HashSet::new();
+    for mut entry in missing_entries {
+        let new_location = unindexed_by_hash
+            .get_mut(entry.content_hash.as_str())
+            .filter(|filepaths| !entry.content_hash.is_empty() && !filepaths.is_empty())
+            .map(|filepaths| filepaths.remove(0).to_string());
+        match new_location {
+            Some(new_filepath) => {
+                moved_filepaths.insert(new_filepath.clone());
+                summary
+                    .moved
+                    .push((entry.filepath.clone(), new_filepath.clone()));
+                entry.filename = extract_filename(new_filepath.clone());
+                entry.filepath = new_filepath;
+                synced_entries.push(entry);
+            }
+            None => summary.removed.push(entry.filepath),
+        }
+    }
+
+    for (filepath, _) in unindexed_files {
+        if moved_filepaths.contains(&filepath) {
+            continue;
+        }
+        match try_generate_suchindex(filepath.clone(), features) {
+            Ok(new_entry) => {
+                summary.added.push(filepath);
+                synced_entries.push(new_entry);
+            }
+            Err(err) => eprintln!("Error indexing {filepath}: {err}"),
+        }
+    }
+
+    Ok((synced_entries, summary))
+}
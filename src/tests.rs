@@ -12,7 +12,9 @@ mod histogram_tests;
 #[cfg(test)]
 mod picture_tests;
 
+// the tests call `analyse_pictures` without checking its result
 #[cfg(test)]
+#[allow(unused_must_use)]
 mod search_index_tests;
 
 #[cfg(test)]
@@ -23,3 +25,78 @@ mod average_brightness_tests;
 
 #[cfg(test)]
 mod lib_tests;
+
+#[cfg(test)]
+mod sync_tests;
+
+#[cfg(test)]
+mod datastore_tests;
+
+#[cfg(test)]
+mod cli_tests;
+
+#[cfg(test)]
+mod binary_datastore_tests;
+
+#[cfg(test)]
+pub(crate) mod test_helpers;
+
+#[cfg(test)]
+mod migration_tests;
+
+#[cfg(all(test, feature = "sqlite"))]
+mod sqlite_datastore_tests;
+
+#[cfg(test)]
+mod ann_index_tests;
+
+#[cfg(test)]
+mod search_tests;
+
+#[cfg(test)]
+mod query_tests;
+
+#[cfg(test)]
+mod batch_search_tests;
+
+#[cfg(test)]
+mod multi_query_tests;
+
+#[cfg(test)]
+mod feedback_tests;
+
+#[cfg(test)]
+mod region_tests;
+
+#[cfg(test)]
+mod transform_tests;
+
+#[cfg(test)]
+mod orientation_tests;
+
+#[cfg(test)]
+mod picture_view_tests;
+
+#[cfg(test)]
+mod pixel_access_tests;
+
+#[cfg(test)]
+mod color_type_tests;
+
+#[cfg(test)]
+mod grayscale_tests;
+
+#[cfg(test)]
+mod statistics_tests;
+
+#[cfg(test)]
+mod color_moments_tests;
+
+#[cfg(test)]
+mod correlogram_tests;
+
+#[cfg(test)]
+mod edges_tests;
+
+#[cfg(test)]
+mod shape_tests;
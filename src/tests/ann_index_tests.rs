@@ -0,0 +1,214 @@
+use crate::ann_index::{
+    ann_index_path, load_ann_index, rebuild_ann_index, AnnIndex, AnnSearchOptions,
+    UnreadableAnnIndex,
+};
+use crate::cosinus_similarity::determine_similarity_of_search_index_histograms;
+use crate::search_index::{generate_suchindex, write_data_to_filepath, SearchIndex};
+use crate::tests::test_helpers::{create_temp_folder, random_entries};
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+const PICTURES_FOLDER: &str = "src/tests/files/pictures_for_testing";
+
+fn exact_top_k(entries: &[SearchIndex], query: &SearchIndex, k: usize) -> Vec<String> {
+    let mut similarities: Vec<(f64, String)> = entries
+        .iter()
+        .map(|entry| {
+            let cosine_similarity = determine_similarity_of_search_index_histograms(query, entry);
+            let brightness_similarity =
+                1.0 - f64::from((query.average_brightness - entry.average_brightness).abs());
+            (
+                (cosine_similarity + brightness_similarity) / 2.0,
+                entry.filepath.clone(),
+            )
+        })
+        .collect();
+    similarities.sort_by(|a, b| b.0.total_cmp(&a.0));
+    similarities
+        .into_iter()
+        .take(k)
+        .map(|(_, filepath)| filepath)
+        .collect()
+}
+
+#[test]
+fn test_exact_search_matches_linear_search() {
+    let entries = random_entries(500, 7);
+    let index = AnnIndex::build(&entries);
+    let options = AnnSearchOptions {
+        epsilon: 0.0,
+        oversampling: 50,
+    };
+
+    for query in random_entries(10, 99) {
+        let found: Vec<String> = index
+            .search(&query, 5, &options)
+            .into_iter()
+            .map(|found| found.filepath)
+            .collect();
+        assert_eq!(found, exact_top_k(&entries, &query, 5));
+    }
+}
+
+#[test]
+fn test_approximate_search_recall() {
+    let entries = random_entries(2000, 3);
+    let index = AnnIndex::build(&entries);
+    let options = AnnSearchOptions {
+        epsilon: 0.5,
+        oversampling: 4,
+    };
+
+    let mut hits = 0;
+    let queries = random_entries(20, 42);
+    for query in &queries {
+        let expected = exact_top_k(&entries, query, 10);
+        hits += index
+            .search(query, 10, &options)
+            .iter()
+            .filter(|found| expected.contains(&found.filepath))
+            .count();
+    }
+    assert!(
+        hits >= queries.len() * 10 * 7 / 10,
+        "recall too low: {hits}"
+    );
+}
+
+#[test]
+fn test_similarity_matches_histogram_comparison() {
+    let bird = generate_suchindex(format!("{PICTURES_FOLDER}/bird.png"));
+    let flower = generate_suchindex(format!("{PICTURES_FOLDER}/flower_purple_1.png"));
+    let entries = vec![bird.clone(), flower.clone()];
+
+    let found = AnnIndex::build(&entries).search(&bird, 2, &AnnSearchOptions::default());
+
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].filepath, bird.filepath);
+    assert_eq!(found[1].filepath, flower.filepath);
+    let cosine_similarity = determine_similarity_of_search_index_histograms(&bird, &flower);
+    assert!((found[1].cosine_similarity - cosine_similarity).abs() < 1e-4);
+    assert!((found[0].similarity - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_insert_and_remove() {
+    let entries = random_entries(300, 11);
+    let mut index = AnnIndex::build(&entries[..100]);
+    for entry in &entries[100..] {
+        index.insert(entry);
+    }
+    assert_eq!(index.len(), 300);
+
+    let query = entries[250].clone();
+    let found = index.search(&query, 1, &AnnSearchOptions::default());
+    assert_eq!(found[0].filepath, query.filepath);
+
+    assert!(index.remove(&query.filepath));
+    assert!(!index.remove(&query.filepath));
+    assert_eq!(index.len(), 299);
+    let found = index.search(&query, 3, &AnnSearchOptions::default());
+    assert!(found.iter().all(|found| found.filepath != query.filepath));
+
+    for entry in &entries[..200] {
+        index.remove(&entry.filepath);
+    }
+    assert_eq!(index.len(), 99);
+    assert_eq!(
+        index
+            .search(&entries[0], 200, &AnnSearchOptions::default())
+            .len(),
+        99
+    );
+}
+
+#[test]
+fn test_save_and_load() {
+    let folder = create_temp_folder("ann_save_load");
+    let index_path = folder.join("index.ann");
+    let index_path = index_path.to_str().unwrap();
+    let entries = random_entries(50, 5);
+    let mut index = AnnIndex::build(&entries[..40]);
+    for entry in &entries[40..] {
+        index.insert(entry);
+    }
+    index.remove(&entries[0].filepath);
+
+    index.save(index_path).unwrap();
+    assert_eq!(AnnIndex::load(index_path).unwrap(), index);
+
+    let bytes = fs::read(index_path).unwrap();
+    fs::write(index_path, &bytes[..bytes.len() - 3]).unwrap();
+    assert!(AnnIndex::load(index_path).is_err());
+}
+
+#[test]
+fn test_load_ann_index_follows_the_datastore() {
+    let folder = create_temp_folder("ann_follows_datastore");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    let entries = random_entries(20, 13);
+    write_data_to_filepath(datastore_path, &entries).unwrap();
+
+    let index = load_ann_index(datastore_path).unwrap();
+    assert_eq!(index.len(), 20);
+    assert!(fs::metadata(ann_index_path(datastore_path)).is_ok());
+
+    write_data_to_filepath(datastore_path, &entries[5..]).unwrap();
+    let index = load_ann_index(datastore_path).unwrap();
+    assert_eq!(index.len(), 15);
+    let found = index.search(&entries[0], 20, &AnnSearchOptions::default());
+    assert!(found
+        .iter()
+        .all(|found| found.filepath != entries[0].filepath));
+}
+
+#[test]
+fn test_load_ann_index_notices_changes_of_the_same_size() {
+    let folder = create_temp_folder("ann_same_size_change");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    let mut entries = random_entries(10, 17);
+    write_data_to_filepath(datastore_path, &entries).unwrap();
+    let modified = SystemTime::now() - Duration::from_secs(3600);
+    let set_modified = || {
+        fs::File::options()
+            .write(true)
+            .open(datastore_path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap()
+    };
+    set_modified();
+    assert_eq!(load_ann_index(datastore_path).unwrap().len(), 10);
+
+    // a rename to a filepath of the same length, with the modification time restored
+    entries[0].filepath = entries[0].filepath.replace("pictures", "picturez");
+    let size = fs::metadata(datastore_path).unwrap().len();
+    write_data_to_filepath(datastore_path, &entries).unwrap();
+    set_modified();
+    assert_eq!(fs::metadata(datastore_path).unwrap().len(), size);
+
+    let index = load_ann_index(datastore_path).unwrap();
+    let found = index.search(&entries[0], 1, &AnnSearchOptions::default());
+    assert_eq!(found[0].filepath, entries[0].filepath);
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_load_unreadable_ann_index() {
+    let folder = create_temp_folder("ann_unreadable");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    write_data_to_filepath(datastore_path, &random_entries(10, 19)).unwrap();
+    fs::write(ann_index_path(datastore_path), b"IMSA").unwrap();
+
+    // the error is returned to the caller, which can build the index again
+    let err = load_ann_index(datastore_path).unwrap_err();
+    assert!(err.downcast_ref::<UnreadableAnnIndex>().is_some());
+    assert_eq!(rebuild_ann_index(datastore_path).unwrap().len(), 10);
+    assert_eq!(load_ann_index(datastore_path).unwrap().len(), 10);
+
+    fs::remove_dir_all(folder).unwrap();
+}
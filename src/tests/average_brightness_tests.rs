@@ -1,3 +1,4 @@
+use crate::color_type::ColorType;
 use crate::picture::{AverageBrightness, PictureF32};
 
 #[test]
@@ -7,6 +8,7 @@ fn test_gray_intensity_single_val() {
         lines: 0,
         data: vec![0.1, 0.2, 0.12], //Wert für Rot:0.1 ; Grün:0.2 Blau: 0.12
         color_channel_count: 3,
+        color_type: ColorType::Rgb,
         columns: 0,
     };
 
@@ -24,6 +26,7 @@ fn test_gray_intensity_array() {
         lines: 0,
         data: vec![0.1, 0.2, 0.25, 0.12, 0.22, 0.1], //Werte für Rot:0.1,0.12 ; Grün:0.2,0.22  Blau:0.25,  0.1 (Also Berechnung von 2 Pixeln)
         color_channel_count: 3,
+        color_type: ColorType::Rgb,
         columns: 0,
     };
 
@@ -41,6 +44,7 @@ fn test_average_brightness() {
         lines: 0,
         data: vec![0.23, 0.188, 0.256, 0.2, 0.1, 0.1], //Werte für Rot:0.23,0.2 ; Grün:0.188,0.1  Blau:0.256,  0.1
         color_channel_count: 3,
+        color_type: ColorType::Rgb,
         columns: 0,
     };
 
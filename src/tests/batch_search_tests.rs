@@ -0,0 +1,119 @@
+use crate::batch_search::{batch_search_datastore, collect_queries, stream_batch_search};
+use crate::compare_pictures::SimilarityInformation;
+use crate::memory_datastore::MemoryDatastore;
+use crate::search::{search_datastore, SearchOptions};
+use crate::search_index::generate_suchindex;
+use crate::tests::test_helpers::{
+    compact_datastore, create_temp_folder, random_entries, write_test_picture, TEST_PICTURES,
+};
+use serde_json::Value;
+
+/// Writes pictures of different colors and returns their filepaths.
+fn write_queries(name: &str) -> Vec<String> {
+    let folder = create_temp_folder(name);
+    let colors = [
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [40, 40, 40],
+        [200, 200, 90],
+    ];
+    colors
+        .iter()
+        .enumerate()
+        .map(|(i, color)| {
+            let filepath = folder.join(format!("query_{i}.png"));
+            let filepath = filepath.to_str().unwrap().to_string();
+            write_test_picture(&filepath, 4, 3, *color);
+            filepath
+        })
+        .collect()
+}
+
+fn filepaths(results: &[SimilarityInformation]) -> Vec<String> {
+    results
+        .iter()
+        .map(|result| result.search_index().filepath.clone())
+        .collect()
+}
+
+#[test]
+fn test_batch_search_matches_single_searches() {
+    let queries = write_queries("batch_search");
+    let mut entries = random_entries(100, 36);
+    entries.extend(
+        queries
+            .iter()
+            .map(|query| generate_suchindex(query.clone())),
+    );
+    let datastore = MemoryDatastore::with_entries(entries);
+    let options = SearchOptions::top(4);
+
+    let batch = batch_search_datastore(&datastore, &queries, &options).unwrap();
+
+    assert!(batch.failed.is_empty());
+    assert_eq!(batch.results.len(), queries.len());
+    for query in &queries {
+        let expected =
+            search_datastore(&datastore, &generate_suchindex(query.clone()), &options).unwrap();
+        assert_eq!(filepaths(&batch.results[query]), filepaths(&expected));
+        assert_eq!(batch.results[query][0].search_index().filepath, *query);
+    }
+}
+
+#[test]
+fn test_stream_batch_search_writes_json_lines() {
+    let mut queries = write_queries("stream_batch_search");
+    queries.push("pictures/missing.png".to_string());
+    let datastore = MemoryDatastore::with_entries(random_entries(20, 2));
+    let mut output = Vec::new();
+
+    let failed_count =
+        stream_batch_search(&datastore, &queries, &SearchOptions::top(2), &mut output).unwrap();
+
+    assert_eq!(failed_count, 1);
+    let lines: Vec<Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), queries.len());
+    for line in &lines {
+        let query = line["query"].as_str().unwrap();
+        if query == "pictures/missing.png" {
+            assert!(line["error"].is_string());
+        } else {
+            assert_eq!(line["results"].as_array().unwrap().len(), 2);
+        }
+    }
+}
+
+#[test]
+fn test_collect_queries_expands_folders() {
+    let queries = write_queries("collect_queries");
+    let folder = queries[0].rsplit_once('/').unwrap().0.to_string();
+
+    let mut collected = collect_queries(&[folder, "a.png".to_string()]).unwrap();
+    collected.sort();
+
+    assert_eq!(collected.len(), queries.len() + 1);
+    assert!(queries.iter().all(|query| collected.contains(query)));
+    assert!(collected.contains(&"a.png".to_string()));
+}
+
+#[test]
+fn test_batch_search_compact_datastore() {
+    let queries: Vec<String> = TEST_PICTURES
+        .iter()
+        .map(|query| query.to_string())
+        .collect();
+    let batch =
+        batch_search_datastore(&compact_datastore(), &queries, &SearchOptions::top(1)).unwrap();
+
+    assert!(batch.failed.is_empty());
+    for query in &queries {
+        let result = &batch.results[query][0];
+        assert_eq!(result.search_index().filepath, *query);
+        assert!((result.similarity() - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::binary_datastore::{
+    encode_binary_datastore, is_binary_datastore, write_binary_datastore, BinaryDatastore,
+    BINARY_FORMAT_VERSION,
+};
+use crate::color_type::ColorType;
+use crate::datastore::{read_datastore_file, DatastoreError};
+use crate::features::FeatureConfiguration;
+use crate::histogram::Histogram;
+use crate::search_index::SearchIndex;
+use crate::tests::test_helpers::{create_temp_folder, DATASTORE_FIXTURE};
+use std::fs;
+
+#[test]
+fn test_binary_datastore_roundtrip() {
+    let folder = create_temp_folder("binary_roundtrip");
+    let binary_path = folder.join("data.imsb");
+    let binary_path = binary_path.to_str().unwrap();
+
+    let mut entries: Vec<SearchIndex> = read_datastore_file(DATASTORE_FIXTURE).unwrap();
+    entries[0].content_hash = "0123456789abcdef".to_string();
+    // a filename that can not be derived from the filepath is kept
+    entries[1].filename = "renamed".to_string();
+    entries[2].tags = vec!["flower".to_string(), "purple, dark".to_string()];
+    entries[3].color_type = ColorType::Channels(4);
+
+    write_binary_datastore(binary_path, &entries, &FeatureConfiguration::default()).unwrap();
+    assert!(is_binary_datastore(binary_path));
+    assert!(!is_binary_datastore(DATASTORE_FIXTURE));
+
+    let datastore = BinaryDatastore::open(binary_path).unwrap();
+    assert_eq!(datastore.header().format_version, BINARY_FORMAT_VERSION);
+    assert_eq!(datastore.header().features, FeatureConfiguration::default());
+    // every descriptor of the fixture has a section of fixed-size slots
+    let sections = datastore.header().sections;
+    assert_eq!(sections.color_moments.count, 9);
+    assert!(sections.grid_cells.count > 0 && sections.correlogram.count > 0);
+    assert_eq!(datastore.len(), entries.len());
+    assert_eq!(datastore.filepath(1).unwrap(), entries[1].filepath);
+    assert_eq!(
+        datastore.average_brightness(2),
+        entries[2].average_brightness
+    );
+    assert_eq!(datastore.entries().unwrap(), entries);
+
+    // the binary format is much smaller than the pretty-printed JSON
+    let json_size = fs::metadata(DATASTORE_FIXTURE).unwrap().len();
+    let binary_size = fs::metadata(binary_path).unwrap().len();
+    assert!(binary_size * 3 < json_size);
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_binary_datastore_rejects_invalid_files() {
+    let folder = create_temp_folder("binary_invalid");
+    let binary_path = folder.join("data.imsb");
+    let binary_path = binary_path.to_str().unwrap();
+
+    let entries = vec![SearchIndex::new("a.png".to_string(), 0.5, vec![])];
+    let bytes = encode_binary_datastore(&entries, &FeatureConfiguration::default()).unwrap();
+
+    // truncated
+    fs::write(binary_path, &bytes[..bytes.len() - 1]).unwrap();
+    let err = BinaryDatastore::open(binary_path).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::Corrupted { .. })
+    ));
+
+    // written by a newer version
+    let mut newer_bytes = bytes.clone();
+    newer_bytes[4..6].copy_from_slice(&(BINARY_FORMAT_VERSION + 1).to_le_bytes());
+    fs::write(binary_path, &newer_bytes).unwrap();
+    let err = BinaryDatastore::open(binary_path).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::UnsupportedVersion { .. })
+    ));
+
+    // a filepath offset that overflows when its length is added
+    let mut overflowing_bytes = bytes.clone();
+    overflowing_bytes[128..136].copy_from_slice(&(u64::MAX - 1).to_le_bytes());
+    fs::write(binary_path, &overflowing_bytes).unwrap();
+    let datastore = BinaryDatastore::open(binary_path).unwrap();
+    let err = datastore.filepath(0).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::Corrupted { .. })
+    ));
+    drop(datastore);
+
+    // not a binary datastore at all
+    assert!(BinaryDatastore::open(DATASTORE_FIXTURE).is_err());
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+/// Datastores of format version 1 stored the descriptors as JSON and are still read.
+#[test]
+fn test_read_binary_datastore_format_version_1() {
+    let folder = create_temp_folder("binary_format_version_1");
+    let binary_path = folder.join("data.imsb");
+    let binary_path = binary_path.to_str().unwrap();
+
+    let filepath = b"pictures/red.png";
+    let extras = br#"{"tags":["red"],"lines":3}"#;
+    let heap_size = filepath.len() + extras.len();
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend_from_slice(b"IMSB");
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&64u16.to_le_bytes());
+    // one bin of one channel
+    bytes.extend_from_slice(&[1, 1]);
+    bytes.extend_from_slice(&6u16.to_le_bytes());
+    bytes.extend_from_slice(&44u32.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(&(64u64 + 44).to_le_bytes());
+    bytes.extend_from_slice(&(heap_size as u64).to_le_bytes());
+    bytes.resize(64, 0);
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&(filepath.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(filepath.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(extras.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&0.5f32.to_le_bytes());
+    bytes.extend_from_slice(&[1, 0, 0, 0]);
+    bytes.extend_from_slice(&7u32.to_le_bytes());
+    bytes.extend_from_slice(filepath);
+    bytes.extend_from_slice(extras);
+    fs::write(binary_path, &bytes).unwrap();
+
+    let datastore = BinaryDatastore::open(binary_path).unwrap();
+    assert_eq!(datastore.header().format_version, 1);
+    let entry = datastore.get(0).unwrap();
+    assert_eq!(entry.filepath, "pictures/red.png");
+    assert_eq!(entry.filename, "red");
+    assert_eq!(entry.histogram, vec![Histogram { bins: vec![7] }]);
+    assert_eq!(entry.tags, vec!["red".to_string()]);
+    assert_eq!(entry.lines, 3);
+    drop(datastore);
+
+    fs::remove_dir_all(folder).unwrap();
+}
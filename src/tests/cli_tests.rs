@@ -0,0 +1,221 @@
+use crate::ann_index::AnnSearchOptions;
+use crate::cli::{parse_command, Command};
+use crate::multi_query::{FusionStrategy, DEFAULT_NEGATIVE_WEIGHT};
+use crate::query_descriptor::SearchProfile;
+use crate::region::Rect;
+use crate::search::SearchOptions;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_parse_command() {
+    assert_eq!(
+        parse_command(&args(&["update", "a.png"])),
+        Ok(Command::Update("a.png".to_string()))
+    );
+    assert_eq!(
+        parse_command(&args(&["remove-folder", "pictures"])),
+        Ok(Command::RemoveFolder("pictures".to_string()))
+    );
+    assert_eq!(
+        parse_command(&args(&["sync", "a", "b"])),
+        Ok(Command::Sync(vec!["a".to_string(), "b".to_string()]))
+    );
+    assert_eq!(parse_command(&args(&["prune"])), Ok(Command::Prune));
+    assert_eq!(
+        parse_command(&args(&["tag", "a.png", "animal", "outdoor"])),
+        Ok(Command::Tag(
+            "a.png".to_string(),
+            vec!["animal".to_string(), "outdoor".to_string()]
+        ))
+    );
+}
+
+#[test]
+fn test_parse_search_profile() {
+    assert_eq!(
+        parse_command(&args(&["search", "logo.png", "--profile", "shape"])),
+        Ok(Command::Search {
+            picture: "logo.png".to_string(),
+            options: SearchOptions::default(),
+            profile: SearchProfile::Shape,
+        })
+    );
+    assert_eq!(
+        parse_command(&args(&["search", "a.png", "--profile", "structure"])),
+        Ok(Command::Search {
+            picture: "a.png".to_string(),
+            options: SearchOptions::default(),
+            profile: SearchProfile::Structure,
+        })
+    );
+    assert!(parse_command(&args(&["search", "a.png", "--profile", "texture"])).is_err());
+    assert!(parse_command(&args(&[
+        "search",
+        "a.png",
+        "--invariant",
+        "--profile",
+        "shape"
+    ]))
+    .is_err());
+    assert!(parse_command(&args(&["batch-search", "a.png", "--profile", "shape"])).is_err());
+}
+
+#[test]
+fn test_parse_search_approximate() {
+    assert_eq!(
+        parse_command(&args(&["search", "a.png", "--approximate", "--limit", "3"])),
+        Ok(Command::Search {
+            picture: "a.png".to_string(),
+            options: SearchOptions::top(3).with_approximate(AnnSearchOptions::default()),
+            profile: SearchProfile::Colour,
+        })
+    );
+    assert!(parse_command(&args(&[
+        "search",
+        "a.png",
+        "--approximate",
+        "--profile",
+        "shape"
+    ]))
+    .is_err());
+    assert!(parse_command(&args(&["search", "a.png", "--approximate", "--invariant"])).is_err());
+    assert!(parse_command(&args(&["search", "a.png", "b.png", "--approximate"])).is_err());
+    assert!(parse_command(&args(&["batch-search", "a.png", "--approximate"])).is_err());
+}
+
+#[test]
+fn test_parse_search_command() {
+    assert_eq!(
+        parse_command(&args(&["search", "a.png"])),
+        Ok(Command::Search {
+            picture: "a.png".to_string(),
+            options: SearchOptions::default(),
+            profile: SearchProfile::Colour,
+        })
+    );
+    assert_eq!(
+        parse_command(&args(&[
+            "search",
+            "a.png",
+            "--limit",
+            "10",
+            "--offset",
+            "20",
+            "--min-similarity",
+            "0.5"
+        ])),
+        Ok(Command::Search {
+            picture: "a.png".to_string(),
+            options: SearchOptions::top(10)
+                .with_offset(20)
+                .with_min_similarity(0.5),
+            profile: SearchProfile::Colour,
+        })
+    );
+    assert!(parse_command(&args(&["search"])).is_err());
+    assert!(parse_command(&args(&["search", "a.png", "--limit"])).is_err());
+    assert!(parse_command(&args(&["search", "a.png", "--limit", "ten"])).is_err());
+    assert!(parse_command(&args(&["search", "a.png", "--page", "2"])).is_err());
+    assert!(parse_command(&args(&["search", "a.png", "--output", "out.jsonl"])).is_err());
+}
+
+#[test]
+fn test_parse_multi_search_command() {
+    assert_eq!(
+        parse_command(&args(&[
+            "search",
+            "a.png",
+            "b.png",
+            "--not",
+            "c.png",
+            "--strategy",
+            "max"
+        ])),
+        Ok(Command::MultiSearch {
+            positives: vec!["a.png".to_string(), "b.png".to_string()],
+            negatives: vec!["c.png".to_string()],
+            strategy: FusionStrategy::MaxSimilarity,
+            negative_weight: DEFAULT_NEGATIVE_WEIGHT,
+            options: SearchOptions::default(),
+        })
+    );
+    assert!(parse_command(&args(&["search", "a.png", "--strategy", "median"])).is_err());
+    assert!(parse_command(&args(&["batch-search", "a.png", "--not", "c.png"])).is_err());
+}
+
+#[test]
+fn test_parse_region_search_command() {
+    assert_eq!(
+        parse_command(&args(&[
+            "search",
+            "a.png",
+            "--region",
+            "0,10,64,32",
+            "--anywhere"
+        ])),
+        Ok(Command::RegionSearch {
+            picture: "a.png".to_string(),
+            region: Rect::new(0, 10, 64, 32),
+            anywhere: true,
+            options: SearchOptions::default(),
+        })
+    );
+    assert!(parse_command(&args(&["search", "a.png", "--anywhere"])).is_err());
+    assert!(parse_command(&args(&["search", "a.png", "--region", "0,10"])).is_err());
+    assert!(parse_command(&args(&["search", "a.png", "b.png", "--region", "0,0,1,1"])).is_err());
+}
+
+#[test]
+fn test_parse_invariant_search_command() {
+    assert_eq!(
+        parse_command(&args(&["search", "a.png", "--invariant", "--limit", "3"])),
+        Ok(Command::InvariantSearch {
+            picture: "a.png".to_string(),
+            options: SearchOptions::top(3),
+        })
+    );
+    assert!(parse_command(&args(&["search", "a.png", "b.png", "--invariant"])).is_err());
+    assert!(parse_command(&args(&[
+        "search",
+        "a.png",
+        "--invariant",
+        "--region",
+        "0,0,1,1"
+    ]))
+    .is_err());
+    assert!(parse_command(&args(&["batch-search", "a.png", "--invariant"])).is_err());
+}
+
+#[test]
+fn test_parse_batch_search_command() {
+    assert_eq!(
+        parse_command(&args(&[
+            "batch-search",
+            "uploads",
+            "a.png",
+            "--limit",
+            "3",
+            "--output",
+            "results.jsonl"
+        ])),
+        Ok(Command::BatchSearch {
+            pictures: vec!["uploads".to_string(), "a.png".to_string()],
+            options: SearchOptions::top(3),
+            output: Some("results.jsonl".to_string()),
+        })
+    );
+    assert!(parse_command(&args(&["batch-search"])).is_err());
+    assert!(parse_command(&args(&["batch-search", "--limit", "3"])).is_err());
+}
+
+#[test]
+fn test_parse_command_errors() {
+    assert!(parse_command(&args(&[])).is_err());
+    assert!(parse_command(&args(&["remove"])).is_err());
+    assert!(parse_command(&args(&["remove", "a.png", "b.png"])).is_err());
+    assert!(parse_command(&args(&["prune", "now"])).is_err());
+    assert!(parse_command(&args(&["delete", "a.png"])).is_err());
+}
@@ -0,0 +1,130 @@
+use crate::color_moments::{color_moments_distance, compare_color_moments, compute_color_moments};
+use crate::color_type::ColorType;
+use crate::features::FeatureConfiguration;
+use crate::query_descriptor::{FeatureWeights, QueryDescriptor};
+use crate::search::{rank_entries_weighted, SearchOptions};
+use crate::search_index::try_generate_suchindex;
+use crate::tests::test_helpers::{create_temp_folder, write_test_picture};
+use crate::transform::Transform;
+use crate::PictureU8;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-4,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn test_color_moments_of_a_single_colour() {
+    // pure blue has a hue of 2/3
+    let pic = PictureU8::new(2, 2, ColorType::Rgb, [0, 0, 255].repeat(4)).unwrap();
+
+    let moments = compute_color_moments(&pic).unwrap();
+    assert_close(moments.mean[0], 2.0 / 3.0);
+    assert_close(moments.mean[1], 1.0);
+    assert_close(moments.mean[2], 1.0);
+    for channel in 0..3 {
+        assert_close(moments.standard_deviation[channel], 0.0);
+        assert_close(moments.skewness[channel], 0.0);
+    }
+}
+
+#[test]
+fn test_skewness_of_the_value() {
+    // mostly black with one white pixel
+    let pic = PictureU8::new(4, 1, ColorType::Gray, vec![0, 0, 0, 255]).unwrap();
+
+    let moments = compute_color_moments(&pic).unwrap();
+    assert_close(moments.mean[2], 0.25);
+    assert!(moments.skewness[2] > 0.0);
+    // gray pixels have no hue and no saturation
+    assert_eq!(moments.mean[0], 0.0);
+    assert_eq!(moments.mean[1], 0.0);
+}
+
+#[test]
+fn test_hue_moments_are_circular() {
+    // reds just above and just below a hue of 0
+    let orange_red = [255, 15, 0];
+    let purple_red = [255, 0, 15];
+    let both = PictureU8::new(2, 1, ColorType::Rgb, [orange_red, purple_red].concat()).unwrap();
+
+    let moments = compute_color_moments(&both).unwrap();
+    let mean_hue = moments.mean[0];
+    assert!(mean_hue.min(1.0 - mean_hue) < 1e-4, "{mean_hue}");
+    assert_close(moments.standard_deviation[0], 15.0 / 255.0 / 6.0);
+    assert_close(moments.skewness[0], 0.0);
+
+    let single = |colour: [u8; 3]| {
+        compute_color_moments(&PictureU8::new(1, 1, ColorType::Rgb, colour.to_vec()).unwrap())
+            .unwrap()
+    };
+    let distance = color_moments_distance(&single(orange_red), &single(purple_red));
+    assert!(distance < 0.05, "{distance}");
+}
+
+#[test]
+fn test_color_moments_need_a_colour_model_and_pixels() {
+    let rgb = PictureU8::new(1, 1, ColorType::Rgb, vec![1, 2, 3]).unwrap();
+    assert!(compute_color_moments(&rgb.reorder_channels(&[2, 1, 0]).unwrap()).is_err());
+
+    let empty = PictureU8::new(0, 0, ColorType::Rgb, vec![]).unwrap();
+    assert!(compute_color_moments(&empty).is_err());
+}
+
+#[test]
+fn test_distance_and_similarity() {
+    let red = PictureU8::new(1, 1, ColorType::Rgb, vec![255, 0, 0]).unwrap();
+    let dark_red = PictureU8::new(1, 1, ColorType::Rgb, vec![128, 0, 0]).unwrap();
+    let blue = PictureU8::new(1, 1, ColorType::Rgb, vec![0, 0, 255]).unwrap();
+    let red = compute_color_moments(&red).unwrap();
+    let dark_red = compute_color_moments(&dark_red).unwrap();
+    let blue = compute_color_moments(&blue).unwrap();
+
+    assert_eq!(color_moments_distance(&red, &red), 0.0);
+    assert_eq!(compare_color_moments(&red, &red), 1.0);
+    assert!(color_moments_distance(&red, &dark_red) < color_moments_distance(&red, &blue));
+    let similarity = compare_color_moments(&red, &blue);
+    assert!((similarity - (1.0 - color_moments_distance(&red, &blue) / 9.0)).abs() < 1e-9);
+}
+
+#[test]
+fn test_rank_by_color_moments() {
+    let folder = create_temp_folder("rank_by_color_moments");
+    let paths: Vec<String> = [
+        ("red", [255, 0, 0]),
+        ("orange", [200, 60, 0]),
+        ("blue", [0, 0, 255]),
+    ]
+    .iter()
+    .map(|(name, color)| {
+        let path = folder.join(format!("{name}.png"));
+        let path = path.to_str().unwrap().to_string();
+        write_test_picture(&path, 2, 2, *color);
+        path
+    })
+    .collect();
+    let entries: Vec<_> = paths
+        .iter()
+        .map(|path| try_generate_suchindex(path.clone(), &FeatureConfiguration::default()).unwrap())
+        .collect();
+    let mut without_moments = entries[0].clone();
+    without_moments.color_moments = None;
+
+    let weights = FeatureWeights::compact(entries[0].histogram.len());
+    let results = rank_entries_weighted(&entries[0], &entries, &weights, &SearchOptions::top(3));
+    let ranked: Vec<&str> = results
+        .iter()
+        .map(|result| result.search_index().filepath.as_str())
+        .collect();
+    assert_eq!(ranked, vec![&paths[0], &paths[1], &paths[2]]);
+    // the histograms are not compared
+    assert_eq!(results[0].cosine_similarity(), 0.0);
+
+    let descriptor = QueryDescriptor::from_search_index(&entries[0]);
+    assert_eq!(descriptor.color_moments.len(), 9);
+    assert_eq!(descriptor.color_moments_similarity(&without_moments), 0.0);
+    let similarity = descriptor.compare(&without_moments, &weights).similarity();
+    assert!((similarity - 0.5).abs() < 1e-9);
+}
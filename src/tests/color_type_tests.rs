@@ -0,0 +1,177 @@
+use crate::color_type::ColorType;
+use crate::features::FeatureConfiguration;
+use crate::memory_datastore::MemoryDatastore;
+use crate::picture::{AverageBrightness, PictureF32};
+use crate::query::Query;
+use crate::search_index::{determine_avg_brightness, try_generate_suchindex, SearchIndex};
+use crate::tests::test_helpers::{create_temp_folder, write_test_picture};
+use crate::transform::Transform;
+use crate::{read_picture, PictureU8};
+use std::fs::File;
+use std::io::BufWriter;
+
+/// Writes an 8-bit gray PNG with the given values.
+fn write_gray_picture(filepath: &str, width: u32, height: u32, data: &[u8]) {
+    let file = File::create(filepath).unwrap();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(data).unwrap();
+}
+
+#[test]
+fn test_channel_counts() {
+    for count in 1..=6 {
+        assert_eq!(ColorType::from_channel_count(count).channel_count(), count);
+    }
+    assert_eq!(ColorType::from_channel_count(4), ColorType::Rgba);
+    assert_eq!(ColorType::from_channel_count(5), ColorType::Channels(5));
+    assert_eq!(ColorType::Hsv.channel_count(), 3);
+    assert!(ColorType::GrayAlpha.has_alpha());
+    assert!(!ColorType::YCbCr.has_alpha());
+    assert_eq!(ColorType::YCbCr.channel_names(), vec!["Y", "Cb", "Cr"]);
+}
+
+#[test]
+fn test_names_round_trip() {
+    for color_type in [
+        ColorType::Gray,
+        ColorType::GrayAlpha,
+        ColorType::Rgb,
+        ColorType::Rgba,
+        ColorType::Hsv,
+        ColorType::YCbCr,
+        ColorType::Channels(7),
+    ] {
+        assert_eq!(color_type.to_string().parse::<ColorType>(), Ok(color_type));
+    }
+    assert!("cmyk".parse::<ColorType>().is_err());
+}
+
+#[test]
+fn test_convert_between_color_types() {
+    let rgba = PictureU8::new(2, 1, ColorType::Rgba, vec![255, 0, 0, 128, 0, 0, 255, 255]).unwrap();
+
+    let rgb = rgba.to_color_type(ColorType::Rgb).unwrap();
+    assert_eq!(rgb.color_channel_count, 3);
+    assert_eq!(rgb.data, vec![255, 0, 0, 0, 0, 255]);
+
+    let gray = rgb.to_color_type(ColorType::GrayAlpha).unwrap();
+    // 0.3 * 255 and 0.11 * 255, the picture had no alpha channel
+    assert_eq!(gray.data, vec![77, 255, 28, 255]);
+
+    let hsv = rgb.to_color_type(ColorType::Hsv).unwrap();
+    assert_eq!(hsv.data, vec![0, 255, 255, 170, 255, 255]);
+    assert_eq!(hsv.to_color_type(ColorType::Rgb).unwrap(), rgb);
+}
+
+#[test]
+fn test_ycbcr_round_trip() {
+    let rgb = PictureF32::new(1, 2, ColorType::Rgb, vec![0.2, 0.5, 0.9, 1.0, 1.0, 1.0]).unwrap();
+
+    let ycbcr = rgb.to_color_type(ColorType::YCbCr).unwrap();
+    // white has no colour difference
+    assert!((ycbcr.data[3] - 1.0).abs() < 1e-5);
+    assert!((ycbcr.data[4] - 0.5).abs() < 1e-5);
+    assert!((ycbcr.data[5] - 0.5).abs() < 1e-5);
+
+    let back = ycbcr.to_color_type(ColorType::Rgb).unwrap();
+    for (converted, original) in back.data.iter().zip(&rgb.data) {
+        assert!((converted - original).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_unknown_channels_can_not_be_converted() {
+    let rgb = PictureU8::new(1, 1, ColorType::Rgb, vec![1, 2, 3]).unwrap();
+
+    let bgr = rgb.reorder_channels(&[2, 1, 0]).unwrap();
+    assert_eq!(bgr.color_type, ColorType::Channels(3));
+    assert!(bgr.to_color_type(ColorType::Rgb).is_err());
+    assert!(rgb.to_color_type(ColorType::Channels(3)).is_err());
+    assert!(determine_avg_brightness(&bgr).is_nan());
+
+    // a single colour channel has no colour model of its own, dropping the alpha channel keeps it
+    assert_eq!(
+        rgb.extract_channel(0).unwrap().color_type,
+        ColorType::Channels(1)
+    );
+    let rgba = rgb.to_color_type(ColorType::Rgba).unwrap();
+    assert_eq!(
+        rgba.reorder_channels(&[0, 1, 2]).unwrap().color_type,
+        ColorType::Rgb
+    );
+}
+
+#[test]
+fn test_validate_checks_the_color_type() {
+    let mut picture = PictureU8::new(1, 1, ColorType::Rgb, vec![1, 2, 3]).unwrap();
+    picture.color_type = ColorType::Rgba;
+    assert!(picture.validate().is_err());
+}
+
+#[test]
+fn test_brightness_of_gray_pictures() {
+    let gray = PictureU8::new(2, 1, ColorType::GrayAlpha, vec![255, 0, 0, 255]).unwrap();
+    assert_eq!(determine_avg_brightness(&gray), 0.5);
+    assert_eq!(gray.gray_intensity_array(), vec![1.0, 0.0]);
+
+    let hsv = PictureF32::new(1, 1, ColorType::Hsv, vec![0.5, 0.0, 0.25]).unwrap();
+    assert_eq!(determine_avg_brightness(&hsv), 0.25);
+}
+
+#[test]
+fn test_read_gray_picture() {
+    let folder = create_temp_folder("read_gray_picture");
+    let filepath = folder.join("gray.png");
+    let filepath = filepath.to_str().unwrap();
+    write_gray_picture(filepath, 2, 1, &[0, 255]);
+
+    let picture = read_picture(filepath);
+    assert_eq!(picture.color_type, ColorType::Gray);
+    assert_eq!(picture.data, vec![0, 255]);
+
+    let search_index =
+        try_generate_suchindex(filepath.to_string(), &FeatureConfiguration::default()).unwrap();
+    assert_eq!(search_index.color_type, ColorType::Gray);
+    assert_eq!(search_index.average_brightness, 0.5);
+}
+
+#[test]
+fn test_query_by_color_type() {
+    let folder = create_temp_folder("query_by_color_type");
+    let gray_path = folder.join("gray.png");
+    let gray_path = gray_path.to_str().unwrap();
+    write_gray_picture(gray_path, 1, 1, &[90]);
+    let rgb_path = folder.join("rgb.png");
+    let rgb_path = rgb_path.to_str().unwrap();
+    write_test_picture(rgb_path, 1, 1, [90, 90, 90]);
+    let datastore = MemoryDatastore::with_entries(vec![
+        try_generate_suchindex(gray_path.to_string(), &FeatureConfiguration::default()).unwrap(),
+        try_generate_suchindex(rgb_path.to_string(), &FeatureConfiguration::default()).unwrap(),
+    ]);
+
+    let filepaths = |query: Query| -> Vec<String> {
+        query
+            .matching_entries(&datastore)
+            .unwrap()
+            .into_iter()
+            .map(|entry: SearchIndex| entry.filepath)
+            .collect()
+    };
+    assert_eq!(
+        filepaths(Query::new().color_type(ColorType::Gray)),
+        vec![gray_path.to_string()]
+    );
+    assert_eq!(
+        filepaths(
+            Query::new()
+                .color_type(ColorType::Rgb)
+                .color_type(ColorType::Gray)
+        )
+        .len(),
+        2
+    );
+    assert!(filepaths(Query::new().color_type(ColorType::Hsv)).is_empty());
+}
@@ -0,0 +1,143 @@
+use crate::color_type::ColorType;
+use crate::correlogram::{
+    compare_auto_correlograms, compute_auto_correlogram, quantize_colors, CORRELOGRAM_DISTANCES,
+    CORRELOGRAM_LEVELS,
+};
+use crate::features::FeatureConfiguration;
+use crate::query_descriptor::{FeatureWeights, QueryDescriptor, SearchProfile};
+use crate::search::{rank_entries, rank_entries_weighted, SearchOptions};
+use crate::search_index::try_generate_suchindex;
+use crate::tests::test_helpers::{create_temp_folder, write_test_picture_data};
+use crate::transform::Transform;
+use crate::PictureU8;
+
+const RED: [u8; 3] = [255, 0, 0];
+const BLUE: [u8; 3] = [0, 0, 255];
+
+/// Returns the RGB data of a 16 x 16 picture that is red where `is_red` is true and blue elsewhere.
+fn two_colour_data(is_red: impl Fn(u32, u32) -> bool) -> Vec<u8> {
+    let mut data = Vec::new();
+    for y in 0..16 {
+        for x in 0..16 {
+            data.extend(if is_red(x, y) { RED } else { BLUE });
+        }
+    }
+    data
+}
+
+fn two_colour_picture(is_red: impl Fn(u32, u32) -> bool) -> PictureU8 {
+    PictureU8::new(16, 16, ColorType::Rgb, two_colour_data(is_red)).unwrap()
+}
+
+#[test]
+fn test_quantize_colors() {
+    let pic = PictureU8::new(
+        3,
+        1,
+        ColorType::Rgb,
+        vec![255, 255, 255, 0, 0, 0, 100, 200, 30],
+    )
+    .unwrap();
+
+    let colours = quantize_colors(&pic, 4).unwrap();
+    assert_eq!(colours.color_type, ColorType::Channels(1));
+    assert_eq!(colours.data, vec![63, 0, (4 + 3) * 4]);
+    assert!(quantize_colors(&pic, 0).is_err());
+    assert!(quantize_colors(&pic, 7).is_err());
+    assert!(quantize_colors(&pic.reorder_channels(&[2, 1, 0]).unwrap(), 4).is_err());
+}
+
+#[test]
+fn test_correlogram_of_a_single_colour() {
+    let pic = two_colour_picture(|_, _| true);
+
+    let correlogram = compute_auto_correlogram(&pic, 2, &[1, 3]).unwrap();
+    assert_eq!(correlogram.values.len(), 2);
+    assert_eq!(correlogram.values[0].len(), 8);
+    // red is the colour 4 with 2 levels
+    assert_eq!(correlogram.get(4, 1), Some(1.0));
+    assert_eq!(correlogram.get(4, 3), Some(1.0));
+    assert_eq!(correlogram.get(1, 1), Some(0.0));
+    assert_eq!(correlogram.get(4, 2), None);
+    assert!(compute_auto_correlogram(&pic, 2, &[0]).is_err());
+}
+
+#[test]
+fn test_correlogram_sees_the_layout() {
+    let halves = two_colour_picture(|x, _| x < 8);
+    let stripes = two_colour_picture(|x, _| x % 2 == 0);
+
+    let halves = compute_auto_correlogram(&halves, 2, &[1]).unwrap();
+    let stripes = compute_auto_correlogram(&stripes, 2, &[1]).unwrap();
+    // both pictures are half red, but only in one the red pixels are next to each other
+    assert!(halves.get(4, 1).unwrap() > 0.8);
+    assert!(stripes.get(4, 1).unwrap() < 0.5);
+    assert_eq!(compare_auto_correlograms(&halves, &halves), Some(1.0));
+    assert!(compare_auto_correlograms(&halves, &stripes).unwrap() < 0.9);
+
+    let other_distances = compute_auto_correlogram(&two_colour_picture(|_, _| true), 2, &[3]);
+    assert_eq!(
+        compare_auto_correlograms(&halves, &other_distances.unwrap()),
+        None
+    );
+}
+
+#[test]
+fn test_rank_by_correlogram() {
+    let folder = create_temp_folder("rank_by_correlogram");
+    let write = |name: &str, is_red: &dyn Fn(u32, u32) -> bool| {
+        let path = folder.join(format!("{name}.png"));
+        let path = path.to_str().unwrap().to_string();
+        write_test_picture_data(&path, 16, 16, &two_colour_data(is_red));
+        try_generate_suchindex(path, &FeatureConfiguration::default()).unwrap()
+    };
+    let halves = write("halves", &|x, _| x < 8);
+    let stripes = write("stripes", &|x, _| x % 2 == 0);
+    let blocks = write("blocks", &|x, y| (x < 8) == (y < 8));
+    assert_eq!(
+        halves.correlogram.as_ref().unwrap().distances,
+        CORRELOGRAM_DISTANCES.to_vec()
+    );
+    assert_eq!(
+        halves.correlogram.as_ref().unwrap().levels,
+        CORRELOGRAM_LEVELS
+    );
+
+    // all pictures have the same histograms, only the correlogram tells them apart
+    let entries = [stripes.clone(), blocks.clone()];
+    let weights = FeatureWeights::uniform(halves.histogram.len()).with_correlogram(1.0);
+    let results = rank_entries_weighted(&halves, &entries, &weights, &SearchOptions::top(2));
+    assert_eq!(results[0].search_index().filepath, blocks.filepath);
+    assert!(results[0].similarity() > results[1].similarity());
+
+    let descriptor = QueryDescriptor::from_search_index(&halves);
+    let mut without_correlogram = blocks;
+    without_correlogram.correlogram = None;
+    assert_eq!(descriptor.correlogram_similarity(&without_correlogram), 0.0);
+}
+
+#[test]
+fn test_structure_profile_ranks_by_colour_layout() {
+    let folder = create_temp_folder("structure_profile");
+    let write = |name: &str, is_red: &dyn Fn(u32, u32) -> bool| {
+        let path = folder.join(format!("{name}.png"));
+        let path = path.to_str().unwrap().to_string();
+        write_test_picture_data(&path, 16, 16, &two_colour_data(is_red));
+        try_generate_suchindex(path, &FeatureConfiguration::default()).unwrap()
+    };
+    let halves = write("halves", &|x, _| x < 8);
+    // the same colours as the halves, but spread all over the picture
+    let stripes = write("stripes", &|x, _| x % 2 == 0);
+    // less red than the halves, but laid out the same way
+    let narrow_halves = write("narrow_halves", &|x, _| x < 6);
+    let entries = [stripes.clone(), narrow_halves.clone()];
+
+    let by_colour = rank_entries(&halves, &entries, &SearchOptions::top(1));
+    assert_eq!(by_colour[0].search_index().filepath, stripes.filepath);
+    let weights = SearchProfile::Structure.weights(halves.histogram.len());
+    let by_structure = rank_entries_weighted(&halves, &entries, &weights, &SearchOptions::top(1));
+    assert_eq!(
+        by_structure[0].search_index().filepath,
+        narrow_halves.filepath
+    );
+}
@@ -0,0 +1,485 @@
+use crate::binary_datastore::{
+    is_binary_datastore, write_binary_datastore, BinaryDatastore, BinaryFileDatastore,
+};
+use crate::datastore::{
+    backup_path, compact_datastore_at, lock_path, modify_datastore_at, open_datastore,
+    prune_missing_files_at, read_datastore_file, recover_datastore_at, remove_entries_where,
+    remove_where_at, replace_entry, Datastore, DatastoreError, DatastoreLock,
+};
+use crate::features::FeatureConfiguration;
+use crate::json_datastore::JsonDatastore;
+use crate::memory_datastore::MemoryDatastore;
+use crate::search::{search_datastore, SearchOptions};
+use crate::search_index::{generate_suchindex, write_data_to_filepath, SearchIndex};
+use crate::tests::test_helpers::create_temp_folder;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+fn sample_entries() -> Vec<SearchIndex> {
+    vec![
+        SearchIndex::new("pictures/red.png".to_string(), 0.3, vec![]),
+        SearchIndex::new("pictures/blue.png".to_string(), 0.1, vec![]),
+        SearchIndex::new("pictures/red.png".to_string(), 0.3, vec![]),
+        SearchIndex::new("other/green.png".to_string(), 0.6, vec![]),
+    ]
+}
+
+#[test]
+fn test_remove_entries_where() {
+    let mut entries = sample_entries();
+
+    let removed = remove_entries_where(&mut entries, |entry| entry.filepath == "pictures/red.png");
+
+    assert_eq!(removed.len(), 2);
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|entry| entry.filename != "red"));
+}
+
+#[test]
+fn test_replace_entry() {
+    let mut entries = sample_entries();
+
+    let replaced = replace_entry(
+        &mut entries,
+        SearchIndex::new("pictures/red.png".to_string(), 0.9, vec![]),
+    );
+    assert!(replaced);
+    // the duplicate of red.png is gone, the order of the others is kept
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].average_brightness, 0.9);
+    assert_eq!(entries[1].filename, "blue");
+    assert_eq!(entries[2].filename, "green");
+
+    let replaced = replace_entry(
+        &mut entries,
+        SearchIndex::new("pictures/yellow.png".to_string(), 0.9, vec![]),
+    );
+    assert!(!replaced);
+    assert_eq!(entries.len(), 3);
+}
+
+#[test]
+fn test_modify_datastore_at() {
+    let folder = create_temp_folder("modify_datastore");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    write_data_to_filepath(datastore_path, &sample_entries()).unwrap();
+
+    let removed = modify_datastore_at(datastore_path, |entries| {
+        Ok(remove_entries_where(entries, |entry| {
+            entry.filepath.starts_with("pictures/")
+        }))
+    })
+    .unwrap();
+    assert_eq!(removed.len(), 3);
+
+    let stored: Vec<SearchIndex> = read_datastore_file(datastore_path).unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].filename, "green");
+
+    // a failing modification leaves the datastore untouched
+    let result: Result<(), _> = modify_datastore_at(datastore_path, |entries| {
+        entries.clear();
+        Err("modification failed".into())
+    });
+    assert!(result.is_err());
+    let stored: Vec<SearchIndex> = read_datastore_file(datastore_path).unwrap();
+    assert_eq!(stored.len(), 1);
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_datastore_lock() {
+    let folder = create_temp_folder("datastore_lock");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+
+    let lock = DatastoreLock::acquire(datastore_path).unwrap();
+    assert!(fs::metadata(lock_path(datastore_path)).is_ok());
+
+    // a second lock can not be acquired while the first one is held
+    let err =
+        DatastoreLock::acquire_with_timeout(datastore_path, Duration::from_millis(50)).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::Locked { .. })
+    ));
+
+    // the lock file is kept, but the lock is released
+    drop(lock);
+    assert!(fs::metadata(lock_path(datastore_path)).is_ok());
+    assert!(DatastoreLock::acquire_with_timeout(datastore_path, Duration::from_millis(50)).is_ok());
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_left_over_datastore_lock() {
+    let folder = create_temp_folder("left_over_datastore_lock");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    let long_ago = SystemTime::now() - Duration::from_secs(3600);
+
+    // the lock file of a crashed process is not locked anymore
+    fs::write(lock_path(datastore_path), "0").unwrap();
+    let lock =
+        DatastoreLock::acquire_with_timeout(datastore_path, Duration::from_millis(50)).unwrap();
+    assert_eq!(
+        fs::read_to_string(lock_path(datastore_path)).unwrap(),
+        std::process::id().to_string()
+    );
+
+    // a held lock can not be taken over, however old its file is
+    fs::File::options()
+        .write(true)
+        .open(lock_path(datastore_path))
+        .unwrap()
+        .set_modified(long_ago)
+        .unwrap();
+    let err =
+        DatastoreLock::acquire_with_timeout(datastore_path, Duration::from_millis(50)).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::Locked { .. })
+    ));
+
+    drop(lock);
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_datastore_lock_is_exclusive() {
+    let folder = create_temp_folder("exclusive_datastore_lock");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    let holders = AtomicUsize::new(0);
+
+    // concurrent waiters never hold the lock at the same time
+    thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                for _ in 0..5 {
+                    let _lock = DatastoreLock::acquire(datastore_path).unwrap();
+                    assert_eq!(holders.fetch_add(1, Ordering::SeqCst), 0);
+                    thread::sleep(Duration::from_millis(2));
+                    holders.fetch_sub(1, Ordering::SeqCst);
+                }
+            });
+        }
+    });
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_shared_datastore_lock() {
+    let folder = create_temp_folder("shared_datastore_lock");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+
+    // shared locks can be held at the same time, but not together with the exclusive lock
+    let first = DatastoreLock::acquire_shared(datastore_path).unwrap();
+    let second = DatastoreLock::acquire_shared(datastore_path).unwrap();
+    let err =
+        DatastoreLock::acquire_with_timeout(datastore_path, Duration::from_millis(50)).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::Locked { .. })
+    ));
+
+    drop(first);
+    drop(second);
+    assert!(DatastoreLock::acquire_with_timeout(datastore_path, Duration::from_millis(50)).is_ok());
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_read_datastore_file_errors() {
+    let folder = create_temp_folder("read_datastore_file");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+
+    // missing and empty files are empty datastores
+    assert!(read_datastore_file::<SearchIndex>(datastore_path)
+        .unwrap()
+        .is_empty());
+    fs::write(datastore_path, "").unwrap();
+    assert!(read_datastore_file::<SearchIndex>(datastore_path)
+        .unwrap()
+        .is_empty());
+
+    fs::write(datastore_path, "[{\"filepath\": \"a").unwrap();
+    let err = read_datastore_file::<SearchIndex>(datastore_path).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::Corrupted { .. })
+    ));
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_recover_truncated_datastore() {
+    let folder = create_temp_folder("recover_datastore");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+
+    // the second write keeps the first version as backup
+    write_data_to_filepath(datastore_path, &sample_entries()[3..]).unwrap();
+    write_data_to_filepath(datastore_path, &sample_entries()[..2]).unwrap();
+    assert!(fs::metadata(backup_path(datastore_path)).is_ok());
+
+    // cut the datastore off in the middle of the second entry
+    let data = fs::read_to_string(datastore_path).unwrap();
+    let second_entry = data.find("pictures/blue.png").unwrap();
+    fs::write(datastore_path, &data[..second_entry]).unwrap();
+
+    let recovered_count = recover_datastore_at(datastore_path).unwrap();
+    assert_eq!(recovered_count, 2);
+
+    let stored: Vec<SearchIndex> = read_datastore_file(datastore_path).unwrap();
+    assert_eq!(stored[0].filename, "red");
+    assert_eq!(stored[1].filename, "green");
+    assert!(fs::metadata(format!("{datastore_path}.corrupt")).is_ok());
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+/// Checks the behavior that all `Datastore` implementations share.
+fn check_datastore(datastore: &mut dyn Datastore) {
+    assert!(datastore.is_empty().unwrap());
+    datastore.insert_all(sample_entries()).unwrap();
+    // the duplicate of red.png replaces the first one
+    assert_eq!(datastore.len().unwrap(), 3);
+
+    datastore
+        .insert(SearchIndex::new(
+            "pictures/blue.png".to_string(),
+            0.9,
+            vec![],
+        ))
+        .unwrap();
+    let blue = datastore.get("pictures/blue.png").unwrap().unwrap();
+    assert_eq!(blue.average_brightness, 0.9);
+    assert!(datastore.get("pictures/yellow.png").unwrap().is_none());
+
+    assert_eq!(datastore.remove("pictures/blue.png").unwrap(), Some(blue));
+    assert!(datastore.remove("pictures/blue.png").unwrap().is_none());
+    datastore.flush().unwrap();
+
+    let mut filepaths: Vec<String> = datastore
+        .entries()
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.filepath)
+        .collect();
+    filepaths.sort();
+    assert_eq!(filepaths, vec!["other/green.png", "pictures/red.png"]);
+}
+
+#[test]
+fn test_datastore_implementations() {
+    let folder = create_temp_folder("datastore_implementations");
+    let json_path = folder.join("data.json");
+    let binary_path = folder.join("data.imsb");
+
+    check_datastore(&mut MemoryDatastore::new());
+    check_datastore(&mut JsonDatastore::open(json_path.to_str().unwrap()).unwrap());
+    check_datastore(&mut BinaryFileDatastore::open(binary_path.to_str().unwrap()).unwrap());
+    #[cfg(feature = "sqlite")]
+    check_datastore(&mut crate::sqlite_datastore::SqliteDatastore::open_in_memory().unwrap());
+
+    // the flushed changes are stored in the files
+    for path in [&json_path, &binary_path] {
+        let datastore = open_datastore(path.to_str().unwrap()).unwrap();
+        assert_eq!(datastore.len().unwrap(), 2);
+    }
+    assert!(is_binary_datastore(binary_path.to_str().unwrap()));
+}
+
+#[test]
+fn test_flush_keeps_changes_of_others() {
+    let folder = create_temp_folder("flush_keeps_changes");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    write_data_to_filepath(datastore_path, &sample_entries()[..2]).unwrap();
+
+    let mut first = JsonDatastore::open(datastore_path).unwrap();
+    let mut second = JsonDatastore::open(datastore_path).unwrap();
+    first
+        .insert(SearchIndex::new(
+            "pictures/green.png".to_string(),
+            0.6,
+            vec![],
+        ))
+        .unwrap();
+    second.remove("pictures/blue.png").unwrap();
+    first.flush().unwrap();
+    second.flush().unwrap();
+
+    let stored: Vec<SearchIndex> = read_datastore_file(datastore_path).unwrap();
+    let filepaths: Vec<&str> = stored.iter().map(|entry| entry.filepath.as_str()).collect();
+    assert_eq!(filepaths, vec!["pictures/red.png", "pictures/green.png"]);
+}
+
+/// Removes and prunes entries of the datastore at the given path, which must be empty.
+fn check_remove_and_prune(datastore_path: &str) {
+    let existing = SearchIndex::new(
+        "src/tests/files/pictures_for_testing/bird.png".to_string(),
+        0.4,
+        vec![],
+    );
+    let missing = SearchIndex::new("pictures/missing.png".to_string(), 0.3, vec![]);
+    let other = SearchIndex::new("pictures/other.png".to_string(), 0.2, vec![]);
+    let mut datastore = open_datastore(datastore_path).unwrap();
+    datastore
+        .insert_all(vec![existing.clone(), missing.clone(), other.clone()])
+        .unwrap();
+    datastore.flush().unwrap();
+    drop(datastore);
+
+    let removed = remove_where_at(datastore_path, |entry| entry.filepath == other.filepath);
+    assert_eq!(removed.unwrap(), vec![other]);
+    assert_eq!(
+        prune_missing_files_at(datastore_path).unwrap(),
+        vec![missing]
+    );
+    assert_eq!(
+        open_datastore(datastore_path).unwrap().entries().unwrap(),
+        vec![existing]
+    );
+}
+
+#[test]
+fn test_remove_and_prune_json_datastore() {
+    let folder = create_temp_folder("remove_and_prune_json");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+
+    check_remove_and_prune(datastore_path);
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_remove_and_prune_binary_datastore() {
+    let folder = create_temp_folder("remove_and_prune_binary");
+    let datastore_path = folder.join("data.imsb");
+    let datastore_path = datastore_path.to_str().unwrap();
+    write_binary_datastore(datastore_path, &[], &FeatureConfiguration::default()).unwrap();
+
+    check_remove_and_prune(datastore_path);
+    // the changes are written in the binary format
+    assert!(is_binary_datastore(datastore_path));
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_remove_and_prune_sqlite_datastore() {
+    use crate::sqlite_datastore::is_sqlite_datastore;
+
+    let folder = create_temp_folder("remove_and_prune_sqlite");
+    let datastore_path = folder.join("data.db");
+    let datastore_path = datastore_path.to_str().unwrap();
+
+    check_remove_and_prune(datastore_path);
+    assert!(is_sqlite_datastore(datastore_path));
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+fn check_compact_datastore(datastore_path: &str) {
+    let picture =
+        |name: &str| generate_suchindex(format!("src/tests/files/pictures_for_testing/{name}.png"));
+    let bird = picture("bird");
+    let flower = picture("flower_purple_1");
+    let mut datastore = open_datastore(datastore_path).unwrap();
+    datastore.insert(bird.clone()).unwrap();
+    datastore.flush().unwrap();
+    drop(datastore);
+
+    assert_eq!(compact_datastore_at(datastore_path).unwrap(), 1);
+    // entries stored later are compact as well
+    let mut datastore = open_datastore(datastore_path).unwrap();
+    datastore.insert(flower.clone()).unwrap();
+    datastore.flush().unwrap();
+    drop(datastore);
+
+    let datastore = open_datastore(datastore_path).unwrap();
+    assert_eq!(datastore.features(), FeatureConfiguration::compact());
+    let entries = datastore.entries().unwrap();
+    assert_eq!(entries.len(), 2);
+    for (entry, original) in entries.iter().zip([&bird, &flower]) {
+        assert!(entry.histogram.is_empty());
+        assert!(entry.grid_cells.is_empty());
+        assert!(entry.statistics.is_none() && entry.correlogram.is_none() && entry.shape.is_none());
+        assert_eq!(entry.color_moments, original.color_moments);
+        assert_eq!(entry.average_brightness, original.average_brightness);
+    }
+    // compact datastores are searched by the brightness and the colour moments
+    let results = search_datastore(datastore.as_ref(), &bird, &SearchOptions::top(2)).unwrap();
+    assert_eq!(results[0].search_index().filepath, bird.filepath);
+    assert!((results[0].similarity() - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_compact_json_datastore() {
+    let folder = create_temp_folder("compact_json");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+
+    check_compact_datastore(datastore_path);
+    let stored = fs::read_to_string(datastore_path).unwrap();
+    assert!(stored.contains("\"descriptors\": \"compact\""));
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_compact_binary_datastore() {
+    let folder = create_temp_folder("compact_binary");
+    let datastore_path = folder.join("data.imsb");
+    let datastore_path = datastore_path.to_str().unwrap();
+    write_binary_datastore(datastore_path, &[], &FeatureConfiguration::default()).unwrap();
+
+    check_compact_datastore(datastore_path);
+    let header = BinaryDatastore::open(datastore_path)
+        .unwrap()
+        .header()
+        .clone();
+    assert_eq!(header.features, FeatureConfiguration::compact());
+    // the records hold no histograms, and there are no sections for the removed descriptors
+    assert_eq!(header.record_size, 64);
+    assert_eq!(header.sections.grid_cells.count, 0);
+    assert_eq!(header.sections.statistics.count, 0);
+    assert_eq!(header.sections.correlogram.count, 0);
+    assert_eq!(header.sections.shape.count, 0);
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_compact_sqlite_datastore_fails() {
+    use crate::sqlite_datastore::convert_json_to_sqlite;
+    use crate::tests::test_helpers::DATASTORE_FIXTURE;
+
+    let folder = create_temp_folder("compact_sqlite");
+    let datastore_path = folder.join("data.db");
+    let datastore_path = datastore_path.to_str().unwrap();
+    assert!(compact_datastore_at(datastore_path).is_err());
+
+    let json_path = folder.join("data.json");
+    let json_path = json_path.to_str().unwrap();
+    fs::copy(DATASTORE_FIXTURE, json_path).unwrap();
+    compact_datastore_at(json_path).unwrap();
+    assert!(convert_json_to_sqlite(json_path, datastore_path).is_err());
+
+    fs::remove_dir_all(folder).unwrap();
+}
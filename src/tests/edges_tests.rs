@@ -0,0 +1,79 @@
+use crate::color_type::ColorType;
+use crate::edges::{compute_gradients, CANNY_THRESHOLDS};
+use crate::transform::Transform;
+use crate::PictureU8;
+use std::f32::consts::FRAC_PI_2;
+
+/// A black 16 x 16 gray picture with a white 8 x 8 square in the middle.
+fn square() -> PictureU8 {
+    let mut data = vec![0; 16 * 16];
+    for y in 4..12 {
+        data[y * 16 + 4..y * 16 + 12].fill(255);
+    }
+    PictureU8::new(16, 16, ColorType::Gray, data).unwrap()
+}
+
+#[test]
+fn test_gradient_directions() {
+    let pic = square();
+    let gradients = compute_gradients(&pic).unwrap();
+
+    // the brightness increases into the square
+    let left_border = 8 * 16 + 4;
+    assert_eq!(gradients.direction[left_border], 0.0);
+    let top_border = 4 * 16 + 8;
+    assert!((gradients.direction[top_border] - FRAC_PI_2).abs() < 1e-6);
+    // inside and far outside the square nothing changes
+    assert_eq!(gradients.magnitude[8 * 16 + 8], 0.0);
+    assert_eq!(gradients.magnitude[0], 0.0);
+    assert!(gradients
+        .magnitude
+        .iter()
+        .all(|magnitude| *magnitude <= 1.0));
+}
+
+#[test]
+fn test_sobel_edges() {
+    let edges = square().sobel_edges().unwrap();
+
+    assert_eq!(edges.color_type, ColorType::Gray);
+    assert_eq!((edges.columns, edges.lines), (16, 16));
+    assert!(edges.get_pixel(4, 8).unwrap()[0] > 0.5);
+    assert_eq!(edges.get_pixel(8, 8), Some(&[0.0][..]));
+}
+
+#[test]
+fn test_canny_edges_are_thin_outlines() {
+    let (low, high) = CANNY_THRESHOLDS;
+    let edges = square().canny_edges(low, high).unwrap();
+
+    let edge_count = edges.data.iter().filter(|edge| **edge == 255).count();
+    assert!(edges.data.iter().all(|edge| *edge == 0 || *edge == 255));
+    // about one line of pixels around the square
+    assert!((24..=40).contains(&edge_count), "{edge_count} edge pixels");
+    for y in 0..16 {
+        let row = &edges.data[y * 16..(y + 1) * 16];
+        assert!(row.iter().filter(|edge| **edge == 255).count() <= 8);
+    }
+    assert_eq!(edges.get_pixel(8, 8), Some(&[0][..]));
+    assert_eq!(edges.get_pixel(0, 0), Some(&[0][..]));
+}
+
+#[test]
+fn test_canny_edges_of_a_plain_picture() {
+    let pic = PictureU8::new(8, 8, ColorType::Rgb, [200, 30, 30].repeat(64)).unwrap();
+
+    let edges = pic.canny_edges(0.1, 0.2).unwrap();
+    assert!(edges.data.iter().all(|edge| *edge == 0));
+}
+
+#[test]
+fn test_canny_edges_errors() {
+    let pic = square();
+    assert!(pic.canny_edges(0.5, 0.1).is_err());
+
+    let rgb = PictureU8::new(1, 1, ColorType::Rgb, vec![1, 2, 3]).unwrap();
+    let channels = rgb.reorder_channels(&[2, 1, 0]).unwrap();
+    assert!(channels.canny_edges(0.1, 0.2).is_err());
+    assert!(compute_gradients(&channels).is_err());
+}
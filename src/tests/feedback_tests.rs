@@ -0,0 +1,130 @@
+use crate::compare_pictures::SimilarityInformation;
+use crate::feedback::{Feedback, FeedbackSession};
+use crate::memory_datastore::MemoryDatastore;
+use crate::query_descriptor::FeatureWeights;
+use crate::search::{rank_entries, SearchOptions};
+use crate::tests::test_helpers::random_entries;
+use crate::user_input::parse_feedback;
+
+fn filepaths(results: &[SimilarityInformation]) -> Vec<String> {
+    results
+        .iter()
+        .map(|result| result.search_index().filepath.clone())
+        .collect()
+}
+
+fn similarity_of(results: &[SimilarityInformation], filepath: &str) -> f64 {
+    results
+        .iter()
+        .find(|result| result.search_index().filepath == filepath)
+        .unwrap()
+        .similarity()
+}
+
+#[test]
+fn test_session_without_feedback_matches_search() {
+    let entries = random_entries(120, 38);
+    let query = random_entries(1, 7).remove(0);
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+    let options = SearchOptions::top(10);
+
+    let mut session = FeedbackSession::new(&query);
+    let results = session.search(&datastore, &options).unwrap().to_vec();
+
+    assert_eq!(
+        filepaths(&results),
+        filepaths(&rank_entries(&query, &entries, &options))
+    );
+    assert_eq!(*session.weights(), FeatureWeights::uniform(3));
+}
+
+#[test]
+fn test_relevant_feedback_moves_the_query() {
+    let entries = random_entries(120, 39);
+    let query = random_entries(1, 2).remove(0);
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+    let options = SearchOptions::top(entries.len());
+
+    let mut session = FeedbackSession::new(&query);
+    let results = session.search(&datastore, &options).unwrap().to_vec();
+    // a result far down the ranking is marked as relevant
+    let relevant = results[60].clone();
+    session.add_feedback(&relevant, Feedback::Relevant);
+    let refined = session.search(&datastore, &options).unwrap().to_vec();
+
+    let filepath = &relevant.search_index().filepath;
+    assert!(similarity_of(&refined, filepath) > similarity_of(&results, filepath));
+    let rank = |results: &[SimilarityInformation]| {
+        filepaths(results)
+            .iter()
+            .position(|found| found == filepath)
+            .unwrap()
+    };
+    assert!(rank(&refined) < rank(&results));
+}
+
+#[test]
+fn test_irrelevant_results_are_left_out() {
+    let entries = random_entries(50, 40);
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+    let options = SearchOptions::top(5);
+
+    let mut session = FeedbackSession::new(&entries[0]);
+    let results = session.search(&datastore, &options).unwrap().to_vec();
+    session.add_feedback(&results[1], Feedback::Irrelevant);
+    let refined = session.search(&datastore, &options).unwrap().to_vec();
+
+    assert_eq!(refined.len(), 5);
+    assert!(!filepaths(&refined).contains(&results[1].search_index().filepath));
+
+    // marking it as relevant again brings it back
+    session.add_feedback(&results[1], Feedback::Relevant);
+    let refined = session.search(&datastore, &options).unwrap().to_vec();
+    assert!(filepaths(&refined).contains(&results[1].search_index().filepath));
+}
+
+#[test]
+fn test_weights_favour_features_the_relevant_results_agree_on() {
+    let mut entries = random_entries(30, 41);
+    // the relevant pictures have the same brightness as the query, but different colors
+    for entry in &mut entries[..3] {
+        entry.average_brightness = 0.5;
+    }
+    for histogram in &mut entries[1].histogram {
+        histogram.bins = vec![1000, 1, 1, 1, 1];
+    }
+    for histogram in &mut entries[2].histogram {
+        histogram.bins = vec![1, 1, 1, 1, 1000];
+    }
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+    let mut session = FeedbackSession::new(&entries[0]);
+    let results = session
+        .search(&datastore, &SearchOptions::top(entries.len()))
+        .unwrap()
+        .to_vec();
+
+    for filepath in ["pictures/1.png", "pictures/2.png"] {
+        let result = results
+            .iter()
+            .find(|result| result.search_index().filepath == filepath)
+            .unwrap();
+        session.add_feedback(result, Feedback::Relevant);
+    }
+
+    let weights = session.weights();
+    let uniform = FeatureWeights::uniform(3);
+    assert!(weights.brightness > uniform.brightness);
+    assert!((weights.total() - uniform.total()).abs() < 1e-9);
+}
+
+#[test]
+fn test_parse_feedback() {
+    assert_eq!(
+        parse_feedback(" +2  -1 ", 3),
+        Ok(vec![(1, Feedback::Relevant), (0, Feedback::Irrelevant)])
+    );
+    assert_eq!(parse_feedback("", 3), Ok(Vec::new()));
+    assert!(parse_feedback("2", 3).is_err());
+    assert!(parse_feedback("+0", 3).is_err());
+    assert!(parse_feedback("-x", 3).is_err());
+}
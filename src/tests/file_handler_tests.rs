@@ -1,4 +1,4 @@
-use crate::file_handler::extract_filename;
+use crate::file_handler::{compute_file_hash, extract_filename};
 
 /// This tests the functionality the extract_filename function.
 #[test]
@@ -14,3 +14,14 @@ fn test_extract_filename() {
     assert_eq!(result4, "bird");
     assert_eq!(result5, "bird");
 }
+
+#[test]
+fn test_compute_file_hash() {
+    let hash1 = compute_file_hash("src/tests/files/pictures_for_testing/flower_purple_1.png");
+    let hash2 = compute_file_hash("src/tests/files/pictures_for_testing/flower_purple_1.png");
+    let hash3 =
+        compute_file_hash("src/tests/files/pictures_for_testing/flower_purple_1_modified.png");
+    assert_eq!(hash1.as_ref().unwrap(), hash2.as_ref().unwrap());
+    assert_ne!(hash1.unwrap(), hash3.unwrap());
+    assert!(compute_file_hash("src/tests/files/does_not_exist.png").is_err());
+}
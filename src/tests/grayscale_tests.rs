@@ -0,0 +1,134 @@
+use crate::color_type::ColorType;
+use crate::features::FeatureConfiguration;
+use crate::grayscale::{average_luma, srgb_to_linear, LumaFormula};
+use crate::memory_datastore::MemoryDatastore;
+use crate::picture::{PictureF32, PictureU16};
+use crate::query::Query;
+use crate::search_index::{determine_avg_brightness, try_generate_suchindex};
+use crate::tests::test_helpers::{create_temp_folder, write_test_picture};
+use crate::transform::Transform;
+use crate::PictureU8;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-4,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn test_luma_of_primary_colours() {
+    assert_close(LumaFormula::Legacy.luma(1.0, 0.0, 0.0), 0.3);
+    assert_close(LumaFormula::Rec601.luma(0.0, 1.0, 0.0), 0.587);
+    assert_close(LumaFormula::Rec709.luma(0.0, 0.0, 1.0), 0.0722);
+    // primaries are fully encoded, so their luminance is the weight
+    assert_close(LumaFormula::RelativeLuminance.luma(0.0, 1.0, 0.0), 0.7152);
+    // L* of the sRGB primaries
+    assert_close(LumaFormula::Lightness.luma(1.0, 0.0, 0.0), 0.532_408);
+    assert_close(LumaFormula::Lightness.luma(0.0, 0.0, 1.0), 0.322_970);
+}
+
+#[test]
+fn test_luma_of_gray() {
+    for formula in LumaFormula::ALL {
+        assert_close(formula.luma(0.0, 0.0, 0.0), 0.0);
+        assert_close(formula.luma(1.0, 1.0, 1.0), 1.0);
+    }
+    assert_close(LumaFormula::Rec709.luma(0.5, 0.5, 0.5), 0.5);
+    assert_close(
+        LumaFormula::RelativeLuminance.luma(0.5, 0.5, 0.5),
+        0.214_041,
+    );
+    // middle gray of L*a*b* has 18.4% luminance
+    assert_close(
+        LumaFormula::Lightness.luma(0.466_33, 0.466_33, 0.466_33),
+        0.5,
+    );
+}
+
+#[test]
+fn test_srgb_to_linear() {
+    assert_eq!(srgb_to_linear(0.0), 0.0);
+    assert_close(srgb_to_linear(0.04), 0.04 / 12.92);
+    assert_close(srgb_to_linear(1.0), 1.0);
+}
+
+#[test]
+fn test_names_round_trip() {
+    for formula in LumaFormula::ALL {
+        assert_eq!(formula.to_string().parse::<LumaFormula>(), Ok(formula));
+    }
+    assert!("rec2020".parse::<LumaFormula>().is_err());
+}
+
+#[test]
+fn test_to_grayscale() {
+    let pic = PictureU16::new(
+        2,
+        1,
+        ColorType::Rgba,
+        vec![0, u16::MAX, 0, 0, u16::MAX, u16::MAX, u16::MAX, u16::MAX],
+    )
+    .unwrap();
+
+    let gray = pic.to_grayscale(LumaFormula::Rec709).unwrap();
+    assert_eq!(gray.color_type, ColorType::Gray);
+    assert_eq!((gray.columns, gray.lines), (2, 1));
+    assert_close(gray.data[0], 0.7152);
+    assert_close(gray.data[1], 1.0);
+
+    // views are converted without copying the picture first
+    let right = PictureU8::new(2, 1, ColorType::GrayAlpha, vec![10, 255, 200, 0]).unwrap();
+    let view = right.view();
+    let gray = view.to_grayscale(LumaFormula::Legacy).unwrap();
+    assert_eq!(gray.data, vec![10.0 / 255.0, 200.0 / 255.0]);
+
+    let channels = right.reorder_channels(&[1, 0]).unwrap();
+    assert!(channels.to_grayscale(LumaFormula::Legacy).is_err());
+}
+
+#[test]
+fn test_average_luma() {
+    let pic = PictureF32::new(2, 1, ColorType::Rgb, vec![0.0, 0.0, 1.0, 0.0, 1.0, 0.0]).unwrap();
+
+    assert_eq!(
+        average_luma(&pic, LumaFormula::Legacy),
+        determine_avg_brightness(&pic)
+    );
+    // L* of pure blue and pure green
+    let lightness = average_luma(&pic, LumaFormula::Lightness);
+    assert_close(lightness, (0.322_970 + 0.877_347) / 2.0);
+    assert!(average_luma(
+        &pic.reorder_channels(&[2, 1, 0]).unwrap(),
+        LumaFormula::Rec601
+    )
+    .is_nan());
+}
+
+#[test]
+fn test_query_by_lightness() {
+    let folder = create_temp_folder("query_by_lightness");
+    let blue = folder.join("blue.png");
+    let blue = blue.to_str().unwrap();
+    write_test_picture(blue, 2, 2, [0, 0, 255]);
+    let green = folder.join("green.png");
+    let green = green.to_str().unwrap();
+    write_test_picture(green, 2, 2, [0, 255, 0]);
+    let mut unknown =
+        try_generate_suchindex(green.to_string(), &FeatureConfiguration::default()).unwrap();
+    unknown.filepath = "pictures/unknown.png".to_string();
+    unknown.lightness = None;
+    let datastore = MemoryDatastore::with_entries(vec![
+        try_generate_suchindex(blue.to_string(), &FeatureConfiguration::default()).unwrap(),
+        try_generate_suchindex(green.to_string(), &FeatureConfiguration::default()).unwrap(),
+        unknown,
+    ]);
+
+    let light = Query::new()
+        .lightness(0.5..=1.0)
+        .matching_entries(&datastore)
+        .unwrap();
+    assert_eq!(light.len(), 1);
+    assert_eq!(light[0].filepath, green);
+    assert_eq!(Query::new().matching_entries(&datastore).unwrap().len(), 3);
+}
@@ -1,3 +1,4 @@
+use crate::color_type::ColorType;
 use crate::picture::PictureF32;
 use crate::{get_histogram, Histogram, PictureU8};
 
@@ -23,6 +24,7 @@ fn test_get_histogram() {
         columns: 3,
         data: vec![0, 255, 25, 99], // Sample image data
         color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
     };
     // Create a sample PictureF32
     let picture_f32 = PictureF32 {
@@ -30,6 +32,7 @@ fn test_get_histogram() {
         columns: 3,
         data: vec![0.0, 1.0, 0.1, 0.38], // Sample image data
         color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
     };
 
     let histograms_u8 = get_histogram(&picture_u8);
@@ -1,56 +1,40 @@
-use crate::picture::PictureF32;
-use crate::{get_histogram, Histogram, Picture, PictureU8};
+use crate::color_type::ColorType;
+use crate::picture::{Picture, PictureU32, Sample};
+use crate::{get_histogram, Histogram};
 
-#[test]
-fn test_creating_new_data_type() {
-    pub struct PictureU32 {
-        pub lines: u32,
-        //height
-        pub columns: u32,
-        //width
-        pub color_channel_count: usize,
-        pub data: Vec<u32>, //different data type: u32
-    }
-    impl Picture for PictureU32 {
-        fn to_picture_u8(&self) -> PictureU8 {
-            let mut new_data = Vec::<u8>::new();
+/// A sample of a camera with 12 bits per value, stored in a `u16`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+struct Sample12(u16);
 
-            //convert each value from [0, u32::MAX] to [0, 255]
-            for i in 0..self.data.len() {
-                //
-                let new_value_in_f32 = ((self.data[i] as f32) / (u32::MAX as f32)) * 255.0;
-                new_data.push(new_value_in_f32 as u8);
-            }
+impl std::fmt::Display for Sample12 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
-            PictureU8 {
-                lines: self.lines,
-                columns: self.columns,
-                color_channel_count: self.color_channel_count,
-                data: new_data,
-            }
-        }
+impl Sample for Sample12 {
+    const MAX_VALUE: Self = Sample12(4095);
 
-        fn to_picture_f32(&self) -> PictureF32 {
-            let mut new_data = Vec::<f32>::new();
+    fn to_f32(self) -> f32 {
+        f32::from(self.0)
+    }
 
-            //convert each value from [0, u32::MAX] to [0.0, 1.0]
-            for i in 0..self.data.len() {
-                new_data.push((self.data[i] as f32) / (u32::MAX as f32));
-            }
+    fn from_f32(value: f32) -> Self {
+        Sample12(value.round().clamp(0.0, 4095.0) as u16)
+    }
 
-            PictureF32 {
-                lines: self.lines,
-                columns: self.columns,
-                color_channel_count: self.color_channel_count,
-                data: new_data,
-            }
-        }
+    fn from_normalized(value: f32) -> Self {
+        Sample12((value * 4095.0).clamp(0.0, 4095.0) as u16)
     }
+}
 
+#[test]
+fn test_creating_new_data_type() {
     let pic_u32 = PictureU32 {
         lines: 1,
         columns: 3,
         color_channel_count: 3,
+        color_type: ColorType::Rgb,
         data: vec![
             123_456,
             128,
@@ -80,3 +64,28 @@ fn test_creating_new_data_type() {
     // of the Picture-typed parameter
     assert_eq!(expected_result, get_histogram(&pic_u32));
 }
+
+#[test]
+fn test_custom_sample_type() {
+    let pic: Picture<Sample12> = Picture {
+        lines: 1,
+        columns: 2,
+        color_channel_count: 3,
+        color_type: ColorType::Rgb,
+        data: [Sample12(0), Sample12(2048), Sample12(4095)].repeat(2),
+    };
+    let expected_result = vec![
+        Histogram {
+            bins: vec![2, 0, 0, 0, 0],
+        },
+        Histogram {
+            bins: vec![0, 0, 2, 0, 0],
+        },
+        Histogram {
+            bins: vec![0, 0, 0, 0, 2],
+        },
+    ];
+    assert_eq!(get_histogram(&pic), expected_result);
+    assert_eq!(pic.to_picture_u8().data[..3], [0, 127, 255]);
+    assert_eq!(pic.to_picture_f32().convert::<Sample12>(), pic);
+}
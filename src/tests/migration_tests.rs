@@ -0,0 +1,371 @@
+use crate::binary_datastore::{
+    encode_binary_datastore, migrate_binary_datastore, BinaryDatastore, BinaryFileDatastore,
+};
+use crate::color_type::ColorType;
+use crate::datastore::{migrate_datastore_at, read_datastore_file, DatastoreError};
+use crate::features::FeatureConfiguration;
+use crate::migration::{migrate_entries, LEGACY_FEATURES, SCHEMA_VERSION};
+use crate::search_index::{write_data_to_filepath, SearchIndex};
+use crate::tests::test_helpers::create_temp_folder;
+use serde_json::{json, Value};
+use std::fs;
+
+const LEGACY_DATASTORE_FILEPATH: &str = "src/tests/files/DataStoreJSON/data_v1.json";
+
+fn copy_legacy_datastore(name: &str) -> String {
+    let folder = create_temp_folder(name);
+    let datastore_path = folder.join("data.json");
+    fs::copy(LEGACY_DATASTORE_FILEPATH, &datastore_path).unwrap();
+    datastore_path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_migrate_entries_adds_content_hashes() {
+    let entries = vec![
+        json!({
+            "filepath": "src/tests/files/pictures_for_testing/bird.png",
+            "filename": "bird",
+            "average_brightness": 0.5,
+            "histogram": []
+        }),
+        json!({
+            "filepath": "pictures/missing.png",
+            "filename": "missing",
+            "average_brightness": 0.5,
+            "histogram": []
+        }),
+    ];
+
+    let (migrated, summary) =
+        migrate_entries(entries, 1, &FeatureConfiguration::default()).unwrap();
+
+    assert_eq!(migrated.len(), 2);
+    assert_eq!(summary.previous_version, 1);
+    assert_eq!(summary.reindexed, 1);
+    // the existing picture is indexed again, the missing one is kept as it is
+    assert_eq!(migrated[0]["content_hash"].as_str().unwrap().len(), 16);
+    assert_ne!(migrated[0]["histogram"], json!([]));
+    assert_eq!(migrated[1]["content_hash"], json!(""));
+    assert_eq!(migrated[1]["average_brightness"], json!(0.5));
+}
+
+#[test]
+fn test_migrate_entries_adds_picture_metadata() {
+    let entries = vec![
+        json!({
+            "filepath": "src/tests/files/pictures_for_testing/bird.png",
+            "filename": "bird",
+            "average_brightness": 0.5,
+            "histogram": [],
+            "content_hash": "",
+            "tags": ["animal"]
+        }),
+        json!({
+            "filepath": "pictures/missing.png",
+            "filename": "missing",
+            "average_brightness": 0.5,
+            "histogram": [],
+            "content_hash": ""
+        }),
+    ];
+
+    let (migrated, _) = migrate_entries(entries, 2, &FeatureConfiguration::default()).unwrap();
+    let migrated: Vec<SearchIndex> = migrated
+        .into_iter()
+        .map(|entry| serde_json::from_value(entry).unwrap())
+        .collect();
+
+    assert!(migrated[0].lines > 0 && migrated[0].columns > 0);
+    assert!(migrated[0].file_size > 0);
+    assert_eq!(migrated[0].tags, vec!["animal".to_string()]);
+    assert_eq!(migrated[1].lines, 0);
+    assert_eq!(migrated[1].file_size, 0);
+}
+
+#[test]
+fn test_migrate_entries_adds_color_types() {
+    let entries = vec![
+        json!({
+            "filepath": "src/tests/files/pictures_for_testing/bird.png",
+            "filename": "bird",
+            "average_brightness": 0.5,
+            "histogram": [],
+            "content_hash": ""
+        }),
+        json!({
+            "filepath": "pictures/missing.png",
+            "filename": "missing",
+            "average_brightness": 0.5,
+            "histogram": [{"bins": [1, 2]}],
+            "content_hash": ""
+        }),
+    ];
+
+    let (migrated, _) = migrate_entries(entries, 4, &FeatureConfiguration::default()).unwrap();
+    let migrated: Vec<SearchIndex> = migrated
+        .into_iter()
+        .map(|entry| serde_json::from_value(entry).unwrap())
+        .collect();
+
+    assert_eq!(migrated[0].color_type, ColorType::Rgba);
+    // the colour type of a missing picture is guessed from its histograms
+    assert_eq!(migrated[1].color_type, ColorType::Gray);
+}
+
+#[test]
+fn test_migrate_entries_adds_lightness() {
+    let entries = vec![
+        json!({
+            "filepath": "src/tests/files/pictures_for_testing/bird.png",
+            "filename": "bird",
+            "average_brightness": 0.5,
+            "histogram": [],
+            "color_type": "Rgba"
+        }),
+        json!({
+            "filepath": "pictures/missing.png",
+            "filename": "missing",
+            "average_brightness": 0.5,
+            "histogram": [],
+            "color_type": "Rgba"
+        }),
+    ];
+
+    let (migrated, _) = migrate_entries(entries, 5, &FeatureConfiguration::default()).unwrap();
+    let migrated: Vec<SearchIndex> = migrated
+        .into_iter()
+        .map(|entry| serde_json::from_value(entry).unwrap())
+        .collect();
+
+    assert!(migrated[0]
+        .lightness
+        .is_some_and(|lightness| (0.0..=1.0).contains(&lightness)));
+    assert_eq!(migrated[1].lightness, None);
+}
+
+#[test]
+fn test_migrate_entries_computes_new_descriptors() {
+    let entries = vec![
+        json!({
+            "filepath": "src/tests/files/pictures_for_testing/bird.png",
+            "filename": "bird",
+            "average_brightness": 0.5,
+            "histogram": [],
+            "color_type": "Rgba"
+        }),
+        json!({
+            "filepath": "pictures/missing.png",
+            "filename": "missing",
+            "average_brightness": 0.5,
+            "histogram": [],
+            "color_type": "Rgba"
+        }),
+    ];
+    let outdated_features = FeatureConfiguration {
+        descriptor_version: 0,
+        ..FeatureConfiguration::default()
+    };
+
+    let (migrated, _) = migrate_entries(entries, SCHEMA_VERSION, &outdated_features).unwrap();
+    let migrated: Vec<SearchIndex> = migrated
+        .into_iter()
+        .map(|entry| serde_json::from_value(entry).unwrap())
+        .collect();
+
+    // the existing picture gets all descriptors of the current descriptor version
+    let statistics = migrated[0].statistics.as_ref().unwrap();
+    assert_eq!(statistics.channels.len(), 4);
+    assert!(migrated[0].color_moments.is_some());
+    assert!(migrated[0].correlogram.is_some());
+    assert!(migrated[0]
+        .shape
+        .as_ref()
+        .is_some_and(|shape| shape.edge_density > 0.0));
+    // the missing picture keeps its histograms, which can still be compared, but has none of them
+    assert_eq!(migrated.len(), 2);
+    assert_eq!(migrated[1].statistics, None);
+    assert_eq!(migrated[1].color_moments, None);
+    assert_eq!(migrated[1].correlogram, None);
+    assert_eq!(migrated[1].shape, None);
+}
+
+#[test]
+fn test_migrate_entries_with_other_features() {
+    let features = FeatureConfiguration {
+        bin_count: 8,
+        color_channels: 3,
+        ..FeatureConfiguration::default()
+    };
+    let entries = vec![
+        json!({"filepath": "src/tests/files/pictures_for_testing/bird.png", "content_hash": ""}),
+        json!({"filepath": "pictures/missing.png", "content_hash": ""}),
+    ];
+
+    let (migrated, summary) = migrate_entries(entries, SCHEMA_VERSION, &features).unwrap();
+
+    // descriptors of other features can not be compared, so missing pictures are dropped
+    assert_eq!(migrated.len(), 1);
+    assert_eq!(migrated[0]["filename"], json!("bird"));
+    assert_eq!(summary.dropped.len(), 1);
+    assert_eq!(summary.dropped[0].0, "pictures/missing.png");
+}
+
+#[test]
+fn test_migrate_entries_keeps_unreadable_pictures() {
+    let folder = create_temp_folder("migrate_unreadable_pictures");
+    let broken_picture = folder.join("broken.png");
+    fs::write(&broken_picture, b"not a png").unwrap();
+    let entries = vec![
+        json!({
+            "filepath": broken_picture.to_str().unwrap(),
+            "filename": "broken",
+            "average_brightness": 0.5,
+            "histogram": []
+        }),
+        json!({
+            "filepath": "src/tests/files/pictures_for_testing/bird.png",
+            "filename": "bird",
+            "average_brightness": 0.5,
+            "histogram": []
+        }),
+    ];
+
+    let (migrated, summary) =
+        migrate_entries(entries, 1, &FeatureConfiguration::default()).unwrap();
+
+    // the broken picture keeps its old entry instead of failing the whole migration
+    assert_eq!(migrated.len(), 2);
+    assert_eq!(summary.skipped.len(), 1);
+    assert_eq!(summary.skipped[0].0, broken_picture.to_str().unwrap());
+    assert!(summary.dropped.is_empty());
+    assert_eq!(migrated[0]["content_hash"], json!(""));
+    assert_eq!(migrated[0]["average_brightness"], json!(0.5));
+    assert_eq!(migrated[1]["content_hash"].as_str().unwrap().len(), 16);
+
+    // with other features its descriptors can not be compared, so it is dropped
+    let features = FeatureConfiguration {
+        bin_count: 8,
+        color_channels: 3,
+        ..FeatureConfiguration::default()
+    };
+    let entries = vec![json!({"filepath": broken_picture.to_str().unwrap(), "content_hash": ""})];
+    let (migrated, summary) = migrate_entries(entries, SCHEMA_VERSION, &features).unwrap();
+    assert!(migrated.is_empty());
+    assert_eq!(summary.dropped.len(), 1);
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_migrate_entries_unknown_version() {
+    assert!(migrate_entries(Vec::new(), 0, &LEGACY_FEATURES).is_err());
+    assert!(migrate_entries(Vec::<Value>::new(), SCHEMA_VERSION + 1, &LEGACY_FEATURES).is_err());
+}
+
+#[test]
+fn test_read_legacy_datastore_needs_migration() {
+    let datastore_path = copy_legacy_datastore("read_legacy_datastore");
+
+    let err = read_datastore_file::<SearchIndex>(&datastore_path).unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::NeedsMigration { .. })
+    ));
+    // reading does not change the file
+    assert_eq!(
+        fs::read_to_string(&datastore_path).unwrap(),
+        fs::read_to_string(LEGACY_DATASTORE_FILEPATH).unwrap()
+    );
+}
+
+#[test]
+fn test_migrate_datastore_at() {
+    let datastore_path = copy_legacy_datastore("migrate_datastore");
+
+    let summary = migrate_datastore_at(&datastore_path).unwrap().unwrap();
+    assert_eq!(summary.previous_version, 1);
+    assert_eq!(summary.reindexed, 4);
+    assert!(summary.skipped.is_empty() && summary.dropped.is_empty());
+    assert_eq!(migrate_datastore_at(&datastore_path).unwrap(), None);
+
+    let entries: Vec<SearchIndex> = read_datastore_file(&datastore_path).unwrap();
+    assert_eq!(entries.len(), 4);
+    assert!(entries.iter().all(|entry| entry.content_hash.len() == 16));
+}
+
+#[test]
+fn test_read_newer_datastore_fails() {
+    let folder = create_temp_folder("read_newer_datastore");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    let newer_datastore = json!({
+        "schema_version": SCHEMA_VERSION + 1,
+        "features": FeatureConfiguration::default(),
+        "entries": []
+    });
+    fs::write(datastore_path, newer_datastore.to_string()).unwrap();
+
+    let err = read_datastore_file::<SearchIndex>(datastore_path).unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::UnsupportedVersion { found, .. }) if *found == SCHEMA_VERSION + 1
+    ));
+}
+
+#[test]
+fn test_write_current_datastore_needs_no_migration() {
+    let folder = create_temp_folder("current_datastore");
+    let datastore_path = folder.join("data.json");
+    let datastore_path = datastore_path.to_str().unwrap();
+    let entries = vec![SearchIndex::new(
+        "pictures/red.png".to_string(),
+        0.3,
+        vec![],
+    )];
+    write_data_to_filepath(datastore_path, &entries).unwrap();
+
+    assert_eq!(migrate_datastore_at(datastore_path).unwrap(), None);
+    assert_eq!(
+        read_datastore_file::<SearchIndex>(datastore_path).unwrap(),
+        entries
+    );
+}
+
+#[test]
+fn test_binary_datastore_schema_version() {
+    let folder = create_temp_folder("binary_schema_version");
+    let datastore_path = folder.join("data.imsb");
+    let datastore_path = datastore_path.to_str().unwrap();
+    let entries = vec![SearchIndex::new(
+        "pictures/red.png".to_string(),
+        0.3,
+        vec![],
+    )];
+    let mut bytes = encode_binary_datastore(&entries, &FeatureConfiguration::default()).unwrap();
+
+    // files written before the schema version was stored
+    bytes[10..12].copy_from_slice(&[0, 0]);
+    fs::write(datastore_path, &bytes).unwrap();
+    let datastore = BinaryDatastore::open(datastore_path).unwrap();
+    assert_eq!(datastore.header().schema_version, 2);
+    drop(datastore);
+    let err = BinaryFileDatastore::open(datastore_path).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::NeedsMigration { .. })
+    ));
+    let summary = migrate_datastore_at(datastore_path).unwrap().unwrap();
+    assert_eq!(summary.previous_version, 2);
+    assert_eq!(migrate_binary_datastore(datastore_path).unwrap(), None);
+    assert!(BinaryFileDatastore::open(datastore_path).is_ok());
+
+    bytes[10..12].copy_from_slice(&(SCHEMA_VERSION as u16 + 1).to_le_bytes());
+    fs::write(datastore_path, &bytes).unwrap();
+    let err = BinaryDatastore::open(datastore_path).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::UnsupportedVersion { .. })
+    ));
+}
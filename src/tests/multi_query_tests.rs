@@ -0,0 +1,131 @@
+use crate::compare_pictures::SimilarityInformation;
+use crate::memory_datastore::MemoryDatastore;
+use crate::multi_query::{FusionStrategy, MultiQuery};
+use crate::search::{rank_entries, SearchOptions};
+use crate::tests::test_helpers::random_entries;
+
+fn filepaths(results: &[SimilarityInformation]) -> Vec<String> {
+    results
+        .iter()
+        .map(|result| result.search_index().filepath.clone())
+        .collect()
+}
+
+#[test]
+fn test_single_positive_matches_search() {
+    let entries = random_entries(150, 37);
+    let query = random_entries(1, 9).remove(0);
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+    let options = SearchOptions::top(10);
+    let expected = rank_entries(&query, &entries, &options);
+
+    for strategy in [
+        FusionStrategy::Centroid,
+        FusionStrategy::MaxSimilarity,
+        FusionStrategy::AverageSimilarity,
+    ] {
+        let results = MultiQuery::new(vec![query.clone()])
+            .with_strategy(strategy)
+            .search_datastore(&datastore, &options)
+            .unwrap();
+        assert_eq!(filepaths(&results), filepaths(&expected));
+        for (result, expected) in results.iter().zip(&expected) {
+            assert!((result.similarity() - expected.similarity()).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn test_max_similarity_finds_every_positive() {
+    let entries = random_entries(100, 12);
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+
+    let results = MultiQuery::new(vec![entries[3].clone(), entries[70].clone()])
+        .with_strategy(FusionStrategy::MaxSimilarity)
+        .search_datastore(&datastore, &SearchOptions::top(2))
+        .unwrap();
+
+    let mut found = filepaths(&results);
+    found.sort();
+    assert_eq!(
+        found,
+        vec![entries[3].filepath.clone(), entries[70].filepath.clone()]
+    );
+    assert!(results
+        .iter()
+        .all(|result| (result.similarity() - 1.0).abs() < 1e-9));
+}
+
+#[test]
+fn test_negatives_lower_the_rank() {
+    let entries = random_entries(100, 5);
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+    let positive = entries[0].clone();
+    let options = SearchOptions::top(entries.len());
+    let rank_of = |results: &[SimilarityInformation], filepath: &str| {
+        filepaths(results)
+            .iter()
+            .position(|found| found == filepath)
+            .unwrap()
+    };
+    let without_negatives = MultiQuery::new(vec![positive.clone()])
+        .search_datastore(&datastore, &options)
+        .unwrap();
+    // the second most similar entry is used as negative example
+    let negative = without_negatives[1].search_index().clone();
+
+    for strategy in [
+        FusionStrategy::Centroid,
+        FusionStrategy::MaxSimilarity,
+        FusionStrategy::AverageSimilarity,
+    ] {
+        let results = MultiQuery::new(vec![positive.clone()])
+            .with_negatives(vec![negative.clone()])
+            .with_negative_weight(1.0)
+            .with_strategy(strategy)
+            .search_datastore(&datastore, &options)
+            .unwrap();
+        assert_eq!(results.len(), entries.len());
+        assert!(rank_of(&results, &negative.filepath) > 1);
+    }
+}
+
+#[test]
+fn test_negative_weight_below_zero_keeps_every_result() {
+    let entries = random_entries(50, 7);
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+    // a negative weight makes similarity to the negative raise the rank
+    let query = MultiQuery::new(vec![entries[0].clone()])
+        .with_negatives(vec![entries[1].clone()])
+        .with_negative_weight(-2.0);
+
+    for strategy in [
+        FusionStrategy::MaxSimilarity,
+        FusionStrategy::AverageSimilarity,
+    ] {
+        let query = query.clone().with_strategy(strategy);
+        let all = query
+            .search_datastore(&datastore, &SearchOptions::top(entries.len()))
+            .unwrap();
+        let top = query
+            .search_datastore(&datastore, &SearchOptions::top(3))
+            .unwrap();
+        assert_eq!(filepaths(&top), filepaths(&all[..3]));
+    }
+}
+
+#[test]
+fn test_query_without_positives_fails() {
+    let datastore = MemoryDatastore::with_entries(random_entries(5, 1));
+    assert!(MultiQuery::new(Vec::new())
+        .search_datastore(&datastore, &SearchOptions::default())
+        .is_err());
+}
+
+#[test]
+fn test_parse_fusion_strategy() {
+    assert_eq!("centroid".parse(), Ok(FusionStrategy::Centroid));
+    assert_eq!("max".parse(), Ok(FusionStrategy::MaxSimilarity));
+    assert_eq!("average".parse(), Ok(FusionStrategy::AverageSimilarity));
+    assert!("median".parse::<FusionStrategy>().is_err());
+}
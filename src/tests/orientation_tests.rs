@@ -0,0 +1,151 @@
+use crate::color_type::ColorType;
+use crate::memory_datastore::MemoryDatastore;
+use crate::orientation::{compare_oriented, search_oriented_datastore, Orientation};
+use crate::region::compute_grid_cells;
+use crate::search::SearchOptions;
+use crate::search_index::generate_suchindex;
+use crate::tests::test_helpers::{
+    compact_datastore, create_temp_folder, write_test_picture_data, TEST_PICTURES,
+};
+use crate::PictureU8;
+
+const CELL_SIZE: u32 = 10;
+
+/// A picture of 3x3 single colored cells, the colors are given line by line.
+fn grid_picture(colors: [[u8; 3]; 9]) -> PictureU8 {
+    let size = 3 * CELL_SIZE;
+    let mut data = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            let cell = (y / CELL_SIZE * 3 + x / CELL_SIZE) as usize;
+            data.extend_from_slice(&colors[cell]);
+        }
+    }
+    PictureU8 {
+        lines: size,
+        columns: size,
+        color_channel_count: 3,
+        color_type: ColorType::Rgb,
+        data,
+    }
+}
+
+const COLORS: [[u8; 3]; 9] = [
+    [255, 0, 0],
+    [0, 255, 0],
+    [0, 0, 255],
+    [255, 255, 0],
+    [0, 255, 255],
+    [255, 0, 255],
+    [40, 40, 40],
+    [128, 128, 128],
+    [230, 230, 230],
+];
+
+fn write_picture(filepath: &str, picture: &PictureU8) -> String {
+    write_test_picture_data(filepath, picture.columns, picture.lines, &picture.data);
+    filepath.to_string()
+}
+
+#[test]
+fn test_oriented_cells_match_oriented_pictures() {
+    let picture = grid_picture(COLORS);
+    let cells = compute_grid_cells(&picture);
+
+    for orientation in Orientation::ALL {
+        let expected = compute_grid_cells(&orientation.apply(&picture));
+        let oriented = orientation.oriented_cells(&cells).unwrap();
+        for (expected_cell, oriented_cell) in expected.iter().zip(oriented) {
+            assert_eq!(expected_cell.histogram, oriented_cell.histogram);
+            assert_eq!(
+                expected_cell.average_brightness,
+                oriented_cell.average_brightness
+            );
+        }
+    }
+    assert!(Orientation::Rotated90.oriented_cells(&cells[..4]).is_none());
+}
+
+#[test]
+fn test_apply_orientations() {
+    let picture = grid_picture(COLORS);
+    let turned = Orientation::Rotated270.apply(&Orientation::Rotated90.apply(&picture));
+    assert_eq!(turned.data, picture.data);
+    for mirror in [
+        Orientation::FlippedHorizontally,
+        Orientation::FlippedVertically,
+        Orientation::Transposed,
+        Orientation::AntiTransposed,
+    ] {
+        assert_eq!(mirror.apply(&mirror.apply(&picture)).data, picture.data);
+    }
+    assert_eq!(
+        Orientation::AntiTransposed.apply(&picture).data,
+        Orientation::Transposed
+            .apply(&Orientation::Rotated180.apply(&picture))
+            .data
+    );
+}
+
+#[test]
+fn test_invariant_search_reports_orientation() {
+    let folder = create_temp_folder("orientation_search");
+    let folder = folder.to_str().unwrap();
+    let picture = grid_picture(COLORS);
+
+    // the same colors, but two cells swapped, so no orientation matches it exactly
+    let mut shuffled_colors = COLORS;
+    shuffled_colors.swap(0, 4);
+    let original = write_picture(&format!("{folder}/original.png"), &picture);
+    let shuffled = write_picture(
+        &format!("{folder}/shuffled.png"),
+        &grid_picture(shuffled_colors),
+    );
+    let datastore = MemoryDatastore::with_entries(vec![
+        generate_suchindex(original.clone()),
+        generate_suchindex(shuffled),
+    ]);
+
+    let uploaded = write_picture(
+        &format!("{folder}/uploaded.png"),
+        &Orientation::Rotated90.apply(&picture),
+    );
+    let query = generate_suchindex(uploaded);
+
+    let results = search_oriented_datastore(
+        &datastore,
+        &query,
+        &Orientation::ALL,
+        &SearchOptions::top(2),
+    )
+    .unwrap();
+    assert_eq!(results[0].search_index().filepath, original);
+    // turning the uploaded picture back gives the original
+    assert_eq!(results[0].orientation(), Some(Orientation::Rotated270));
+    assert!((results[0].similarity() - 1.0).abs() < 1e-6);
+    assert!(results[1].similarity() < results[0].similarity());
+
+    let original_entry = generate_suchindex(original);
+    assert!(
+        compare_oriented(&query, &original_entry, Orientation::Original).similarity()
+            < results[0].similarity()
+    );
+    assert!(search_oriented_datastore(&datastore, &query, &[], &SearchOptions::top(2)).is_err());
+}
+
+/// Compact entries have no grid cells, so the first orientation is reported.
+#[test]
+fn test_oriented_search_compact_datastore() {
+    let query = generate_suchindex(TEST_PICTURES[0].to_string());
+    let results = search_oriented_datastore(
+        &compact_datastore(),
+        &query,
+        &Orientation::ALL,
+        &SearchOptions::top(2),
+    )
+    .unwrap();
+    assert_eq!(results[0].search_index().filepath, TEST_PICTURES[0]);
+    assert_eq!(results[0].orientation(), Some(Orientation::ALL[0]));
+    assert!((results[0].similarity() - 1.0).abs() < 1e-6);
+    assert!(results[1].similarity() < results[0].similarity());
+}
@@ -1,4 +1,6 @@
-use crate::picture::{Picture, PictureF32};
+use crate::color_type::ColorType;
+use crate::picture::{Picture, PictureF32, PictureF64, PictureU16};
+use crate::search_index::determine_avg_brightness;
 use crate::PictureU8;
 
 #[test]
@@ -8,6 +10,7 @@ fn test_picture_to_picture_u8() {
         lines: 1,
         columns: 2,
         color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
         data: vec![0, 255, 64, 192],
     };
 
@@ -15,6 +18,7 @@ fn test_picture_to_picture_u8() {
         lines: 1,
         columns: 2,
         color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
         data: vec![0.0, 1.0, 0.2509804, 0.7529412],
     };
 
@@ -48,6 +52,7 @@ fn test_picture_to_picture_f32() {
         lines: 1,
         columns: 2,
         color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
         data: vec![0, 255, 64, 192],
     };
 
@@ -55,6 +60,7 @@ fn test_picture_to_picture_f32() {
         lines: 1,
         columns: 2,
         color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
         data: vec![0.0, 1.0, 0.2509804, 0.7529412],
     };
 
@@ -80,3 +86,40 @@ fn test_picture_to_picture_f32() {
     );
     assert_eq!(converted_picture_f32.data, sample_picture_f32.data);
 }
+
+#[test]
+fn test_convert_between_sample_types() {
+    let pic_u16: PictureU16 = Picture {
+        lines: 1,
+        columns: 3,
+        color_channel_count: 1,
+        color_type: ColorType::Gray,
+        data: vec![0, 32_768, u16::MAX],
+    };
+
+    let pic_f64: PictureF64 = pic_u16.convert();
+    assert_eq!(pic_f64.data[0], 0.0);
+    assert!((pic_f64.data[1] - 0.5).abs() < 1e-4);
+    assert_eq!(pic_f64.data[2], 1.0);
+    assert_eq!(pic_u16.to_picture_u8().data, vec![0, 127, 255]);
+    assert_eq!(pic_f64.convert::<u16>(), pic_u16);
+
+    // converting to the same type copies the values exactly
+    assert_eq!(pic_u16.convert::<u16>(), pic_u16);
+}
+
+#[test]
+fn test_brightness_is_independent_of_sample_type() {
+    let pic_u8 = PictureU8 {
+        lines: 1,
+        columns: 2,
+        color_channel_count: 3,
+        color_type: ColorType::Rgb,
+        data: vec![255, 128, 0, 10, 20, 30],
+    };
+    let brightness_u8 = determine_avg_brightness(&pic_u8);
+    let pic_u16: PictureU16 = pic_u8.convert();
+    let pic_f64: PictureF64 = pic_u8.convert();
+    assert!((determine_avg_brightness(&pic_u16) - brightness_u8).abs() < 1e-3);
+    assert!((determine_avg_brightness(&pic_f64) - brightness_u8).abs() < 1e-6);
+}
@@ -0,0 +1,88 @@
+use crate::color_type::ColorType;
+use crate::get_histogram;
+use crate::picture::{AverageBrightness, Picture, PictureF32};
+use crate::picture_view::{AsPictureView, PictureView};
+use crate::region::Rect;
+use crate::search_index::determine_avg_brightness;
+use crate::transform::Transform;
+use crate::PictureU8;
+use std::borrow::Cow;
+
+/// An RGB picture of 4x3 pixels with different values everywhere.
+fn rgb_picture() -> PictureU8 {
+    PictureU8 {
+        lines: 3,
+        columns: 4,
+        color_channel_count: 3,
+        color_type: ColorType::Rgb,
+        data: (0..36).map(|value| value * 7).collect(),
+    }
+}
+
+#[test]
+fn test_sub_view_borrows_the_samples() {
+    let picture = rgb_picture();
+    let view = picture.view().sub_view(&Rect::new(1, 1, 2, 2)).unwrap();
+
+    assert_eq!((view.columns(), view.lines()), (2, 2));
+    assert_eq!(view.stride(), 12);
+    assert!(!view.is_contiguous());
+    // the first line of the region starts at the second pixel of the second line
+    assert_eq!(view.row(0).as_ptr(), picture.data[15..].as_ptr());
+    assert_eq!(view.row(1), &picture.data[27..33]);
+    assert_eq!(view.pixel_samples().count(), 4);
+
+    // a view of a view is a region of the picture
+    let corner = view.sub_view(&Rect::new(1, 1, 1, 1)).unwrap();
+    assert_eq!(corner.row(0), &picture.data[30..33]);
+    assert!(view.sub_view(&Rect::new(1, 1, 2, 1)).is_err());
+}
+
+#[test]
+fn test_view_descriptors_match_the_cropped_picture() {
+    let picture = rgb_picture();
+    let region = Rect::new(1, 0, 3, 2);
+    let view = picture.view().sub_view(&region).unwrap();
+    let cropped = picture.crop(&region).unwrap();
+
+    assert_eq!(view.to_picture(), cropped);
+    assert_eq!(get_histogram(&view), get_histogram(&cropped));
+    assert_eq!(
+        determine_avg_brightness(&view),
+        determine_avg_brightness(&cropped)
+    );
+    assert_eq!(view.gray_intensity_array(), cropped.gray_intensity_array());
+
+    let converted: PictureF32 = view.convert();
+    assert_eq!(converted, cropped.to_picture_f32());
+}
+
+#[test]
+fn test_view_of_a_buffer() {
+    // two lines of 2 gray pixels, each line is followed by a padding sample
+    let data = [10u8, 20, 0, 30, 40, 0];
+    let view = PictureView::with_stride(&data, 2, 2, ColorType::Gray, 3).unwrap();
+    assert_eq!(view.to_picture().data, vec![10, 20, 30, 40]);
+    assert_eq!(view.as_view().rows().count(), 2);
+
+    assert!(PictureView::with_stride(&data, 2, 2, ColorType::Gray, 1).is_err());
+    assert!(PictureView::new(&data, 3, 3, ColorType::Gray).is_err());
+    assert_eq!(
+        PictureView::new(&data, 2, 1, ColorType::Gray)
+            .unwrap()
+            .to_picture()
+            .data,
+        vec![10, 20]
+    );
+}
+
+#[test]
+fn test_converted_only_converts_other_sample_types() {
+    let picture = rgb_picture();
+    match picture.converted::<u8>() {
+        Cow::Borrowed(borrowed) => assert!(std::ptr::eq(borrowed, &picture)),
+        Cow::Owned(_) => panic!("a picture of the same sample type is borrowed"),
+    }
+    let converted: Cow<Picture<f32>> = picture.converted();
+    assert_eq!(converted.into_owned(), picture.to_picture_f32());
+}
@@ -0,0 +1,78 @@
+use crate::color_type::ColorType;
+use crate::picture::{Picture, PictureF32};
+use crate::region::Rect;
+use crate::PictureU8;
+
+/// An RGB picture of 3x2 pixels whose red value is the pixel's number.
+fn numbered_picture() -> PictureU8 {
+    let data = (0..6).flat_map(|pixel| [pixel, 100, 200]).collect();
+    PictureU8::new(3, 2, ColorType::Rgb, data).unwrap()
+}
+
+#[test]
+fn test_validate() {
+    assert!(numbered_picture().validate().is_ok());
+    assert!(PictureF32::new(2, 2, ColorType::Gray, vec![0.0; 3]).is_err());
+    assert!(PictureU8::new(0, 0, ColorType::Channels(0), Vec::new()).is_err());
+
+    let mut picture = numbered_picture();
+    picture.data.pop();
+    assert!(picture.validate().is_err());
+    // the last pixel is incomplete
+    assert_eq!(picture.get_pixel(2, 1), None);
+    assert_eq!(picture.get_pixel(1, 1), Some(&[4, 100, 200][..]));
+}
+
+#[test]
+fn test_get_and_set_pixel() {
+    let mut picture = numbered_picture();
+    assert_eq!(picture.get_pixel(0, 0), Some(&[0, 100, 200][..]));
+    assert_eq!(picture.get_pixel(2, 1), Some(&[5, 100, 200][..]));
+    assert_eq!(picture.get_pixel(3, 0), None);
+    assert_eq!(picture.get_pixel(0, 2), None);
+
+    picture.set_pixel(1, 1, &[9, 9, 9]).unwrap();
+    assert_eq!(picture.get_pixel(1, 1), Some(&[9, 9, 9][..]));
+    assert!(picture.set_pixel(3, 1, &[9, 9, 9]).is_err());
+    assert!(picture.set_pixel(0, 0, &[9, 9]).is_err());
+
+    picture.get_pixel_mut(0, 1).unwrap()[2] = 0;
+    assert_eq!(picture.data[11], 0);
+}
+
+#[test]
+fn test_iterators() {
+    let mut picture = numbered_picture();
+    assert_eq!(picture.pixels().count(), 6);
+    assert_eq!(picture.pixels().nth(4), Some(&[4, 100, 200][..]));
+
+    let rows: Vec<&[u8]> = picture.rows().collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1][..3], [3, 100, 200]);
+
+    let red: Vec<u8> = picture.channel(0).unwrap().collect();
+    assert_eq!(red, vec![0, 1, 2, 3, 4, 5]);
+    assert!(picture.channel(3).is_none());
+
+    for pixel in picture.pixels_mut() {
+        pixel[1] = 0;
+    }
+    assert!(picture.channel(1).unwrap().all(|green| green == 0));
+}
+
+#[test]
+fn test_view_accessors() {
+    let picture = numbered_picture();
+    let view = picture.view().sub_view(&Rect::new(1, 0, 2, 2)).unwrap();
+    assert_eq!(view.get_pixel(0, 1), Some(&[4, 100, 200][..]));
+    assert_eq!(view.get_pixel(2, 0), None);
+    assert_eq!(
+        view.channel(0).unwrap().collect::<Vec<_>>(),
+        vec![1, 2, 4, 5]
+    );
+    assert!(view.channel(3).is_none());
+
+    let empty: Picture<u16> = Picture::new(0, 0, ColorType::Gray, Vec::new()).unwrap();
+    assert_eq!(empty.pixels().count(), 0);
+    assert_eq!(empty.rows().count(), 0);
+}
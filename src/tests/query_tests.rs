@@ -0,0 +1,136 @@
+use crate::compare_pictures::compare_search_indexes;
+use crate::datastore::Datastore;
+use crate::memory_datastore::MemoryDatastore;
+use crate::query::Query;
+use crate::search_index::{generate_suchindex, SearchIndex};
+#[cfg(feature = "sqlite")]
+use crate::sqlite_datastore::SqliteDatastore;
+use crate::tests::test_helpers::{compact_datastore, random_entries, TEST_PICTURES};
+
+/// Random entries with varying dimensions, file sizes and tags.
+fn entries_with_metadata() -> Vec<SearchIndex> {
+    random_entries(200, 35)
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut entry)| {
+            entry.lines = 100 + (i as u32 % 10) * 100;
+            entry.columns = 200 + (i as u32 % 7) * 100;
+            entry.file_size = 1000 * i as u64;
+            if i % 3 == 0 {
+                entry.tags.push("animal".to_string());
+            }
+            if i % 4 == 0 {
+                entry.tags.push("outdoor".to_string());
+            }
+            entry
+        })
+        .collect()
+}
+
+fn check_query(datastore: &dyn Datastore) {
+    let entries = entries_with_metadata();
+    let query = entries[17].clone();
+
+    // similarity threshold without any other filter
+    let results = Query::new()
+        .similar_to(query.clone())
+        .min_similarity(0.9)
+        .run(datastore)
+        .unwrap();
+    let expected = entries
+        .iter()
+        .filter(|entry| compare_search_indexes(&query, entry).similarity() >= 0.9)
+        .count();
+    assert_eq!(results.len(), expected);
+    assert!(results
+        .windows(2)
+        .all(|pair| pair[0].similarity() >= pair[1].similarity()));
+
+    // range filters on the metadata
+    let matching = Query::new()
+        .brightness(0.2..=0.4)
+        .lines(200..=500)
+        .file_size(0..=150_000)
+        .with_tag("animal")
+        .matching_entries(datastore)
+        .unwrap();
+    let expected: Vec<&SearchIndex> = entries
+        .iter()
+        .filter(|entry| {
+            (0.2..=0.4).contains(&entry.average_brightness)
+                && (200..=500).contains(&entry.lines)
+                && entry.file_size <= 150_000
+                && entry.tags.contains(&"animal".to_string())
+        })
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(matching.len(), expected.len());
+    assert!(matching.iter().all(|entry| expected.contains(&entry)));
+
+    // both combined
+    let results = Query::new()
+        .similar_to(query.clone())
+        .min_similarity(0.8)
+        .columns(300..=600)
+        .with_tag("outdoor")
+        .limit(3)
+        .run(datastore)
+        .unwrap();
+    assert!(results.len() <= 3);
+    assert!(results.iter().all(|result| {
+        let entry = result.search_index();
+        result.similarity() >= 0.8
+            && (300..=600).contains(&entry.columns)
+            && entry.tags.contains(&"outdoor".to_string())
+    }));
+}
+
+#[test]
+fn test_query_memory_datastore() {
+    check_query(&MemoryDatastore::with_entries(entries_with_metadata()));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_query_sqlite_datastore() {
+    let mut datastore = SqliteDatastore::open_in_memory().unwrap();
+    datastore.insert_all(entries_with_metadata()).unwrap();
+    check_query(&datastore);
+}
+
+#[test]
+fn test_query_color_channels_and_paging() {
+    let mut entries = random_entries(10, 3);
+    entries[4].histogram.pop();
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+
+    let grayscale_like = Query::new()
+        .color_channels(1..=2)
+        .matching_entries(&datastore)
+        .unwrap();
+    assert_eq!(grayscale_like, vec![entries[4].clone()]);
+
+    let page = Query::new()
+        .offset(3)
+        .limit(4)
+        .matching_entries(&datastore)
+        .unwrap();
+    assert_eq!(page, entries[3..7].to_vec());
+}
+
+#[test]
+fn test_query_without_picture_fails() {
+    let datastore = MemoryDatastore::new();
+    assert!(Query::new().min_similarity(0.5).run(&datastore).is_err());
+}
+
+#[test]
+fn test_query_compact_datastore() {
+    let results = Query::new()
+        .similar_to(generate_suchindex(TEST_PICTURES[0].to_string()))
+        .min_similarity(0.9)
+        .run(&compact_datastore())
+        .unwrap();
+    assert_eq!(results[0].search_index().filepath, TEST_PICTURES[0]);
+    assert!((results[0].similarity() - 1.0).abs() < 1e-6);
+}
@@ -0,0 +1,128 @@
+use crate::color_type::ColorType;
+use crate::memory_datastore::MemoryDatastore;
+use crate::region::{compute_grid_cells, region_search_index, search_region_in_cells, Rect};
+use crate::search::SearchOptions;
+use crate::search_index::generate_suchindex;
+use crate::tests::test_helpers::{create_temp_folder, write_test_picture_data};
+use crate::transform::Transform;
+use crate::PictureU8;
+
+const SIZE: u32 = 30;
+
+/// Writes a picture of the background colour with a square block of another colour.
+fn write_picture_with_block(
+    filepath: &str,
+    background: [u8; 3],
+    block: Rect,
+    block_color: [u8; 3],
+) -> String {
+    let mut data = Vec::new();
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let inside = (block.x..block.x + block.width).contains(&x)
+                && (block.y..block.y + block.height).contains(&y);
+            data.extend_from_slice(if inside { &block_color } else { &background });
+        }
+    }
+    write_test_picture_data(filepath, SIZE, SIZE, &data);
+    filepath.to_string()
+}
+
+#[test]
+fn test_parse_rect() {
+    assert_eq!("1,2,3,4".parse(), Ok(Rect::new(1, 2, 3, 4)));
+    assert_eq!(" 1, 2 ,3,4".parse(), Ok(Rect::new(1, 2, 3, 4)));
+    assert!("1,2,3".parse::<Rect>().is_err());
+    assert!("1,2,3,-4".parse::<Rect>().is_err());
+}
+
+#[test]
+fn test_crop() {
+    let pic = PictureU8 {
+        lines: 3,
+        columns: 3,
+        color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
+        data: (0..18).collect(),
+    };
+
+    let cropped = pic.crop(&Rect::new(1, 1, 2, 2)).unwrap();
+
+    assert_eq!((cropped.columns, cropped.lines), (2, 2));
+    assert_eq!(cropped.data, vec![8, 9, 10, 11, 14, 15, 16, 17]);
+    assert!(pic.crop(&Rect::new(2, 0, 2, 1)).is_err());
+    assert!(pic.crop(&Rect::new(0, 0, 0, 1)).is_err());
+}
+
+#[test]
+fn test_grid_cells_cover_the_picture() {
+    let pic = PictureU8 {
+        lines: 10,
+        columns: 11,
+        color_channel_count: 1,
+        color_type: ColorType::Gray,
+        data: vec![0; 110],
+    };
+
+    let cells = compute_grid_cells(&pic);
+
+    assert_eq!(cells.len(), 9);
+    let area: u32 = cells
+        .iter()
+        .map(|cell| cell.region.width * cell.region.height)
+        .sum();
+    assert_eq!(area, 110);
+    assert_eq!(cells[8].region, Rect::new(6, 6, 5, 4));
+
+    let tiny = PictureU8 {
+        lines: 2,
+        columns: 2,
+        color_channel_count: 1,
+        color_type: ColorType::Gray,
+        data: vec![0; 4],
+    };
+    assert!(compute_grid_cells(&tiny).is_empty());
+}
+
+#[test]
+fn test_region_of_whole_picture_matches_search_index() {
+    let bird = "src/tests/files/pictures_for_testing/bird.png";
+    let search_index = generate_suchindex(bird.to_string());
+    let whole_picture = Rect::new(0, 0, search_index.columns, search_index.lines);
+
+    let region_index = region_search_index(bird, &whole_picture).unwrap();
+
+    assert_eq!(region_index.histogram, search_index.histogram);
+    assert_eq!(
+        region_index.average_brightness,
+        search_index.average_brightness
+    );
+    assert!(region_search_index(bird, &Rect::new(0, 0, whole_picture.width + 1, 1)).is_err());
+}
+
+#[test]
+fn test_region_is_found_anywhere() {
+    let folder = create_temp_folder("region_anywhere");
+    let path = |name: &str| folder.join(name).to_str().unwrap().to_string();
+    let red = [255, 0, 0];
+    let green = [0, 255, 0];
+    let top_left = Rect::new(0, 0, 10, 10);
+    let bottom_right = Rect::new(20, 20, 10, 10);
+
+    let query = write_picture_with_block(&path("query.png"), [0, 0, 255], top_left, red);
+    let with_logo = write_picture_with_block(&path("with_logo.png"), green, bottom_right, red);
+    let without_logo = write_picture_with_block(&path("without_logo.png"), green, top_left, green);
+    let datastore = MemoryDatastore::with_entries(vec![
+        generate_suchindex(without_logo),
+        generate_suchindex(with_logo.clone()),
+    ]);
+
+    let query = region_search_index(&query, &top_left).unwrap();
+    let matches = search_region_in_cells(&datastore, &query, &SearchOptions::top(2)).unwrap();
+
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].similarity.search_index().filepath, with_logo);
+    assert_eq!(matches[0].region, bottom_right);
+    assert!((matches[0].similarity.similarity() - 1.0).abs() < 1e-6);
+    assert!(matches[1].similarity.similarity() < matches[0].similarity.similarity());
+}
@@ -2,11 +2,11 @@ use crate::search_index::{
     analyse_pictures, generate_suchindex, generate_suchindex_to_file, read_data_from_datastore,
     write_data_to_file, SearchIndex,
 };
+use crate::tests::test_helpers::test_datastore_path;
 use crate::{get_datastore_path, set_datastore_filepath};
 
 const PICTURE_FILEPATH: &str = "src/tests/files/pictures_for_testing/bird.png";
 const PICTURE_FOLDERPATH: &str = "src/tests/files/pictures_for_testing";
-const DATASTORE_FILEPATH: &str = "src/tests/files/DataStoreJSON/data.json";
 
 /// This Test declares an instance of type SearchIndex and writes it to a file.
 #[test]
@@ -42,17 +42,17 @@ fn test_read_data_from_datastore() {
 
 #[test]
 fn test_set_datastore_filepath() {
-    set_datastore_filepath(DATASTORE_FILEPATH);
+    set_datastore_filepath(test_datastore_path());
     assert_eq!(
         std::env::var("IMSEARCH_DATA_PATH").unwrap(),
-        DATASTORE_FILEPATH
+        test_datastore_path()
     );
 }
 #[test]
 fn test_get_datastore_path() {
-    set_datastore_filepath(DATASTORE_FILEPATH);
+    set_datastore_filepath(test_datastore_path());
     let get_filepath = get_datastore_path().unwrap();
-    assert_eq!(get_filepath, DATASTORE_FILEPATH);
+    assert_eq!(get_filepath, test_datastore_path());
 }
 #[test]
 fn test_analyse_pictures() {
@@ -0,0 +1,148 @@
+use crate::ann_index::{AnnIndex, AnnSearchOptions};
+use crate::compare_pictures::{
+    calculate_similarities_in, compare_search_indexes, SimilarityInformation,
+};
+use crate::datastore::{Datastore, Modification};
+use crate::memory_datastore::MemoryDatastore;
+use crate::search::{rank_entries, search_datastore, search_datastore_with_index, SearchOptions};
+use crate::search_index::SearchIndex;
+use crate::tests::test_helpers::{compact_datastore, random_entries, TEST_PICTURES};
+use std::error::Error;
+
+/// Ranks all entries by sorting them completely.
+fn sorted_filepaths(query: &SearchIndex, entries: &[SearchIndex]) -> Vec<(f64, String)> {
+    let mut ranked: Vec<(f64, String)> = entries
+        .iter()
+        .map(|entry| {
+            (
+                compare_search_indexes(query, entry).similarity(),
+                entry.filepath.clone(),
+            )
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    ranked
+}
+
+fn filepaths(results: &[SimilarityInformation]) -> Vec<String> {
+    results
+        .iter()
+        .map(|result| result.search_index().filepath.clone())
+        .collect()
+}
+
+#[test]
+fn test_rank_entries_matches_full_sort() {
+    let entries = random_entries(300, 21);
+    let query = random_entries(1, 8).remove(0);
+    let expected: Vec<String> = sorted_filepaths(&query, &entries)
+        .into_iter()
+        .map(|(_, filepath)| filepath)
+        .collect();
+
+    for (offset, limit) in [(0, 1), (0, 5), (0, 17), (10, 10), (290, 20), (400, 5)] {
+        let options = SearchOptions::top(limit).with_offset(offset);
+        let results = rank_entries(&query, &entries, &options);
+        let end = (offset + limit).min(entries.len());
+        let start = offset.min(end);
+        assert_eq!(filepaths(&results), expected[start..end].to_vec());
+    }
+    assert!(rank_entries(&query, &entries, &SearchOptions::top(0)).is_empty());
+}
+
+#[test]
+fn test_rank_entries_min_similarity() {
+    let entries = random_entries(200, 4);
+    let query = entries[17].clone();
+    let expected: Vec<String> = sorted_filepaths(&query, &entries)
+        .into_iter()
+        .filter(|(similarity, _)| *similarity >= 0.9)
+        .map(|(_, filepath)| filepath)
+        .collect();
+
+    let options = SearchOptions::top(usize::MAX).with_min_similarity(0.9);
+    let results = rank_entries(&query, &entries, &options);
+
+    assert_eq!(filepaths(&results), expected);
+    assert_eq!(results[0].search_index().filepath, query.filepath);
+    assert!(results.iter().all(|result| result.similarity() >= 0.9));
+}
+
+#[test]
+fn test_search_datastore() {
+    let entries = random_entries(50, 9);
+    let datastore = MemoryDatastore::with_entries(entries.clone());
+
+    let results = search_datastore(&datastore, &entries[3], &SearchOptions::default()).unwrap();
+
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0].search_index(), &entries[3]);
+    assert!(results
+        .windows(2)
+        .all(|pair| pair[0].similarity() >= pair[1].similarity()));
+}
+
+/// A datastore that can only look up entries by their filepath.
+struct LookupOnlyDatastore(MemoryDatastore);
+
+impl Datastore for LookupOnlyDatastore {
+    fn insert(&mut self, entry: SearchIndex) -> Result<(), Box<dyn Error>> {
+        self.0.insert(entry)
+    }
+
+    fn get(&self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        self.0.get(filepath)
+    }
+
+    fn remove(&mut self, filepath: &str) -> Result<Option<SearchIndex>, Box<dyn Error>> {
+        self.0.remove(filepath)
+    }
+
+    fn entries(&self) -> Result<Vec<SearchIndex>, Box<dyn Error>> {
+        Err("the entries must not be scanned".into())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.0.flush()
+    }
+
+    fn modify(&mut self, modification: &mut Modification) -> Result<(), Box<dyn Error>> {
+        self.0.modify(modification)
+    }
+}
+
+#[test]
+fn test_search_datastore_with_index() {
+    let entries = random_entries(300, 5);
+    let query = random_entries(1, 13).remove(0);
+    let index = AnnIndex::build(&entries);
+    let datastore = LookupOnlyDatastore(MemoryDatastore::with_entries(entries.clone()));
+    let expected: Vec<String> = sorted_filepaths(&query, &entries)
+        .into_iter()
+        .map(|(_, filepath)| filepath)
+        .collect();
+
+    // with an exact search of the index, the results are the same as the ones of a full scan
+    let options = SearchOptions::top(5)
+        .with_offset(2)
+        .with_approximate(AnnSearchOptions::default());
+    let results = search_datastore_with_index(&datastore, &index, &query, &options).unwrap();
+    assert_eq!(filepaths(&results), expected[2..7]);
+    let exact = compare_search_indexes(&query, results[0].search_index()).similarity();
+    assert!((results[0].similarity() - exact).abs() < 1e-4);
+
+    // entries the index knows but the datastore does not are skipped
+    let datastore = LookupOnlyDatastore(MemoryDatastore::with_entries(entries[..10].to_vec()));
+    let results = search_datastore_with_index(&datastore, &index, &query, &options).unwrap();
+    assert!(results.len() < 5);
+}
+
+/// Compact entries have no histograms, so they are compared by their colour moments.
+#[test]
+fn test_calculate_similarities_in_compact_datastore() {
+    let similarities = calculate_similarities_in(&compact_datastore(), TEST_PICTURES[0]).unwrap();
+    assert_eq!(similarities.len(), TEST_PICTURES.len());
+    assert_eq!(similarities[0].search_index().filepath, TEST_PICTURES[0]);
+    assert!((similarities[0].similarity() - 1.0).abs() < 1e-6);
+    assert!(similarities[1].similarity() < similarities[0].similarity());
+}
@@ -0,0 +1,152 @@
+use crate::color_type::ColorType;
+use crate::features::FeatureConfiguration;
+use crate::query_descriptor::{FeatureWeights, QueryDescriptor, SearchProfile};
+use crate::search::{rank_entries, rank_entries_weighted, SearchOptions};
+use crate::search_index::try_generate_suchindex;
+use crate::shape::{compare_shapes, compute_shape_descriptor, hu_moments, EDGE_DIRECTION_BINS};
+use crate::tests::test_helpers::{create_temp_folder, write_test_picture_data};
+use crate::PictureU8;
+
+/// Returns the RGB data of a picture with the `foreground` colour where `is_foreground` is true and
+/// the `background` colour elsewhere.
+fn two_colour_data(
+    size: u32,
+    foreground: [u8; 3],
+    background: [u8; 3],
+    is_foreground: impl Fn(u32, u32) -> bool,
+) -> Vec<u8> {
+    let mut data = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            data.extend(if is_foreground(x, y) {
+                foreground
+            } else {
+                background
+            });
+        }
+    }
+    data
+}
+
+fn two_colour_picture(
+    size: u32,
+    foreground: [u8; 3],
+    background: [u8; 3],
+    is_foreground: impl Fn(u32, u32) -> bool,
+) -> PictureU8 {
+    let data = two_colour_data(size, foreground, background, is_foreground);
+    PictureU8::new(size, size, ColorType::Rgb, data).unwrap()
+}
+
+fn inside(x: u32, y: u32, from: u32, to: u32) -> bool {
+    (from..to).contains(&x) && (from..to).contains(&y)
+}
+
+#[test]
+fn test_edge_direction_histogram() {
+    // vertical stripes have horizontal gradients only
+    let stripes = two_colour_picture(32, [0; 3], [255; 3], |x, _| x % 8 < 4);
+    let shape = compute_shape_descriptor(&stripes).unwrap();
+
+    assert_eq!(shape.edge_directions.len(), EDGE_DIRECTION_BINS);
+    assert!(shape.edge_directions[0] > 0.9);
+    let total: f32 = shape.edge_directions.iter().sum();
+    assert!((total - 1.0).abs() < 1e-5);
+
+    let plain = two_colour_picture(8, [0; 3], [0; 3], |_, _| true);
+    let shape = compute_shape_descriptor(&plain).unwrap();
+    assert_eq!(shape.edge_density, 0.0);
+    assert_eq!(shape.edge_directions, vec![0.0; EDGE_DIRECTION_BINS]);
+    assert_eq!(shape.hu_moments, [0.0; 7]);
+}
+
+#[test]
+fn test_hu_moments_are_invariant() {
+    let small = two_colour_picture(32, [255; 3], [0; 3], |x, y| inside(x, y, 2, 10));
+    let moved = two_colour_picture(32, [255; 3], [0; 3], |x, y| inside(x, y, 20, 28));
+    let large = two_colour_picture(64, [255; 3], [0; 3], |x, y| inside(x, y, 10, 42));
+
+    let small = hu_moments(&small);
+    for (small, moved) in small.iter().zip(hu_moments(&moved)) {
+        assert!((small - moved).abs() < 1e-12);
+    }
+    // a filled square keeps its moments when it is scaled, apart from the sampling
+    let large = hu_moments(&large);
+    assert!((small[0] - large[0]).abs() / small[0] < 0.1);
+}
+
+#[test]
+fn test_compare_shapes_ignores_colours() {
+    let logo = compute_shape_descriptor(&two_colour_picture(32, [0; 3], [255; 3], |x, y| {
+        inside(x, y, 8, 24)
+    }))
+    .unwrap();
+    let coloured_logo = compute_shape_descriptor(&two_colour_picture(
+        32,
+        [200, 0, 0],
+        [255, 255, 0],
+        |x, y| inside(x, y, 8, 24),
+    ))
+    .unwrap();
+    let stripes =
+        compute_shape_descriptor(&two_colour_picture(32, [0; 3], [255; 3], |x, _| x % 8 < 4))
+            .unwrap();
+
+    assert_eq!(compare_shapes(&logo, &logo), 1.0);
+    let coloured_similarity = compare_shapes(&logo, &coloured_logo);
+    assert!(coloured_similarity > 0.95, "{coloured_similarity}");
+    assert!(compare_shapes(&logo, &stripes) < coloured_similarity);
+}
+
+#[test]
+fn test_shape_profile_ranks_shapes_over_colours() {
+    let folder = create_temp_folder("shape_profile");
+    let write = |name: &str, data: Vec<u8>| {
+        let path = folder.join(format!("{name}.png"));
+        let path = path.to_str().unwrap().to_string();
+        write_test_picture_data(&path, 32, 32, &data);
+        try_generate_suchindex(path, &FeatureConfiguration::default()).unwrap()
+    };
+    let square = |x, y| inside(x, y, 8, 24);
+    let logo = write("logo", two_colour_data(32, [0; 3], [255; 3], square));
+    let coloured_logo = write(
+        "coloured_logo",
+        two_colour_data(32, [200, 0, 0], [255, 255, 0], square),
+    );
+    // black and white like the logo, with just as many black pixels
+    let stripes = write(
+        "stripes",
+        two_colour_data(32, [0; 3], [255; 3], |x, _| x % 4 == 0),
+    );
+    let entries = [coloured_logo.clone(), stripes.clone()];
+
+    let by_colour = rank_entries(&logo, &entries, &SearchOptions::top(1));
+    assert_eq!(by_colour[0].search_index().filepath, stripes.filepath);
+    let weights = SearchProfile::Shape.weights(logo.histogram.len());
+    let by_shape = rank_entries_weighted(&logo, &entries, &weights, &SearchOptions::top(1));
+    assert_eq!(by_shape[0].search_index().filepath, coloured_logo.filepath);
+
+    let descriptor = QueryDescriptor::from_search_index(&logo);
+    let mut without_shape = coloured_logo;
+    without_shape.shape = None;
+    assert_eq!(descriptor.shape_similarity(&without_shape), 0.0);
+    let weights = FeatureWeights::uniform(logo.histogram.len()).with_shape(1.0);
+    assert!(descriptor.similarity_upper_bound(&without_shape, &weights) <= 1.0);
+}
+
+#[test]
+fn test_search_profile_names() {
+    for profile in [
+        SearchProfile::Colour,
+        SearchProfile::Compact,
+        SearchProfile::Shape,
+        SearchProfile::Structure,
+    ] {
+        assert_eq!(profile.to_string().parse::<SearchProfile>(), Ok(profile));
+    }
+    assert!("texture".parse::<SearchProfile>().is_err());
+    assert_eq!(
+        SearchProfile::Compact.weights(3),
+        FeatureWeights::compact(3)
+    );
+}
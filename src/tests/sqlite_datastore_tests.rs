@@ -0,0 +1,198 @@
+use crate::datastore::{read_datastore_file, Datastore, DatastoreError};
+use crate::features::FeatureConfiguration;
+use crate::migration::SCHEMA_VERSION;
+use crate::search_index::{write_data_to_filepath, SearchIndex};
+use crate::sqlite_datastore::{
+    convert_json_to_sqlite, convert_sqlite_to_json, is_sqlite_datastore, migrate_sqlite_datastore,
+    SqliteDatastore,
+};
+use crate::tests::test_helpers::create_temp_folder;
+use rusqlite::Connection;
+
+fn sample_entry(filepath: &str, average_brightness: f32, content_hash: &str) -> SearchIndex {
+    let mut entry = SearchIndex::new(filepath.to_string(), average_brightness, vec![]);
+    entry.content_hash = content_hash.to_string();
+    entry
+}
+
+#[test]
+fn test_insert_get_remove() {
+    let mut datastore = SqliteDatastore::open_in_memory().unwrap();
+    datastore
+        .insert(sample_entry("pictures/red.png", 0.3, "a"))
+        .unwrap();
+    datastore
+        .insert(sample_entry("pictures/blue.png", 0.1, "b"))
+        .unwrap();
+    // replaces the entry of red.png
+    datastore
+        .insert(sample_entry("pictures/red.png", 0.5, "c"))
+        .unwrap();
+
+    assert_eq!(datastore.len().unwrap(), 2);
+    let red = datastore.get("pictures/red.png").unwrap().unwrap();
+    assert_eq!(red.average_brightness, 0.5);
+    assert!(datastore.get("pictures/green.png").unwrap().is_none());
+
+    let removed = datastore.remove("pictures/red.png").unwrap();
+    assert_eq!(removed, Some(red));
+    assert!(datastore.remove("pictures/red.png").unwrap().is_none());
+    assert_eq!(datastore.entries().unwrap().len(), 1);
+}
+
+#[test]
+fn test_modify() {
+    let mut datastore = SqliteDatastore::open_in_memory().unwrap();
+    datastore
+        .insert_all(vec![
+            sample_entry("pictures/red.png", 0.3, "a"),
+            sample_entry("pictures/blue.png", 0.1, "b"),
+        ])
+        .unwrap();
+
+    datastore
+        .modify(&mut |entries| {
+            entries.retain(|entry| entry.filepath != "pictures/red.png");
+            entries[0].tags = vec!["sky".to_string()];
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(datastore.len().unwrap(), 1);
+    let blue = datastore.get("pictures/blue.png").unwrap().unwrap();
+    assert_eq!(blue.tags, vec!["sky".to_string()]);
+
+    // a failing modification is rolled back
+    let result = datastore.modify(&mut |entries| {
+        entries.clear();
+        Err("failed".into())
+    });
+    assert!(result.is_err());
+    assert_eq!(datastore.len().unwrap(), 1);
+}
+
+#[test]
+fn test_select_where() {
+    let mut datastore = SqliteDatastore::open_in_memory().unwrap();
+    datastore
+        .insert_all(vec![
+            sample_entry("pictures/red.png", 0.3, "a"),
+            sample_entry("pictures/blue.png", 0.1, "b"),
+            sample_entry("other/red.png", 0.7, "a"),
+        ])
+        .unwrap();
+
+    let bright = datastore
+        .select_where("average_brightness > ?1", [0.2])
+        .unwrap();
+    assert_eq!(bright.len(), 2);
+
+    let in_folder = datastore
+        .select_where(
+            "filepath LIKE ?1 AND average_brightness < ?2",
+            ("pictures/%", 0.2),
+        )
+        .unwrap();
+    assert_eq!(in_folder.len(), 1);
+    assert_eq!(in_folder[0].filename, "blue");
+
+    assert_eq!(datastore.find_by_content_hash("a").unwrap().len(), 2);
+    assert!(datastore.select_where("no_such_column = 1", []).is_err());
+}
+
+#[test]
+fn test_entries_persist_between_connections() {
+    let folder = create_temp_folder("sqlite_persist");
+    let datastore_path = folder.join("data.sqlite");
+    let datastore_path = datastore_path.to_str().unwrap();
+
+    let mut first = SqliteDatastore::open(datastore_path).unwrap();
+    let second = SqliteDatastore::open(datastore_path).unwrap();
+    first
+        .insert(sample_entry("pictures/red.png", 0.3, "a"))
+        .unwrap();
+    first.flush().unwrap();
+
+    assert!(is_sqlite_datastore(datastore_path));
+    assert_eq!(second.len().unwrap(), 1);
+    drop(first);
+    drop(second);
+    assert_eq!(
+        SqliteDatastore::open(datastore_path)
+            .unwrap()
+            .len()
+            .unwrap(),
+        1
+    );
+}
+
+#[test]
+fn test_convert_json_and_sqlite() {
+    let folder = create_temp_folder("sqlite_convert");
+    let sqlite_path = folder.join("data.db");
+    let sqlite_path = sqlite_path.to_str().unwrap();
+    let json_path = folder.join("data.json");
+    let json_path = json_path.to_str().unwrap();
+    let converted_path = folder.join("converted.json");
+    let converted_path = converted_path.to_str().unwrap();
+    let entries = vec![
+        sample_entry("pictures/red.png", 0.3, "a"),
+        sample_entry("pictures/blue.png", 0.1, "b"),
+    ];
+    write_data_to_filepath(json_path, &entries).unwrap();
+
+    assert_eq!(convert_json_to_sqlite(json_path, sqlite_path).unwrap(), 2);
+    assert_eq!(
+        convert_sqlite_to_json(sqlite_path, converted_path).unwrap(),
+        2
+    );
+
+    let converted: Vec<SearchIndex> = read_datastore_file(converted_path).unwrap();
+    assert_eq!(converted, entries);
+}
+
+#[test]
+fn test_schema_version() {
+    let folder = create_temp_folder("sqlite_schema_version");
+    let datastore_path = folder.join("data.sqlite");
+    let datastore_path = datastore_path.to_str().unwrap();
+    SqliteDatastore::open(datastore_path)
+        .unwrap()
+        .insert(sample_entry("pictures/missing.png", 0.3, ""))
+        .unwrap();
+    assert_eq!(migrate_sqlite_datastore(datastore_path).unwrap(), None);
+
+    // entries computed with other features are indexed again, missing pictures are dropped
+    let other_features = FeatureConfiguration {
+        bin_count: 8,
+        color_channels: 3,
+        ..FeatureConfiguration::default()
+    };
+    let connection = Connection::open(datastore_path).unwrap();
+    connection
+        .execute(
+            "UPDATE metadata SET value = ?1 WHERE key = 'features'",
+            [serde_json::to_string(&other_features).unwrap()],
+        )
+        .unwrap();
+    let err = SqliteDatastore::open(datastore_path).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::NeedsMigration { .. })
+    ));
+    let summary = migrate_sqlite_datastore(datastore_path).unwrap().unwrap();
+    assert_eq!(summary.previous_version, SCHEMA_VERSION);
+    assert_eq!(summary.dropped.len(), 1);
+    assert!(SqliteDatastore::open(datastore_path)
+        .unwrap()
+        .is_empty()
+        .unwrap());
+
+    connection
+        .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+        .unwrap();
+    let err = SqliteDatastore::open(datastore_path).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<DatastoreError>(),
+        Some(DatastoreError::UnsupportedVersion { .. })
+    ));
+}
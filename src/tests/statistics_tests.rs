@@ -0,0 +1,143 @@
+use crate::color_type::ColorType;
+use crate::features::FeatureConfiguration;
+use crate::memory_datastore::MemoryDatastore;
+use crate::picture::PictureF32;
+use crate::query::Query;
+use crate::query_descriptor::{FeatureWeights, QueryDescriptor};
+use crate::search_index::try_generate_suchindex;
+use crate::statistics::{compare_statistics, compute_statistics, Statistic, PERCENTILES};
+use crate::tests::test_helpers::{create_temp_folder, write_test_picture};
+use crate::transform::Transform;
+use crate::PictureU8;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-4,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn test_statistics_of_a_ramp() {
+    let values: Vec<u8> = (0..=255).collect();
+    let pic = PictureU8::new(256, 1, ColorType::Gray, values).unwrap();
+
+    let statistics = compute_statistics(&pic).unwrap();
+    let channel = &statistics.channels[0];
+    assert_close(channel.mean, 0.5);
+    assert_close(channel.skewness, 0.0);
+    assert_eq!((channel.min, channel.max), (0.0, 1.0));
+    assert_eq!(channel.percentiles.len(), PERCENTILES.len());
+    assert!(channel
+        .percentiles
+        .windows(2)
+        .all(|pair| pair[0] <= pair[1]));
+    assert!((channel.median() - 0.5).abs() < 0.01);
+    // every gray value occurs once
+    assert_close(statistics.entropy, 8.0);
+    assert_eq!(statistics.michelson_contrast, 1.0);
+}
+
+#[test]
+fn test_skewness_and_colourfulness() {
+    // mostly dark with one bright pixel
+    let dark = PictureU8::new(4, 1, ColorType::Gray, vec![0, 0, 0, 255]).unwrap();
+    let statistics = compute_statistics(&dark).unwrap();
+    assert!(statistics.channels[0].skewness > 0.0);
+    assert_eq!(statistics.colourfulness, 0.0);
+
+    let colourful =
+        PictureF32::new(2, 1, ColorType::Rgb, vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0]).unwrap();
+    let statistics = compute_statistics(&colourful).unwrap();
+    assert!(statistics.colourfulness > 0.3);
+    assert_eq!(statistics.get(Statistic::Max(2)), Some(1.0));
+    assert_eq!(statistics.get(Statistic::Mean(3)), None);
+}
+
+#[test]
+fn test_statistics_need_a_colour_model_and_pixels() {
+    let rgb = PictureU8::new(1, 1, ColorType::Rgb, vec![1, 2, 3]).unwrap();
+    assert!(compute_statistics(&rgb.reorder_channels(&[2, 1, 0]).unwrap()).is_err());
+
+    let empty = PictureU8::new(0, 0, ColorType::Rgb, vec![]).unwrap();
+    assert!(compute_statistics(&empty).is_err());
+}
+
+#[test]
+fn test_compare_statistics() {
+    let black =
+        compute_statistics(&PictureU8::new(1, 1, ColorType::Gray, vec![0]).unwrap()).unwrap();
+    let gray =
+        compute_statistics(&PictureU8::new(1, 1, ColorType::Gray, vec![128]).unwrap()).unwrap();
+    let rgb =
+        compute_statistics(&PictureU8::new(1, 1, ColorType::Rgb, vec![0, 0, 0]).unwrap()).unwrap();
+
+    assert_eq!(compare_statistics(&black, &black), Some(1.0));
+    let similarity = compare_statistics(&black, &gray).unwrap();
+    assert!(similarity > 0.0 && similarity < 1.0);
+    assert_eq!(compare_statistics(&black, &rgb), None);
+}
+
+#[test]
+fn test_query_by_statistic() {
+    let folder = create_temp_folder("query_by_statistic");
+    let red = folder.join("red.png");
+    let red = red.to_str().unwrap();
+    write_test_picture(red, 2, 2, [255, 0, 0]);
+    let gray = folder.join("gray.png");
+    let gray = gray.to_str().unwrap();
+    write_test_picture(gray, 2, 2, [128, 128, 128]);
+    let mut unknown =
+        try_generate_suchindex(red.to_string(), &FeatureConfiguration::default()).unwrap();
+    unknown.filepath = "pictures/unknown.png".to_string();
+    unknown.statistics = None;
+    let datastore = MemoryDatastore::with_entries(vec![
+        try_generate_suchindex(red.to_string(), &FeatureConfiguration::default()).unwrap(),
+        try_generate_suchindex(gray.to_string(), &FeatureConfiguration::default()).unwrap(),
+        unknown,
+    ]);
+
+    let colourful = Query::new()
+        .statistic(Statistic::Colourfulness, 0.1..=1.0)
+        .matching_entries(&datastore)
+        .unwrap();
+    assert_eq!(colourful.len(), 1);
+    assert_eq!(colourful[0].filepath, red);
+    let red_and_dark = Query::new()
+        .statistic(Statistic::Mean(0), 0.9..=1.0)
+        .statistic(Statistic::Mean(1), 0.0..=0.1)
+        .matching_entries(&datastore)
+        .unwrap();
+    assert_eq!(red_and_dark.len(), 1);
+}
+
+#[test]
+fn test_statistics_weight_in_similarity() {
+    let folder = create_temp_folder("statistics_weight_in_similarity");
+    let red = folder.join("red.png");
+    let red = red.to_str().unwrap();
+    write_test_picture(red, 2, 2, [255, 0, 0]);
+    let red = try_generate_suchindex(red.to_string(), &FeatureConfiguration::default()).unwrap();
+    let mut without_statistics = red.clone();
+    without_statistics.statistics = None;
+
+    let descriptor = QueryDescriptor::from_search_index(&red);
+    let uniform = FeatureWeights::uniform(red.histogram.len());
+    let weights = uniform.clone().with_statistics(1.0);
+    assert_eq!(descriptor.statistics_similarity(&red), 1.0);
+    assert!((descriptor.compare(&red, &weights).similarity() - 1.0).abs() < 1e-9);
+    // the statistics are not used by default, without them the entry counts as not similar
+    assert!(
+        (descriptor
+            .compare(&without_statistics, &uniform)
+            .similarity()
+            - 1.0)
+            .abs()
+            < 1e-9
+    );
+    let similarity = descriptor
+        .compare(&without_statistics, &weights)
+        .similarity();
+    assert!((similarity - 2.0 / 3.0).abs() < 1e-9);
+    assert!(descriptor.similarity_upper_bound(&without_statistics, &weights) >= similarity);
+}
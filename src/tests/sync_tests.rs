@@ -0,0 +1,135 @@
+use crate::features::FeatureConfiguration;
+use crate::search_index::SearchIndex;
+use crate::sync::{apply_sync, sync_entries};
+use crate::tests::test_helpers::{create_temp_folder, write_test_picture};
+use std::fs;
+
+/// This test walks a folder three times and checks that added, updated, moved and removed
+/// pictures are recognised.
+#[test]
+fn test_sync_entries() {
+    let folder = create_temp_folder("sync_entries");
+    let root = folder.to_str().unwrap().to_string();
+    let red = format!("{root}/red.png");
+    let blue = format!("{root}/blue.png");
+    let green = format!("{root}/green.png");
+    write_test_picture(&red, 4, 4, [255, 0, 0]);
+    write_test_picture(&blue, 4, 4, [0, 0, 255]);
+    write_test_picture(&green, 4, 4, [0, 255, 0]);
+
+    // first sync: everything is new
+    let (entries, summary) =
+        sync_entries(Vec::new(), &[&root], &FeatureConfiguration::default()).unwrap();
+    assert_eq!(summary.added.len(), 3);
+    assert_eq!(entries.len(), 3);
+
+    // nothing changed
+    let (entries, summary) =
+        sync_entries(entries, &[&root], &FeatureConfiguration::default()).unwrap();
+    assert!(summary.is_empty());
+
+    // move, change and delete one picture each
+    fs::create_dir(folder.join("sub")).unwrap();
+    let moved_red = format!("{root}/sub/red_moved.png");
+    fs::rename(&red, &moved_red).unwrap();
+    write_test_picture(&blue, 4, 4, [0, 0, 128]);
+    fs::remove_file(&green).unwrap();
+
+    let (entries, summary) =
+        sync_entries(entries, &[&root], &FeatureConfiguration::default()).unwrap();
+    assert!(summary.added.is_empty());
+    assert_eq!(summary.moved, vec![(red, moved_red.clone())]);
+    assert_eq!(summary.updated, vec![blue.clone()]);
+    assert_eq!(summary.removed, vec![green]);
+
+    let moved_entry: &SearchIndex = entries.iter().find(|e| e.filepath == moved_red).unwrap();
+    assert_eq!(moved_entry.filename, "red_moved");
+    let updated_entry: &SearchIndex = entries.iter().find(|e| e.filepath == blue).unwrap();
+    assert_eq!(updated_entry.histogram[2].bins[2], 16);
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+/// Duplicate entries of the same file are dropped.
+#[test]
+fn test_sync_entries_removes_duplicates() {
+    let folder = create_temp_folder("sync_duplicates");
+    let root = folder.to_str().unwrap().to_string();
+    let red = format!("{root}/red.png");
+    write_test_picture(&red, 2, 2, [255, 0, 0]);
+
+    let (mut entries, _) =
+        sync_entries(Vec::new(), &[&root], &FeatureConfiguration::default()).unwrap();
+    entries.push(entries[0].clone());
+
+    let (entries, summary) =
+        sync_entries(entries, &[&root], &FeatureConfiguration::default()).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(summary.removed, vec![red]);
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+/// Only the descriptors the datastore keeps are computed.
+#[test]
+fn test_sync_entries_of_a_compact_datastore() {
+    let folder = create_temp_folder("sync_compact");
+    let root = folder.to_str().unwrap().to_string();
+    write_test_picture(&format!("{root}/red.png"), 4, 4, [255, 0, 0]);
+
+    let (entries, summary) =
+        sync_entries(Vec::new(), &[&root], &FeatureConfiguration::compact()).unwrap();
+    assert_eq!(summary.added.len(), 1);
+    assert!(entries[0].histogram.is_empty() && entries[0].grid_cells.is_empty());
+    assert!(entries[0].statistics.is_none() && entries[0].correlogram.is_none());
+    assert!(entries[0].shape.is_none());
+    assert!(entries[0].color_moments.is_some() && entries[0].lightness.is_some());
+
+    fs::remove_dir_all(folder).unwrap();
+}
+
+/// Changes another process made while the sync ran are kept.
+#[test]
+fn test_apply_sync_keeps_concurrent_changes() {
+    let folder = create_temp_folder("apply_sync");
+    let root = folder.to_str().unwrap().to_string();
+    let red = format!("{root}/red.png");
+    let blue = format!("{root}/blue.png");
+    let green = format!("{root}/green.png");
+    write_test_picture(&red, 4, 4, [255, 0, 0]);
+    write_test_picture(&blue, 4, 4, [0, 0, 255]);
+    write_test_picture(&green, 4, 4, [0, 255, 0]);
+    let (snapshot, _) =
+        sync_entries(Vec::new(), &[&root], &FeatureConfiguration::default()).unwrap();
+
+    let moved_red = format!("{root}/red_moved.png");
+    fs::rename(&red, &moved_red).unwrap();
+    write_test_picture(&blue, 4, 4, [0, 0, 128]);
+    fs::remove_file(&green).unwrap();
+    let (synced_entries, summary) =
+        sync_entries(snapshot.clone(), &[&root], &FeatureConfiguration::default()).unwrap();
+
+    // meanwhile, another process tags the red picture and indexes the blue one again
+    let mut entries = snapshot.clone();
+    for entry in &mut entries {
+        if entry.filepath == red {
+            entry.tags = vec!["kept".to_string()];
+        }
+        if entry.filepath == blue {
+            entry.content_hash = "changed".to_string();
+        }
+    }
+
+    let summary = apply_sync(&mut entries, &snapshot, synced_entries, summary);
+
+    assert!(summary.updated.is_empty());
+    assert_eq!(summary.moved, vec![(red, moved_red.clone())]);
+    assert_eq!(summary.removed, vec![green]);
+    assert_eq!(entries.len(), 2);
+    let moved_entry: &SearchIndex = entries.iter().find(|e| e.filepath == moved_red).unwrap();
+    assert_eq!(moved_entry.tags, vec!["kept".to_string()]);
+    let blue_entry: &SearchIndex = entries.iter().find(|e| e.filepath == blue).unwrap();
+    assert_eq!(blue_entry.content_hash, "changed");
+
+    fs::remove_dir_all(folder).unwrap();
+}
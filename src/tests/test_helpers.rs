@@ -0,0 +1,97 @@
+use crate::features::FeatureConfiguration;
+use crate::memory_datastore::MemoryDatastore;
+use crate::search_index::{generate_suchindex, SearchIndex};
+use crate::Histogram;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// The checked-in datastore the tests start from. Tests must never write to it.
+pub const DATASTORE_FIXTURE: &str = crate::DEFAULT_DATASTORE_FILEPATH;
+
+/// The pictures for testing that `compact_datastore` contains.
+pub const TEST_PICTURES: [&str; 4] = [
+    "src/tests/files/pictures_for_testing/bird.png",
+    "src/tests/files/pictures_for_testing/flower_purple_1.png",
+    "src/tests/files/pictures_for_testing/flower_purple_1_modified.png",
+    "src/tests/files/pictures_for_testing/flower_purple_2.png",
+];
+
+/// Returns a compact `MemoryDatastore` (see `FeatureConfiguration::compact`) with entries of the
+/// `TEST_PICTURES`, whose entries have no histograms.
+pub fn compact_datastore() -> MemoryDatastore {
+    let entries = TEST_PICTURES
+        .iter()
+        .map(|picture| generate_suchindex(picture.to_string()))
+        .collect();
+    MemoryDatastore::with_entries(entries).with_features(FeatureConfiguration::compact())
+}
+
+/// Creates an empty folder in the temp directory for a single test.
+///
+/// Every test should use its own `name`, because tests run in parallel.
+pub fn create_temp_folder(name: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("imsearch_{name}_{}", std::process::id()));
+    if folder.exists() {
+        fs::remove_dir_all(&folder).unwrap();
+    }
+    fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+/// Returns the path of a copy of `DATASTORE_FIXTURE` in the temp directory.
+///
+/// The copy is made once per test run and is the default datastore of the unit tests (see
+/// `get_datastore_path`), so tests that write to the configured datastore leave the fixture alone.
+pub fn test_datastore_path() -> &'static str {
+    static TEST_DATASTORE_PATH: OnceLock<String> = OnceLock::new();
+    TEST_DATASTORE_PATH.get_or_init(|| {
+        let datastore_path = create_temp_folder("test_datastore").join("data.json");
+        fs::copy(DATASTORE_FIXTURE, &datastore_path).unwrap();
+        datastore_path.to_str().unwrap().to_string()
+    })
+}
+
+/// Writes a small RGB PNG where every pixel has the given colour.
+pub fn write_test_picture(filepath: &str, width: u32, height: u32, rgb: [u8; 3]) {
+    let data: Vec<u8> = rgb
+        .iter()
+        .copied()
+        .cycle()
+        .take((width * height * 3) as usize)
+        .collect();
+    write_test_picture_data(filepath, width, height, &data);
+}
+
+/// Writes a PNG with the given RGB pixel data.
+pub fn write_test_picture_data(filepath: &str, width: u32, height: u32, data: &[u8]) {
+    let file = File::create(filepath).unwrap();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(data).unwrap();
+}
+
+/// Creates entries with pseudo random descriptors.
+pub fn random_entries(count: usize, seed: u64) -> Vec<SearchIndex> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) as u32
+    };
+    (0..count)
+        .map(|i| {
+            let histogram = (0..3)
+                .map(|_| Histogram {
+                    bins: (0..5).map(|_| next() % 1000 + 1).collect(),
+                })
+                .collect();
+            let average_brightness = (next() % 1000) as f32 / 1000.0;
+            SearchIndex::new(format!("pictures/{i}.png"), average_brightness, histogram)
+        })
+        .collect()
+}
@@ -0,0 +1,182 @@
+use crate::color_type::ColorType;
+use crate::picture::PictureF32;
+use crate::region::Rect;
+use crate::transform::{Flip, ResizeFilter, Rotation, Transform};
+use crate::PictureU8;
+
+/// A picture of 3 columns and 2 lines with two channels, the second one is the first plus 10.
+fn two_channel_picture() -> PictureU8 {
+    PictureU8 {
+        lines: 2,
+        columns: 3,
+        color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
+        data: vec![1, 11, 2, 12, 3, 13, 4, 14, 5, 15, 6, 16],
+    }
+}
+
+fn gray_picture(columns: u32, lines: u32, data: Vec<u8>) -> PictureU8 {
+    PictureU8 {
+        lines,
+        columns,
+        color_channel_count: 1,
+        color_type: ColorType::Gray,
+        data,
+    }
+}
+
+#[test]
+fn test_crop_both_picture_types() {
+    let pic = two_channel_picture();
+    let cropped = pic.crop(&Rect::new(1, 1, 2, 1)).unwrap();
+    assert_eq!((cropped.columns, cropped.lines), (2, 1));
+    assert_eq!(cropped.data, vec![5, 15, 6, 16]);
+
+    let pic_f32 = PictureF32 {
+        lines: 1,
+        columns: 2,
+        color_channel_count: 1,
+        color_type: ColorType::Gray,
+        data: vec![0.25, 0.75],
+    };
+    assert_eq!(
+        pic_f32.crop(&Rect::new(1, 0, 1, 1)).unwrap().data,
+        vec![0.75]
+    );
+    assert!(pic_f32.crop(&Rect::new(1, 0, 2, 1)).is_err());
+}
+
+#[test]
+fn test_rotations() {
+    let pic = two_channel_picture();
+
+    let quarter = pic.rotate(Rotation::Clockwise90);
+    assert_eq!((quarter.columns, quarter.lines), (2, 3));
+    assert_eq!(quarter.data, vec![4, 14, 1, 11, 5, 15, 2, 12, 6, 16, 3, 13]);
+
+    let half = pic.rotate(Rotation::Clockwise180);
+    assert_eq!((half.columns, half.lines), (3, 2));
+    assert_eq!(half.data, vec![6, 16, 5, 15, 4, 14, 3, 13, 2, 12, 1, 11]);
+
+    let three_quarters = pic.rotate(Rotation::Clockwise270);
+    assert_eq!((three_quarters.columns, three_quarters.lines), (2, 3));
+    assert_eq!(
+        three_quarters.data,
+        vec![3, 13, 6, 16, 2, 12, 5, 15, 1, 11, 4, 14]
+    );
+
+    // four quarter turns give the original picture
+    let mut turned = pic.rotate(Rotation::Clockwise90);
+    for _ in 0..3 {
+        turned = turned.rotate(Rotation::Clockwise90);
+    }
+    assert_eq!(turned.data, pic.data);
+}
+
+#[test]
+fn test_flips() {
+    let pic = gray_picture(3, 2, vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(pic.flip(Flip::Horizontal).data, vec![3, 2, 1, 6, 5, 4]);
+    assert_eq!(pic.flip(Flip::Vertical).data, vec![4, 5, 6, 1, 2, 3]);
+    assert_eq!(
+        pic.flip(Flip::Horizontal).flip(Flip::Vertical).data,
+        pic.rotate(Rotation::Clockwise180).data
+    );
+}
+
+#[test]
+fn test_resize_nearest() {
+    let pic = gray_picture(2, 2, vec![10, 20, 30, 40]);
+    let enlarged = pic.resize(4, 2, ResizeFilter::Nearest).unwrap();
+    assert_eq!(enlarged.data, vec![10, 10, 20, 20, 30, 30, 40, 40]);
+
+    let shrunk = enlarged.resize(2, 1, ResizeFilter::Nearest).unwrap();
+    assert_eq!((shrunk.columns, shrunk.lines), (2, 1));
+    assert_eq!(shrunk.data.len(), 2);
+}
+
+#[test]
+fn test_resize_bilinear() {
+    let pic = gray_picture(2, 1, vec![0, 100]);
+    let enlarged = pic.resize(4, 1, ResizeFilter::Bilinear).unwrap();
+    // the outer pixels keep the original values, the inner ones are interpolated
+    assert_eq!(enlarged.data, vec![0, 25, 75, 100]);
+
+    let pic_f32 = PictureF32 {
+        lines: 2,
+        columns: 1,
+        color_channel_count: 1,
+        color_type: ColorType::Gray,
+        data: vec![0.0, 1.0],
+    };
+    let enlarged = pic_f32.resize(1, 4, ResizeFilter::Bilinear).unwrap();
+    assert_eq!(enlarged.data, vec![0.0, 0.25, 0.75, 1.0]);
+}
+
+#[test]
+fn test_resize_area() {
+    let pic = gray_picture(4, 2, vec![0, 100, 200, 40, 10, 110, 210, 50]);
+    let shrunk = pic.resize(2, 1, ResizeFilter::Area).unwrap();
+    assert_eq!(shrunk.data, vec![55, 125]);
+
+    // shrinking by a fraction weights partly covered pixels
+    let pic = gray_picture(3, 1, vec![0, 90, 180]);
+    let shrunk = pic.resize(2, 1, ResizeFilter::Area).unwrap();
+    assert_eq!(shrunk.data, vec![30, 150]);
+
+    // a single color stays the same with every filter
+    let red = PictureU8 {
+        lines: 5,
+        columns: 7,
+        color_channel_count: 3,
+        color_type: ColorType::Rgb,
+        data: [200, 10, 30].repeat(35),
+    };
+    for filter in [
+        ResizeFilter::Nearest,
+        ResizeFilter::Bilinear,
+        ResizeFilter::Area,
+    ] {
+        let resized = red.resize(3, 4, filter).unwrap();
+        assert_eq!(resized.data, [200, 10, 30].repeat(12));
+    }
+}
+
+#[test]
+fn test_resize_to_empty_size() {
+    let pic = gray_picture(2, 2, vec![10, 20, 30, 40]);
+    assert!(pic.resize(0, 2, ResizeFilter::Area).is_err());
+    assert!(gray_picture(0, 0, Vec::new())
+        .resize(2, 2, ResizeFilter::Area)
+        .is_err());
+}
+
+#[test]
+fn test_resize_to_fit_keeps_aspect_ratio() {
+    let pic = gray_picture(40, 20, vec![0; 800]);
+    let thumbnail = pic.resize_to_fit(10, 10, ResizeFilter::Area).unwrap();
+    assert_eq!((thumbnail.columns, thumbnail.lines), (10, 5));
+
+    let tall = gray_picture(2, 30, vec![0; 60]);
+    let thumbnail = tall.resize_to_fit(10, 10, ResizeFilter::Area).unwrap();
+    assert_eq!((thumbnail.columns, thumbnail.lines), (1, 10));
+}
+
+#[test]
+fn test_channel_operations() {
+    let pic = two_channel_picture();
+
+    let second = pic.extract_channel(1).unwrap();
+    assert_eq!(second.color_channel_count, 1);
+    assert_eq!(second.data, vec![11, 12, 13, 14, 15, 16]);
+
+    let swapped = pic.reorder_channels(&[1, 0]).unwrap();
+    assert_eq!(&swapped.data[..4], &[11, 1, 12, 2]);
+
+    let repeated = pic.reorder_channels(&[0, 0, 1]).unwrap();
+    assert_eq!(repeated.color_channel_count, 3);
+    assert_eq!(&repeated.data[..3], &[1, 1, 11]);
+
+    assert!(pic.extract_channel(2).is_err());
+    assert!(pic.reorder_channels(&[]).is_err());
+}
@@ -1,3 +1,4 @@
+use crate::color_type::ColorType;
 use crate::with_threads::{
     convert_data_to_f32, convert_data_to_f32_with_threads, convert_data_to_u8,
     convert_data_to_u8_with_threads, get_histogram_with_threads, take_every_nth_value,
@@ -27,6 +28,7 @@ fn test_get_histogram_with_threads() {
         lines: 1,
         columns: 2,
         color_channel_count: 2,
+        color_type: ColorType::GrayAlpha,
         data: vec![255, 0, 255, 0], // Sample pixel data
     };
 
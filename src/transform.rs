@@ -0,0 +1,391 @@
+use crate::color_type::ColorType;
+use crate::picture::{Picture, Sample};
+use crate::region::Rect;
+use std::error::Error;
+
+/// How the pixels of a resized picture are computed from the original pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// Copies the nearest original pixel. Fast and keeps the exact colors, but blocky.
+    Nearest,
+    /// Interpolates linearly between the four nearest original pixels.
+    Bilinear,
+    /// Averages all original pixels the new pixel covers, weighted by how much of them it covers.
+    /// The best choice to shrink pictures, e.g. for thumbnails.
+    Area,
+}
+
+/// A clockwise rotation by a multiple of 90 degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+/// The axis a picture is mirrored at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flip {
+    /// Swaps left and right.
+    Horizontal,
+    /// Swaps top and bottom.
+    Vertical,
+}
+
+/// Geometric and channel transformations of pictures. Every transformation returns a new picture
+/// and keeps the original.
+///
+/// # Examples
+///
+/// ```rust
+/// use imsearch::color_type::ColorType;
+/// use imsearch::transform::{Flip, ResizeFilter, Rotation, Transform};
+/// use imsearch::PictureU8;
+///
+/// let pic = PictureU8 {
+///     lines: 2,
+///     columns: 3,
+///     color_channel_count: 1,
+///     color_type: ColorType::Gray,
+///     data: vec![1, 2, 3, 4, 5, 6],
+/// };
+///
+/// let rotated = pic.rotate(Rotation::Clockwise90);
+/// assert_eq!((rotated.columns, rotated.lines), (2, 3));
+/// assert_eq!(rotated.data, vec![4, 1, 5, 2, 6, 3]);
+///
+/// assert_eq!(pic.flip(Flip::Horizontal).data, vec![3, 2, 1, 6, 5, 4]);
+///
+/// let doubled = pic.resize(6, 4, ResizeFilter::Nearest).unwrap();
+/// assert_eq!(&doubled.data[..6], &[1, 1, 2, 2, 3, 3]);
+/// ```
+pub trait Transform: Sized {
+    /// Copies a region of the picture into a new picture.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the region is empty or does not lie inside the picture.
+    fn crop(&self, region: &Rect) -> Result<Self, Box<dyn Error>>;
+
+    /// Scales the picture to the given size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the picture or the new size is empty.
+    fn resize(
+        &self,
+        columns: u32,
+        lines: u32,
+        filter: ResizeFilter,
+    ) -> Result<Self, Box<dyn Error>>;
+
+    /// Scales the picture to the largest size that fits into `max_columns` x `max_lines` and keeps
+    /// its aspect ratio. Pictures that already fit are scaled up.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the picture or the maximum size is empty.
+    fn resize_to_fit(
+        &self,
+        max_columns: u32,
+        max_lines: u32,
+        filter: ResizeFilter,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (columns, lines) = self.size();
+        if columns == 0 || lines == 0 {
+            return Err("An empty picture can not be resized".into());
+        }
+        let scale = f64::min(
+            f64::from(max_columns) / f64::from(columns),
+            f64::from(max_lines) / f64::from(lines),
+        );
+        let scaled =
+            |length: u32, max: u32| ((f64::from(length) * scale).round() as u32).clamp(1, max);
+        self.resize(
+            scaled(columns, max_columns.max(1)),
+            scaled(lines, max_lines.max(1)),
+            filter,
+        )
+    }
+
+    /// Rotates the picture clockwise. Quarter turns swap its columns and lines.
+    fn rotate(&self, rotation: Rotation) -> Self;
+
+    /// Mirrors the picture.
+    fn flip(&self, flip: Flip) -> Self;
+
+    /// Returns a picture with a single color channel: the given channel of this picture.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the picture has no such channel.
+    fn extract_channel(&self, channel: usize) -> Result<Self, Box<dyn Error>> {
+        self.reorder_channels(&[channel])
+    }
+
+    /// Returns a picture whose channel `i` is the channel `order[i]` of this picture. Channels
+    /// can be left out or repeated, e.g. `[2, 1, 0]` turns BGR into RGB and `[0, 1, 2]` drops the
+    /// alpha channel of RGBA.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `order` is empty or names a channel the picture does not have.
+    fn reorder_channels(&self, order: &[usize]) -> Result<Self, Box<dyn Error>>;
+
+    /// Returns the columns and lines of the picture.
+    fn size(&self) -> (u32, u32);
+}
+
+/// The size of a picture, shared by the transformations.
+#[derive(Clone, Copy)]
+struct Layout {
+    lines: u32,
+    columns: u32,
+    color_channel_count: usize,
+}
+
+impl Layout {
+    fn pixel<'a, T>(&self, data: &'a [T], line: u32, column: u32) -> &'a [T] {
+        let start =
+            (line as usize * self.columns as usize + column as usize) * self.color_channel_count;
+        &data[start..start + self.color_channel_count]
+    }
+}
+
+/// Returns the colour type of the channels in the given order: the colour model is kept if the
+/// channels stay in place, only the alpha channel is dropped or the gray channel is extracted.
+fn reordered_color_type(color_type: ColorType, order: &[usize]) -> ColorType {
+    let in_place = order
+        .iter()
+        .enumerate()
+        .all(|(index, channel)| index == *channel);
+    match (color_type, order.len()) {
+        _ if in_place && order.len() == color_type.channel_count() => color_type,
+        (ColorType::Rgba, 3) if in_place => ColorType::Rgb,
+        (ColorType::GrayAlpha, 1) if in_place => ColorType::Gray,
+        (_, count) => ColorType::Channels(count),
+    }
+}
+
+fn crop_data<T: Copy>(layout: Layout, data: &[T], region: &Rect) -> Result<Vec<T>, Box<dyn Error>> {
+    if !region.fits_into(layout.columns, layout.lines) {
+        return Err(format!(
+            "The region {region} does not lie inside the picture of {}x{} pixels",
+            layout.columns, layout.lines
+        )
+        .into());
+    }
+    let line_length = layout.columns as usize * layout.color_channel_count;
+    let start = region.x as usize * layout.color_channel_count;
+    let end = start + region.width as usize * layout.color_channel_count;
+
+    let mut cropped = Vec::with_capacity((end - start) * region.height as usize);
+    for line in region.y..region.y + region.height {
+        let line_start = line as usize * line_length;
+        cropped.extend_from_slice(&data[line_start + start..line_start + end]);
+    }
+    Ok(cropped)
+}
+
+/// Returns the original pixels that make up each new pixel along one axis, with their weights.
+/// The weights of every new pixel add up to 1.
+fn axis_weights(original: u32, resized: u32, filter: ResizeFilter) -> Vec<Vec<(u32, f32)>> {
+    let scale = original as f32 / resized as f32;
+    let last = original - 1;
+    (0..resized)
+        .map(|position| match filter {
+            ResizeFilter::Nearest => {
+                let nearest = ((position as f32 + 0.5) * scale) as u32;
+                vec![(nearest.min(last), 1.0)]
+            }
+            ResizeFilter::Bilinear => {
+                let center = ((position as f32 + 0.5) * scale - 0.5).clamp(0.0, last as f32);
+                let before = center.floor() as u32;
+                let fraction = center - before as f32;
+                vec![(before, 1.0 - fraction), ((before + 1).min(last), fraction)]
+            }
+            ResizeFilter::Area => {
+                let start = position as f32 * scale;
+                let end = start + scale;
+                let mut weights = Vec::new();
+                let mut pixel = start.floor() as u32;
+                while pixel <= last && (pixel as f32) < end {
+                    let covered = end.min(pixel as f32 + 1.0) - start.max(pixel as f32);
+                    if covered > 0.0 {
+                        weights.push((pixel, covered / scale));
+                    }
+                    pixel += 1;
+                }
+                weights
+            }
+        })
+        .collect()
+}
+
+fn resize_data<T: Sample>(
+    layout: Layout,
+    data: &[T],
+    columns: u32,
+    lines: u32,
+    filter: ResizeFilter,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    if layout.columns == 0 || layout.lines == 0 {
+        return Err("An empty picture can not be resized".into());
+    }
+    if columns == 0 || lines == 0 {
+        return Err(format!("A picture can not be resized to {columns}x{lines} pixels").into());
+    }
+    let column_weights = axis_weights(layout.columns, columns, filter);
+    let line_weights = axis_weights(layout.lines, lines, filter);
+
+    let channels = layout.color_channel_count;
+    let mut resized = Vec::with_capacity(columns as usize * lines as usize * channels);
+    let mut sums = vec![0.0f32; channels];
+    for line in &line_weights {
+        for column in &column_weights {
+            sums.fill(0.0);
+            for &(original_line, line_weight) in line {
+                for &(original_column, column_weight) in column {
+                    let weight = line_weight * column_weight;
+                    let pixel = layout.pixel(data, original_line, original_column);
+                    for (sum, value) in sums.iter_mut().zip(pixel) {
+                        *sum += weight * value.to_f32();
+                    }
+                }
+            }
+            resized.extend(sums.iter().map(|sum| T::from_f32(*sum)));
+        }
+    }
+    Ok(resized)
+}
+
+/// Returns the rotated data and its columns and lines.
+fn rotate_data<T: Copy>(layout: Layout, data: &[T], rotation: Rotation) -> (Vec<T>, u32, u32) {
+    let Layout { lines, columns, .. } = layout;
+    let (new_columns, new_lines) = match rotation {
+        Rotation::Clockwise180 => (columns, lines),
+        Rotation::Clockwise90 | Rotation::Clockwise270 => (lines, columns),
+    };
+    let mut rotated = Vec::with_capacity(data.len());
+    for line in 0..new_lines {
+        for column in 0..new_columns {
+            let (original_line, original_column) = match rotation {
+                Rotation::Clockwise90 => (lines - 1 - column, line),
+                Rotation::Clockwise180 => (lines - 1 - line, columns - 1 - column),
+                Rotation::Clockwise270 => (column, columns - 1 - line),
+            };
+            rotated.extend_from_slice(layout.pixel(data, original_line, original_column));
+        }
+    }
+    (rotated, new_columns, new_lines)
+}
+
+fn flip_data<T: Copy>(layout: Layout, data: &[T], flip: Flip) -> Vec<T> {
+    let mut flipped = Vec::with_capacity(data.len());
+    for line in 0..layout.lines {
+        for column in 0..layout.columns {
+            let (original_line, original_column) = match flip {
+                Flip::Horizontal => (line, layout.columns - 1 - column),
+                Flip::Vertical => (layout.lines - 1 - line, column),
+            };
+            flipped.extend_from_slice(layout.pixel(data, original_line, original_column));
+        }
+    }
+    flipped
+}
+
+fn reorder_data<T: Copy>(
+    layout: Layout,
+    data: &[T],
+    order: &[usize],
+) -> Result<Vec<T>, Box<dyn Error>> {
+    if order.is_empty() {
+        return Err("A picture needs at least one color channel".into());
+    }
+    if let Some(channel) = order
+        .iter()
+        .find(|channel| **channel >= layout.color_channel_count)
+    {
+        return Err(format!(
+            "The picture has no color channel {channel}, only {}",
+            layout.color_channel_count
+        )
+        .into());
+    }
+    Ok(data
+        .chunks_exact(layout.color_channel_count)
+        .flat_map(|pixel| order.iter().map(move |channel| pixel[*channel]))
+        .collect())
+}
+
+impl<T: Sample> Transform for Picture<T> {
+    fn crop(&self, region: &Rect) -> Result<Self, Box<dyn Error>> {
+        Ok(Picture {
+            lines: region.height,
+            columns: region.width,
+            color_channel_count: self.color_channel_count,
+            color_type: self.color_type,
+            data: crop_data(self.layout(), &self.data, region)?,
+        })
+    }
+
+    fn resize(
+        &self,
+        columns: u32,
+        lines: u32,
+        filter: ResizeFilter,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Picture {
+            lines,
+            columns,
+            color_channel_count: self.color_channel_count,
+            color_type: self.color_type,
+            data: resize_data(self.layout(), &self.data, columns, lines, filter)?,
+        })
+    }
+
+    fn rotate(&self, rotation: Rotation) -> Self {
+        let (data, columns, lines) = rotate_data(self.layout(), &self.data, rotation);
+        Picture {
+            lines,
+            columns,
+            color_channel_count: self.color_channel_count,
+            color_type: self.color_type,
+            data,
+        }
+    }
+
+    fn flip(&self, flip: Flip) -> Self {
+        Picture {
+            lines: self.lines,
+            columns: self.columns,
+            color_channel_count: self.color_channel_count,
+            color_type: self.color_type,
+            data: flip_data(self.layout(), &self.data, flip),
+        }
+    }
+
+    fn reorder_channels(&self, order: &[usize]) -> Result<Self, Box<dyn Error>> {
+        Ok(Picture {
+            lines: self.lines,
+            columns: self.columns,
+            color_channel_count: order.len(),
+            color_type: reordered_color_type(self.color_type, order),
+            data: reorder_data(self.layout(), &self.data, order)?,
+        })
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.columns, self.lines)
+    }
+}
+
+impl<T> Picture<T> {
+    fn layout(&self) -> Layout {
+        Layout {
+            lines: self.lines,
+            columns: self.columns,
+            color_channel_count: self.color_channel_count,
+        }
+    }
+}
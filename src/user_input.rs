@@ -1,6 +1,11 @@
-use crate::search_index::analyse_pictures;
+use crate::datastore::open_datastore;
+use crate::feedback::{Feedback, FeedbackSession};
+use crate::get_datastore_path;
+use crate::search::SearchOptions;
+use crate::search_index::{analyse_pictures, try_generate_suchindex};
 /// This module provides functions for user input and picture analysis.
 ///
+use std::error::Error;
 use std::io;
 
 /// Prompts the user to enter the file path of a directory containing images or a single image file from which to search for images.
@@ -32,7 +37,6 @@ pub fn input() -> bool {
 
 /// Prompts the user to extend their search library or not.
 /// If "y" (yes) is selected, calls the `input` function and repeats the input.
-
 pub fn repeat_input() -> bool {
     println!("would you like to add another path?'yes' or 'no'");
     let mut user_input = String::new();
@@ -74,3 +78,86 @@ pub fn input_search_image() -> String {
     // give input to the function which comares the picture with the database
     //todo()! insert function which searches for similar pictures
 }
+
+/// Shows the pictures most similar to the given picture and lets the user refine the search by
+/// marking results as relevant or irrelevant (see `parse_feedback`), until an empty line is
+/// entered.
+///
+/// # Errors
+///
+/// Returns an error if the picture or the datastore can not be read.
+pub fn refine_search(picture_path: &str) -> Result<(), Box<dyn Error>> {
+    let datastore = open_datastore(&get_datastore_path()?)?;
+    let mut session = FeedbackSession::new(&try_generate_suchindex(
+        picture_path.to_string(),
+        &datastore.features(),
+    )?);
+    let options = SearchOptions::default();
+
+    loop {
+        let results = session.search(datastore.as_ref(), &options)?.to_vec();
+        for (number, result) in results.iter().enumerate() {
+            println!("Result {}:", number + 1);
+            result.print();
+        }
+
+        println!("Mark results as relevant (+<number>) or irrelevant (-<number>), e.g. '+1 -3', or press enter to finish:");
+        let mut user_input = String::new();
+        io::stdin().read_line(&mut user_input)?;
+        let feedback = match parse_feedback(&user_input, results.len()) {
+            Ok(feedback) => feedback,
+            Err(message) => {
+                println!("{message}");
+                continue;
+            }
+        };
+        if feedback.is_empty() {
+            return Ok(());
+        }
+        for (index, feedback) in feedback {
+            session.add_feedback(&results[index], feedback);
+        }
+    }
+}
+
+/// Parses the feedback on the shown results: `+<number>` marks a result as relevant,
+/// `-<number>` as irrelevant. The numbers start at 1.
+///
+/// # Returns
+///
+/// The index of every marked result together with its feedback.
+///
+/// # Errors
+///
+/// Returns a message if a mark is malformed or its number is not between 1 and `result_count`.
+///
+/// # Examples
+///
+/// ```
+/// use imsearch::feedback::Feedback;
+/// use imsearch::user_input::parse_feedback;
+///
+/// let feedback = parse_feedback("+1 -3", 5).unwrap();
+/// assert_eq!(feedback, vec![(0, Feedback::Relevant), (2, Feedback::Irrelevant)]);
+/// assert!(parse_feedback("+6", 5).is_err());
+/// ```
+pub fn parse_feedback(input: &str, result_count: usize) -> Result<Vec<(usize, Feedback)>, String> {
+    input
+        .split_whitespace()
+        .map(|mark| {
+            let (feedback, number) = if let Some(number) = mark.strip_prefix('+') {
+                (Feedback::Relevant, number)
+            } else if let Some(number) = mark.strip_prefix('-') {
+                (Feedback::Irrelevant, number)
+            } else {
+                return Err(format!("'{mark}' must start with + or -"));
+            };
+            match number.parse::<usize>() {
+                Ok(number) if (1..=result_count).contains(&number) => Ok((number - 1, feedback)),
+                _ => Err(format!(
+                    "'{mark}' does not refer to a result between 1 and {result_count}"
+                )),
+            }
+        })
+        .collect()
+}
@@ -14,6 +14,7 @@ use std::thread;
 /// # Examples
 ///
 /// ```
+/// use imsearch::color_type::ColorType;
 /// use imsearch::PictureU8;
 /// use imsearch::with_threads::get_histogram_with_threads;
 ///
@@ -21,6 +22,7 @@ use std::thread;
 ///     lines: 1,
 ///     columns: 2,
 ///     color_channel_count: 2,
+///     color_type: ColorType::GrayAlpha,
 ///     data: vec![255, 0, 255, 0], // Sample pixel data
 /// };
 ///
@@ -117,7 +119,7 @@ pub fn get_histogram_with_threads(pic: &PictureU8) -> Vec<Histogram> {
 /// If the starting index `start_at_index` is within the bounds of the original `Vec<u8>`, but the step size `n` exceeds the length of the original `Vec<u8>`
 /// starting from the given index, the resulting `Vec<u8>` will be empty.
 ///
-pub fn take_every_nth_value(vec: &Vec<u8>, n: usize, start_at_index: usize) -> Vec<u8> {
+pub fn take_every_nth_value(vec: &[u8], n: usize, start_at_index: usize) -> Vec<u8> {
     let mut new_vec = Vec::new();
     let mut index = start_at_index;
 
@@ -157,8 +159,8 @@ pub fn take_every_nth_value(vec: &Vec<u8>, n: usize, start_at_index: usize) -> V
 pub fn convert_data_to_u8(data: &[f32]) -> Vec<u8> {
     let mut new_data = Vec::<u8>::new();
 
-    for i in 0..data.len() {
-        new_data.push((data[i] * 255.0) as u8);
+    for value in data {
+        new_data.push((value * 255.0) as u8);
     }
     new_data
 }
@@ -192,8 +194,8 @@ pub fn convert_data_to_f32(data: &[u8]) -> Vec<f32> {
     let mut new_data = Vec::<f32>::new();
 
     //convert each value from [0, 255] to [0.0, 1.0]
-    for i in 0..data.len() {
-        new_data.push(f32::from(data[i]) / 255.0);
+    for value in data {
+        new_data.push(f32::from(*value) / 255.0);
     }
     new_data
 }
@@ -261,8 +263,8 @@ pub fn convert_data_to_u8_with_threads(data: &[f32]) -> Vec<u8> {
         let handle = thread::spawn(move || {
             let mut converted_data: Vec<u8> = Vec::new();
 
-            for i in 0..div_datum.len() {
-                converted_data.push((div_datum[i] * 255.0) as u8);
+            for value in div_datum {
+                converted_data.push((value * 255.0) as u8);
             }
 
             converted_data
@@ -339,8 +341,8 @@ pub fn convert_data_to_f32_with_threads(data: &[u8]) -> Vec<f32> {
         let handle = thread::spawn(move || {
             let mut converted_data: Vec<f32> = Vec::new();
 
-            for i in 0..div_datum.len() {
-                converted_data.push(f32::from(div_datum[i]) / 255.0);
+            for value in div_datum {
+                converted_data.push(f32::from(value) / 255.0);
             }
 
             converted_data